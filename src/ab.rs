@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Instant;
+
+use crate::args::AbArgs;
+use crate::batch::BatchItem;
+use crate::eval::parse_verdict;
+use crate::executor::AIExecutor;
+use crate::ollama::Message;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Winner {
+    #[serde(alias = "a")]
+    A,
+    #[serde(alias = "b")]
+    B,
+    #[serde(alias = "TIE", alias = "Tie", rename = "tie")]
+    Tie,
+}
+
+#[derive(Debug, Deserialize)]
+struct Judgement {
+    winner: Winner,
+    #[serde(default)]
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VariantResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<u64>,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AbResult {
+    pub line: usize,
+    pub prompt: String,
+    pub a: VariantResult,
+    pub b: VariantResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winner: Option<Winner>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+pub async fn run(executor: &AIExecutor, args: AbArgs) -> Result<()> {
+    let system_a = fs::read_to_string(&args.system_a)
+        .context(format!("Failed to read system prompt A: {}", args.system_a.display()))?;
+    let system_b = fs::read_to_string(&args.system_b)
+        .context(format!("Failed to read system prompt B: {}", args.system_b.display()))?;
+    let content = fs::read_to_string(&args.prompts)
+        .context(format!("Failed to read prompts: {}", args.prompts.display()))?;
+
+    let model = args.model.clone().unwrap_or_else(|| executor.get_model().to_string());
+
+    let mut items = Vec::new();
+    for (i, line) in content.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let item: BatchItem = serde_json::from_str(line)
+            .context(format!("Invalid prompt on line {}", i + 1))?;
+        items.push((i + 1, item));
+    }
+
+    println!("{} Comparing 2 system prompts on {} prompt(s) with {}{}",
+        "⚙".bright_blue(), items.len(), model.bright_cyan(),
+        args.judge.as_ref().map(|j| format!(" (judge: {})", j)).unwrap_or_default());
+
+    let mut results = Vec::new();
+    let (mut wins_a, mut wins_b, mut ties) = (0, 0, 0);
+
+    for (line, item) in items {
+        let model = item.model.clone().unwrap_or_else(|| model.clone());
+        let a = run_variant(executor, &model, &system_a, &item).await;
+        let b = run_variant(executor, &model, &system_b, &item).await;
+
+        println!("\n{} {}", format!("[{}]", line).bright_yellow(), item.prompt.bright_white());
+        print_variant("A", &a);
+        print_variant("B", &b);
+
+        let judgement = match (&args.judge, &a.response, &b.response) {
+            (Some(judge), Some(response_a), Some(response_b)) => {
+                // Alternate presentation order to offset the judge's position bias
+                let swapped = line % 2 == 0;
+                match judge_pair(executor, judge, &item.prompt, response_a, response_b, swapped).await {
+                    Ok(judgement) => Some(judgement),
+                    Err(e) => {
+                        eprintln!("  {} {:#}", "Judge error:".bright_red(), e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let (winner, reason) = match judgement {
+            Some(j) => (Some(j.winner), Some(j.reason)),
+            None => (None, None),
+        };
+        if let Some(winner) = winner {
+            match winner {
+                Winner::A => wins_a += 1,
+                Winner::B => wins_b += 1,
+                Winner::Tie => ties += 1,
+            }
+            println!("  {} {:?} {}", "Winner:".bright_green().bold(), winner,
+                reason.as_deref().unwrap_or("").bright_black());
+        }
+
+        results.push(AbResult {
+            line,
+            prompt: item.prompt,
+            a,
+            b,
+            winner,
+            reason,
+        });
+    }
+
+    if args.judge.is_some() {
+        println!("\n{} A wins {}, B wins {}, {} tie(s)",
+            "Summary:".bright_yellow().bold(), wins_a, wins_b, ties);
+    }
+
+    if let Some(path) = &args.out {
+        let lines: Vec<String> = results.iter()
+            .map(serde_json::to_string)
+            .collect::<serde_json::Result<_>>()?;
+        fs::write(path, lines.join("\n") + "\n")
+            .context(format!("Failed to write results: {}", path.display()))?;
+        println!("{} Results written to {}", "✓".bright_green(), path.display());
+    }
+
+    Ok(())
+}
+
+async fn run_variant(executor: &AIExecutor, model: &str, system: &str, item: &BatchItem) -> VariantResult {
+    let messages = vec![
+        Message {
+            role: "system".to_string(),
+            content: system.trim().to_string(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        },
+        Message {
+            role: "user".to_string(),
+            content: item.prompt.clone(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        },
+    ];
+
+    let started = Instant::now();
+    let result = executor.batch_complete(Some(model), messages, item.options.clone()).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(response) => VariantResult {
+            response: Some(response.message.content),
+            completion_tokens: response.eval_count,
+            latency_ms,
+            error: None,
+        },
+        Err(e) => VariantResult {
+            response: None,
+            completion_tokens: None,
+            latency_ms,
+            error: Some(format!("{:#}", e)),
+        },
+    }
+}
+
+fn print_variant(label: &str, result: &VariantResult) {
+    let tokens = result.completion_tokens
+        .map(|t| format!(", {} tokens", t))
+        .unwrap_or_default();
+    println!("  {} {} ms{}", format!("{}:", label).bright_cyan().bold(), result.latency_ms, tokens);
+
+    match (&result.response, &result.error) {
+        (Some(response), _) => {
+            for line in response.lines() {
+                println!("    {}", line);
+            }
+        }
+        (None, Some(error)) => println!("    {} {}", "Error:".bright_red(), error),
+        (None, None) => {}
+    }
+}
+
+async fn judge_pair(
+    executor: &AIExecutor,
+    judge: &str,
+    prompt: &str,
+    response_a: &str,
+    response_b: &str,
+    swapped: bool,
+) -> Result<Judgement> {
+    let (first, second) = if swapped { (response_b, response_a) } else { (response_a, response_b) };
+
+    let judge_prompt = format!(
+        "Compare two AI assistant responses to the same prompt.\n\n\
+         Prompt:\n{}\n\n\
+         Response A:\n{}\n\n\
+         Response B:\n{}\n\n\
+         Which response is more helpful, correct and clear? Reply with only a JSON object: \
+         {{\"winner\": \"A\", \"B\" or \"tie\", \"reason\": \"one sentence\"}}",
+        prompt, first, second
+    );
+
+    let messages = vec![Message {
+        role: "user".to_string(),
+        content: judge_prompt,
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    }];
+
+    let reply = executor.batch_complete(Some(judge), messages, Some(serde_json::json!({"temperature": 0})))
+        .await?
+        .message
+        .content;
+
+    let mut judgement: Judgement = parse_verdict(&reply)?;
+    if swapped {
+        judgement.winner = match judgement.winner {
+            Winner::A => Winner::B,
+            Winner::B => Winner::A,
+            Winner::Tie => Winner::Tie,
+        };
+    }
+
+    Ok(judgement)
+}
@@ -0,0 +1,47 @@
+//! --accessible: output that reads well with a screen reader or braille display.
+//!
+//! Colors are off and nothing is drawn for the eye alone: no spinner or
+//! progress bar redrawn in place, no rules, panels or gutters, and no glyph
+//! standing in for a word. Role changes and tool events are announced as
+//! whole lines starting with a fixed word ("Assistant:", "Tool request:",
+//! "Tool result:", "End of response."), in the order they happen.
+//!
+//! "accessibility.bell" rings the terminal bell when a reply is complete,
+//! with or without the rest of the mode.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::config::AccessibilityConfig;
+
+/// Width of the rules between sections when they are drawn
+const RULE_WIDTH: usize = 60;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static BELL: AtomicBool = AtomicBool::new(false);
+
+/// Applies the "accessibility" config for the whole process
+pub fn init(config: &AccessibilityConfig) {
+    ENABLED.store(config.enabled, Ordering::Relaxed);
+    BELL.store(config.bell, Ordering::Relaxed);
+    if config.enabled {
+        colored::control::set_override(false);
+    }
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A line of `character` between sections, or an empty line in accessible mode
+pub fn rule(character: char) -> String {
+    if enabled() { String::new() } else { character.to_string().repeat(RULE_WIDTH) }
+}
+
+/// Rings the bell, if configured, on stderr so that piped replies stay clean
+pub fn reply_complete() {
+    if BELL.load(Ordering::Relaxed) {
+        eprint!("\x07");
+        let _ = std::io::stderr().flush();
+    }
+}
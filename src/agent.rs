@@ -0,0 +1,158 @@
+//! The agent loop: the model calls tools itself until it can answer.
+//!
+//! In agent mode the tools prompt asks the model to reply with a ```tool
+//! block naming one tool and its arguments; with "toolCalling" set to
+//! "native" the tools are sent with the request instead and the model calls
+//! them through Ollama's `tool_calls`. The call goes through
+//! `McpManager::call_tool`, so the guardrail policy and read-only mode apply,
+//! its result is added as a "tool" message and the model is asked again,
+//! until it replies without a tool call or runs out of iterations. Each step
+//! is reported as an `Event`.
+//!
+//! The REPL's agent mode runs the same loop in `ChatCLI`, calling tools the
+//! way `/mcp-call` does so that its approvals and argument repair apply.
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::ops::Range;
+use std::time::Instant;
+
+use crate::error::Failure;
+use crate::executor::AIExecutor;
+use crate::mcp_manager::McpManager;
+use crate::ollama::Message;
+use crate::policy::{Approval, ApprovalRequest};
+
+pub const DEFAULT_MAX_ITERATIONS: usize = 10;
+
+/// A step of the loop, serialized as one line of `run --output-format jsonl`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Start { task: String, model: String },
+    /// A piece of the reply being generated
+    Token { text: String },
+    /// A reply of the model, tool call or not
+    Turn { iteration: usize, content: String },
+    ToolCall { iteration: usize, name: String, arguments: Value },
+    ToolResult { iteration: usize, name: String, output: String, is_error: bool, elapsed_ms: u64 },
+    Final { content: String, iterations: usize },
+    Error { message: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// The tool call in a reply: a ```tool block, or a ```json block shaped
+/// like one, holding {"name": ..., "arguments": {...}}
+pub fn parse_tool_call(reply: &str) -> Option<ToolCall> {
+    find_tool_call(reply).map(|(_, call)| call)
+}
+
+/// The tool a reply calls: its first native `tool_calls` entry, or else a
+/// block in its text
+pub fn reply_tool_call(reply: &Message) -> Option<ToolCall> {
+    match reply.tool_calls.first() {
+        Some(call) => Some(ToolCall {
+            name: call.function.name.clone(),
+            arguments: match &call.function.arguments {
+                Value::Null => Value::Object(Default::default()),
+                arguments => arguments.clone(),
+            },
+        }),
+        None => parse_tool_call(&reply.content),
+    }
+}
+
+/// Like `parse_tool_call`, along with where the block is in the reply
+pub fn find_tool_call(reply: &str) -> Option<(Range<usize>, ToolCall)> {
+    let block = Regex::new(r"(?s)```(tool|json)\s*\n(.*?)```").unwrap();
+    block.captures_iter(reply).find_map(|found| {
+        let value: Value = serde_json::from_str(found[2].trim()).ok()?;
+        let name = value["name"].as_str()?.to_string();
+        let arguments = match &value["arguments"] {
+            Value::Null => Value::Object(Default::default()),
+            arguments => arguments.clone(),
+        };
+        Some((found.get(0)?.range(), ToolCall { name, arguments }))
+    })
+}
+
+/// Asks the model and runs the tools it calls until it gives a final answer,
+/// adding every reply and result to `history`. Calls the policy wants
+/// approved are put to `approve` first. Failed and refused tool calls are
+/// reported back to the model rather than ending the loop.
+pub async fn run(
+    executor: &AIExecutor,
+    mcp: &mut McpManager,
+    history: &mut Vec<Message>,
+    max_iterations: usize,
+    mut on_event: impl FnMut(Event) + Send,
+    mut approve: impl AsyncFnMut(ApprovalRequest) -> Approval,
+) -> Result<String> {
+    for iteration in 1..=max_iterations {
+        let reply = executor.complete_with_tools(history.clone(), mcp.native_tools(), |text| on_event(Event::Token { text: text.to_string() })).await
+            .map_err(|e| match e.downcast_ref::<Failure>() {
+                // A spent budget keeps its own exit code
+                Some(Failure::BudgetExhausted) => e,
+                _ => e.context(Failure::Generation),
+            })?;
+        let content = reply.message.content.clone();
+        on_event(Event::Turn { iteration, content: content.clone() });
+        let call = reply_tool_call(&reply.message);
+        let mut tool_calls = reply.message.tool_calls;
+        // One call per reply, as with blocks, so every call recorded gets its result
+        tool_calls.truncate(1);
+        history.push(Message {
+            role: "assistant".to_string(),
+            content: content.clone(),
+            images: Vec::new(),
+            tool_calls,
+        });
+
+        let Some(call) = call else {
+            on_event(Event::Final { content: content.clone(), iterations: iteration });
+            return Ok(content);
+        };
+        on_event(Event::ToolCall { iteration, name: call.name.clone(), arguments: call.arguments.clone() });
+
+        if let Some(request) = mcp.approval_request(&call.name, &call.arguments) {
+            let approval = approve(request).await;
+            // A refusal makes the call below fail with the reason
+            mcp.decide(&call.name, approval);
+        }
+
+        let started = Instant::now();
+        let (output, images, is_error) = match mcp.call_tool(&call.name, call.arguments).await {
+            Ok(result) => {
+                let is_error = result.is_error == Some(true);
+                let (images, texts): (Vec<_>, Vec<_>) = result.content.into_iter()
+                    .filter(|c| c.content_type == "text" || c.content_type == "image")
+                    .partition(|c| c.content_type == "image");
+                let texts: Vec<String> = texts.into_iter().map(|c| c.text).collect();
+                (texts.join("\n"), images.into_iter().filter_map(|c| c.data).collect(), is_error)
+            }
+            Err(e) => (format!("Error: {:#}", e), Vec::new(), true),
+        };
+        on_event(Event::ToolResult {
+            iteration,
+            name: call.name,
+            output: output.clone(),
+            is_error,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        });
+        history.push(Message {
+            role: "tool".to_string(),
+            content: output,
+            images,
+            tool_calls: Vec::new(),
+        });
+    }
+
+    anyhow::bail!("No final answer after {} model replies", max_iterations)
+}
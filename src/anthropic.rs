@@ -0,0 +1,386 @@
+//! Chat replies from Claude through the Anthropic Messages API, selected
+//! with "chatBackend": "anthropic" in config.json or `/backend anthropic`.
+//!
+//! The conversation is kept in Ollama's shape everywhere else, so requests
+//! are translated here: system messages become the top-level `system`
+//! prompt, tool results become user turns (`tool_result` blocks when they
+//! answer a native tool call), turns of the same role are merged, and
+//! `max_tokens`, which the API requires, comes from "generation.numPredict"
+//! or "anthropic.maxTokens".
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+use crate::backend::ChatBackend;
+use crate::config::AnthropicConfig;
+use crate::error::Failure;
+use crate::ollama::{ChatResponse, FunctionCall, Message, ToolCall};
+use crate::secrets;
+
+pub const DEFAULT_MODEL: &str = "claude-sonnet-4-0";
+const DEFAULT_API_URL: &str = "https://api.anthropic.com";
+const API_VERSION: &str = "2023-06-01";
+/// Reply length when neither "generation.numPredict" nor "anthropic.maxTokens" is set
+const DEFAULT_MAX_TOKENS: u64 = 4096;
+/// What every current Claude model takes in; the API does not report it
+const CONTEXT_WINDOW: usize = 200_000;
+
+pub struct AnthropicClient {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    max_tokens: u64,
+}
+
+impl AnthropicClient {
+    /// Reads the key from "anthropic.apiKey" (usually a keyring: reference),
+    /// or from ANTHROPIC_API_KEY when that is unset
+    pub fn new(config: &AnthropicConfig) -> Result<Self> {
+        let api_key = match &config.api_key {
+            Some(key) => secrets::resolve(key)?,
+            None => std::env::var("ANTHROPIC_API_KEY").unwrap_or_default(),
+        };
+        if api_key.is_empty() {
+            anyhow::bail!("No Anthropic API key: set \"anthropic.apiKey\" in config.json (e.g. \"keyring:anthropic\") or ANTHROPIC_API_KEY");
+        }
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            base_url: config.api_url.as_deref().unwrap_or(DEFAULT_API_URL).trim_end_matches('/').to_string(),
+            api_key,
+            max_tokens: config.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        })
+    }
+
+    /// The Messages API request for a conversation and Ollama-style options
+    fn request(&self, model: &str, messages: &[Message], options: Option<&Value>, tools: Option<&[Value]>, stream: bool) -> Value {
+        let (system, messages) = translate(messages);
+        let mut request = json!({
+            "model": model,
+            "max_tokens": options.and_then(|o| o["num_predict"].as_u64()).unwrap_or(self.max_tokens),
+            "messages": messages,
+            "stream": stream,
+        });
+        if !system.is_empty() {
+            request["system"] = json!(system);
+        }
+        if let Some(options) = options {
+            for (from, to) in [("temperature", "temperature"), ("top_p", "top_p"), ("top_k", "top_k"), ("stop", "stop_sequences")] {
+                if !options[from].is_null() {
+                    request[to] = options[from].clone();
+                }
+            }
+        }
+        if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+            request["tools"] = tools.iter()
+                .map(|tool| json!({
+                    "name": tool["function"]["name"],
+                    "description": tool["function"]["description"],
+                    "input_schema": tool["function"]["parameters"],
+                }))
+                .collect();
+        }
+        request
+    }
+
+    async fn send(&self, request: &Value) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(request)
+            .send()
+            .await
+            .context("Failed to send request to the Anthropic API")
+            .context(Failure::Connection)?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+        Ok(response)
+    }
+
+    pub async fn complete(&self, model: &str, messages: Vec<Message>, options: Option<Value>) -> Result<ChatResponse> {
+        let request = self.request(model, &messages, options.as_ref(), None, false);
+        let reply: Value = self.send(&request).await?
+            .json()
+            .await
+            .context("Failed to parse Anthropic response")?;
+
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for block in reply["content"].as_array().into_iter().flatten() {
+            match block["type"].as_str() {
+                Some("text") => content.push_str(block["text"].as_str().unwrap_or_default()),
+                Some("tool_use") => tool_calls.push(tool_call(&block["name"], block["input"].clone())),
+                _ => {}
+            }
+        }
+        Ok(chat_response(content, tool_calls, reply["usage"]["input_tokens"].as_u64(), reply["usage"]["output_tokens"].as_u64()))
+    }
+
+    /// Like `complete`, but streams the reply as server-sent events, calling
+    /// `on_token` with each piece of text, and offers the model `tools` to call
+    pub async fn stream_complete(
+        &self,
+        model: &str,
+        messages: Vec<Message>,
+        options: Option<Value>,
+        tools: Option<Vec<Value>>,
+        mut on_token: impl FnMut(&str),
+    ) -> Result<ChatResponse> {
+        let request = self.request(model, &messages, options.as_ref(), tools.as_deref(), true);
+        let mut response = self.send(&request).await?;
+
+        let mut content = String::new();
+        // Tool calls arrive as a name, then their input as pieces of JSON, by block index
+        let mut calls: BTreeMap<u64, (Value, String)> = BTreeMap::new();
+        let (mut input_tokens, mut output_tokens) = (None, None);
+        let mut buffer = Vec::new();
+        while let Some(chunk) = response.chunk().await.context("Reply interrupted").context(Failure::Connection)? {
+            buffer.extend_from_slice(&chunk);
+
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim_end().strip_prefix("data:") else {
+                    continue;
+                };
+                let Ok(event) = serde_json::from_str::<Value>(data.trim_start()) else {
+                    continue;
+                };
+
+                match event["type"].as_str().unwrap_or_default() {
+                    "message_start" => input_tokens = event["message"]["usage"]["input_tokens"].as_u64(),
+                    "content_block_start" if event["content_block"]["type"] == "tool_use" => {
+                        let index = event["index"].as_u64().unwrap_or_default();
+                        calls.insert(index, (event["content_block"]["name"].clone(), String::new()));
+                    }
+                    "content_block_delta" => {
+                        let delta = &event["delta"];
+                        if let Some(text) = delta["text"].as_str().filter(|text| !text.is_empty()) {
+                            on_token(text);
+                            content.push_str(text);
+                        } else if let Some(json) = delta["partial_json"].as_str()
+                            && let Some((_, input)) = calls.get_mut(&event["index"].as_u64().unwrap_or_default())
+                        {
+                            input.push_str(json);
+                        }
+                    }
+                    "message_delta" => output_tokens = event["usage"]["output_tokens"].as_u64().or(output_tokens),
+                    "message_stop" => {
+                        let tool_calls = calls.into_values()
+                            .map(|(name, input)| {
+                                // A call without arguments streams no input at all
+                                let arguments = serde_json::from_str(&input).unwrap_or_else(|_| json!({}));
+                                tool_call(&name, arguments)
+                            })
+                            .collect();
+                        return Ok(chat_response(content, tool_calls, input_tokens, output_tokens));
+                    }
+                    "error" => {
+                        return Err(anyhow::anyhow!("Anthropic API error: {}", event["error"]["message"].as_str().unwrap_or("unknown error"))
+                            .context(Failure::Generation));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Err(anyhow::anyhow!("Reply ended before it was complete").context(Failure::Connection))
+    }
+
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self
+            .client
+            .get(format!("{}/v1/models", self.base_url))
+            .query(&[("limit", "1000")])
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .send()
+            .await
+            .context("Failed to list models")
+            .context(Failure::Connection)?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+        let data: Value = response.json().await?;
+        Ok(data["data"].as_array()
+            .map(|models| models.iter().filter_map(|m| m["id"].as_str().map(String::from)).collect())
+            .unwrap_or_default())
+    }
+
+    /// Asks for the model by name, since aliases such as "claude-sonnet-4-0"
+    /// are accepted by the API but listed only under their dated ids
+    pub async fn has_model(&self, model: &str) -> Result<bool> {
+        let response = self
+            .client
+            .get(format!("{}/v1/models/{}", self.base_url, model))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .send()
+            .await
+            .context("Failed to look up the model")
+            .context(Failure::Connection)?;
+
+        match response.status() {
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            _ => Err(api_error(response).await),
+        }
+    }
+}
+
+#[async_trait]
+impl ChatBackend for AnthropicClient {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    async fn chat(&self, model: &str, messages: Vec<Message>, options: Option<Value>) -> Result<ChatResponse> {
+        self.complete(model, messages, options).await
+    }
+
+    async fn stream(
+        &self,
+        model: &str,
+        messages: Vec<Message>,
+        options: Option<Value>,
+        tools: Option<Vec<Value>>,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<ChatResponse> {
+        self.stream_complete(model, messages, options, tools, |text| on_token(text)).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        AnthropicClient::list_models(self).await
+    }
+
+    async fn has_model(&self, model: &str) -> Result<bool> {
+        AnthropicClient::has_model(self, model).await
+    }
+
+    async fn context_window(&self, _model: &str) -> Result<Option<usize>> {
+        Ok(Some(CONTEXT_WINDOW))
+    }
+}
+
+/// The system prompt and the Messages API turns for an Ollama-style conversation
+fn translate(messages: &[Message]) -> (String, Vec<Value>) {
+    let mut system = Vec::new();
+    let mut turns: Vec<(&str, Vec<Value>)> = Vec::new();
+    // Ids of native tool calls not yet answered, in the order tool messages answer them
+    let mut open_calls: Vec<String> = Vec::new();
+
+    for (index, message) in messages.iter().enumerate() {
+        let mut blocks: Vec<Value> = message.images.iter().map(|image| image_block(image)).collect();
+        let role = match message.role.as_str() {
+            "system" => {
+                system.push(message.content.as_str());
+                continue;
+            }
+            "assistant" => {
+                if !message.content.trim().is_empty() {
+                    blocks.push(json!({ "type": "text", "text": message.content }));
+                }
+                for (n, call) in message.tool_calls.iter().enumerate() {
+                    let id = format!("toolu_{}_{}", index, n);
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": id,
+                        "name": call.function.name,
+                        "input": if call.function.arguments.is_object() { call.function.arguments.clone() } else { json!({}) },
+                    }));
+                    open_calls.push(id);
+                }
+                "assistant"
+            }
+            "tool" => {
+                match (!open_calls.is_empty()).then(|| open_calls.remove(0)) {
+                    Some(id) => blocks.insert(0, json!({ "type": "tool_result", "tool_use_id": id, "content": message.content })),
+                    None => blocks.push(json!({ "type": "text", "text": format!("Tool result:\n{}", message.content) })),
+                }
+                "user"
+            }
+            _ => {
+                // Every tool_use needs its result, even a call that was never run
+                for (n, id) in open_calls.drain(..).enumerate() {
+                    blocks.insert(n, json!({ "type": "tool_result", "tool_use_id": id, "content": "The tool was not run", "is_error": true }));
+                }
+                if !message.content.trim().is_empty() {
+                    blocks.push(json!({ "type": "text", "text": message.content }));
+                }
+                "user"
+            }
+        };
+        if blocks.is_empty() {
+            continue;
+        }
+
+        match turns.last_mut() {
+            Some((last, content)) if *last == role => content.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+
+    let turns = turns.into_iter()
+        .map(|(role, content)| json!({ "role": role, "content": content }))
+        .collect();
+    (system.join("\n\n"), turns)
+}
+
+/// A base64 image, its type told from the first bytes the encoding shows
+fn image_block(data: &str) -> Value {
+    let media_type = if data.starts_with("/9j/") {
+        "image/jpeg"
+    } else if data.starts_with("R0lGOD") {
+        "image/gif"
+    } else if data.starts_with("UklGR") {
+        "image/webp"
+    } else {
+        "image/png"
+    };
+    json!({ "type": "image", "source": { "type": "base64", "media_type": media_type, "data": data } })
+}
+
+fn tool_call(name: &Value, arguments: Value) -> ToolCall {
+    ToolCall {
+        function: FunctionCall { name: name.as_str().unwrap_or_default().to_string(), arguments },
+    }
+}
+
+fn chat_response(content: String, tool_calls: Vec<ToolCall>, prompt_tokens: Option<u64>, completion_tokens: Option<u64>) -> ChatResponse {
+    ChatResponse {
+        message: Message {
+            role: "assistant".to_string(),
+            content,
+            images: Vec::new(),
+            tool_calls,
+        },
+        done: true,
+        prompt_eval_count: prompt_tokens,
+        eval_count: completion_tokens,
+        cached: false,
+    }
+}
+
+async fn api_error(response: reqwest::Response) -> anyhow::Error {
+    let status = response.status();
+    let failure = match status {
+        reqwest::StatusCode::NOT_FOUND => Failure::ModelMissing,
+        _ => Failure::Generation,
+    };
+
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    let message = body["error"]["message"].as_str().map(String::from).unwrap_or_else(|| status.to_string());
+    let hint = if status == reqwest::StatusCode::UNAUTHORIZED {
+        "; check \"anthropic.apiKey\" or ANTHROPIC_API_KEY"
+    } else {
+        ""
+    };
+    anyhow::anyhow!("Anthropic API error: {}{}", message, hint).context(failure)
+}
@@ -0,0 +1,460 @@
+//! The list_archive and extract_archive tools, for zip, tar and tar.gz
+//! files, recognized by their first bytes rather than their names.
+//!
+//! Extraction stays inside the destination: entries named with `..` or an
+//! absolute path are refused, links, devices, FIFOs and other entries that
+//! are not regular files are skipped, and a file is only written
+//! where its directory resolves to somewhere below the destination. The
+//! bytes actually unpacked are counted against `max_bytes`, whatever sizes
+//! the archive claims, so a zip bomb stops at the limit. Existing files are
+//! kept unless `overwrite` is set, and every file written is recorded in
+//! the journal, so `/rollback` undoes an extraction.
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use serde_json::{Value, json};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+/// Unpacked bytes one extraction may write unless `max_bytes` says otherwise
+pub const DEFAULT_MAX_BYTES: u64 = 100 * 1024 * 1024;
+/// Entries one extraction may write
+const MAX_ENTRIES: usize = 10_000;
+/// Entries shown by list_archive as text before the rest are counted
+const MAX_LISTED: usize = 1000;
+
+const BLOCK: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Format {
+    pub fn detect(path: &Path) -> Result<Self> {
+        let mut head = Vec::new();
+        fs::File::open(path)
+            .context(format!("Failed to open {}", path.display()))?
+            .take(BLOCK as u64)
+            .read_to_end(&mut head)?;
+
+        if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+            Ok(Format::Zip)
+        } else if head.starts_with(b"\x1F\x8B") {
+            Ok(Format::TarGz)
+        } else if head.get(257..262) == Some(b"ustar") {
+            Ok(Format::Tar)
+        } else {
+            anyhow::bail!("{} is not a zip, tar or tar.gz archive", path.display())
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::Zip => "zip",
+            Format::Tar => "tar",
+            Format::TarGz => "tar.gz",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Dir,
+    Link,
+    /// Anything else, such as a device or a FIFO, named for messages
+    Special(&'static str),
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    pub kind: Kind,
+    /// Unpacked size, as the archive states it
+    pub size: u64,
+    /// Where a link points
+    pub target: Option<String>,
+}
+
+/// Every entry of the archive, in archive order
+pub fn list(path: &Path) -> Result<(Format, Vec<Entry>)> {
+    let format = Format::detect(path)?;
+    let mut entries = Vec::new();
+    visit(path, format, |entry, _| {
+        entries.push(entry);
+        Ok(())
+    })?;
+    Ok((format, entries))
+}
+
+pub fn list_text(path: &Path, format: Format, entries: &[Entry]) -> String {
+    let total: u64 = entries.iter().filter(|e| e.kind == Kind::File).map(|e| e.size).sum();
+    let mut text = format!("{}: {}, {} entries, {} bytes unpacked\n", path.display(), format.name(), entries.len(), total);
+    for entry in entries.iter().take(MAX_LISTED) {
+        text.push_str(&match entry.kind {
+            Kind::File => format!("{:>12}  {}\n", entry.size, entry.name),
+            Kind::Dir => format!("{:>12}  {}\n", "DIR", entry.name),
+            Kind::Link => format!("{:>12}  {} -> {}\n", "LINK", entry.name, entry.target.as_deref().unwrap_or("?")),
+            Kind::Special(what) => format!("{:>12}  {} ({})\n", "SPECIAL", entry.name, what),
+        });
+    }
+    if entries.len() > MAX_LISTED {
+        text.push_str(&format!("(+{} more entries; format json lists them all)\n", entries.len() - MAX_LISTED));
+    }
+    text
+}
+
+pub fn list_json(entries: &[Entry]) -> Value {
+    entries.iter()
+        .map(|entry| json!({
+            "name": entry.name,
+            "type": match entry.kind {
+                Kind::File => "file",
+                Kind::Dir => "dir",
+                Kind::Link => "link",
+                Kind::Special(_) => "special",
+            },
+            "size": entry.size,
+            "target": entry.target,
+            "special": match entry.kind {
+                Kind::Special(what) => Some(what),
+                _ => None,
+            },
+        }))
+        .collect()
+}
+
+pub struct ExtractOptions<'a> {
+    /// Names of entries to extract, a directory taking everything below it; all when empty
+    pub only: &'a [String],
+    pub overwrite: bool,
+    pub max_bytes: u64,
+}
+
+#[derive(Default)]
+pub struct ExtractReport {
+    pub files: Vec<PathBuf>,
+    pub bytes: u64,
+    /// Entry names and why they were not extracted
+    pub skipped: Vec<(String, String)>,
+}
+
+impl ExtractReport {
+    pub fn text(&self, destination: &Path) -> String {
+        let mut text = format!("Extracted {} file(s), {} bytes, to {}\n", self.files.len(), self.bytes, destination.display());
+        for (name, reason) in &self.skipped {
+            text.push_str(&format!("  skipped {}: {}\n", name, reason));
+        }
+        text
+    }
+}
+
+/// Unpacks the archive's entries below `destination`, creating it if needed
+pub fn extract(path: &Path, destination: &Path, options: &ExtractOptions) -> Result<ExtractReport> {
+    let format = Format::detect(path)?;
+    fs::create_dir_all(destination)
+        .context(format!("Failed to create {}", destination.display()))?;
+    let root = fs::canonicalize(destination)?;
+    let mut report = ExtractReport::default();
+
+    visit(path, format, |entry, data| {
+        let selected = options.only.is_empty() || options.only.iter().any(|only| {
+            let only = only.trim_end_matches('/');
+            entry.name.trim_end_matches('/') == only || entry.name.starts_with(&format!("{}/", only))
+        });
+        if !selected {
+            return Ok(());
+        }
+        let Some(relative) = confined(&entry.name) else {
+            report.skipped.push((entry.name, "outside the destination".to_string()));
+            return Ok(());
+        };
+        let target = root.join(&relative);
+        match entry.kind {
+            Kind::Link => {
+                report.skipped.push((entry.name, "links are not extracted".to_string()));
+                return Ok(());
+            }
+            Kind::Special(what) => {
+                report.skipped.push((entry.name, format!("not a regular file ({})", what)));
+                return Ok(());
+            }
+            Kind::File | Kind::Dir => {}
+        }
+        // A directory already there may be a link to elsewhere
+        if !inside(&target, &root) {
+            report.skipped.push((entry.name, "outside the destination".to_string()));
+            return Ok(());
+        }
+        if entry.kind == Kind::Dir {
+            fs::create_dir_all(&target)?;
+            return Ok(());
+        }
+
+        if report.files.len() >= MAX_ENTRIES {
+            anyhow::bail!("Stopped after {} files; extract fewer entries at a time", MAX_ENTRIES);
+        }
+        if target.is_dir() {
+            report.skipped.push((entry.name, "a directory is in the way".to_string()));
+            return Ok(());
+        }
+        if target.exists() && !options.overwrite {
+            report.skipped.push((entry.name, "already exists (set overwrite)".to_string()));
+            return Ok(());
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        crate::journal::record(&target);
+        let remaining = options.max_bytes.saturating_sub(report.bytes);
+        let mut file = fs::File::create(&target)
+            .context(format!("Failed to create {}", target.display()))?;
+        let written = io::copy(&mut data.take(remaining.saturating_add(1)), &mut file)
+            .context(format!("Failed to extract {}", entry.name))?;
+        if written > remaining {
+            drop(file);
+            let _ = fs::remove_file(&target);
+            anyhow::bail!("Stopped at {}: the archive unpacks to more than {} bytes (max_bytes); {} file(s) were extracted",
+                entry.name, options.max_bytes, report.files.len());
+        }
+        report.bytes += written;
+        report.files.push(relative);
+        Ok(())
+    })?;
+
+    Ok(report)
+}
+
+/// `name` as a path below the destination, or `None` when it would land elsewhere
+fn confined(name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!path.as_os_str().is_empty()).then_some(path)
+}
+
+/// Whether `path`, or the part of it that exists, resolves to `root` or
+/// below it, following links; a dangling link does not
+fn inside(path: &Path, root: &Path) -> bool {
+    path.ancestors()
+        .find(|ancestor| ancestor.symlink_metadata().is_ok())
+        .and_then(|existing| fs::canonicalize(existing).ok())
+        .is_some_and(|existing| existing.starts_with(root))
+}
+
+/// Calls `on_entry` with each entry and a reader of its content
+fn visit(path: &Path, format: Format, mut on_entry: impl FnMut(Entry, &mut dyn Read) -> Result<()>) -> Result<()> {
+    let file = fs::File::open(path)
+        .context(format!("Failed to open {}", path.display()))?;
+    match format {
+        Format::Zip => {
+            let mut archive = zip::ZipArchive::new(file)
+                .context(format!("Failed to read {} as a zip archive", path.display()))?;
+            for index in 0..archive.len() {
+                let mut item = archive.by_index(index)?;
+                let kind = if item.is_dir() {
+                    Kind::Dir
+                } else if item.is_symlink() {
+                    Kind::Link
+                } else {
+                    Kind::File
+                };
+                let target = if kind == Kind::Link {
+                    let mut target = String::new();
+                    item.read_to_string(&mut target)?;
+                    Some(target)
+                } else {
+                    None
+                };
+                let entry = Entry { name: item.name().to_string(), kind, size: item.size(), target };
+                on_entry(entry, &mut item)?;
+            }
+            Ok(())
+        }
+        Format::Tar => visit_tar(io::BufReader::new(file), &mut on_entry)
+            .context(format!("Failed to read {} as a tar archive", path.display())),
+        Format::TarGz => visit_tar(GzDecoder::new(io::BufReader::new(file)), &mut on_entry)
+            .context(format!("Failed to read {} as a tar.gz archive", path.display())),
+    }
+}
+
+/// The entries of a tar stream, with the names of GNU long name entries and
+/// pax `path` records; pax global headers are skipped
+fn visit_tar(reader: impl Read, on_entry: &mut dyn FnMut(Entry, &mut dyn Read) -> Result<()>) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for item in archive.entries()? {
+        let mut item = item?;
+        let type_flag = item.header().entry_type().as_byte();
+        if type_flag == b'g' {
+            continue;
+        }
+        let kind = match type_flag {
+            b'0' | b'\0' | b'7' => Kind::File,
+            b'5' => Kind::Dir,
+            b'1' | b'2' => Kind::Link,
+            b'3' => Kind::Special("character device"),
+            b'4' => Kind::Special("block device"),
+            b'6' => Kind::Special("FIFO"),
+            b'S' => Kind::Special("GNU sparse file"),
+            b'V' => Kind::Special("volume label"),
+            _ => Kind::Special("unknown entry type"),
+        };
+        let name = String::from_utf8_lossy(&item.path_bytes()).to_string();
+        let target = match kind {
+            Kind::Link => item.link_name_bytes().map(|target| String::from_utf8_lossy(&target).to_string()),
+            _ => None,
+        };
+        let size = if kind == Kind::File { item.size() } else { 0 };
+        on_entry(Entry { name, kind, size, target }, &mut item)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    const OPTIONS: ExtractOptions = ExtractOptions { only: &[], overwrite: false, max_bytes: DEFAULT_MAX_BYTES };
+
+    /// A file header with `name` written as is, which `tar::Builder` would refuse for `..` or `/`
+    fn raw_header(name: &str, size: u64) -> tar::Header {
+        let mut header = tar::Header::new_ustar();
+        header.as_ustar_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_size(size);
+        header.set_mode(0o644);
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_cksum();
+        header
+    }
+
+    fn file_header(size: u64) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(size);
+        header.set_mode(0o644);
+        header
+    }
+
+    fn write_archive(dir: &TempDir, build: impl FnOnce(&mut tar::Builder<Vec<u8>>)) -> PathBuf {
+        let mut builder = tar::Builder::new(Vec::new());
+        build(&mut builder);
+        let path = dir.path().join("test.tar");
+        fs::write(&path, builder.into_inner().unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_gnu_long_names_and_link_targets() {
+        let dir = TempDir::new().unwrap();
+        let long_name = format!("{}/file.txt", "d".repeat(120));
+        let long_target = format!("{}/target", "t".repeat(120));
+        let archive = write_archive(&dir, |builder| {
+            builder.append_data(&mut file_header(5), &long_name, &b"hello"[..]).unwrap();
+            let mut link = tar::Header::new_gnu();
+            link.set_entry_type(tar::EntryType::Symlink);
+            link.set_size(0);
+            builder.append_link(&mut link, "link", &long_target).unwrap();
+        });
+
+        let (format, entries) = list(&archive).unwrap();
+        assert_eq!(format, Format::Tar);
+        assert_eq!(entries[0].name, long_name);
+        assert_eq!((entries[0].kind, entries[0].size), (Kind::File, 5));
+        assert_eq!(entries[1].kind, Kind::Link);
+        assert_eq!(entries[1].target.as_deref(), Some(long_target.as_str()));
+
+        let destination = dir.path().join("out");
+        let report = extract(&archive, &destination, &OPTIONS).unwrap();
+        assert_eq!(fs::read(destination.join(&long_name)).unwrap(), b"hello");
+        assert_eq!(report.skipped.len(), 1);
+        assert!(!destination.join("link").exists());
+    }
+
+    #[test]
+    fn reads_pax_path_records() {
+        let dir = TempDir::new().unwrap();
+        let archive = write_archive(&dir, |builder| {
+            builder.append_pax_extensions([("path", &b"pax/named.txt"[..])]).unwrap();
+            builder.append_data(&mut file_header(3), "short.txt", &b"pax"[..]).unwrap();
+        });
+
+        let (_, entries) = list(&archive).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "pax/named.txt");
+
+        let destination = dir.path().join("out");
+        extract(&archive, &destination, &OPTIONS).unwrap();
+        assert_eq!(fs::read(destination.join("pax/named.txt")).unwrap(), b"pax");
+    }
+
+    #[test]
+    fn skips_names_outside_the_destination() {
+        let dir = TempDir::new().unwrap();
+        let escape = dir.path().join("escaped.txt");
+        let absolute = escape.to_string_lossy().to_string();
+        let archive = write_archive(&dir, |builder| {
+            builder.append(&raw_header("../escaped.txt", 4), &b"evil"[..]).unwrap();
+            builder.append(&raw_header("a/../../escaped.txt", 4), &b"evil"[..]).unwrap();
+            builder.append(&raw_header(&absolute, 4), &b"evil"[..]).unwrap();
+            builder.append(&raw_header("kept.txt", 2), &b"ok"[..]).unwrap();
+        });
+
+        let destination = dir.path().join("out");
+        let report = extract(&archive, &destination, &OPTIONS).unwrap();
+        assert!(!escape.exists());
+        assert_eq!(report.skipped.len(), 3);
+        assert!(report.skipped.iter().all(|(_, reason)| reason == "outside the destination"));
+        assert_eq!(report.files, [PathBuf::from("kept.txt")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn does_not_follow_a_linked_directory_out_of_the_destination() {
+        let dir = TempDir::new().unwrap();
+        let outside = dir.path().join("outside");
+        let destination = dir.path().join("out");
+        fs::create_dir_all(&outside).unwrap();
+        fs::create_dir_all(&destination).unwrap();
+        std::os::unix::fs::symlink(&outside, destination.join("linked")).unwrap();
+        let archive = write_archive(&dir, |builder| {
+            builder.append_data(&mut file_header(4), "linked/evil.txt", &b"evil"[..]).unwrap();
+        });
+
+        let report = extract(&archive, &destination, &OPTIONS).unwrap();
+        assert!(!outside.join("evil.txt").exists());
+        assert_eq!(report.skipped[0].1, "outside the destination");
+    }
+
+    #[test]
+    fn stops_at_max_bytes_whatever_the_archive_claims() {
+        let dir = TempDir::new().unwrap();
+        let tar = write_archive(&dir, |builder| {
+            builder.append_data(&mut file_header(6), "small.txt", &b"small\n"[..]).unwrap();
+            builder.append_data(&mut file_header(1000), "large.txt", &[b'x'; 1000][..]).unwrap();
+        });
+        let archive = dir.path().join("test.tar.gz");
+        let mut encoder = flate2::write::GzEncoder::new(fs::File::create(&archive).unwrap(), flate2::Compression::default());
+        encoder.write_all(&fs::read(&tar).unwrap()).unwrap();
+        encoder.finish().unwrap();
+
+        let destination = dir.path().join("out");
+        let options = ExtractOptions { max_bytes: 100, ..OPTIONS };
+        let Err(error) = extract(&archive, &destination, &options) else {
+            panic!("extracted past max_bytes");
+        };
+        assert!(format!("{:#}", error).contains("more than 100 bytes"));
+        assert!(destination.join("small.txt").exists());
+        assert!(!destination.join("large.txt").exists());
+    }
+}
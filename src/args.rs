@@ -0,0 +1,394 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::import::ImportSource;
+use crate::training::TrainingFormat;
+
+#[derive(Debug, Parser)]
+#[command(name = "ai-chat-cli", version, about = "AI chat in your terminal, powered by Ollama")]
+pub struct Args {
+    /// Start the full-screen terminal interface instead of the line-based REPL
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Pull the model without asking when it is not installed
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Queue prompts of a piped session that fail to send, for a later `flush-queue`
+    #[arg(long)]
+    pub queue_on_failure: bool,
+
+    /// Summarize large tool results with the "toolSummary" model before adding them to the conversation
+    #[arg(long)]
+    pub summarize_tool_output: bool,
+
+    /// Add a map of the current repository's files and public symbols as context
+    #[arg(long)]
+    pub repo_map: bool,
+
+    /// Serve editor plugins with JSON-RPC over stdin and stdout instead of the REPL
+    #[arg(long)]
+    pub stdio_api: bool,
+
+    /// Let the model call tools itself, running them until it gives a final answer
+    #[arg(long)]
+    pub agent: bool,
+
+    /// Refuse tools that change anything: file edits, and shell commands other than reading ones
+    #[arg(long)]
+    pub read_only: bool,
+
+    /// Load the model in the background at startup and after /model, so the first prompt does not wait for it
+    #[arg(long)]
+    pub warm_up: bool,
+
+    /// Command run after each tool call that changes files, e.g. "cargo check"
+    #[arg(long, value_name = "COMMAND")]
+    pub verify: Option<String>,
+
+    /// Print only the assistant's replies: no banners, status lines or colors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Disable colors and other ANSI styling
+    #[arg(long, global = true)]
+    pub plain: bool,
+
+    /// Screen reader friendly output: no colors, spinners or drawing, and one line per role change or tool event
+    #[arg(long, global = true)]
+    pub accessible: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run prompts from a JSONL file and write one JSON result per line
+    Batch(BatchArgs),
+
+    /// Score a model against a YAML suite of prompts using a judge model
+    Eval(EvalArgs),
+
+    /// Re-run the user turns of a saved conversation and diff the new responses
+    Replay(ReplayArgs),
+
+    /// Compare two system prompts on the same prompt set
+    Ab(AbArgs),
+
+    /// Convert saved conversations into a fine-tuning dataset
+    ExportTraining(ExportTrainingArgs),
+
+    /// Convert ChatGPT or Claude exports into saved sessions
+    Import(ImportArgs),
+
+    /// Run the prompts configured under "schedules" on their cron schedules
+    Schedule(ScheduleArgs),
+
+    /// Re-run a prompt against the current diff whenever files change
+    Watch(WatchArgs),
+
+    /// Retry prompts queued by --queue-on-failure while Ollama was unreachable
+    FlushQueue(FlushQueueArgs),
+
+    /// Manage secrets in the OS credential store, referenced as keyring:<name>
+    Secret(SecretArgs),
+
+    /// Propose code to insert at a position in a file, from a fill-in-the-middle model
+    Fim(FimArgs),
+
+    /// Run a command and, if it fails, print the model's explanation and a fix
+    Explain(ExplainArgs),
+
+    /// Suggest one shell command for a task and run it after confirming
+    Cmd(CmdArgs),
+
+    /// Work on a task with tools, without interaction, reporting each step
+    Run(RunArgs),
+
+    /// Keep the model, tools and conversation warm in the background
+    Daemon(DaemonArgs),
+
+    /// Chat with the running daemon; detaching leaves the conversation there
+    Attach,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct BatchArgs {
+    /// Input file with one JSON object per line ({"prompt": ..., "model": ..., "options": ...})
+    pub input: PathBuf,
+
+    /// File to write results to (default: stdout)
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    /// Number of items processed at the same time
+    #[arg(long, default_value_t = 4)]
+    pub concurrency: usize,
+
+    /// Model used for items that do not specify one
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// File whose contents are sent as the system prompt for every item
+    #[arg(long)]
+    pub system: Option<PathBuf>,
+
+    /// Conversation (JSON array of messages, e.g. from /save) placed before every prompt
+    #[arg(long)]
+    pub few_shot: Option<PathBuf>,
+
+    /// Continue an interrupted run, skipping answered items and appending to --out
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Always query the model instead of reusing cached or duplicate responses
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Queue failed items for a later `flush-queue`
+    #[arg(long)]
+    pub queue_on_failure: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct EvalArgs {
+    /// YAML suite with cases of prompts and criteria
+    pub suite: PathBuf,
+
+    /// Model under test (default: the suite's model, then the default model)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Model that scores responses (default: the suite's judge, then the model under test)
+    #[arg(long)]
+    pub judge: Option<String>,
+
+    /// File to write the full JSON report to
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ReplayArgs {
+    /// Conversation saved with /save
+    pub session: PathBuf,
+
+    /// Model to replay against (default: the default model)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Show unchanged lines as well as differences
+    #[arg(long)]
+    pub full: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct AbArgs {
+    /// System prompt for variant A
+    #[arg(long)]
+    pub system_a: PathBuf,
+
+    /// System prompt for variant B
+    #[arg(long)]
+    pub system_b: PathBuf,
+
+    /// JSONL prompts in the batch input format
+    #[arg(long)]
+    pub prompts: PathBuf,
+
+    /// Model that answers the prompts (default: the default model)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Model that picks the better response for each prompt
+    #[arg(long)]
+    pub judge: Option<String>,
+
+    /// File to write per-prompt JSONL results to
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExportTrainingArgs {
+    /// Saved session files, or directories containing them
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// JSONL file to write
+    #[arg(long)]
+    pub out: PathBuf,
+
+    /// Record format
+    #[arg(long, value_enum, default_value = "openai")]
+    pub format: TrainingFormat,
+
+    /// Only export sessions carrying this tag
+    #[arg(long)]
+    pub tag: Option<String>,
+
+    /// Minimum number of assistant replies a session needs to be exported
+    #[arg(long, default_value_t = 1)]
+    pub min_turns: usize,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ImportArgs {
+    /// Service the export comes from
+    #[arg(long, value_enum)]
+    pub from: ImportSource,
+
+    /// Export archive (.zip) or its conversations.json
+    pub path: PathBuf,
+
+    /// Directory to write the converted sessions to
+    #[arg(long, default_value = "imported")]
+    pub out_dir: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ScheduleArgs {
+    /// List configured jobs and their next run time, then exit
+    #[arg(long, conflicts_with = "run")]
+    pub list: bool,
+
+    /// Run a single job immediately, then exit
+    #[arg(long, value_name = "NAME")]
+    pub run: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct WatchArgs {
+    /// Instruction sent along with the diff (e.g. "review this diff")
+    pub prompt: String,
+
+    /// Files or directories to watch
+    #[arg(long, num_args = 1.., default_value = ".")]
+    pub paths: Vec<PathBuf>,
+
+    /// Model to use instead of the default
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Milliseconds to wait for changes to settle before re-running
+    #[arg(long, default_value_t = 500)]
+    pub debounce: u64,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct FlushQueueArgs {
+    /// List queued prompts without sending them
+    #[arg(long)]
+    pub list: bool,
+
+    /// File to append one JSON result per prompt to (default: responses on stdout)
+    #[arg(long, conflicts_with = "list")]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct FimArgs {
+    /// File to complete
+    #[arg(long)]
+    pub file: PathBuf,
+
+    /// Line to insert at, counting from 1; one past the last line appends
+    #[arg(long)]
+    pub line: usize,
+
+    /// Column to insert at, counting from 1 (default: the start of the line)
+    #[arg(long, default_value_t = 1)]
+    pub column: usize,
+
+    /// FIM-capable model such as qwen2.5-coder or codellama:code (default: the chat model)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Write the completion into the file after confirming
+    #[arg(long)]
+    pub apply: bool,
+
+    /// With --apply, write without asking
+    #[arg(long, requires = "apply")]
+    pub yes: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExplainArgs {
+    /// Model to use instead of the default
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Command to run: a program and its arguments, or one quoted shell command line
+    #[arg(last = true, required = true)]
+    pub command: Vec<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct CmdArgs {
+    /// What the command should do, e.g. "find large files modified today"
+    #[arg(required = true)]
+    pub description: Vec<String>,
+
+    /// Model to use instead of the default
+    #[arg(long)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RunArgs {
+    /// What to do, e.g. "fix the failing tests"
+    #[arg(long)]
+    pub task: String,
+
+    /// How steps are reported on stdout
+    #[arg(long, value_enum, default_value = "text")]
+    pub output_format: RunOutputFormat,
+
+    /// Model to use instead of the default
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Model replies before giving up on a final answer
+    #[arg(long, default_value_t = crate::agent::DEFAULT_MAX_ITERATIONS)]
+    pub max_iterations: usize,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DaemonArgs {
+    /// Start the daemon detached from this terminal, logging to ~/.ai-chat-cli/daemon.log
+    #[arg(long, conflicts_with = "stop")]
+    pub background: bool,
+
+    /// Stop the running daemon
+    #[arg(long)]
+    pub stop: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RunOutputFormat {
+    /// Progress on stderr and the final answer on stdout
+    Text,
+    /// One JSON event per line: model turns, tool calls, results and the answer
+    Jsonl,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct SecretArgs {
+    #[command(subcommand)]
+    pub command: SecretCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SecretCommand {
+    /// Store a secret, read from the terminal or stdin
+    Set { name: String },
+
+    /// Print a stored secret
+    Get { name: String },
+
+    /// Delete a stored secret
+    Rm { name: String },
+}
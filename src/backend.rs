@@ -0,0 +1,77 @@
+//! The provider behind chat replies. `AIExecutor` talks to a `ChatBackend`
+//! rather than to Ollama directly, so another provider, or a stand-in that
+//! answers from a script, can take its place without touching the REPL.
+//!
+//! Only chat, streaming and the model list are required. What only some
+//! providers can do, such as reporting the context window or loading a
+//! model ahead of time, has a default that does nothing or says it is not
+//! supported.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::anthropic::{self, AnthropicClient};
+use crate::config::{AppConfig, Provider};
+use crate::ollama::{self, ChatResponse, Message, OllamaClient};
+
+#[async_trait]
+pub trait ChatBackend: Send + Sync {
+    /// Shown in messages, e.g. "ollama"
+    fn name(&self) -> &'static str;
+
+    /// The whole reply to `messages` at once
+    async fn chat(&self, model: &str, messages: Vec<Message>, options: Option<Value>) -> Result<ChatResponse>;
+
+    /// The reply to `messages`, calling `on_token` with each piece as it
+    /// arrives; `tools` are definitions the model may call
+    async fn stream(
+        &self,
+        model: &str,
+        messages: Vec<Message>,
+        options: Option<Value>,
+        tools: Option<Vec<Value>>,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<ChatResponse>;
+
+    /// Names of the models that can be chatted with
+    async fn list_models(&self) -> Result<Vec<String>>;
+
+    /// Whether `model` can be chatted with; by default, whether a listed
+    /// model starts with it, so "llama3" finds "llama3:8b"
+    async fn has_model(&self, model: &str) -> Result<bool> {
+        Ok(self.list_models().await?.iter().any(|m| m.starts_with(model)))
+    }
+
+    /// Tokens `model` can take in, when the provider says
+    async fn context_window(&self, _model: &str) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    /// Loads `model` so that the next request does not wait for it
+    async fn preload(&self, _model: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// The text between `prefix` and `suffix`, from a fill-in-the-middle model
+    async fn fill_in_middle(&self, _model: &str, _prefix: &str, _suffix: &str, _options: Option<Value>) -> Result<String> {
+        anyhow::bail!("Fill-in-the-middle completion is not supported by the {} backend", self.name())
+    }
+}
+
+/// The backend for `provider`, set up from config.json
+pub fn connect(provider: Provider, config: &AppConfig) -> Result<Arc<dyn ChatBackend>> {
+    Ok(match provider {
+        Provider::Ollama => Arc::new(OllamaClient::new().with_keep_alive(config.warm_up.keep_alive.clone())),
+        Provider::Anthropic => Arc::new(AnthropicClient::new(&config.anthropic)?),
+    })
+}
+
+/// The model chatted with on `provider` until /model picks another
+pub fn default_model(provider: Provider, config: &AppConfig) -> String {
+    match provider {
+        Provider::Ollama => ollama::DEFAULT_MODEL.to_string(),
+        Provider::Anthropic => config.anthropic.model.clone().unwrap_or_else(|| anthropic::DEFAULT_MODEL.to_string()),
+    }
+}
@@ -0,0 +1,533 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::OnceCell;
+use tokio::task::JoinSet;
+
+use crate::args::BatchArgs;
+use crate::cache::{digest, CachedResponse, ResponseCache};
+use crate::executor::AIExecutor;
+use crate::notify;
+use crate::ollama::Message;
+use crate::queue::{OfflineQueue, QueuedPrompt};
+
+#[derive(Debug, Deserialize)]
+pub struct BatchItem {
+    /// Caller-supplied identifier, echoed back in the result
+    #[serde(default)]
+    pub id: Option<serde_json::Value>,
+    pub prompt: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Ollama model options (temperature, num_ctx, ...)
+    #[serde(default)]
+    pub options: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchStatus {
+    Ok,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheStatus {
+    /// Served from the on-disk response cache
+    Hit,
+    /// Shared with an identical item earlier in the same run
+    Duplicate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    /// 1-indexed line of the item in the input file
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub status: BatchStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<u64>,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Stored in the offline queue for `flush-queue`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub queued: bool,
+}
+
+impl BatchResult {
+    fn failed(line: usize, id: Option<serde_json::Value>, model: Option<String>, error: String) -> Self {
+        Self {
+            line,
+            id,
+            model,
+            status: BatchStatus::Error,
+            response: None,
+            prompt_tokens: None,
+            completion_tokens: None,
+            latency_ms: 0,
+            cache: None,
+            error: Some(error),
+            queued: false,
+        }
+    }
+}
+
+#[derive(Default)]
+struct BatchSummary {
+    total: usize,
+    done: usize,
+    succeeded: usize,
+    failed: usize,
+    skipped: usize,
+    cache_hits: usize,
+    duplicates: usize,
+    queued: usize,
+}
+
+/// Records which input lines have been answered so an interrupted run can resume
+struct Checkpoint {
+    file: File,
+    done: HashSet<(usize, String)>,
+}
+
+impl Checkpoint {
+    fn path_for(out: &Path) -> PathBuf {
+        let mut path = out.as_os_str().to_owned();
+        path.push(".checkpoint");
+        PathBuf::from(path)
+    }
+
+    fn open(out: &Path, resume: bool) -> Result<Self> {
+        let path = Self::path_for(out);
+        let mut done = HashSet::new();
+
+        if resume && path.exists() {
+            let content = fs::read_to_string(&path)
+                .context(format!("Failed to read checkpoint: {}", path.display()))?;
+            for entry in content.lines().filter_map(|l| serde_json::from_str::<CheckpointEntry>(l).ok()) {
+                done.insert((entry.line, entry.digest));
+            }
+        }
+
+        let file = open_output(&path, resume)
+            .context(format!("Failed to open checkpoint: {}", path.display()))?;
+
+        Ok(Self { file, done })
+    }
+
+    fn is_done(&self, line: usize, digest: &str) -> bool {
+        self.done.contains(&(line, digest.to_string()))
+    }
+
+    fn mark(&mut self, line: usize, digest: String) -> Result<()> {
+        let entry = CheckpointEntry { line, digest };
+        writeln!(self.file, "{}", serde_json::to_string(&entry)?)?;
+        self.file.flush()?;
+        self.done.insert((entry.line, entry.digest));
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CheckpointEntry {
+    line: usize,
+    /// SHA-256 of the raw input line, so edited items are not mistaken for finished ones
+    digest: String,
+}
+
+/// Opens a file for appending when resuming, or truncates it for a fresh run
+fn open_output(path: &Path, resume: bool) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resume)
+        .truncate(!resume)
+        .open(path)
+}
+
+/// Rewrites the output of a resumed run to keep only the results of items
+/// the checkpoint will skip, so failed or edited items that run again do not
+/// leave a second result for their line
+fn drop_unfinished(out: &Path, checkpoint: &Checkpoint, input: &str) -> Result<()> {
+    if !out.exists() {
+        return Ok(());
+    }
+    let content = fs::read_to_string(out)
+        .context(format!("Failed to read output file: {}", out.display()))?;
+    let input: Vec<&str> = input.lines().collect();
+
+    let mut seen = HashSet::new();
+    let kept: String = content.lines()
+        .filter(|record| {
+            // A record cut short by an interruption does not parse and is dropped too
+            let Ok(record) = serde_json::from_str::<serde_json::Value>(record) else {
+                return false;
+            };
+            let Some(line) = record["line"].as_u64().map(|line| line as usize) else {
+                return false;
+            };
+            record["status"] == "ok"
+                && input.get(line.wrapping_sub(1)).is_some_and(|raw| checkpoint.is_done(line, &digest(raw)))
+                && seen.insert(line)
+        })
+        .map(|record| format!("{}\n", record))
+        .collect();
+
+    fs::write(out, kept).context(format!("Failed to rewrite output file: {}", out.display()))
+}
+
+struct BatchOutput {
+    writer: Box<dyn Write>,
+    checkpoint: Option<Checkpoint>,
+    summary: BatchSummary,
+}
+
+impl BatchOutput {
+    /// Writes a result as soon as it is available so partial output survives interruption
+    fn record(&mut self, digest: String, result: BatchResult) -> Result<()> {
+        writeln!(self.writer, "{}", serde_json::to_string(&result)?)?;
+        self.writer.flush()?;
+
+        self.summary.done += 1;
+        match result.cache {
+            Some(CacheStatus::Hit) => self.summary.cache_hits += 1,
+            Some(CacheStatus::Duplicate) => self.summary.duplicates += 1,
+            None => {}
+        }
+        if result.queued {
+            self.summary.queued += 1;
+        }
+        let marker = match result.status {
+            BatchStatus::Ok => {
+                self.summary.succeeded += 1;
+                // Only answered items are checkpointed; failures are retried on resume
+                if let Some(checkpoint) = &mut self.checkpoint {
+                    checkpoint.mark(result.line, digest)?;
+                }
+                "✓".bright_green()
+            }
+            BatchStatus::Error => {
+                self.summary.failed += 1;
+                "✗".bright_red()
+            }
+        };
+
+        eprintln!("[{}/{}] {} line {} ({} ms)",
+            self.summary.done, self.summary.total, marker, result.line, result.latency_ms);
+
+        Ok(())
+    }
+
+    async fn write_next(&mut self, tasks: &mut JoinSet<(String, BatchResult)>) -> Result<()> {
+        match tasks.join_next().await {
+            Some(Ok((digest, result))) => self.record(digest, result),
+            Some(Err(e)) => anyhow::bail!("Batch task panicked: {}", e),
+            None => Ok(()),
+        }
+    }
+}
+
+pub async fn run(executor: Arc<AIExecutor>, args: BatchArgs) -> Result<()> {
+    if args.concurrency == 0 {
+        anyhow::bail!("--concurrency must be at least 1");
+    }
+
+    let content = fs::read_to_string(&args.input)
+        .context(format!("Failed to read batch input: {}", args.input.display()))?;
+
+    let (writer, checkpoint): (Box<dyn Write>, _) = match &args.out {
+        Some(path) => {
+            let checkpoint = Checkpoint::open(path, args.resume)?;
+            if args.resume {
+                drop_unfinished(path, &checkpoint, &content)?;
+            }
+            let file = open_output(path, args.resume)
+                .context(format!("Failed to open output file: {}", path.display()))?;
+            (Box::new(file), Some(checkpoint))
+        }
+        None if args.resume => anyhow::bail!("--resume requires --out"),
+        None => (Box::new(io::stdout()), None),
+    };
+
+    let prefix = Arc::new(load_prefix(&args)?);
+
+    let responses = Arc::new(if args.no_cache {
+        None
+    } else {
+        Some(SharedResponses {
+            cache: ResponseCache::open(executor.cache_ttl())?,
+            in_flight: Mutex::new(HashMap::new()),
+        })
+    });
+
+    let queue = match args.queue_on_failure {
+        true => Some(Arc::new(OfflineQueue::open()?)),
+        false => None,
+    };
+
+    let default_model = args.model.clone()
+        .unwrap_or_else(|| executor.get_model().to_string());
+
+    let mut output = BatchOutput {
+        writer,
+        checkpoint,
+        summary: BatchSummary::default(),
+    };
+
+    let lines: Vec<(usize, &str, String)> = content.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| (i + 1, line, digest(line)))
+        .filter(|(line, _, digest)| {
+            let done = output.checkpoint.as_ref().is_some_and(|c| c.is_done(*line, digest));
+            if done {
+                output.summary.skipped += 1;
+            }
+            !done
+        })
+        .collect();
+    output.summary.total = lines.len();
+
+    let started = Instant::now();
+
+    if output.summary.skipped > 0 {
+        eprintln!("{} Resuming: {} item(s) already answered",
+            "↻".bright_blue(), output.summary.skipped);
+    }
+    eprintln!("{} Processing {} item(s) with concurrency {}...",
+        "⚙".bright_blue(), output.summary.total, args.concurrency);
+
+    let mut tasks = JoinSet::new();
+
+    for (line, text, digest) in lines {
+        while tasks.len() >= args.concurrency {
+            output.write_next(&mut tasks).await?;
+        }
+
+        match serde_json::from_str::<BatchItem>(text) {
+            Ok(item) => {
+                let executor = executor.clone();
+                let prefix = prefix.clone();
+                let responses = responses.clone();
+                let queue = queue.clone();
+                let model = item.model.clone().unwrap_or_else(|| default_model.clone());
+                tasks.spawn(async move {
+                    let result = run_item(executor, prefix, responses, queue, line, item, model).await;
+                    (digest, result)
+                });
+            }
+            Err(e) => {
+                let result = BatchResult::failed(line, None, None, format!("Invalid batch item: {}", e));
+                output.record(digest, result)?;
+            }
+        }
+    }
+
+    while !tasks.is_empty() {
+        output.write_next(&mut tasks).await?;
+    }
+
+    let summary = &output.summary;
+    eprintln!("{} {} succeeded, {} failed, {} skipped in {:.1}s",
+        "✓".bright_green(), summary.succeeded, summary.failed, summary.skipped,
+        started.elapsed().as_secs_f64());
+    notify::finished(started, "Batch finished",
+        &format!("{} succeeded, {} failed", summary.succeeded, summary.failed));
+    if !args.no_cache {
+        eprintln!("{} Cache: {} hit(s), {} duplicate(s) of {} item(s)",
+            "ℹ".bright_blue(), summary.cache_hits, summary.duplicates, summary.done);
+    }
+    if summary.queued > 0 {
+        eprintln!("{} {} failed item(s) queued; run {} once Ollama is reachable",
+            "ℹ".bright_blue(), summary.queued, "ai-chat-cli flush-queue".bright_cyan());
+    }
+
+    Ok(())
+}
+
+/// Builds the messages shared by every item: the system prompt followed by few-shot examples
+fn load_prefix(args: &BatchArgs) -> Result<Vec<Message>> {
+    let mut prefix = Vec::new();
+
+    if let Some(path) = &args.system {
+        let system = fs::read_to_string(path)
+            .context(format!("Failed to read system prompt: {}", path.display()))?;
+        prefix.push(Message {
+            role: "system".to_string(),
+            content: system.trim().to_string(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        });
+    }
+
+    if let Some(path) = &args.few_shot {
+        let json = fs::read_to_string(path)
+            .context(format!("Failed to read few-shot file: {}", path.display()))?;
+        let examples: Vec<Message> = serde_json::from_str(&json)
+            .context("Few-shot file must be a JSON array of {\"role\", \"content\"} messages")?;
+        prefix.extend(examples);
+    }
+
+    Ok(prefix)
+}
+
+async fn run_item(
+    executor: Arc<AIExecutor>,
+    prefix: Arc<Vec<Message>>,
+    responses: Arc<Option<SharedResponses>>,
+    queue: Option<Arc<OfflineQueue>>,
+    line: usize,
+    item: BatchItem,
+    model: String,
+) -> BatchResult {
+    let mut messages = prefix.as_ref().clone();
+    messages.push(Message {
+        role: "user".to_string(),
+        content: item.prompt,
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    });
+
+    // Kept for the offline queue in case the request fails
+    let request = queue.as_ref().map(|_| (messages.clone(), item.options.clone()));
+
+    let started = Instant::now();
+    let (outcome, cache) = match responses.as_ref() {
+        Some(shared) => shared.complete(&executor, &model, messages, item.options).await,
+        None => (complete(&executor, &model, messages, item.options).await, None),
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match outcome {
+        Ok(response) => BatchResult {
+            line,
+            id: item.id,
+            model: Some(model),
+            status: BatchStatus::Ok,
+            response: Some(response.response),
+            prompt_tokens: response.prompt_tokens,
+            completion_tokens: response.completion_tokens,
+            latency_ms,
+            cache,
+            error: None,
+            queued: false,
+        },
+        Err(error) => {
+            let queued = match (&queue, request) {
+                (Some(queue), Some((messages, options))) => {
+                    let prompt = QueuedPrompt {
+                        id: item.id.clone(),
+                        options,
+                        ..QueuedPrompt::new("batch", &model, messages, error.clone())
+                    };
+                    match queue.push(&prompt) {
+                        Ok(()) => true,
+                        Err(e) => {
+                            eprintln!("{} Failed to queue line {}: {:#}", "Warning:".bright_yellow(), line, e);
+                            false
+                        }
+                    }
+                }
+                _ => false,
+            };
+            BatchResult {
+                latency_ms,
+                cache,
+                queued,
+                ..BatchResult::failed(line, item.id, Some(model), error)
+            }
+        }
+    }
+}
+
+type Outcome = std::result::Result<CachedResponse, String>;
+
+async fn complete(
+    executor: &AIExecutor,
+    model: &str,
+    messages: Vec<Message>,
+    options: Option<serde_json::Value>,
+) -> Outcome {
+    match executor.batch_complete(Some(model), messages, options).await {
+        Ok(response) => Ok(CachedResponse {
+            response: response.message.content,
+            prompt_tokens: response.prompt_eval_count,
+            completion_tokens: response.eval_count,
+        }),
+        Err(e) => Err(format!("{:#}", e)),
+    }
+}
+
+/// Serves repeated requests from the disk cache and lets identical items
+/// in the same run share a single model call
+struct SharedResponses {
+    cache: ResponseCache,
+    in_flight: Mutex<HashMap<String, Arc<OnceCell<Outcome>>>>,
+}
+
+impl SharedResponses {
+    async fn complete(
+        &self,
+        executor: &AIExecutor,
+        model: &str,
+        messages: Vec<Message>,
+        options: Option<serde_json::Value>,
+    ) -> (Outcome, Option<CacheStatus>) {
+        let key = ResponseCache::key(model, options.as_ref(), &messages);
+
+        let (cell, first) = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key) {
+                Some(cell) => (cell.clone(), false),
+                None => {
+                    let cell = Arc::new(OnceCell::new());
+                    in_flight.insert(key.clone(), cell.clone());
+                    (cell, true)
+                }
+            }
+        };
+
+        if !first {
+            let outcome = cell.get_or_init(|| complete(executor, model, messages, options)).await;
+            return (outcome.clone(), Some(CacheStatus::Duplicate));
+        }
+
+        // Looked up inside the cell, so duplicates wait for the cached answer instead of asking the model
+        let hit = AtomicBool::new(false);
+        let outcome = cell.get_or_init(|| async {
+            if let Some(cached) = self.cache.get(&key) {
+                hit.store(true, Ordering::Relaxed);
+                return Ok(cached);
+            }
+            complete(executor, model, messages, options).await
+        }).await;
+        if hit.load(Ordering::Relaxed) {
+            return (outcome.clone(), Some(CacheStatus::Hit));
+        }
+        if let Ok(response) = outcome
+            && let Err(e) = self.cache.put(&key, response)
+        {
+            eprintln!("{} Failed to cache response: {}", "Warning:".bright_yellow(), e);
+        }
+
+        (outcome.clone(), None)
+    }
+}
@@ -25,7 +25,13 @@ pub struct ToolResult {
 pub struct ToolContent {
     #[serde(rename = "type")]
     pub content_type: String,
+    #[serde(default)]
     pub text: String,
+    /// Base64 payload of "image" content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
 }
 
 impl ToolResult {
@@ -34,6 +40,8 @@ impl ToolResult {
             content: vec![ToolContent {
                 content_type: "text".to_string(),
                 text,
+                data: None,
+                mime_type: None,
             }],
             is_error: None,
         }
@@ -44,6 +52,8 @@ impl ToolResult {
             content: vec![ToolContent {
                 content_type: "text".to_string(),
                 text,
+                data: None,
+                mime_type: None,
             }],
             is_error: Some(true),
         }
@@ -58,13 +68,33 @@ impl BuiltinToolRegistry {
     pub fn new() -> Self {
         let tools = vec![
             Self::bash_tool(),
+            Self::env_tool(),
             Self::read_file_tool(),
             Self::list_files_tool(),
+            Self::stat_tool(),
+            Self::hash_files_tool(),
+            Self::list_archive_tool(),
+            Self::extract_archive_tool(),
             Self::search_glob_tool(),
             Self::grep_tool(),
             Self::edit_file_tool(),
             Self::write_file_tool(),
             Self::think_tool(),
+            Self::screenshot_tool(),
+            Self::ocr_tool(),
+            Self::recall_tool(),
+            Self::repo_map_tool(),
+            Self::code_outline_tool(),
+            Self::find_definition_tool(),
+            Self::find_references_tool(),
+            Self::get_diagnostics_tool(),
+            Self::cargo_check_tool("cargo_check", "Run `cargo check` and return its errors and warnings as a deduplicated list of file:line:column and message, with help notes. Use it after editing Rust code instead of running cargo through bash."),
+            Self::cargo_check_tool("cargo_clippy", "Run `cargo clippy` and return its errors and lint warnings as a deduplicated list of file:line:column and message, with the suggested fix where clippy has one."),
+            Self::cargo_fmt_tool(),
+            Self::github_list_tool("github_list_issues", "List issues of a GitHub repository (without pull requests), recently updated first, with labels, author and comment count. Use it to triage issues."),
+            Self::github_list_tool("github_list_pulls", "List pull requests of a GitHub repository, recently updated first, with author and draft state."),
+            Self::github_read_issue_tool(),
+            Self::github_comment_tool(),
         ];
 
         Self { tools }
@@ -77,13 +107,33 @@ impl BuiltinToolRegistry {
     pub async fn execute(&self, name: &str, args: serde_json::Value) -> Result<ToolResult> {
         match name {
             "bash" => self.execute_bash(args).await,
+            "env" => self.execute_env(args),
             "read_file" => self.execute_read_file(args),
             "list_files" => self.execute_list_files(args),
+            "stat" => self.execute_stat(args),
+            "hash_files" => self.execute_hash_files(args),
+            "list_archive" => self.execute_list_archive(args),
+            "extract_archive" => self.execute_extract_archive(args),
             "search_glob" => self.execute_search_glob(args),
             "grep" => self.execute_grep(args),
             "edit_file" => self.execute_edit_file(args),
             "write_file" => self.execute_write_file(args),
             "think" => self.execute_think(args),
+            "screenshot" => self.execute_screenshot(args).await,
+            "ocr" => self.execute_ocr(args).await,
+            "recall" => self.execute_recall(args).await,
+            "repo_map" => self.execute_repo_map(args),
+            "code_outline" => self.execute_code_outline(args),
+            "find_definition" => self.execute_find_definition(args),
+            "find_references" => self.execute_find_references(args),
+            "get_diagnostics" => self.execute_get_diagnostics(args).await,
+            "cargo_check" => self.execute_cargo_check("check", args).await,
+            "cargo_clippy" => self.execute_cargo_check("clippy", args).await,
+            "cargo_fmt" => self.execute_cargo_fmt(args).await,
+            "github_list_issues" => self.execute_github_list(crate::github::Kind::Issues, args).await,
+            "github_list_pulls" => self.execute_github_list(crate::github::Kind::PullRequests, args).await,
+            "github_read_issue" => self.execute_github_read_issue(args).await,
+            "github_comment" => self.execute_github_comment(args).await,
             _ => anyhow::bail!("Unknown built-in tool: {}", name),
         }
     }
@@ -112,6 +162,43 @@ impl BuiltinToolRegistry {
         }
     }
 
+    fn env_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "env".to_string(),
+            description: "List environment variables, with values that look like tokens, keys or passwords masked. Use it instead of running env or printenv through bash. Variables set or unset here apply to bash, cargo and verification commands for the rest of the session.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "names": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Variables to show, including ones that are not set; all when omitted"
+                    },
+                    "pattern": {
+                        "type": "string",
+                        "description": "Glob over variable names, e.g. 'CARGO_*' (case-insensitive)"
+                    },
+                    "set": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                        "description": "Variables to set for commands run later in this session"
+                    },
+                    "unset": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Variables to remove for commands run later in this session"
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["text", "json"],
+                        "description": "NAME=value lines, or JSON",
+                        "default": "text"
+                    }
+                }
+            }),
+        }
+    }
+
     fn read_file_tool() -> BuiltinTool {
         BuiltinTool {
             name: "read_file".to_string(),
@@ -140,7 +227,7 @@ impl BuiltinToolRegistry {
     fn list_files_tool() -> BuiltinTool {
         BuiltinTool {
             name: "list_files".to_string(),
-            description: "List files and directories with metadata (size, modified time, permissions).".to_string(),
+            description: "List files and directories with their sizes, as a tree, a flat list or JSON with modification times.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -153,12 +240,134 @@ impl BuiltinToolRegistry {
                         "type": "boolean",
                         "description": "List recursively",
                         "default": false
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["tree", "flat", "json"],
+                        "description": "An indented tree, one line per entry with its path, or a JSON array (default: tree when recursive, flat otherwise)"
+                    },
+                    "ascii": {
+                        "type": "boolean",
+                        "description": "Draw the tree with ASCII characters only",
+                        "default": false
                     }
                 }
             }),
         }
     }
 
+    fn stat_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "stat".to_string(),
+            description: "Show the metadata of one file or directory as JSON: type, size, permissions, owner, modified and changed times, symlink target and a MIME type guess. Use it instead of `ls -la` through bash.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the file, directory or symlink"
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    fn hash_files_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "hash_files".to_string(),
+            description: "Compute SHA-256 checksums of files given by path or glob, or find files with identical content (e.g. duplicated assets) with duplicates=true. Directories are searched recursively. Use it instead of sha256sum or find pipelines through bash.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "paths": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Files, or directories to search recursively"
+                    },
+                    "pattern": {
+                        "type": "string",
+                        "description": "Glob pattern of files to include, e.g. '*.png' (any depth) or 'assets/**/*.svg'"
+                    },
+                    "base_path": {
+                        "type": "string",
+                        "description": "Directory the pattern is searched from (default: current directory)",
+                        "default": "."
+                    },
+                    "duplicates": {
+                        "type": "boolean",
+                        "description": "Report only groups of files with the same content, largest waste first",
+                        "default": false
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["text", "json"],
+                        "description": "sha256sum-style lines, or JSON",
+                        "default": "text"
+                    }
+                }
+            }),
+        }
+    }
+
+    fn list_archive_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "list_archive".to_string(),
+            description: "List the entries of a zip, tar or tar.gz archive with their sizes, without extracting it. Use it to see what is inside a release artifact or download.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the archive"
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["text", "json"],
+                        "description": "One line per entry, or a JSON array of name, type, size and link target",
+                        "default": "text"
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    fn extract_archive_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "extract_archive".to_string(),
+            description: "Extract a zip, tar or tar.gz archive, or some of its entries, into a directory. Entries that would land outside it and links are skipped, existing files are kept unless overwrite is set, and extraction stops at max_bytes.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the archive"
+                    },
+                    "destination": {
+                        "type": "string",
+                        "description": "Directory to extract into, created if missing"
+                    },
+                    "entries": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Entry names to extract, as list_archive shows them; a directory takes everything below it (default: all)"
+                    },
+                    "overwrite": {
+                        "type": "boolean",
+                        "description": "Replace files that already exist",
+                        "default": false
+                    },
+                    "max_bytes": {
+                        "type": "integer",
+                        "description": "Most bytes to unpack in total (default: 100 MiB)"
+                    }
+                },
+                "required": ["path", "destination"]
+            }),
+        }
+    }
+
     fn search_glob_tool() -> BuiltinTool {
         BuiltinTool {
             name: "search_glob".to_string(),
@@ -174,6 +383,12 @@ impl BuiltinToolRegistry {
                         "type": "string",
                         "description": "Base directory to search from (default: current directory)",
                         "default": "."
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["text", "json"],
+                        "description": "One path per line, or a JSON array of path, size and modification time",
+                        "default": "text"
                     }
                 },
                 "required": ["pattern"]
@@ -205,6 +420,12 @@ impl BuiltinToolRegistry {
                         "type": "boolean",
                         "description": "Case-insensitive search",
                         "default": false
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["text", "json"],
+                        "description": "grep's path:line:text lines, or a JSON array of files with their matching lines",
+                        "default": "text"
                     }
                 },
                 "required": ["pattern", "path"]
@@ -275,6 +496,313 @@ impl BuiltinToolRegistry {
         }
     }
 
+    fn screenshot_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "screenshot".to_string(),
+            description: "Capture the screen or a window as an image for vision models. Always asks the user first.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "target": {
+                        "type": "string",
+                        "enum": ["screen", "window"],
+                        "description": "The whole screen, or a window the user picks (default: screen)",
+                        "default": "screen"
+                    }
+                }
+            }),
+        }
+    }
+
+    fn ocr_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "ocr".to_string(),
+            description: "Extract the text from an image file (screenshot, scan, photo) with Tesseract. Use it to read images without a vision model.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "The image file to read"
+                    },
+                    "language": {
+                        "type": "string",
+                        "description": "Tesseract language code(s), e.g. 'eng' or 'deu+eng' (default: eng)",
+                        "default": "eng"
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    fn recall_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "recall".to_string(),
+            description: "Search the user's past conversations by meaning. Use it when the user refers to something discussed before.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "What to look for, e.g. 'the nginx config we wrote'"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Maximum number of exchanges to return (default: 3)",
+                        "default": 3
+                    }
+                },
+                "required": ["query"]
+            }),
+        }
+    }
+
+    fn repo_map_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "repo_map".to_string(),
+            description: "Get a compact map of a repository: its directories, files and the public symbols each file defines. Use it to find where to look before reading files.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "A path inside the repository (default: current directory)",
+                        "default": "."
+                    },
+                    "max_chars": {
+                        "type": "integer",
+                        "description": "Size limit of the map; detail is dropped to fit (default: 8000)",
+                        "default": 8000
+                    }
+                }
+            }),
+        }
+    }
+
+    fn code_outline_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "code_outline".to_string(),
+            description: "Outline a source file: its imports, functions, types and methods with line ranges. Use it before read_file to read only the lines you need with start_line and end_line.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path to the source file"
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    fn find_definition_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "find_definition".to_string(),
+            description: "Find where a function, type or other symbol is defined in the repository, with its line range. Accepts qualified names like Type::method.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "Symbol name, e.g. 'parse_args' or 'Config::load'"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "A path inside the repository (default: current directory)",
+                        "default": "."
+                    }
+                },
+                "required": ["name"]
+            }),
+        }
+    }
+
+    fn find_references_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "find_references".to_string(),
+            description: "Find every line in the repository's source files that uses a symbol, skipping comments and strings. Use it before renaming or changing a signature.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "name": {
+                        "type": "string",
+                        "description": "Symbol name, e.g. 'parse_args'"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "A path inside the repository (default: current directory)",
+                        "default": "."
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Maximum number of lines to return (default: 200)",
+                        "default": 200
+                    }
+                },
+                "required": ["name"]
+            }),
+        }
+    }
+
+    fn get_diagnostics_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "get_diagnostics".to_string(),
+            description: "Get compiler and type errors and warnings for a file from its language server. Use it right after editing a file to check the edit.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path to the source file"
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    /// cargo_check and cargo_clippy take the same arguments
+    fn cargo_check_tool(name: &str, description: &str) -> BuiltinTool {
+        BuiltinTool {
+            name: name.to_string(),
+            description: description.to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Directory of the crate or workspace (default: current directory)",
+                        "default": "."
+                    },
+                    "package": {
+                        "type": "string",
+                        "description": "Only check this workspace package"
+                    },
+                    "all_targets": {
+                        "type": "boolean",
+                        "description": "Also check tests, examples and benches (default: false)",
+                        "default": false
+                    },
+                    "timeout": {
+                        "type": "integer",
+                        "description": "Timeout in seconds (default: 300)",
+                        "default": 300
+                    }
+                }
+            }),
+        }
+    }
+
+    fn cargo_fmt_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "cargo_fmt".to_string(),
+            description: "Check Rust formatting with `cargo fmt --check` and list the files and lines that would change. Set apply to format them.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Directory of the crate or workspace (default: current directory)",
+                        "default": "."
+                    },
+                    "apply": {
+                        "type": "boolean",
+                        "description": "Format the files instead of only reporting them (default: false)",
+                        "default": false
+                    }
+                }
+            }),
+        }
+    }
+
+    /// github_list_issues and github_list_pulls take the same arguments
+    fn github_list_tool(name: &str, description: &str) -> BuiltinTool {
+        let mut properties = json!({
+            "repo": {
+                "type": "string",
+                "description": "Repository as owner/name (default: the origin remote of the current directory)"
+            },
+            "state": {
+                "type": "string",
+                "enum": ["open", "closed", "all"],
+                "description": "Which ones to list (default: open)",
+                "default": "open"
+            },
+            "limit": {
+                "type": "integer",
+                "description": "Maximum number to return (default: 30)",
+                "default": 30
+            }
+        });
+        if name == "github_list_issues" {
+            properties["labels"] = json!({
+                "type": "string",
+                "description": "Only issues with all of these comma-separated labels, e.g. 'bug,ui'"
+            });
+        }
+        BuiltinTool {
+            name: name.to_string(),
+            description: description.to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": properties
+            }),
+        }
+    }
+
+    fn github_read_issue_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "github_read_issue".to_string(),
+            description: "Read a GitHub issue or pull request: description, labels, comments and, for pull requests, the branches and changed files.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "number": {
+                        "type": "integer",
+                        "description": "Issue or pull request number"
+                    },
+                    "repo": {
+                        "type": "string",
+                        "description": "Repository as owner/name (default: the origin remote of the current directory)"
+                    },
+                    "comments": {
+                        "type": "boolean",
+                        "description": "Include the comments (default: true)",
+                        "default": true
+                    }
+                },
+                "required": ["number"]
+            }),
+        }
+    }
+
+    fn github_comment_tool() -> BuiltinTool {
+        BuiltinTool {
+            name: "github_comment".to_string(),
+            description: "Post a comment on a GitHub issue or pull request. The user approves each comment before it is posted.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "number": {
+                        "type": "integer",
+                        "description": "Issue or pull request number"
+                    },
+                    "body": {
+                        "type": "string",
+                        "description": "Comment text in Markdown"
+                    },
+                    "repo": {
+                        "type": "string",
+                        "description": "Repository as owner/name (default: the origin remote of the current directory)"
+                    }
+                },
+                "required": ["number", "body"]
+            }),
+        }
+    }
+
     // Tool Implementations
 
     async fn execute_bash(&self, args: serde_json::Value) -> Result<ToolResult> {
@@ -283,21 +811,15 @@ impl BuiltinToolRegistry {
         
         let timeout_secs = args["timeout"].as_u64().unwrap_or(30);
 
-        // Security: Basic command validation
-        let dangerous_patterns = ["rm -rf /", "dd if=", "mkfs", "format", "> /dev/"];
-        for pattern in &dangerous_patterns {
-            if command.contains(pattern) {
-                return Ok(ToolResult::error(
-                    format!("Command blocked for security: contains '{}'", pattern)
-                ));
-            }
-        }
-
+        // Forbidden commands are refused by the policy in McpManager::call_tool
         let execution = async {
-            let output = Command::new("sh")
-                .arg("-c")
-                .arg(command)
-                .output()
+            // Async so the timeout can fire; dropping the future (timeout or
+            // shutdown) kills the shell instead of orphaning it
+            let mut process = tokio::process::Command::new("sh");
+            process.arg("-c").arg(command).kill_on_drop(true);
+            crate::env_vars::apply(&mut process);
+            let output = process.output()
+                .await
                 .context("Failed to execute command")?;
 
             let stdout = String::from_utf8_lossy(&output.stdout).to_string();
@@ -333,6 +855,39 @@ impl BuiltinToolRegistry {
         }
     }
 
+    fn execute_env(&self, args: serde_json::Value) -> Result<ToolResult> {
+        use crate::env_vars;
+
+        let mut changes = Vec::new();
+        for (name, value) in args["set"].as_object().into_iter().flatten() {
+            let value = value.as_str().context(format!("The value of '{}' in 'set' must be a string", name))?;
+            changes.push((name.clone(), Some(value.to_string())));
+        }
+        for name in args["unset"].as_array().into_iter().flatten() {
+            let name = name.as_str().context("'unset' must list variable names")?;
+            changes.push((name.to_string(), None));
+        }
+        env_vars::change(&changes)?;
+        let changed: Vec<String> = changes.into_iter().map(|(name, _)| name).collect();
+
+        let mut names: Vec<String> = args["names"].as_array()
+            .map(|names| names.iter().filter_map(|n| n.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+        let pattern = args["pattern"].as_str();
+        // A call that only changes variables shows what it changed
+        if names.is_empty() && pattern.is_none() {
+            names = changed;
+        }
+
+        let variables = env_vars::variables(&names, pattern);
+        let result = if args["format"].as_str() == Some("json") {
+            serde_json::to_string_pretty(&env_vars::variables_json(&variables))?
+        } else {
+            env_vars::variables_text(&variables)
+        };
+        Ok(ToolResult::success(result))
+    }
+
     fn execute_read_file(&self, args: serde_json::Value) -> Result<ToolResult> {
         let path = args["path"].as_str()
             .context("Missing 'path' parameter")?;
@@ -359,76 +914,142 @@ impl BuiltinToolRegistry {
     fn execute_list_files(&self, args: serde_json::Value) -> Result<ToolResult> {
         let path = args["path"].as_str().unwrap_or(".");
         let recursive = args["recursive"].as_bool().unwrap_or(false);
-
-        let mut result = String::new();
-        
-        if recursive {
-            self.list_files_recursive(Path::new(path), &mut result, 0)?;
+        let format = args["format"].as_str().unwrap_or(if recursive { "tree" } else { "flat" });
+        // Screen readers spell out box-drawing characters one by one
+        let glyphs = if args["ascii"].as_bool().unwrap_or_else(crate::accessibility::enabled) {
+            &ASCII_TREE
         } else {
-            self.list_files_single(Path::new(path), &mut result)?;
-        }
+            &UNICODE_TREE
+        };
+
+        let entries = read_entries(Path::new(path), Path::new(""), recursive)?;
+        let result = match format {
+            "tree" => {
+                let mut result = format!("{}/\n", path.trim_end_matches('/'));
+                render_tree(&entries, "", glyphs, &mut result);
+                result
+            }
+            "flat" => {
+                let mut result = String::new();
+                render_flat(&entries, &mut result);
+                result
+            }
+            "json" => {
+                let mut listed = Vec::new();
+                flatten_json(&entries, &mut listed);
+                serde_json::to_string_pretty(&listed)?
+            }
+            other => anyhow::bail!("Unknown list_files format '{}' (use tree, flat or json)", other),
+        };
 
         Ok(ToolResult::success(result))
     }
 
-    fn list_files_single(&self, path: &Path, result: &mut String) -> Result<()> {
-        let entries = fs::read_dir(path)
-            .context(format!("Failed to read directory: {:?}", path))?;
-
-        for entry in entries {
-            let entry = entry?;
-            let metadata = entry.metadata()?;
-            let file_type = if metadata.is_dir() { "DIR " } else { "FILE" };
-            let size = metadata.len();
-            let name = entry.file_name().to_string_lossy().to_string();
-            
-            result.push_str(&format!("{} {:>10} {}\n", file_type, size, name));
-        }
-
-        Ok(())
+    fn execute_stat(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let path = args["path"].as_str()
+            .context("Missing 'path' parameter")?;
+        let info = crate::file_info::stat(Path::new(path))?;
+        Ok(ToolResult::success(serde_json::to_string_pretty(&info)?))
     }
 
-    fn list_files_recursive(&self, path: &Path, result: &mut String, depth: usize) -> Result<()> {
-        let entries = fs::read_dir(path)
-            .context(format!("Failed to read directory: {:?}", path))?;
+    fn execute_hash_files(&self, args: serde_json::Value) -> Result<ToolResult> {
+        use crate::checksums;
 
-        let indent = "  ".repeat(depth);
+        let paths: Vec<String> = args["paths"].as_array()
+            .map(|paths| paths.iter().filter_map(|p| p.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+        let pattern = args["pattern"].as_str();
+        if paths.is_empty() && pattern.is_none() {
+            anyhow::bail!("Give 'paths', 'pattern' or both");
+        }
+        let base_path = Path::new(args["base_path"].as_str().unwrap_or("."));
+        let json = args["format"].as_str() == Some("json");
 
-        for entry in entries {
-            let entry = entry?;
-            let metadata = entry.metadata()?;
-            let name = entry.file_name().to_string_lossy().to_string();
-            
-            if metadata.is_dir() {
-                result.push_str(&format!("{}📁 {}/\n", indent, name));
-                self.list_files_recursive(&entry.path(), result, depth + 1)?;
+        let files = checksums::collect(&paths, pattern, base_path)?;
+        if files.is_empty() {
+            return Ok(ToolResult::success("No files to hash".to_string()));
+        }
+        let limit = if files.len() == checksums::MAX_FILES {
+            format!("\n(stopped at {} files; narrow the paths or pattern)", checksums::MAX_FILES)
+        } else {
+            String::new()
+        };
+
+        let result = if args["duplicates"].as_bool() == Some(true) {
+            let groups = checksums::duplicates(&files);
+            if json {
+                serde_json::to_string_pretty(&checksums::duplicates_json(&groups))?
             } else {
-                let size = metadata.len();
-                result.push_str(&format!("{}📄 {} ({} bytes)\n", indent, name, size));
+                checksums::duplicates_text(&groups, files.len()) + &limit
             }
+        } else if json {
+            serde_json::to_string_pretty(&checksums::checksums_json(&files))?
+        } else {
+            checksums::checksums_text(&files) + &limit
+        };
+        Ok(ToolResult::success(result))
+    }
+
+    fn execute_list_archive(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let path = Path::new(args["path"].as_str()
+            .context("Missing 'path' parameter")?);
+        let (format, entries) = crate::archive::list(path)?;
+        if args["format"].as_str() == Some("json") {
+            return Ok(ToolResult::success(serde_json::to_string_pretty(&crate::archive::list_json(&entries))?));
         }
+        Ok(ToolResult::success(crate::archive::list_text(path, format, &entries)))
+    }
 
-        Ok(())
+    fn execute_extract_archive(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let path = Path::new(args["path"].as_str()
+            .context("Missing 'path' parameter")?);
+        let destination = Path::new(args["destination"].as_str()
+            .context("Missing 'destination' parameter")?);
+        let only: Vec<String> = args["entries"].as_array()
+            .map(|entries| entries.iter().filter_map(|e| e.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+        let options = crate::archive::ExtractOptions {
+            only: &only,
+            overwrite: args["overwrite"].as_bool().unwrap_or(false),
+            max_bytes: args["max_bytes"].as_u64().unwrap_or(crate::archive::DEFAULT_MAX_BYTES),
+        };
+
+        let report = crate::archive::extract(path, destination, &options)?;
+        Ok(ToolResult::success(report.text(destination)))
     }
 
     fn execute_search_glob(&self, args: serde_json::Value) -> Result<ToolResult> {
         let pattern = args["pattern"].as_str()
             .context("Missing 'pattern' parameter")?;
         let base_path = args["base_path"].as_str().unwrap_or(".");
+        // find reads a leading '-' as part of its expression, like `-fprint`
+        let base_path = if base_path.starts_with('-') {
+            format!("./{}", base_path)
+        } else {
+            base_path.to_string()
+        };
 
-        // Use glob crate for pattern matching
-        let _glob_pattern = format!("{}/{}", base_path, pattern);
-        
-        // For now, use basic shell globbing via bash
-        let command = format!("find {} -name '{}'", base_path, pattern.replace("**", "*"));
-        
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(&command)
+        // The arguments go to find as they are, never through a shell
+        let output = Command::new("find")
+            .arg(&base_path)
+            .arg("-name")
+            .arg(pattern.replace("**", "*"))
             .output()
             .context("Failed to execute glob search")?;
 
         let result = String::from_utf8_lossy(&output.stdout).to_string();
+        if args["format"].as_str() == Some("json") {
+            let files: Vec<serde_json::Value> = result.lines().map(|path| {
+                let metadata = fs::metadata(path).ok();
+                json!({
+                    "path": path,
+                    "size": metadata.as_ref().map(|m| m.len()),
+                    "modified": metadata.and_then(|m| m.modified().ok())
+                        .map(|m| chrono::DateTime::<chrono::Local>::from(m).to_rfc3339()),
+                })
+            }).collect();
+            return Ok(ToolResult::success(serde_json::to_string_pretty(&files)?));
+        }
         
         Ok(ToolResult::success(if result.is_empty() {
             format!("No files found matching pattern: {}", pattern)
@@ -445,7 +1066,9 @@ impl BuiltinToolRegistry {
         let recursive = args["recursive"].as_bool().unwrap_or(false);
         let ignore_case = args["ignore_case"].as_bool().unwrap_or(false);
 
-        let mut cmd_args = vec!["grep"];
+        let json = args["format"].as_str() == Some("json");
+
+        let mut cmd_args = Vec::new();
         
         if ignore_case {
             cmd_args.push("-i");
@@ -454,6 +1077,10 @@ impl BuiltinToolRegistry {
             cmd_args.push("-r");
         }
         cmd_args.push("-n"); // Show line numbers
+        if json {
+            // File names even for a single file, so every line parses the same
+            cmd_args.push("-H");
+        }
         cmd_args.push(pattern);
         cmd_args.push(path);
 
@@ -468,6 +1095,9 @@ impl BuiltinToolRegistry {
         if !stderr.is_empty() {
             return Ok(ToolResult::error(stderr));
         }
+        if json {
+            return Ok(ToolResult::success(serde_json::to_string_pretty(&grep_matches_json(&stdout))?));
+        }
 
         Ok(ToolResult::success(if stdout.is_empty() {
             format!("No matches found for pattern: {}", pattern)
@@ -494,7 +1124,8 @@ impl BuiltinToolRegistry {
         }
 
         let new_content = content.replace(old_text, new_text);
-        
+
+        crate::journal::record(Path::new(path));
         fs::write(path, new_content)
             .context(format!("Failed to write file: {}", path))?;
 
@@ -513,6 +1144,7 @@ impl BuiltinToolRegistry {
                 .context("Failed to create parent directories")?;
         }
 
+        crate::journal::record(Path::new(path));
         fs::write(path, content)
             .context(format!("Failed to write file: {}", path))?;
 
@@ -527,9 +1159,324 @@ impl BuiltinToolRegistry {
         let thoughts = args["thoughts"].as_str()
             .context("Missing 'thoughts' parameter")?;
 
-        Ok(ToolResult::success(format!(
-            "💭 Internal reasoning:\n{}",
-            thoughts
+        Ok(ToolResult::success(format!("{}{}", crate::thoughts::HEADER, thoughts)))
+    }
+
+    async fn execute_screenshot(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let window = match args["target"].as_str().unwrap_or("screen") {
+            "screen" => false,
+            "window" => true,
+            other => anyhow::bail!("Unknown screenshot target '{}' (use screen or window)", other),
+        };
+
+        let path = std::env::temp_dir().join(format!("ai-chat-screenshot-{}.png", uuid::Uuid::new_v4()));
+        let output = path.to_string_lossy().to_string();
+
+        // The first capture tool that is installed and succeeds wins
+        let mut tried = Vec::new();
+        for (program, args) in screenshot_commands(window, &output) {
+            tried.push(program);
+            let status = tokio::process::Command::new(program)
+                .args(&args)
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+                .await;
+            if status.is_ok_and(|s| s.success()) && path.exists() {
+                let image = crate::image::from_file(&path)?;
+                return Ok(ToolResult {
+                    content: vec![
+                        ToolContent {
+                            content_type: "text".to_string(),
+                            text: format!("Screenshot saved to {}", path.display()),
+                            data: None,
+                            mime_type: None,
+                        },
+                        ToolContent {
+                            content_type: "image".to_string(),
+                            text: String::new(),
+                            data: Some(image.data),
+                            mime_type: Some("image/png".to_string()),
+                        },
+                    ],
+                    is_error: None,
+                });
+            }
+        }
+
+        Ok(ToolResult::error(format!(
+            "No screenshot could be taken; tried {}",
+            if tried.is_empty() { "nothing on this platform".to_string() } else { tried.join(", ") }
         )))
     }
+
+    async fn execute_ocr(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let path = args["path"].as_str()
+            .context("Missing 'path' parameter")?;
+        let language = args["language"].as_str().unwrap_or("eng");
+
+        if !Path::new(path).is_file() {
+            return Ok(ToolResult::error(format!("No such image: {}", path)));
+        }
+
+        let output = match tokio::process::Command::new("tesseract")
+            .args([path, "stdout", "-l", language])
+            .kill_on_drop(true)
+            .output()
+            .await
+        {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(ToolResult::error(
+                    "tesseract is not installed (brew install tesseract, apt install tesseract-ocr)".to_string()
+                ));
+            }
+            Err(e) => return Err(e).context("Failed to run tesseract"),
+        };
+
+        if !output.status.success() {
+            return Ok(ToolResult::error(format!(
+                "tesseract failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if text.is_empty() {
+            Ok(ToolResult::success(format!("No text found in {}", path)))
+        } else {
+            Ok(ToolResult::success(text))
+        }
+    }
+
+    async fn execute_recall(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let query = args["query"].as_str()
+            .context("Missing 'query' parameter")?;
+        let limit = args["limit"].as_u64().unwrap_or(3) as usize;
+
+        let (hits, _) = crate::recall::search(query, limit).await?;
+        if hits.is_empty() {
+            return Ok(ToolResult::success("No related past exchanges found".to_string()));
+        }
+
+        let exchanges: Vec<String> = hits.iter().map(crate::recall::format_exchange).collect();
+        Ok(ToolResult::success(exchanges.join("\n\n")))
+    }
+
+    fn execute_repo_map(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let path = args["path"].as_str().unwrap_or(".");
+        let max_chars = args["max_chars"].as_u64()
+            .map_or(crate::repo_map::DEFAULT_MAX_CHARS, |n| n as usize);
+
+        let map = crate::repo_map::build(Path::new(path), max_chars)?;
+        Ok(ToolResult::success(map.text))
+    }
+
+    fn execute_code_outline(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let path = args["path"].as_str()
+            .context("Missing 'path' parameter")?;
+
+        Ok(ToolResult::success(crate::symbols::outline(Path::new(path))?))
+    }
+
+    fn execute_find_definition(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let name = args["name"].as_str()
+            .context("Missing 'name' parameter")?;
+        let path = args["path"].as_str().unwrap_or(".");
+
+        Ok(ToolResult::success(crate::navigation::find_definition(Path::new(path), name)?))
+    }
+
+    fn execute_find_references(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let name = args["name"].as_str()
+            .context("Missing 'name' parameter")?;
+        let path = args["path"].as_str().unwrap_or(".");
+        let limit = args["limit"].as_u64().unwrap_or(200) as usize;
+
+        Ok(ToolResult::success(crate::navigation::find_references(Path::new(path), name, limit)?))
+    }
+
+    async fn execute_get_diagnostics(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let path = args["path"].as_str()
+            .context("Missing 'path' parameter")?;
+
+        Ok(ToolResult::success(crate::lsp::diagnostics(Path::new(path)).await?))
+    }
+
+    async fn execute_cargo_check(&self, subcommand: &str, args: serde_json::Value) -> Result<ToolResult> {
+        let path = args["path"].as_str().unwrap_or(".");
+        let package = args["package"].as_str();
+        let all_targets = args["all_targets"].as_bool().unwrap_or(false);
+        let time_limit = args["timeout"].as_u64().map(Duration::from_secs)
+            .unwrap_or(crate::cargo_tools::DEFAULT_TIMEOUT);
+
+        let report = crate::cargo_tools::check(Path::new(path), subcommand, package, all_targets, time_limit).await?;
+        Ok(if report.failed { ToolResult::error(report.text) } else { ToolResult::success(report.text) })
+    }
+
+    async fn execute_cargo_fmt(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let path = args["path"].as_str().unwrap_or(".");
+        let apply = args["apply"].as_bool().unwrap_or(false);
+
+        let report = crate::cargo_tools::fmt(Path::new(path), apply, crate::cargo_tools::DEFAULT_TIMEOUT).await?;
+        Ok(if report.failed { ToolResult::error(report.text) } else { ToolResult::success(report.text) })
+    }
+
+    async fn execute_github_list(&self, kind: crate::github::Kind, args: serde_json::Value) -> Result<ToolResult> {
+        let repo = args["repo"].as_str();
+        let state = args["state"].as_str().unwrap_or("open");
+        let labels = args["labels"].as_str();
+        let limit = args["limit"].as_u64().unwrap_or(30) as usize;
+
+        Ok(ToolResult::success(crate::github::list(repo, kind, state, labels, limit).await?))
+    }
+
+    async fn execute_github_read_issue(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let number = args["number"].as_u64()
+            .context("Missing 'number' parameter")?;
+        let repo = args["repo"].as_str();
+        let comments = args["comments"].as_bool().unwrap_or(true);
+
+        Ok(ToolResult::success(crate::github::read(repo, number, comments).await?))
+    }
+
+    async fn execute_github_comment(&self, args: serde_json::Value) -> Result<ToolResult> {
+        let number = args["number"].as_u64()
+            .context("Missing 'number' parameter")?;
+        let body = args["body"].as_str()
+            .context("Missing 'body' parameter")?;
+        let repo = args["repo"].as_str();
+
+        Ok(ToolResult::success(crate::github::comment(repo, number, body).await?))
+    }
+}
+
+/// `path:line:text` lines of `grep -Hn` as `[{"path", "matches": [{"line", "text"}]}]`
+fn grep_matches_json(output: &str) -> Vec<serde_json::Value> {
+    let line = regex::Regex::new(r"^(.*?):(\d+):(.*)$").unwrap();
+    let mut files: Vec<(String, Vec<serde_json::Value>)> = Vec::new();
+    for found in output.lines().filter_map(|l| line.captures(l)) {
+        let entry = json!({ "line": found[2].parse::<u64>().unwrap_or_default(), "text": &found[3] });
+        match files.last_mut() {
+            Some((path, matches)) if *path == found[1] => matches.push(entry),
+            _ => files.push((found[1].to_string(), vec![entry])),
+        }
+    }
+    files.into_iter()
+        .map(|(path, matches)| json!({ "path": path, "matches": matches }))
+        .collect()
+}
+
+/// A directory entry found by `list_files`, with its path relative to the
+/// listed directory
+struct FileEntry {
+    path: std::path::PathBuf,
+    is_dir: bool,
+    size: u64,
+    modified: Option<std::time::SystemTime>,
+    children: Vec<FileEntry>,
+}
+
+/// The pieces a `list_files` tree is drawn with
+struct TreeGlyphs {
+    branch: &'static str,
+    last: &'static str,
+    pipe: &'static str,
+    blank: &'static str,
+    dir: &'static str,
+    file: &'static str,
+}
+
+const UNICODE_TREE: TreeGlyphs = TreeGlyphs {
+    branch: "├── ", last: "└── ", pipe: "│   ", blank: "    ", dir: "📁 ", file: "📄 ",
+};
+
+const ASCII_TREE: TreeGlyphs = TreeGlyphs {
+    branch: "|-- ", last: "`-- ", pipe: "|   ", blank: "    ", dir: "", file: "",
+};
+
+/// The entries of `dir` sorted by name, and those below them when `recursive`
+fn read_entries(dir: &Path, relative: &Path, recursive: bool) -> Result<Vec<FileEntry>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir).context(format!("Failed to read directory: {:?}", dir))? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let path = relative.join(entry.file_name());
+        let children = if recursive && metadata.is_dir() {
+            read_entries(&entry.path(), &path, true)?
+        } else {
+            Vec::new()
+        };
+        entries.push(FileEntry {
+            path,
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            children,
+        });
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+fn render_tree(entries: &[FileEntry], prefix: &str, glyphs: &TreeGlyphs, result: &mut String) {
+    for (i, entry) in entries.iter().enumerate() {
+        let last = i + 1 == entries.len();
+        let connector = if last { glyphs.last } else { glyphs.branch };
+        let name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+        if entry.is_dir {
+            result.push_str(&format!("{}{}{}{}/\n", prefix, connector, glyphs.dir, name));
+            let prefix = format!("{}{}", prefix, if last { glyphs.blank } else { glyphs.pipe });
+            render_tree(&entry.children, &prefix, glyphs, result);
+        } else {
+            result.push_str(&format!("{}{}{}{} ({} bytes)\n", prefix, connector, glyphs.file, name, entry.size));
+        }
+    }
+}
+
+/// `DIR        4096 src/bin`, one line per entry
+fn render_flat(entries: &[FileEntry], result: &mut String) {
+    for entry in entries {
+        let file_type = if entry.is_dir { "DIR " } else { "FILE" };
+        result.push_str(&format!("{} {:>10} {}\n", file_type, entry.size, entry.path.display()));
+        render_flat(&entry.children, result);
+    }
+}
+
+fn flatten_json(entries: &[FileEntry], listed: &mut Vec<serde_json::Value>) {
+    for entry in entries {
+        listed.push(json!({
+            "path": entry.path,
+            "type": if entry.is_dir { "dir" } else { "file" },
+            "size": entry.size,
+            "modified": entry.modified.map(|m| chrono::DateTime::<chrono::Local>::from(m).to_rfc3339()),
+        }));
+        flatten_json(&entry.children, listed);
+    }
+}
+
+/// Capture commands to try in order; window captures let the user pick the window
+fn screenshot_commands(window: bool, output: &str) -> Vec<(&'static str, Vec<String>)> {
+    let args = |args: &[&str]| args.iter().map(|a| a.to_string()).chain([output.to_string()]).collect();
+
+    if cfg!(target_os = "macos") {
+        vec![("screencapture", args(if window { &["-x", "-w"] } else { &["-x"] }))]
+    } else if cfg!(unix) && window {
+        vec![
+            ("gnome-screenshot", args(&["-w", "-f"])),
+            ("spectacle", args(&["-b", "-n", "-a", "-o"])),
+            ("scrot", args(&["-s", "-o"])),
+            ("import", args(&[])),
+        ]
+    } else if cfg!(unix) {
+        vec![
+            ("grim", args(&[])),
+            ("gnome-screenshot", args(&["-f"])),
+            ("spectacle", args(&["-b", "-n", "-f", "-o"])),
+            ("scrot", args(&["-o"])),
+            ("import", args(&["-window", "root"])),
+        ]
+    } else {
+        Vec::new()
+    }
 }
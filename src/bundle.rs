@@ -0,0 +1,138 @@
+//! Files attached to a conversation as context, and named bundles of them.
+//!
+//! /attach adds a text file as a system message. A bundle in config.json
+//! names a set of files plus an optional note, attached together by
+//! `/bundle load <name>`; `/bundle save <name>` records the files attached
+//! to the current conversation. Attached files are recognized in the history
+//! by their header, so they survive /clear, tabs and /load like any other
+//! system message. Before each request, attachments whose file changed on
+//! disk since are found by `stale_attachments`, to be refreshed or reported.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::config::{AppConfig, BundleConfig};
+use crate::ollama::Message;
+
+/// Larger files would crowd out the conversation; /summarize-dir suits them better
+const MAX_FILE_BYTES: u64 = 100 * 1024;
+const HEADER: &str = "Attached file ";
+
+/// Reads a text file into a system message
+pub fn attach_file(path: &str) -> Result<Message> {
+    let resolved = expand_home(path);
+    let size = fs::metadata(&resolved)
+        .context(format!("Failed to read {}", path))?
+        .len();
+    if size > MAX_FILE_BYTES {
+        anyhow::bail!("{} is too large to attach ({} KB); try /summarize-dir", path, size / 1024);
+    }
+    let content = fs::read_to_string(&resolved)
+        .context(format!("Failed to read {} (only text files can be attached)", path))?;
+
+    Ok(Message {
+        role: "system".to_string(),
+        content: format!("{}{}:\n```\n{}\n```", HEADER, path, content.trim_end()),
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    })
+}
+
+/// The note of a bundle as a system message
+pub fn note_message(note: &str) -> Message {
+    Message {
+        role: "system".to_string(),
+        content: note.to_string(),
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    }
+}
+
+/// Paths of the files attached in `history`, in the order they were attached
+pub fn attached_files(history: &[Message]) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+    for message in history.iter().filter(|m| m.role == "system") {
+        let Some(rest) = message.content.strip_prefix(HEADER) else {
+            continue;
+        };
+        let Some((path, _)) = rest.split_once(":\n```") else {
+            continue;
+        };
+        if !files.iter().any(|f| f == path) {
+            files.push(path.to_string());
+        }
+    }
+    files
+}
+
+/// An attachment that no longer matches its file
+pub struct StaleAttachment {
+    /// Index of the attachment in the history
+    pub index: usize,
+    pub path: String,
+    /// The current content as an attachment, or `None` once the file is gone or unreadable
+    pub refreshed: Option<Message>,
+}
+
+/// The latest attachment of each file whose content on disk differs from
+/// it. `verified` remembers the modification time each file last matched at,
+/// so unchanged files are not read again.
+pub fn stale_attachments(history: &[Message], verified: &mut HashMap<String, SystemTime>) -> Vec<StaleAttachment> {
+    let mut stale = Vec::new();
+    for path in attached_files(history) {
+        let header = format!("{}{}:\n```", HEADER, path);
+        let Some(index) = history.iter().rposition(|m| m.role == "system" && m.content.starts_with(&header)) else {
+            continue;
+        };
+        let modified = fs::metadata(expand_home(&path)).and_then(|m| m.modified()).ok();
+        if modified.is_some() && verified.get(&path) == modified.as_ref() {
+            continue;
+        }
+
+        let refreshed = attach_file(&path).ok();
+        match (&refreshed, modified) {
+            (Some(message), Some(modified)) if message.content == history[index].content => {
+                verified.insert(path, modified);
+            }
+            _ => stale.push(StaleAttachment { index, path, refreshed }),
+        }
+    }
+    stale
+}
+
+/// Writes a bundle into config.json, replacing one with the same name
+pub fn save(name: &str, bundle: &BundleConfig) -> Result<()> {
+    let path = AppConfig::config_path()?;
+    let mut config: serde_json::Value = if path.exists() {
+        let content = fs::read_to_string(&path)
+            .context("Failed to read configuration file")?;
+        serde_json::from_str(&content)
+            .context("Failed to parse configuration")?
+    } else {
+        serde_json::json!({})
+    };
+
+    // Edited as JSON so settings this version does not know about are kept
+    let root = config.as_object_mut()
+        .context("The configuration file is not a JSON object")?;
+    let bundles = root.entry("bundles").or_insert_with(|| serde_json::json!({}));
+    let bundles = bundles.as_object_mut()
+        .context("\"bundles\" in the configuration file is not an object")?;
+    bundles.insert(name.to_string(), serde_json::to_value(bundle)?);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(&config)? + "\n")
+        .context(format!("Failed to write {}", path.display()))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
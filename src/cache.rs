@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::encryption;
+use crate::ollama::Message;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub response: String,
+    #[serde(default)]
+    pub prompt_tokens: Option<u64>,
+    #[serde(default)]
+    pub completion_tokens: Option<u64>,
+}
+
+/// A cached response as stored on disk; entries written before TTLs
+/// existed have no timestamp and count as expired once a TTL is set
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    response: CachedResponse,
+    #[serde(rename = "cachedAt", default, skip_serializing_if = "Option::is_none")]
+    cached_at: Option<u64>,
+}
+
+/// On-disk store of model responses keyed by a hash of the full request
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+}
+
+impl ResponseCache {
+    /// Opens the cache; entries older than `ttl` are treated as missing
+    pub fn open(ttl: Option<Duration>) -> Result<Self> {
+        let home = dirs::home_dir()
+            .context("Could not find home directory")?;
+        Self::at(home.join(".ai-chat-cli").join("cache").join("responses"), ttl)
+    }
+
+    /// Opens a cache kept in `dir`
+    pub fn at(dir: PathBuf, ttl: Option<Duration>) -> Result<Self> {
+        fs::create_dir_all(&dir)
+            .context("Failed to create response cache directory")?;
+
+        Ok(Self { dir, ttl })
+    }
+
+    /// Hashes everything that influences the response: model, options and messages
+    pub fn key(model: &str, options: Option<&serde_json::Value>, messages: &[Message]) -> String {
+        let request = serde_json::json!({
+            "model": model,
+            "options": options,
+            "messages": messages,
+        });
+        digest(&request.to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        let path = self.dir.join(format!("{}.json", key));
+        let content = encryption::read_to_string(&path).ok()?;
+        let entry: Entry = serde_json::from_str(&content).ok()?;
+
+        if let Some(ttl) = self.ttl {
+            let age = entry.cached_at.map(|at| now().saturating_sub(at));
+            if age.is_none_or(|age| age > ttl.as_secs()) {
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+        }
+        Some(entry.response)
+    }
+
+    pub fn put(&self, key: &str, response: &CachedResponse) -> Result<()> {
+        let entry = Entry {
+            response: response.clone(),
+            cached_at: Some(now()),
+        };
+        let json = serde_json::to_string(&entry)?;
+        encryption::write(&self.dir.join(format!("{}.json", key)), &json)
+    }
+
+    /// Number of stored entries, including expired ones not yet removed
+    pub fn len(&self) -> usize {
+        self.entries().count()
+    }
+
+    /// Deletes every entry, returning how many were removed
+    pub fn clear(&self) -> Result<usize> {
+        let mut removed = 0;
+        for path in self.entries() {
+            fs::remove_file(&path)
+                .context(format!("Failed to remove {}", path.display()))?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    fn entries(&self) -> impl Iterator<Item = PathBuf> {
+        fs::read_dir(&self.dir).into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Hex-encoded SHA-256 of the given text
+pub fn digest(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
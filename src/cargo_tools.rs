@@ -0,0 +1,195 @@
+//! The cargo_check, cargo_clippy and cargo_fmt tools.
+//!
+//! Cargo's JSON messages are reduced to one line per diagnostic (level,
+//! code, location, message) plus its help notes, with the duplicates that
+//! appear when several targets share a file removed. That is far shorter
+//! than the rendered output and tells the model exactly where to look.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::process::Output;
+use std::time::Duration;
+use tokio::process::Command;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+/// Diagnostics beyond this are counted but not listed
+const MAX_LISTED: usize = 100;
+const MAX_NOTES: usize = 2;
+
+pub struct Report {
+    pub text: String,
+    /// Errors were found or cargo itself failed
+    pub failed: bool,
+}
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    #[serde(default)]
+    message: Option<Diagnostic>,
+}
+
+#[derive(Deserialize)]
+struct Diagnostic {
+    message: String,
+    level: String,
+    #[serde(default)]
+    code: Option<DiagnosticCode>,
+    #[serde(default)]
+    spans: Vec<Span>,
+    #[serde(default)]
+    children: Vec<Diagnostic>,
+}
+
+#[derive(Deserialize)]
+struct DiagnosticCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct Span {
+    file_name: String,
+    line_start: usize,
+    column_start: usize,
+    is_primary: bool,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    suggested_replacement: Option<String>,
+}
+
+/// Runs `cargo check` or `cargo clippy` in `dir` and lists the diagnostics
+pub async fn check(dir: &Path, subcommand: &str, package: Option<&str>, all_targets: bool, timeout: Duration) -> Result<Report> {
+    let mut command = Command::new("cargo");
+    command.arg(subcommand).args(["--message-format=json", "--color", "never"]);
+    if let Some(package) = package {
+        command.args(["-p", package]);
+    }
+    if all_targets {
+        command.arg("--all-targets");
+    }
+    let output = run(command, dir, timeout, subcommand).await?;
+
+    let mut seen = HashSet::new();
+    let mut diagnostics = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Ok(CargoMessage { reason, message: Some(diagnostic) }) = serde_json::from_str::<CargoMessage>(line) else {
+            continue;
+        };
+        // Summaries like "aborting due to 2 previous errors" have no location
+        if reason != "compiler-message" || !diagnostic.spans.iter().any(|s| s.is_primary) {
+            continue;
+        }
+        let formatted = format_diagnostic(&diagnostic);
+        if seen.insert(formatted.clone()) {
+            diagnostics.push((diagnostic.level == "error", formatted));
+        }
+    }
+    // Errors first; the sort is stable, so each group keeps cargo's order
+    diagnostics.sort_by_key(|(is_error, _)| !is_error);
+
+    let errors = diagnostics.iter().filter(|(is_error, _)| *is_error).count();
+    let warnings = diagnostics.len() - errors;
+    let mut text = format!("cargo {}: {} error(s), {} warning(s)", subcommand, errors, warnings);
+
+    if diagnostics.is_empty() && !output.status.success() {
+        // Failed before compiling, e.g. a broken manifest or a missing dependency
+        text.push_str(&format!(" but cargo failed:\n{}", stderr_tail(&output)));
+        return Ok(Report { text, failed: true });
+    }
+    for (_, diagnostic) in diagnostics.iter().take(MAX_LISTED) {
+        text.push('\n');
+        text.push_str(diagnostic);
+    }
+    if diagnostics.len() > MAX_LISTED {
+        text.push_str(&format!("\n… and {} more", diagnostics.len() - MAX_LISTED));
+    }
+    Ok(Report { text, failed: errors > 0 || !output.status.success() })
+}
+
+/// Runs `cargo fmt --check` in `dir` and lists the files and lines that
+/// would change; with `apply`, formats them too
+pub async fn fmt(dir: &Path, apply: bool, timeout: Duration) -> Result<Report> {
+    let mut command = Command::new("cargo");
+    command.args(["fmt", "--check", "--", "--color", "never"]);
+    let output = run(command, dir, timeout, "fmt").await?;
+
+    // "Diff in /path/to/file.rs:12:", or "Diff in /path at line 12:" from older rustfmt
+    let diff = Regex::new(r"(?m)^Diff in (.+?)(?::| at line )(\d+):")?;
+    let root = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let mut files: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for captures in diff.captures_iter(&String::from_utf8_lossy(&output.stdout)) {
+        let path = Path::new(&captures[1]);
+        let file = path.strip_prefix(&root).unwrap_or(path).display().to_string();
+        files.entry(file).or_default().push(captures[2].to_string());
+    }
+
+    if files.is_empty() {
+        if output.status.success() {
+            return Ok(Report { text: "cargo fmt: all files are formatted".to_string(), failed: false });
+        }
+        return Ok(Report { text: format!("cargo fmt failed:\n{}", stderr_tail(&output)), failed: true });
+    }
+
+    let listing: Vec<String> = files.iter()
+        .map(|(file, lines)| format!("{} (line {})", file, lines.join(", ")))
+        .collect();
+    if !apply {
+        return Ok(Report {
+            text: format!("cargo fmt: {} file(s) need formatting:\n{}", files.len(), listing.join("\n")),
+            failed: true,
+        });
+    }
+
+    let output = run(Command::new("cargo").arg("fmt"), dir, timeout, "fmt").await?;
+    if !output.status.success() {
+        return Ok(Report { text: format!("cargo fmt failed:\n{}", stderr_tail(&output)), failed: true });
+    }
+    Ok(Report {
+        text: format!("cargo fmt: formatted {} file(s):\n{}", files.len(), listing.join("\n")),
+        failed: false,
+    })
+}
+
+async fn run(mut command: impl std::borrow::BorrowMut<Command>, dir: &Path, timeout: Duration, name: &str) -> Result<Output> {
+    let command = command.borrow_mut();
+    command.current_dir(dir).kill_on_drop(true);
+    crate::env_vars::apply(command);
+    tokio::time::timeout(timeout, command.output()).await
+        .map_err(|_| anyhow::anyhow!("cargo {} timed out after {} seconds", name, timeout.as_secs()))?
+        .context("Failed to run cargo")
+}
+
+/// `error[E0308] src/main.rs:2:18: mismatched types (expected `u32`, found `&str`)` plus help notes
+fn format_diagnostic(diagnostic: &Diagnostic) -> String {
+    let span = diagnostic.spans.iter().find(|s| s.is_primary).unwrap_or(&diagnostic.spans[0]);
+    let code = diagnostic.code.as_ref().map(|c| format!("[{}]", c.code)).unwrap_or_default();
+    let label = span.label.as_deref().filter(|l| !l.is_empty()).map(|l| format!(" ({})", l)).unwrap_or_default();
+    let mut text = format!("{}{} {}:{}:{}: {}{}", diagnostic.level, code,
+        span.file_name, span.line_start, span.column_start, diagnostic.message, label);
+
+    // Lint explanations repeat on every warning; only the concrete advice is kept
+    let notes = diagnostic.children.iter().filter(|child| {
+        !child.message.starts_with("for further information visit")
+            && !child.message.contains("on by default")
+            && !child.message.starts_with("requested on the command line")
+            && !child.message.contains("implied by")
+    });
+    for child in notes.take(MAX_NOTES) {
+        text.push_str(&format!("\n  {}: {}", child.level, child.message));
+        let replacement = child.spans.iter().find_map(|s| s.suggested_replacement.as_deref());
+        if let Some(replacement) = replacement.filter(|r| !r.is_empty() && r.len() <= 120) {
+            text.push_str(&format!(": `{}`", replacement));
+        }
+    }
+    text
+}
+
+fn stderr_tail(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+    lines[lines.len().saturating_sub(20)..].join("\n")
+}
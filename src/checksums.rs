@@ -0,0 +1,220 @@
+//! The hash_files tool: SHA-256 checksums of files named or matched by a
+//! glob, and groups of files with the same content.
+//!
+//! Directories are walked without following symlinks and without entering
+//! .git. To find duplicates, files are grouped by size first and only those
+//! sharing their size with another are read, so a tree of distinct files
+//! costs little more than listing it.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files looked at in one call, so that a glob over a whole disk stops early
+pub const MAX_FILES: usize = 10_000;
+
+/// The files named by `paths`, with directories walked, then those under
+/// `base` matching `pattern`, each once and at most `MAX_FILES` of them.
+/// A pattern without a slash matches file names at any depth, as `find -name` does.
+pub fn collect(paths: &[String], pattern: Option<&str>, base: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            walk(path, &mut files)?;
+        } else {
+            // Missing files are reported when they are hashed
+            files.push(path.to_path_buf());
+        }
+    }
+
+    if let Some(pattern) = pattern {
+        let matcher = glob_regex(pattern)?;
+        let by_name = !pattern.contains('/');
+        let mut found = Vec::new();
+        walk(base, &mut found)?;
+        files.extend(found.into_iter().filter(|file| {
+            let subject = if by_name {
+                file.file_name().unwrap_or_default().to_string_lossy().to_string()
+            } else {
+                file.strip_prefix(base).unwrap_or(file).to_string_lossy().replace('\\', "/")
+            };
+            matcher.is_match(&subject)
+        }));
+    }
+
+    // A file named and matched, or reached through two paths, is one file
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(fs::canonicalize(file).unwrap_or_else(|_| file.clone())));
+    for file in &mut files {
+        if let Ok(relative) = file.strip_prefix(".") {
+            *file = relative.to_path_buf();
+        }
+    }
+    files.truncate(MAX_FILES);
+    Ok(files)
+}
+
+/// The SHA-256 of a file's content, in hex, read a piece at a time
+pub fn sha256(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)
+        .context(format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .context(format!("Failed to read {}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// `sha256sum`-style lines, with the files that could not be read after them
+pub fn checksums_text(files: &[PathBuf]) -> String {
+    let mut lines = Vec::new();
+    let mut failures = Vec::new();
+    for file in files {
+        match sha256(file) {
+            Ok(hash) => lines.push(format!("{}  {}", hash, file.display())),
+            Err(e) => failures.push(format!("{}: {:#}", file.display(), e)),
+        }
+    }
+    lines.extend(failures);
+    lines.join("\n")
+}
+
+pub fn checksums_json(files: &[PathBuf]) -> Value {
+    files.iter()
+        .map(|file| match sha256(file) {
+            Ok(hash) => json!({
+                "path": file,
+                "size": fs::metadata(file).map(|m| m.len()).ok(),
+                "sha256": hash,
+            }),
+            Err(e) => json!({ "path": file, "error": format!("{:#}", e) }),
+        })
+        .collect()
+}
+
+/// Files with the same content
+pub struct DuplicateGroup {
+    pub sha256: String,
+    pub size: u64,
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Bytes taken by all copies but one
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// Groups of two or more files with identical content, those wasting the
+/// most space first. Empty files are all alike and left out, as are files
+/// that cannot be read.
+pub fn duplicates(files: &[PathBuf]) -> Vec<DuplicateGroup> {
+    let mut by_size: HashMap<u64, Vec<&PathBuf>> = HashMap::new();
+    for file in files {
+        if let Ok(metadata) = fs::metadata(file)
+            && metadata.is_file()
+            && metadata.len() > 0
+        {
+            by_size.entry(metadata.len()).or_default().push(file);
+        }
+    }
+
+    let mut groups = Vec::new();
+    for (size, candidates) in by_size.into_iter().filter(|(_, files)| files.len() > 1) {
+        let mut by_hash: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+        for file in candidates {
+            if let Ok(hash) = sha256(file) {
+                by_hash.entry(hash).or_default().push(file.clone());
+            }
+        }
+        groups.extend(by_hash.into_iter()
+            .filter(|(_, paths)| paths.len() > 1)
+            .map(|(sha256, mut paths)| {
+                paths.sort();
+                DuplicateGroup { sha256, size, paths }
+            }));
+    }
+    groups.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then_with(|| a.paths.cmp(&b.paths)));
+    groups
+}
+
+pub fn duplicates_text(groups: &[DuplicateGroup], files: usize) -> String {
+    if groups.is_empty() {
+        return format!("No duplicates among {} file(s)", files);
+    }
+    let wasted: u64 = groups.iter().map(DuplicateGroup::wasted).sum();
+    let mut text = format!("{} group(s) of duplicates among {} file(s), {} bytes in extra copies\n",
+        groups.len(), files, wasted);
+    for group in groups {
+        text.push_str(&format!("\n{} {} copies of {} bytes\n", &group.sha256[..12], group.paths.len(), group.size));
+        for path in &group.paths {
+            text.push_str(&format!("  {}\n", path.display()));
+        }
+    }
+    text
+}
+
+pub fn duplicates_json(groups: &[DuplicateGroup]) -> Value {
+    groups.iter()
+        .map(|group| json!({
+            "sha256": group.sha256,
+            "size": group.size,
+            "wasted": group.wasted(),
+            "paths": group.paths,
+        }))
+        .collect()
+}
+
+/// Every file below `dir`, stopping once `MAX_FILES` are found
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .context(format!("Failed to read directory: {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        if files.len() >= MAX_FILES {
+            break;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() && entry.file_name() != ".git" {
+            // An unreadable directory does not spoil the rest
+            let _ = walk(&entry.path(), files);
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// `**/` is any number of directories, `*` and `?` stay within one
+fn glob_regex(pattern: &str) -> Result<Regex> {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).context(format!("Invalid glob pattern: {}", pattern))
+}
@@ -1,278 +1,2263 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::*;
 use rustyline::error::ReadlineError;
-use rustyline::DefaultEditor;
+use rustyline::{DefaultEditor, ExternalPrinter};
+use crate::accessibility;
+use crate::agent;
+use crate::backend;
+use crate::bundle;
+use crate::cache::ResponseCache;
+use crate::config::{AppConfig, AutoResume, BundleConfig, Provider, StaleAttachments, ToolSelection};
+use crate::editor;
+use crate::encryption;
+use crate::error::Failure;
 use crate::executor::AIExecutor;
+use crate::filters;
+use crate::github;
+use crate::i18n::{self, tr};
+use crate::image;
+use crate::journal;
+use crate::lifecycle::{self, TerminalState};
+use crate::mapreduce::{self, DEFAULT_CHUNK_SIZE};
+use crate::mcp_client::InvalidArguments;
 use crate::mcp_manager::McpManager;
+use crate::notify;
 use crate::ollama::Message;
+use crate::output::{self, say, say_error, status};
+use crate::policy::{Approval, ApprovalRequest};
+use crate::pr::{self, BranchChanges};
+use crate::prefix_cache::{self, PrefixTracker};
+use crate::queue::{OfflineQueue, QueuedPrompt};
+use crate::recall;
+use crate::repo_map;
+use crate::resume;
+use crate::session::{Session, SessionStats};
+use crate::share;
+use crate::shell_history;
+use crate::thoughts;
+use crate::tokens;
+use crate::tool_format;
+use crate::tool_output;
+use crate::tool_selection;
+use crate::tool_stats;
+use crate::training::{self, TrainingFormat};
+use crate::voice;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::fs;
-use serde_json;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc as std_mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+enum Input {
+    Line(String),
+    Interrupted,
+    Eof,
+}
+
+/// Where the live status line of a generating reply is drawn
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Footer {
+    /// On its own line just above the open prompt
+    AbovePrompt,
+    /// On the current line of stderr, without a newline
+    Inline,
+}
+
+/// Runs readline on its own thread so the prompt can stay open while a
+/// reply is generating
+struct InputReader {
+    prompts: std_mpsc::Sender<String>,
+    lines: mpsc::UnboundedReceiver<Input>,
+    /// Whether a prompt is currently shown and waiting for a line
+    reading: bool,
+    closed: bool,
+    /// The status line currently shown, replaced by the next update or print
+    footer: Option<Footer>,
+}
+
+impl InputReader {
+    fn spawn(mut rl: DefaultEditor) -> Self {
+        // Only available on a terminal; elsewhere output is printed directly
+        output::set_printer(rl.create_external_printer()
+            .ok()
+            .map(|p| Box::new(p) as Box<dyn ExternalPrinter + Send>));
+
+        let (prompts, prompt_rx) = std_mpsc::channel::<String>();
+        let (line_tx, lines) = mpsc::unbounded_channel();
+
+        thread::spawn(move || {
+            for prompt in prompt_rx {
+                let input = match rl.readline(&prompt) {
+                    Ok(line) => {
+                        // Add line to readline history
+                        let trimmed = line.trim();
+                        if !trimmed.is_empty() && !trimmed.starts_with('/') {
+                            let _ = rl.add_history_entry(trimmed);
+                        }
+                        Input::Line(line)
+                    }
+                    Err(ReadlineError::Interrupted) => Input::Interrupted,
+                    Err(ReadlineError::Eof) => Input::Eof,
+                    Err(err) => {
+                        eprintln!("{} {:?}", tr!("label.error"), err);
+                        Input::Eof
+                    }
+                };
+
+                if line_tx.send(input).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { prompts, lines, reading: false, closed: false, footer: None }
+    }
+
+    /// Shows a prompt unless one is already open
+    fn request(&mut self, prompt: String) {
+        if !self.reading && !self.closed {
+            self.reading = self.prompts.send(prompt).is_ok();
+            output::set_prompt_open(self.reading);
+        }
+    }
+
+    async fn recv(&mut self) -> Input {
+        if !self.reading {
+            return std::future::pending().await;
+        }
+
+        let input = self.lines.recv().await.unwrap_or(Input::Eof);
+        self.reading = false;
+        output::set_prompt_open(false);
+        if matches!(input, Input::Eof) {
+            self.closed = true;
+        }
+        input
+    }
+
+    async fn next(&mut self, prompt: String) -> Input {
+        if self.closed {
+            return Input::Eof;
+        }
+        self.request(prompt);
+        self.recv().await
+    }
+
+    /// Prints above the open prompt without disturbing what is being typed
+    fn print(&mut self, text: String) {
+        let clear = self.clear_footer();
+        output::line(format!("{}{}", clear, text));
+    }
+
+    /// Shows `text` as the status line, in place of the previous one
+    fn footer(&mut self, text: String) {
+        let clear = self.clear_footer();
+        if output::above_prompt(&format!("{}{}", clear, text)) {
+            self.footer = Some(Footer::AbovePrompt);
+        } else {
+            eprint!("{}", text);
+            let _ = io::Write::flush(&mut io::stderr());
+            self.footer = Some(Footer::Inline);
+        }
+    }
+
+    /// Erases a shown status line, or returns the escape codes that make
+    /// the next external print overwrite it
+    fn clear_footer(&mut self) -> &'static str {
+        let above_prompt = output::prompt_open();
+        match self.footer.take() {
+            Some(Footer::AbovePrompt) if above_prompt => "\x1b[1A\r\x1b[2K",
+            Some(Footer::Inline) => {
+                eprint!("\r\x1b[2K");
+                ""
+            }
+            _ => "",
+        }
+    }
+}
+
+/// How far a streamed reply has come, for the live status line
+#[derive(Default)]
+struct ReplyProgress {
+    /// Pieces received, about one token each
+    pieces: u64,
+    first_piece: Option<Instant>,
+}
+
+impl ReplyProgress {
+    fn received(&mut self) {
+        self.pieces += 1;
+        self.first_piece.get_or_insert_with(Instant::now);
+    }
+
+    /// Tokens per second since the first one arrived, once there is enough to tell
+    fn rate(&self, tokens: u64) -> Option<f64> {
+        let elapsed = self.first_piece?.elapsed();
+        (tokens > 1 && elapsed >= MIN_RATE_WINDOW).then(|| tokens as f64 / elapsed.as_secs_f64())
+    }
+
+    /// `⠹ llama3.2 · 128 tokens · 21.4 tok/s · 6.0s`
+    fn footer(&self, model: &str, started: Instant, frame: usize) -> String {
+        let spinner = SPINNER[frame % SPINNER.len()];
+        let elapsed = started.elapsed().as_secs_f64();
+        let text = match self.rate(self.pieces) {
+            _ if self.pieces == 0 => format!("{} {} · {:.1}s", spinner, tr!("footer.waiting", model = model), elapsed),
+            Some(rate) => format!("{} {} · {} · {:.1} tok/s · {:.1}s", spinner, model,
+                tr!("footer.tokens", count = self.pieces), rate, elapsed),
+            None => format!("{} {} · {} · {:.1}s", spinner, model, tr!("footer.tokens", count = self.pieces), elapsed),
+        };
+        text.bright_black().to_string()
+    }
+
+    /// The line left after the reply, using the model's own token count when it gives one
+    fn summary(&self, eval_count: Option<u64>, started: Instant) -> String {
+        let tokens = eval_count.unwrap_or(self.pieces);
+        let mut text = tr!("footer.summary", count = tokens, seconds = format!("{:.1}", started.elapsed().as_secs_f64()));
+        if let Some(rate) = self.rate(tokens) {
+            text.push_str(&format!(" · {:.1} tok/s", rate));
+        }
+        text.bright_black().to_string()
+    }
+}
+
+/// A conversation that is open but not currently active
+#[derive(Default)]
+struct Tab {
+    history: Vec<Message>,
+    pinned: BTreeSet<usize>,
+    tags: Vec<String>,
+    checkpoints: Vec<Checkpoint>,
+    model: String,
+}
+
+/// The conversation and the file journal as they were at a /checkpoint
+struct Checkpoint {
+    name: String,
+    history: Vec<Message>,
+    pinned: BTreeSet<usize>,
+    journal: u64,
+}
 
 pub struct ChatCLI {
     executor: AIExecutor,
     history: Vec<Message>,
+    /// Indices into `history` that /clear must keep
+    pinned: BTreeSet<usize>,
+    /// Labels from /tag, saved with the conversation
+    tags: Vec<String>,
+    /// Snapshots from /checkpoint, oldest first
+    checkpoints: Vec<Checkpoint>,
+    /// Modification time each attached file last matched its attachment at
+    verified_attachments: HashMap<String, SystemTime>,
+    /// Open conversations; the entry at `current` is a placeholder because
+    /// the active one lives in `history` and the executor's model
+    tabs: Vec<Tab>,
+    current: usize,
+    /// Messages typed while a reply was generating, sent in order afterwards
+    queue: VecDeque<String>,
+    /// Images from /image and /paste-image, sent with the next message
+    attachments: Vec<String>,
+    confirm: bool,
+    /// Whether tool calls in replies are run and the model asked again
+    agent: bool,
+    /// Replies that failed, reported through the exit code of piped sessions
+    failed_turns: usize,
+    /// Messages answered, for the summary on exit
+    turns: usize,
+    started: Instant,
+    /// Whether a message was refused because the monthly budget ran out
+    budget_exhausted: bool,
+    /// How much of each request could be served from the backend's prompt cache
+    prefix: PrefixTracker,
+    /// Context window per model, looked up once via /api/show
+    context_windows: HashMap<String, Option<usize>>,
+    /// False while Ollama is unreachable; reconnection is retried while idle
+    online: bool,
+    /// The model last used on each backend switched away from, for /backend to return to
+    backend_models: HashMap<Provider, String>,
+    /// Where prompts that cannot be sent are kept (--queue-on-failure)
+    offline_queue: Option<OfflineQueue>,
     mcp_manager: Option<McpManager>,
+    /// The tool list currently in the system prompt, replaced when it changes
+    tools_prompt: Option<String>,
+    config: AppConfig,
+    /// Built once at startup when "repoMap" is enabled, for every new conversation
+    repo_map: Option<String>,
+    terminal: TerminalState,
 }
 
 impl ChatCLI {
-    pub fn new(executor: AIExecutor, mcp_manager: Option<McpManager>) -> Self {
+    pub fn new(executor: AIExecutor, mcp_manager: Option<McpManager>, config: AppConfig, online: bool) -> Self {
         let mut cli = Self {
             executor,
             history: Vec::new(),
+            pinned: BTreeSet::new(),
+            tags: Vec::new(),
+            checkpoints: Vec::new(),
+            verified_attachments: HashMap::new(),
+            tabs: vec![Tab::default()],
+            current: 0,
+            queue: VecDeque::new(),
+            attachments: Vec::new(),
+            confirm: config.confirm_before_send,
+            agent: config.agent.enabled,
+            failed_turns: 0,
+            turns: 0,
+            started: Instant::now(),
+            budget_exhausted: false,
+            prefix: PrefixTracker::default(),
+            context_windows: HashMap::new(),
+            online,
+            backend_models: HashMap::new(),
+            offline_queue: None,
+            tools_prompt: None,
             mcp_manager,
+            repo_map: repo_map::startup_context(&config.repo_map),
+            config,
+            terminal: TerminalState::capture(),
         };
-    
-        // Auto-inject MCP tools into context
-        if let Some(mcp) = &cli.mcp_manager {
-            if mcp.has_tools() {
-                let tools = mcp.list_tools();
-                let mut msg = String::from("SYSTEM: You have access to these MCP tools:\n\n");
-                for t in tools {
-                    msg.push_str(&format!("- {}: {}\n", t.name, t.description));
-                }
-                msg.push_str("\nWhen relevant, tell users they can execute these with /mcp-call <tool> <args>");
-            
-                cli.history.push(Message {
-                    role: "system".to_string(),
-                    content: msg,
-                });
-            }
+        if let Some(mcp) = &mut cli.mcp_manager {
+            mcp.set_agent_mode(cli.agent);
+            cli.tools_prompt = mcp.tools_prompt();
         }
-    
+        cli.history = cli.initial_history();
         cli
     }
 
+    fn initial_history(&self) -> Vec<Message> {
+        // Auto-inject the preferred language, the repo map and MCP tools into context
+        self.config.language_prompt().into_iter()
+            .chain(self.repo_map.clone())
+            .chain(self.tools_prompt.clone())
+            .map(|prompt| Message {
+                role: "system".to_string(),
+                content: prompt,
+                images: Vec::new(),
+                tool_calls: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Queues prompts that fail to send instead of counting them as failed turns
+    pub fn set_offline_queue(&mut self, queue: OfflineQueue) {
+        self.offline_queue = Some(queue);
+    }
+
+    /// Stores the conversation up to `text` for `flush-queue`; false without a queue
+    fn queue_prompt(&self, text: &str, error: String, input: &mut InputReader) -> bool {
+        let Some(queue) = &self.offline_queue else {
+            return false;
+        };
+
+        let mut messages = self.history.clone();
+        if messages.last().is_none_or(|m| m.role != "user" || m.content != text) {
+            messages.push(Message {
+                role: "user".to_string(),
+                content: text.to_string(),
+                images: Vec::new(),
+                tool_calls: Vec::new(),
+            });
+        }
+
+        let note = format!("{} {}", "⏳".bright_yellow(),
+            tr!("chat.queued_for", command = "ai-chat-cli flush-queue".bright_cyan()));
+        let prompt = QueuedPrompt::new("chat", self.executor.get_model(), messages, error);
+        match queue.push(&prompt) {
+            Ok(()) => {
+                if output::is_quiet() {
+                    say_error!("{}", note);
+                } else {
+                    input.print(note);
+                }
+                true
+            }
+            Err(e) => {
+                say_error!("{} {:#}", tr!("label.warning").bright_yellow(), e);
+                false
+            }
+        }
+    }
+
+    /// Releases MCP servers and the terminal; call once `run` has returned
+    pub async fn shutdown(&mut self) {
+        lifecycle::shutdown(self.mcp_manager.take(), &self.terminal).await;
+    }
+
+    /// Saves every open conversation that has more than system context,
+    /// for when the process is stopped by a signal instead of /quit
+    pub fn save_open_conversations(&mut self) -> Result<Vec<PathBuf>> {
+        let dir = lifecycle::sessions_dir()?;
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+
+        self.tabs[self.current].history = std::mem::take(&mut self.history);
+        self.tabs[self.current].tags = self.tags.clone();
+        let saved: Result<Vec<PathBuf>> = self.tabs.iter().enumerate()
+            .filter(|(_, tab)| tab.history.iter().any(|m| m.role != "system"))
+            .map(|(i, tab)| {
+                fs::create_dir_all(&dir)
+                    .context(tr!("file.create_failed", path = dir.display()))?;
+                let path = dir.join(format!("interrupted-{}-{}.json", stamp, i + 1));
+                let session = Session {
+                    title: Some(tr!("session.interrupted_title", number = i + 1)),
+                    tags: std::iter::once("interrupted".to_string()).chain(tab.tags.iter().cloned()).collect(),
+                    stats: Some(self.session_stats()),
+                    messages: tab.history.clone(),
+                    ..Default::default()
+                };
+                session.save(&path)?;
+                Ok(path)
+            })
+            .collect();
+        // The conversation goes back before a failure is passed on
+        self.history = std::mem::take(&mut self.tabs[self.current].history);
+        let saved = saved?;
+        if self.resumes_per_directory() {
+            self.keep_for_directory()?;
+        }
+
+        Ok(saved)
+    }
+
     pub fn save_conversation(&self, filename: &str) -> Result<()> {
-        let json = serde_json::to_string_pretty(&self.history)?;
-        fs::write(filename, json)?;
-        println!("Conversation saved to {}", filename);
+        // Deterministic runs keep the model and seed with the messages so they can be reproduced;
+        // without tags or a seed the file stays a bare message array
+        let seed = self.executor.generation().pinned_seed();
+        if seed.is_some() || !self.tags.is_empty() {
+            Session {
+                tags: self.tags.clone(),
+                model: seed.map(|_| self.executor.get_model().to_string()),
+                seed,
+                messages: self.history.clone(),
+                ..Default::default()
+            }.save(Path::new(filename))?;
+        } else {
+            encryption::write(Path::new(filename), &serde_json::to_string_pretty(&self.history)?)?;
+        }
+        let message = if encryption::is_enabled() { "chat.saved_encrypted" } else { "chat.saved" };
+        say!("{}", tr!(message, file = filename));
         Ok(())
     }
 
     pub fn load_conversation(&mut self, filename: &str) -> Result<()> {
-        let json = fs::read_to_string(filename)?;
-        self.history = serde_json::from_str(&json)?;
-        println!("Conversation loaded from {}", filename);
+        let session = Session::load(Path::new(filename))?;
+        say!("{}", tr!("chat.loaded", file = filename));
+        self.restore_session(session);
+        Ok(())
+    }
+
+    /// Makes a saved conversation the current one, with its tags and seed
+    fn restore_session(&mut self, session: Session) {
+        self.history = session.messages;
+        self.pinned.clear();
+        self.tags = session.tags;
+        if !self.tags.is_empty() {
+            say!("{} {}", tr!("session.tags").bright_black(), self.tags.join(", "));
+        }
+
+        if let Some(seed) = session.seed {
+            let generation = self.executor.generation_mut();
+            generation.deterministic = true;
+            generation.seed = Some(seed);
+            say!("{} {}", "ℹ".bright_blue(), tr!("session.seed_restored", seed = seed));
+            if let Some(model) = session.model.filter(|m| m != self.executor.get_model()) {
+                say!("{} {}", tr!("label.warning").bright_yellow(), tr!("session.recorded_with",
+                    model = model.bright_cyan(), command = format!("{} {}", "/model".bright_cyan(), model)));
+            }
+        }
+    }
+
+    /// Picks up the last conversation held in the working directory, asking
+    /// first unless "autoResume" is "always"
+    async fn offer_resume(&mut self, input: &mut InputReader) {
+        let Ok(dir) = resume::current_dir() else {
+            return;
+        };
+        let Some((session, saved)) = resume::find(&dir) else {
+            return;
+        };
+
+        let saved = chrono::DateTime::<chrono::Local>::from(saved).format("%Y-%m-%d %H:%M");
+        let messages = session.messages.iter().filter(|m| m.role != "system").count();
+        if self.config.auto_resume == AutoResume::Ask {
+            let question = format!("{} ", tr!("session.resume_question", saved = saved, messages = messages));
+            match input.next(question.bright_yellow().to_string()).await {
+                Input::Line(answer) if matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes") => {}
+                _ => {
+                    say!("{}", tr!("session.resume_declined").bright_black());
+                    return;
+                }
+            }
+        }
+
+        // Back on the model it was held with, when that is still installed
+        if let Some(model) = session.model.clone().filter(|m| m != self.executor.get_model())
+            && let Err(e) = self.executor.switch_model(model).await
+        {
+            say!("{} {:#}", tr!("label.warning").bright_yellow(), e);
+        }
+        say!("{} {}", "✓".bright_green(), tr!("session.resumed",
+            saved = saved, messages = messages, model = self.executor.get_model().bright_cyan()));
+        self.restore_session(session);
+    }
+
+    /// Interactive sessions only; scripts piping prompts neither resume nor leave anything behind
+    fn resumes_per_directory(&self) -> bool {
+        self.config.auto_resume != AutoResume::Off && io::stdin().is_terminal()
+    }
+
+    /// Keeps the current conversation for the next start in this directory
+    fn keep_for_directory(&self) -> Result<()> {
+        if !self.history.iter().any(|m| m.role != "system") {
+            return Ok(());
+        }
+        let seed = self.executor.generation().pinned_seed();
+        resume::save(&resume::current_dir()?, Session {
+            tags: self.tags.clone(),
+            model: Some(self.executor.get_model().to_string()),
+            seed,
+            stats: Some(self.session_stats()),
+            messages: self.history.clone(),
+            ..Default::default()
+        })
+    }
+
+    fn session_stats(&self) -> SessionStats {
+        let usage = self.executor.usage().session_total();
+        SessionStats {
+            turns: self.turns,
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            tool_calls: tool_stats::session(),
+            files_modified: journal::changed_files().iter().map(|path| path.display().to_string()).collect(),
+            duration_secs: self.started.elapsed().as_secs(),
+        }
+    }
+
+    /// `Session: 12m 04s · 8 turns · 18.4k tokens in, 3.1k out`, then the tools
+    /// called and files changed; nothing when the session did nothing
+    fn print_session_stats(&self) {
+        let stats = self.session_stats();
+        if stats.turns == 0 && stats.tool_calls.is_empty() {
+            return;
+        }
+
+        say!("\n{} {}", tr!("summary.session").bright_yellow().bold(), tr!("summary.totals",
+            duration = format_duration(stats.duration_secs), turns = stats.turns,
+            prompt = tokens::format_count(stats.prompt_tokens as usize),
+            completion = tokens::format_count(stats.completion_tokens as usize)));
+        if !stats.tool_calls.is_empty() {
+            let mut calls: Vec<_> = stats.tool_calls.iter().collect();
+            calls.sort_by_key(|(tool, calls)| (std::cmp::Reverse(**calls), *tool));
+            let calls: Vec<String> = calls.iter().map(|(tool, calls)| format!("{} ×{}", tool.bright_cyan(), calls)).collect();
+            say!("  {} {}", tr!("summary.tools"), calls.join(", "));
+        }
+        if !stats.files_modified.is_empty() {
+            say!("  {} {}", tr!("summary.files"), stats.files_modified.join(", "));
+        }
+    }
+
+    /// Saved conversations, newest first: those in ~/.ai-chat-cli/sessions,
+    /// "recall.dirs" and every file written by /save
+    async fn list_sessions(&self, tag: Option<&str>) -> Result<()> {
+        let mut sessions = Vec::new();
+        for path in recall::session_files().await? {
+            let Ok(session) = Session::load(&path) else {
+                continue;
+            };
+            if tag.is_some_and(|tag| !session.has_tag(tag)) {
+                continue;
+            }
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            sessions.push((modified, path, session));
+        }
+        sessions.sort_by_key(|(modified, _, _)| std::cmp::Reverse(*modified));
+
+        if sessions.is_empty() {
+            match tag {
+                Some(tag) => say!("{}", tr!("session.none_tagged", tag = tag).yellow()),
+                None => say!("{}", tr!("session.none").yellow()),
+            }
+            return Ok(());
+        }
+        say!("\n{}", tr!("session.title").bright_yellow().bold());
+        for (modified, path, session) in &sessions {
+            let date = modified.map(|m| chrono::DateTime::<chrono::Local>::from(m).format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            let title = session.title.as_deref().map(|t| format!(" {}", t)).unwrap_or_default();
+            let tags = if session.tags.is_empty() { String::new() } else { format!(" [{}]", session.tags.join(", ")) };
+            say!("  {} {}{}{} {}", date.bright_black(), path.display().to_string().bright_cyan(),
+                title, tags.bright_green(), tr!("session.messages", count = session.messages.len()).bright_black());
+        }
+        say!("\n{}\n", tr!("session.open_hint", command = format!("{} <file>", "/load".bright_cyan())));
         Ok(())
     }
 
     pub async fn run(&mut self) -> Result<()> {
-        self.print_welcome();
+        if !output::is_quiet() {
+            self.print_welcome();
+        }
 
-        let mut rl = DefaultEditor::new()?;
+        let mut input = InputReader::spawn(editor::build(&self.config.editor)?);
+        if self.resumes_per_directory() {
+            self.offer_resume(&mut input).await;
+        }
 
         loop {
-            let prompt = format!("{} ", "You:".bright_green().bold());
-            
-            match rl.readline(&prompt) {
-                Ok(line) => {
-                    let input = line.trim();
-                    
-                    if input.is_empty() {
-                        continue;
-                    }
+            if self.online {
+                self.load_context_window().await;
+            }
 
-                    // Handle commands
-                    if input.starts_with('/') {
-                        if !self.handle_command(input).await? {
-                            break;
+            // Messages typed while a reply was generating go first
+            let line = match self.queue.pop_front() {
+                Some(line) => {
+                    if !output::is_quiet() {
+                        input.print(format!("{}{}", self.prompt(), line));
+                        if !self.queue.is_empty() {
+                            input.print(tr!("chat.more_queued", count = self.queue.len()).bright_black().to_string());
+                        }
+                    }
+                    line
+                }
+                None => {
+                    let next = tokio::select! {
+                        next = input.next(self.prompt()) => next,
+                        _ = tokio::time::sleep(RECONNECT_INTERVAL), if !self.online => {
+                            // The open prompt stays as it is; only news is printed above it
+                            if let Ok(warning) = self.reconnect().await {
+                                input.print(format!("{} {}", "✓".bright_green(), tr!("chat.reconnected")));
+                                if let Some(warning) = warning {
+                                    input.print(warning);
+                                }
+                            }
+                            continue;
+                        }
+                    };
+                    match next {
+                        Input::Line(line) => line,
+                        Input::Interrupted => {
+                            say!("{}", tr!("chat.use_quit").yellow());
+                            continue;
                         }
-                        continue;
+                        Input::Eof => break,
                     }
+                }
+            };
 
-                    // Add line to readline history
-                    rl.add_history_entry(input)?;
+            let expanded = self.expand_alias(line.trim());
+            let input_text = expanded.as_str();
+            if input_text.is_empty() {
+                continue;
+            }
 
-                    // Add user message to history
-                    self.history.push(Message {
-                        role: "user".to_string(),
-                        content: input.to_string(),
-                    });
+            // Handle commands
+            if input_text.starts_with('/') {
+                if !self.handle_command(input_text, &mut input).await? {
+                    break;
+                }
+                continue;
+            }
 
-                    // Get AI response
-                    print!("{} ", "AI:".bright_blue().bold());
-                    
-                    match self.executor.chat(self.history.clone()).await {
-                        Ok(response) => {
-                            println!("{}\n", response.bright_white());
-                            
-                            // Add assistant response to history
-                            self.history.push(Message {
-                                role: "assistant".to_string(),
-                                content: response,
-                            });
-                        }
-                        Err(e) => {
-                            eprintln!("{} {}\n", "Error:".bright_red().bold(), e);
+            self.send_message(input_text, &mut input).await;
+        }
+
+        if !output::is_quiet() {
+            self.print_session_stats();
+        }
+        if self.resumes_per_directory() && let Err(e) = self.keep_for_directory() {
+            say_error!("{} {}", tr!("label.warning").bright_yellow(), tr!("session.keep_failed", error = format!("{:#}", e)));
+        }
+
+        // Scripts feeding prompts on stdin need to know a reply went missing
+        if self.failed_turns > 0 && !io::stdin().is_terminal() {
+            let failure = if self.budget_exhausted { Failure::BudgetExhausted } else { Failure::Generation };
+            return Err(anyhow::anyhow!(tr!("chat.requests_failed", count = self.failed_turns))
+                .context(failure));
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether Ollama is reachable, returning a warning when it is
+    /// but the current model is not installed
+    async fn reconnect(&mut self) -> Result<Option<String>> {
+        let model = self.executor.get_model();
+        let installed = self.executor.has_model(model).await?;
+        self.online = true;
+
+        Ok((!installed).then(|| match self.executor.provider() {
+            Provider::Ollama => format!("{} {}", tr!("label.warning").bright_yellow(),
+                tr!("chat.model_not_installed", model = model, command = format!("ollama pull {}", model).bright_cyan())),
+            provider => format!("{} {}", tr!("label.warning").bright_yellow(),
+                tr!("chat.model_unavailable", model = model, backend = provider.name())),
+        }))
+    }
+
+    /// Shows what the next request contains and asks whether to send it
+    async fn confirm_send(&self, input: &mut InputReader) -> bool {
+        let count = |role: &str| self.history.iter().filter(|m| m.role == role).count();
+
+        input.print(tr!("preview.title").bright_black().to_string());
+        input.print(format!("  {} {}", tr!("preview.model"), self.executor.get_model().bright_cyan()));
+        input.print(format!("  {} {}", tr!("preview.messages"), tr!("preview.message_counts",
+            total = self.history.len(), system = count("system"), user = count("user"), assistant = count("assistant"))));
+        input.print(format!("  {} {}", tr!("preview.tokens"),
+            tr!("preview.estimated", tokens = tokens::format_count(tokens::estimate(&self.history)))));
+
+        let native = self.mcp_manager.as_ref().and_then(McpManager::native_tools);
+        let tools = match (&self.mcp_manager, &self.tools_prompt, native) {
+            (Some(mcp), _, Some(native)) => tr!("preview.tools_native",
+                sent = native.len(), total = mcp.ranked_tools().len()),
+            (Some(mcp), Some(prompt), None) if self.history.iter().any(|m| &m.content == prompt) => {
+                let ranked = mcp.ranked_tools();
+                tr!("preview.tools_prompt", sent = ranked.iter().filter(|t| t.sent).count(), total = ranked.len())
+            }
+            _ => tr!("preview.no_tools").to_string(),
+        };
+        input.print(format!("  {} {}", tr!("preview.tools"), tools));
+
+        match input.next(format!("{} ", tr!("chat.confirm_send").bright_yellow())).await {
+            Input::Line(answer) => matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes"),
+            Input::Interrupted | Input::Eof => false,
+        }
+    }
+
+    /// Replaces a configured alias in the first word of a command, keeping
+    /// any arguments; chat messages are sent as typed
+    fn expand_alias(&self, input: &str) -> String {
+        if !input.starts_with('/') {
+            return input.to_string();
+        }
+        let (name, rest) = input.split_once(' ').unwrap_or((input, ""));
+
+        match self.config.aliases.get(name) {
+            Some(target) if rest.is_empty() => target.clone(),
+            Some(target) => format!("{} {}", target, rest),
+            None => input.to_string(),
+        }
+    }
+
+    fn prompt(&self) -> String {
+        if output::is_quiet() {
+            return String::new();
+        }
+
+        let images = if self.attachments.is_empty() {
+            String::new()
+        } else {
+            format!(" 📎{}", self.attachments.len())
+        };
+        let label = if self.tabs.len() > 1 {
+            format!("{} [{}]{}:", tr!("role.user"), self.current + 1, images)
+        } else {
+            format!("{}{}:", tr!("role.user"), images)
+        };
+        let status = if self.online {
+            self.context_meter()
+        } else {
+            format!("[{}]", tr!("chat.offline")).bright_red()
+        };
+        format!("{} {} ", status, label.bright_green().bold())
+    }
+
+    async fn load_context_window(&mut self) {
+        let model = self.executor.get_model().to_string();
+        if !self.context_windows.contains_key(&model) {
+            // Without a known window the meter shows usage alone
+            let window = self.executor.context_window(&model).await.ok().flatten();
+            self.context_windows.insert(model, window);
+        }
+    }
+
+    /// Estimated tokens in the conversation against the model's context window, e.g. [3.2k/8k]
+    fn context_meter(&self) -> ColoredString {
+        let used = tokens::estimate(&self.history);
+        let window = self.context_windows.get(self.executor.get_model()).copied().flatten();
+
+        match window {
+            Some(window) => {
+                let meter = format!("[{}/{}]", tokens::format_count(used), tokens::format_window(window));
+                match used * 100 / window.max(1) {
+                    0..70 => meter.bright_black(),
+                    70..90 => meter.yellow(),
+                    _ => meter.bright_red(),
+                }
+            }
+            None => format!("[{}]", tokens::format_count(used)).bright_black(),
+        }
+    }
+
+    /// Sends a message while keeping the prompt open, so anything typed
+    /// before the reply arrives is queued for the following turns
+    async fn send_message(&mut self, text: &str, input: &mut InputReader) {
+        if !self.online && let Err(e) = self.reconnect().await {
+            if !self.queue_prompt(text, format!("{:#}", e), input) {
+                input.print(format!("{} {}", tr!("label.error").bright_red().bold(),
+                    tr!("chat.offline_not_sent", seconds = RECONNECT_INTERVAL.as_secs())));
+            }
+            return;
+        }
+
+        if let Err(e) = self.executor.check_budget() {
+            self.failed_turns += 1;
+            self.budget_exhausted = true;
+            if output::is_quiet() {
+                say_error!("{} {:#}", tr!("label.error").bright_red().bold(), e);
+            } else {
+                input.print(format!("{} {:#}", tr!("label.error").bright_red().bold(), e));
+            }
+            return;
+        }
+
+        self.select_tools(text).await;
+        self.sync_mcp().await;
+        let refresh = self.config.stale_attachments == StaleAttachments::Refresh;
+        for line in self.check_attachments(refresh) {
+            input.print(line);
+        }
+
+        // Add user message to history
+        self.history.push(Message {
+            role: "user".to_string(),
+            content: text.to_string(),
+            images: std::mem::take(&mut self.attachments),
+            tool_calls: Vec::new(),
+        });
+
+        if self.confirm && !self.confirm_send(input).await {
+            // Keep the images for the next attempt
+            if let Some(message) = self.history.pop() {
+                self.attachments = message.images;
+            }
+            input.print(tr!("chat.not_sent").yellow().to_string());
+            return;
+        }
+
+        // Lines typed while the reply is generating are queued; the prompt
+        // is the usual one, as it stays open once the reply is done
+        let prompt = self.prompt();
+        let max_iterations = self.config.agent.max_iterations.unwrap_or(agent::DEFAULT_MAX_ITERATIONS);
+        let mut replies = 0;
+        // In agent mode the tool a reply calls is run and the model asked again
+        while let Some(reply) = self.complete_turn(text, replies == 0, &prompt, input).await {
+            replies += 1;
+            let Some(call) = agent::reply_tool_call(&reply).filter(|_| self.agent) else {
+                break;
+            };
+            if replies >= max_iterations {
+                input.print(format!("{} {}", tr!("label.warning").bright_yellow(),
+                    tr!("agent.max_iterations", count = max_iterations)));
+                break;
+            }
+            if let Err(e) = self.call_mcp_tool(&call.name, call.arguments, input).await {
+                input.print(format!("{} {:#}", tr!("label.error").bright_red(), e));
+                // Told about the failure, the model can try another way
+                self.history.push(Message {
+                    role: "tool".to_string(),
+                    content: format!("Error: {:#}", e),
+                    images: Vec::new(),
+                    tool_calls: Vec::new(),
+                });
+            }
+        }
+    }
+
+    /// Asks the model about the conversation and shows and records the
+    /// reply, returning it; `first` is whether this answers the user's message
+    async fn complete_turn(&mut self, text: &str, first: bool, prompt: &str, input: &mut InputReader) -> Option<Message> {
+        input.request(prompt.to_string());
+
+        // Get AI response
+        let volatile = self.tools_prompt.as_deref().filter(|_| self.config.tool_selection.mode != ToolSelection::Off);
+        let request = prefix_cache::arrange(&self.history, volatile);
+        let tools = self.mcp_manager.as_ref().and_then(McpManager::native_tools);
+        self.prefix.sent(&request);
+        let started = Instant::now();
+        let progress = std::sync::Mutex::new(ReplyProgress::default());
+        // A line redrawn in place is read out again on every frame
+        let show_footer = !output::is_quiet() && io::stderr().is_terminal() && !accessibility::enabled();
+        let result = {
+            let chat = self.executor.complete_with_tools(request, tools, |_| {
+                progress.lock().unwrap().received();
+            });
+            tokio::pin!(chat);
+            // Quick replies finish before the first update, without a flash of status
+            let mut ticker = tokio::time::interval_at((started + FOOTER_DELAY).into(), FOOTER_INTERVAL);
+            let mut frame = 0;
+
+            loop {
+                tokio::select! {
+                    result = &mut chat => break result,
+                    _ = ticker.tick(), if show_footer => {
+                        let model = self.executor.get_model();
+                        input.footer(progress.lock().unwrap().footer(model, started, frame));
+                        frame += 1;
+                    }
+                    event = input.recv() => {
+                        match event {
+                            Input::Line(line) if !line.trim().is_empty() => {
+                                self.queue.push_back(line.trim().to_string());
+                                if !output::is_quiet() {
+                                    input.print(format!("{} {}",
+                                        "⏳".bright_yellow(), tr!("chat.queued", count = self.queue.len())));
+                                }
+                            }
+                            Input::Line(_) => {}
+                            Input::Interrupted => input.print(tr!("chat.use_quit").yellow().to_string()),
+                            // Finish the current turn and the queue before exiting
+                            Input::Eof => {}
                         }
+                        input.request(prompt.to_string());
                     }
                 }
-                Err(ReadlineError::Interrupted) => {
-                    println!("{}",  "Use /quit to exit".yellow());
-                    continue;
+            }
+        };
+
+        match result {
+            Ok(reply) => {
+                notify::finished(started, tr!("notify.reply_ready"), &reply.message.content);
+                accessibility::reply_complete();
+                let first_token = progress.lock().unwrap().first_piece.map(|at| at - started);
+                self.prefix.replied(&reply.message, reply.prompt_eval_count, first_token);
+                let warning = self.executor.usage().take_warning();
+                let mut tool_calls = reply.message.tool_calls.clone();
+                // One call per reply, as with blocks, so every call run gets its result
+                tool_calls.truncate(1);
+                let message = Message {
+                    role: "assistant".to_string(),
+                    content: filters::apply(&reply.message.content),
+                    images: Vec::new(),
+                    tool_calls,
+                };
+                if output::is_quiet() {
+                    input.print(message.content.clone());
+                } else if accessibility::enabled() {
+                    let cached = if reply.cached { format!(" {}", tr!("chat.cached")) } else { String::new() };
+                    input.print(format!("{}{}:\n{}", tr!("role.assistant"), cached, render_message(&message)));
+                    let summary = if reply.cached { String::new() } else { format!(" {}.", progress.lock().unwrap().summary(reply.eval_count, started)) };
+                    input.print(format!("{}{}\n", tr!("chat.end_of_response"), summary));
+                } else {
+                    let cached = if reply.cached { format!(" {}", tr!("chat.cached")).bright_black() } else { "".normal() };
+                    let shown = match agent::reply_tool_call(&message) {
+                        Some(_) => render_message(&message),
+                        None => message.content.bright_white().to_string(),
+                    };
+                    input.print(format!("{}{} {}\n", tr!("chat.ai").bright_blue().bold(), cached, shown));
+                    if !reply.cached {
+                        input.print(progress.lock().unwrap().summary(reply.eval_count, started));
+                    }
                 }
-                Err(ReadlineError::Eof) => {
-                    break;
+
+                if let Some(warning) = warning {
+                    input.print(warning);
                 }
-                Err(err) => {
-                    eprintln!("Error: {:?}", err);
-                    break;
+
+                // Add assistant response to history
+                self.history.push(message.clone());
+                self.turns += 1;
+                Some(message)
+            }
+            Err(e) => {
+                notify::finished(started, tr!("notify.reply_failed"), &format!("{:#}", e));
+                accessibility::reply_complete();
+                if e.downcast_ref::<Failure>() == Some(&Failure::Connection) {
+                    self.online = false;
+                }
+                // Also reached between tool calls, when a turn used up what was left
+                let exhausted = e.downcast_ref::<Failure>() == Some(&Failure::BudgetExhausted);
+                self.budget_exhausted |= exhausted;
+
+                // Keep stdout clean for pipelines
+                if output::is_quiet() {
+                    say_error!("{} {:#}", tr!("label.error").bright_red().bold(), e);
+                } else {
+                    input.print(format!("{} {:#}\n", tr!("label.error").bright_red().bold(), e));
                 }
+
+                // The reply arrives through flush-queue instead
+                if first && !exhausted && self.queue_prompt(text, format!("{:#}", e), input) {
+                    self.history.pop();
+                } else {
+                    self.failed_turns += 1;
+                }
+                None
+            }
+        }
+    }
+
+    async fn handle_command(&mut self, cmd: &str, input: &mut InputReader) -> Result<bool> {
+        match cmd {
+            "/quit" | "/exit" => {
+                status!("{}", tr!("chat.goodbye").bright_cyan());
+                return Ok(false);
+            }
+            "/clear" => {
+                // Injected tool instructions, other system context and pins survive
+                let pinned = self.pinned.clone();
+                self.retain_messages(|i, m| m.role == "system" || pinned.contains(&i));
+                say!("{}", tr!("chat.cleared").yellow());
+            }
+            "/clear --all" => {
+                self.history.clear();
+                self.pinned.clear();
+                self.tags.clear();
+                say!("{}", tr!("chat.cleared_all").yellow());
             }
+            "/reconnect" => {
+                match self.reconnect().await {
+                    Ok(warning) => {
+                        say!("{} {}", "✓".bright_green(), tr!("startup.connected"));
+                        if let Some(warning) = warning {
+                            say!("{}", warning);
+                        }
+                    }
+                    Err(e) => say_error!("{} {:#}", tr!("label.error").bright_red(), e),
+                }
+            }
+            "/history" => {
+                self.show_history("");
+            }
+            cmd if cmd.starts_with("/history ") => {
+                self.show_history(cmd.strip_prefix("/history ").unwrap());
+            }
+            cmd if cmd.starts_with("/edit-msg ") => {
+                let arg = cmd.strip_prefix("/edit-msg ").unwrap().trim();
+                if let Err(e) = self.edit_message(arg) {
+                    say_error!("{} {}", tr!("label.error").bright_red(), e);
+                }
+            }
+            cmd if cmd.starts_with("/delete-msg ") => {
+                let arg = cmd.strip_prefix("/delete-msg ").unwrap().trim();
+                match self.message_index(arg) {
+                    Ok(index) => {
+                        let removed = self.history.remove(index);
+                        self.pinned = self.pinned.iter()
+                            .filter(|&&i| i != index)
+                            .map(|&i| if i > index { i - 1 } else { i })
+                            .collect();
+                        say!("{} {}", "✓".bright_green(), tr!("history.deleted", role = role_label(&removed.role), number = index + 1));
+                        if index < self.history.len() {
+                            say!("{}", tr!("history.renumbered").bright_black());
+                        }
+                    }
+                    Err(e) => say_error!("{} {}", tr!("label.error").bright_red(), e),
+                }
+            }
+            cmd if cmd.starts_with("/pin ") || cmd.starts_with("/unpin ") => {
+                let (command, arg) = cmd.split_once(' ').unwrap();
+                match self.message_index(arg.trim()) {
+                    Ok(index) if command == "/pin" => {
+                        self.pinned.insert(index);
+                        say!("{} {}", "📌".bright_yellow(), tr!("history.pinned", number = index + 1));
+                    }
+                    Ok(index) => {
+                        if self.pinned.remove(&index) {
+                            say!("{} {}", "✓".bright_green(), tr!("history.unpinned", number = index + 1));
+                        } else {
+                            say!("{}", tr!("history.not_pinned", number = index + 1).yellow());
+                        }
+                    }
+                    Err(e) => say_error!("{} {}", tr!("label.error").bright_red(), e),
+                }
+            }
+            "/tag" => {
+                if self.tags.is_empty() {
+                    say!("{} {} /tag <tag> [tag ...]", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                    say!("{}", tr!("session.tags_hint"));
+                } else {
+                    say!("{} {}", tr!("session.tags").bright_cyan(), self.tags.join(", "));
+                }
+            }
+            cmd if cmd.starts_with("/tag ") => {
+                for tag in cmd.strip_prefix("/tag ").unwrap().split_whitespace() {
+                    if !self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                        self.tags.push(tag.to_string());
+                    }
+                }
+                say!("{} {}", "✓".bright_green(), tr!("session.tags_set", tags = self.tags.join(", ").bright_cyan()));
+            }
+            "/untag" => say!("{} {} /untag <tag> [tag ...]", tr!("label.info").bright_yellow(), tr!("label.usage")),
+            cmd if cmd.starts_with("/untag ") => {
+                let remove: Vec<&str> = cmd.strip_prefix("/untag ").unwrap().split_whitespace().collect();
+                self.tags.retain(|t| !remove.iter().any(|r| t.eq_ignore_ascii_case(r)));
+                let tags = if self.tags.is_empty() { tr!("session.no_tags").to_string() } else { self.tags.join(", ") };
+                say!("{} {} {}", "✓".bright_green(), tr!("session.tags"), tags.bright_cyan());
+            }
+            "/sessions" => {
+                if let Err(e) = self.list_sessions(None).await {
+                    say_error!("{} {:#}", tr!("label.error").bright_red(), e);
+                }
+            }
+            cmd if cmd.starts_with("/sessions ") => {
+                let args: Vec<&str> = cmd.split_whitespace().skip(1).collect();
+                match args.as_slice() {
+                    ["--tag", tag] => if let Err(e) = self.list_sessions(Some(tag)).await {
+                        say_error!("{} {:#}", tr!("label.error").bright_red(), e);
+                    },
+                    _ => say!("{} {} /sessions [--tag <tag>]", tr!("label.info").bright_yellow(), tr!("label.usage")),
+                }
+            }
+            "/checkpoint" => self.checkpoint(None),
+            cmd if cmd.starts_with("/checkpoint ") => {
+                self.checkpoint(Some(cmd.strip_prefix("/checkpoint ").unwrap().trim()));
+            }
+            "/rollback" => {
+                if self.checkpoints.is_empty() {
+                    say!("{} {} /rollback <name>", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                    say!("{}", tr!("checkpoint.none"));
+                } else {
+                    say!("{}", tr!("checkpoint.title").bright_cyan());
+                    for checkpoint in &self.checkpoints {
+                        say!("  {} {}", checkpoint.name.bright_cyan(), tr!("checkpoint.messages", count = checkpoint.history.len()));
+                    }
+                }
+            }
+            cmd if cmd.starts_with("/rollback ") => {
+                let name = cmd.strip_prefix("/rollback ").unwrap().trim();
+                if let Err(e) = self.rollback(name) {
+                    say_error!("{} {:#}", tr!("label.error").bright_red(), e);
+                }
+            }
+            "/pin" | "/unpin" => {
+                say!("{} {} {} <n>", tr!("label.info").bright_yellow(), tr!("label.usage"), cmd);
+                say!("{}", tr!("history.pin_hint"));
+            }
+            "/edit-msg" | "/delete-msg" => {
+                say!("{} {} {} <n>", tr!("label.info").bright_yellow(), tr!("label.usage"), cmd);
+                say!("{}", tr!("history.number_hint"));
+            }
+            "/help" => {
+                self.show_help();
+            }
+            "/model" => {
+                say!("{}", tr!("chat.current_model", model = self.executor.get_model().bright_cyan()));
+            }
+            "/backend" => {
+                say!("{}", tr!("chat.current_backend",
+                    backend = self.executor.provider().name().bright_cyan(), model = self.executor.get_model().bright_cyan()));
+            }
+            cmd if cmd.starts_with("/backend ") => {
+                let name = cmd.strip_prefix("/backend ").unwrap().trim();
+                if let Err(e) = self.switch_backend(name).await {
+                    say_error!("{} {:#}", tr!("label.error").bright_red(), e);
+                }
+            }
+            "/set" => self.show_settings(),
+            cmd if cmd.starts_with("/set ") => {
+                let args = cmd.strip_prefix("/set ").unwrap().trim();
+                match self.set_option(args) {
+                    Ok(()) => self.show_settings(),
+                    Err(e) => say_error!("{} {:#}", tr!("label.error").bright_red(), e),
+                }
+            }
+            "/mcp-tools" => {
+                self.sync_mcp().await;
+                self.show_mcp_tools();
+            }
+            "/tools stats" => {
+                self.sync_mcp().await;
+                let servers = self.mcp_manager.as_ref().map(|mcp| mcp.server_names()).unwrap_or_default();
+                tool_stats::print(&servers)?;
+            }
+            "/tools prune" => {
+                self.sync_mcp().await;
+                self.show_tool_budget();
+            }
+            "/workers" => {
+                self.show_workers().await;
+            }
+            "/tabs" => {
+                self.show_tabs();
+            }
+            "/cache" => {
+                self.show_cache();
+            }
+            "/cache clear" => {
+                match ResponseCache::open(self.executor.cache_ttl()).and_then(|cache| cache.clear()) {
+                    Ok(removed) => say!("{} {}", "✓".bright_green(), tr!("cache.cleared", count = removed)),
+                    Err(e) => say_error!("{} {:#}", tr!("label.error").bright_red(), e),
+                }
+            }
+            "/show-thoughts" => {
+                thoughts::set_shown(true);
+                let thoughts: Vec<&Message> = self.history.iter().filter(|m| thoughts::is_thought(m)).collect();
+                if thoughts.is_empty() {
+                    say!("{}", tr!("thoughts.none").bright_black());
+                }
+                for thought in thoughts {
+                    say!("{}", thoughts::display(&thought.content));
+                }
+            }
+            "/show-thoughts off" => {
+                thoughts::set_shown(false);
+                say!("{}", tr!("thoughts.collapsed").bright_black());
+            }
+            "/stats" => {
+                self.executor.usage().print_stats();
+                self.prefix.print_stats();
+            }
+            "/confirm" => {
+                self.confirm = !self.confirm;
+                let state = if self.confirm { tr!("state.on") } else { tr!("state.off") };
+                say!("{} {}", "✓".bright_green(), tr!("chat.confirm_state", state = state.bright_cyan()));
+            }
+            "/agent" => {
+                let Some(mcp) = &mut self.mcp_manager else {
+                    say!("{}", tr!("tools.none").yellow());
+                    return Ok(true);
+                };
+                self.agent = !self.agent;
+                // The tools prompt tells the model whether it may call tools itself
+                mcp.set_agent_mode(self.agent);
+                self.sync_mcp().await;
+                let state = if self.agent { tr!("state.on") } else { tr!("state.off") };
+                say!("{} {}", "✓".bright_green(), tr!("agent.state", state = state.bright_cyan()));
+            }
+            "/new" => {
+                self.new_tab(None).await;
+            }
+            cmd if cmd.starts_with("/new ") => {
+                let model = cmd.strip_prefix("/new ").unwrap().trim();
+                self.new_tab(Some(model)).await;
+            }
+            cmd if cmd.starts_with("/switch ") => {
+                let arg = cmd.strip_prefix("/switch ").unwrap().trim();
+                match arg.parse::<usize>() {
+                    Ok(n) if (1..=self.tabs.len()).contains(&n) => {
+                        self.switch_tab(n - 1);
+                        say!("{} {}", "✓".bright_green(), tr!("tabs.switched", number = n, model = self.executor.get_model().bright_cyan()));
+                    }
+                    _ => say_error!("{} {}", tr!("label.error").bright_red(), tr!("tabs.no_such", name = arg)),
+                }
+            }
+            "/switch" => {
+                say!("{} {} /switch <n>", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                say!("{}", tr!("tabs.list_hint"));
+            }
+            cmd if cmd.starts_with("/mcp-call ") => {
+                let rest = cmd.strip_prefix("/mcp-call ").unwrap().trim();
+                let parts: Vec<&str> = rest.splitn(2, ' ').collect();
+                
+                if parts.len() < 2 {
+                    say!("{} {} /mcp-call <tool_name> <json_args>", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                    say!("{} /mcp-call add {{\"a\": 5, \"b\": 3}}", tr!("label.example"));
+                } else {
+                    let tool_name = parts[0];
+                    let args_str = parts[1];
+                    
+                    match serde_json::from_str(args_str) {
+                        Ok(args) => {
+                            if let Err(e) = self.call_mcp_tool(tool_name, args, input).await {
+                                say_error!("{} {:#}", tr!("label.error").bright_red(), e);
+                            }
+                        }
+                        Err(e) => {
+                            say_error!("{} {}", tr!("label.error").bright_red(), tr!("tools.invalid_json", error = e));
+                        }
+                    }
+                }
+            }
+            "/mcp-reload" => {
+                if let Err(e) = self.reload_mcp().await {
+                    say_error!("{} {}", tr!("label.error").bright_red(), tr!("tools.reload_failed", error = e));
+                } else {
+                    say!("{} {}", "✓".bright_green(), tr!("tools.reloaded"));
+                }
+            }
+            cmd if cmd.starts_with("/model ") => {
+                let model = cmd.strip_prefix("/model ").unwrap().trim();
+                let previous = self.executor.get_model().to_string();
+                match self.executor.switch_model(model.to_string()).await {
+                    Ok(_) => {
+                        say!("{} {}", "✓".bright_green(), tr!("chat.switched_model", model = model.bright_cyan()));
+                        self.warn_deterministic_switch(&previous);
+                        self.history.clear();
+                        self.pinned.clear();
+                        self.tags.clear();
+                    }
+                    Err(e) => {
+                        say_error!("{} {:#}", tr!("label.error").bright_red(), e);
+                    }
+                }
+            }
+            cmd if cmd.starts_with("/save ") => {
+                let filename = cmd.strip_prefix("/save ").unwrap().trim();
+                if let Err(e) = self.save_conversation(filename) {
+                    say_error!("{} {}", tr!("label.error").bright_red(), tr!("chat.save_failed", error = e));
+                } else {
+                    say!("{} {}", "✓".bright_green(), tr!("chat.saved", file = filename.bright_cyan()));
+                    // A failure here only means /recall will not search this file
+                    let _ = recall::remember(Path::new(filename)).await;
+                }
+            }
+            "/save" => {
+                say!("{} {} /save <filename>", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                say!("{} /save my_chat.json", tr!("label.example"));
+            }
+            cmd if cmd.starts_with("/load ") => {
+                let filename = cmd.strip_prefix("/load ").unwrap().trim();
+                if let Err(e) = self.load_conversation(filename) {
+                    say_error!("{} {}", tr!("label.error").bright_red(), tr!("chat.load_failed", error = format!("{:#}", e)));
+                } else {
+                    say!("{} {}", "✓".bright_green(), tr!("chat.loaded", file = filename.bright_cyan()));
+                }
+            }
+            "/load" => {
+                say!("{} {} /load <filename>", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                say!("{} /load my_chat.json", tr!("label.example"));
+            }
+            cmd if cmd.starts_with("/batch ") => {
+                let args = cmd.strip_prefix("/batch ").unwrap().trim();
+                if let Err(e) = self.process_batch_file(args).await {
+                    say_error!("{} {}", tr!("label.error").bright_red(), tr!("batch.failed", error = e));
+                } else {
+                    say!("{} {}", "✓".bright_green(), tr!("batch.complete"));
+                }
+            }
+            cmd if cmd.starts_with("/export-training ") => {
+                let rest = cmd.strip_prefix("/export-training ").unwrap().trim();
+                let (filename, format) = match rest.split_once(' ') {
+                    Some((filename, format)) => (filename, TrainingFormat::parse(format.trim())),
+                    None => (rest, Some(TrainingFormat::Openai)),
+                };
+                match format {
+                    Some(format) => match training::append(Path::new(filename), &self.history, format) {
+                        Ok(()) => say!("{} {}", "✓".bright_green(), tr!("training.appended", file = filename.bright_cyan())),
+                        Err(e) => say_error!("{} {}", tr!("label.error").bright_red(), tr!("training.failed", error = e)),
+                    },
+                    None => say_error!("{} {}", tr!("label.error").bright_red(), tr!("training.unknown_format")),
+                }
+            }
+            "/export-training" => {
+                say!("{} {} /export-training <file.jsonl> [openai|sharegpt]", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                say!("{} /export-training dataset.jsonl sharegpt", tr!("label.example"));
+            }
+            cmd if cmd.starts_with("/compare ") => {
+                let prompt = cmd.strip_prefix("/compare ").unwrap().trim();
+                self.compare_models(prompt).await;
+            }
+            "/compare" => {
+                say!("{} {} /compare <prompt>", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                say!("{}", tr!("compare.hint"));
+            }
+            cmd if cmd.starts_with("/summarize-dir ") => {
+                let rest = cmd.strip_prefix("/summarize-dir ").unwrap().trim();
+                let (path, instruction) = match rest.split_once(' ') {
+                    Some((path, instruction)) => (path, instruction.trim()),
+                    None => (rest, "Summarize the content."),
+                };
+                if let Err(e) = self.summarize_path(path, instruction).await {
+                    say_error!("{} {}", tr!("label.error").bright_red(), tr!("summary.failed", error = e));
+                }
+            }
+            cmd if cmd.starts_with("/image ") => {
+                let path = cmd.strip_prefix("/image ").unwrap().trim();
+                self.attach(image::from_file(Path::new(path)));
+            }
+            "/image" => {
+                say!("{} {} /image <file>", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                say!("{}", tr!("image.hint"));
+            }
+            "/paste-image" => {
+                self.attach(image::from_clipboard());
+            }
+            cmd if cmd.starts_with("/attach ") => {
+                let path = cmd.strip_prefix("/attach ").unwrap().trim();
+                if let Err(e) = self.attach_file(path) {
+                    say_error!("{} {:#}", tr!("label.error").bright_red(), e);
+                }
+            }
+            "/refresh" => {
+                let lines = self.check_attachments(true);
+                if lines.is_empty() {
+                    say!("{}", tr!("attach.up_to_date").bright_black());
+                }
+                for line in lines {
+                    say!("{}", line);
+                }
+            }
+            "/attach" => {
+                say!("{} {} /attach <file>", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                say!("{}", tr!("attach.hint"));
+            }
+            cmd if cmd.starts_with("/bundle ") => {
+                let args = cmd.strip_prefix("/bundle ").unwrap().trim();
+                if let Err(e) = self.bundle(args) {
+                    say_error!("{} {:#}", tr!("label.error").bright_red(), e);
+                }
+            }
+            "/bundle" => {
+                self.list_bundles();
+            }
+            "/repo-map" => {
+                if let Err(e) = self.refresh_repo_map() {
+                    say_error!("{} {:#}", tr!("label.error").bright_red(), e);
+                }
+            }
+            cmd if cmd.starts_with("/recall ") => {
+                let query = cmd.strip_prefix("/recall ").unwrap().trim();
+                if let Err(e) = self.recall(query, input).await {
+                    say_error!("{} {}", tr!("label.error").bright_red(), tr!("recall.failed", error = format!("{:#}", e)));
+                }
+            }
+            "/recall" => {
+                say!("{} {} /recall <query>", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                say!("{}", tr!("recall.hint"));
+            }
+            cmd if cmd == "/why" || cmd.starts_with("/why ") => {
+                let question = cmd.strip_prefix("/why").unwrap().trim();
+                match shell_history::prompt(&self.config.shell_history, Some(question).filter(|q| !q.is_empty())) {
+                    Ok((prompt, commands)) => {
+                        status!("{} {}", "⚙".bright_blue(), tr!("why.sending", count = commands));
+                        self.send_message(&prompt, input).await;
+                    }
+                    Err(e) => say_error!("{} {:#}", tr!("label.error").bright_red(), e),
+                }
+            }
+            "/voice" => {
+                match self.record_voice(input).await {
+                    Ok(Some(text)) => self.send_message(&text, input).await,
+                    Ok(None) => {}
+                    Err(e) => say_error!("{} {}", tr!("label.error").bright_red(), tr!("voice.failed", error = format!("{:#}", e))),
+                }
+            }
+            cmd if cmd.starts_with("/translate ") => {
+                let rest = cmd.strip_prefix("/translate ").unwrap().trim();
+                if let Err(e) = self.translate(rest).await {
+                    say_error!("{} {}", tr!("label.error").bright_red(), tr!("translate.failed", error = format!("{:#}", e)));
+                }
+            }
+            "/translate" => {
+                say!("{} {} /translate <language> [n]", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                say!("{}", tr!("translate.hint"));
+            }
+            cmd if cmd == "/share" || cmd.starts_with("/share ") => {
+                let args = cmd.strip_prefix("/share").unwrap().trim();
+                if let Err(e) = self.share(args, input).await {
+                    say_error!("{} {}", tr!("label.error").bright_red(), tr!("share.failed", error = format!("{:#}", e)));
+                }
+            }
+            cmd if cmd == "/pr-description" || cmd.starts_with("/pr-description ") => {
+                let args = cmd.strip_prefix("/pr-description").unwrap().trim();
+                if let Err(e) = self.pr_description(args, input).await {
+                    say_error!("{} {}", tr!("label.error").bright_red(), tr!("pr.failed", error = format!("{:#}", e)));
+                }
+            }
+            "/summarize" => {
+                if let Err(e) = self.summarize_conversation(None).await {
+                    say_error!("{} {}", tr!("label.error").bright_red(), tr!("summary.failed", error = e));
+                }
+            }
+            cmd if cmd.starts_with("/summarize --to") => {
+                let filename = cmd.strip_prefix("/summarize --to").unwrap().trim();
+                if filename.is_empty() {
+                    say!("{} {} /summarize --to <file.md>", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                } else if let Err(e) = self.summarize_conversation(Some(filename)).await {
+                    say_error!("{} {}", tr!("label.error").bright_red(), tr!("summary.failed", error = e));
+                }
+            }
+            "/summarize-dir" => {
+                say!("{} {} /summarize-dir <path> [instruction]", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                say!("{} /summarize-dir src/ {}", tr!("label.example"), tr!("summarize_dir.example"));
+            }
+            "/batch" => {
+                say!("{} {} /batch <filename> [--concurrency n]", tr!("label.info").bright_yellow(), tr!("label.usage"));
+                say!("{} /batch prompts.txt --concurrency 4", tr!("label.example"));
+                say!("\n{}", tr!("batch.format"));
+                for line in tr!("batch.example").lines() {
+                    say!("  {}", line);
+                }
+            }
+            _ => {
+                say!("{} {}", tr!("chat.unknown_command").bright_red(), cmd);
+                say!("{}", tr!("chat.help_hint", command = "/help".bright_cyan()));
+            }
+        }
+        Ok(true)
+    }
+    
+    async fn new_tab(&mut self, model: Option<&str>) {
+        let previous = self.current;
+        self.tabs.push(Tab::default());
+        self.switch_tab(self.tabs.len() - 1);
+        self.history = self.initial_history();
+        self.pinned.clear();
+        self.tags.clear();
+        self.checkpoints.clear();
+
+        if let Some(model) = model
+            && let Err(e) = self.executor.switch_model(model.to_string()).await
+        {
+            say_error!("{} {}", tr!("label.error").bright_red(), e);
+            self.tabs.pop();
+            self.restore_tab(previous);
+            return;
+        }
+
+        say!("{} {}", "✓".bright_green(), tr!("tabs.opened", number = self.current + 1, model = self.executor.get_model().bright_cyan()));
+    }
+
+    fn switch_tab(&mut self, index: usize) {
+        if index == self.current {
+            return;
+        }
+
+        self.tabs[self.current] = Tab {
+            history: std::mem::take(&mut self.history),
+            pinned: std::mem::take(&mut self.pinned),
+            tags: std::mem::take(&mut self.tags),
+            checkpoints: std::mem::take(&mut self.checkpoints),
+            model: self.executor.get_model().to_string(),
+        };
+        self.restore_tab(index);
+    }
+
+    fn restore_tab(&mut self, index: usize) {
+        let tab = std::mem::take(&mut self.tabs[index]);
+        // A fresh tab starts on the model of the conversation it was opened from
+        if !tab.model.is_empty() {
+            let previous = self.executor.get_model().to_string();
+            self.executor.set_model(tab.model);
+            self.warn_deterministic_switch(&previous);
+        }
+        self.history = tab.history;
+        self.pinned = tab.pinned;
+        self.tags = tab.tags;
+        self.checkpoints = tab.checkpoints;
+        self.current = index;
+    }
+
+    /// Moves chat to another backend, on the model last used there or its
+    /// default; the conversation carries over
+    async fn switch_backend(&mut self, name: &str) -> Result<()> {
+        let provider = Provider::parse(name).ok_or_else(|| anyhow::anyhow!(tr!("backend.unknown",
+            name = name, choices = Provider::ALL.map(Provider::name).join(", "))))?;
+        let current = self.executor.provider();
+        if provider == current {
+            say!("{}", tr!("chat.current_backend", backend = provider.name().bright_cyan(), model = self.executor.get_model().bright_cyan()));
+            return Ok(());
+        }
+
+        let backend = backend::connect(provider, &self.config)?;
+        let model = self.backend_models.get(&provider).cloned()
+            .unwrap_or_else(|| backend::default_model(provider, &self.config));
+        let previous = self.executor.get_model().to_string();
+        self.executor.switch_backend(provider, backend, model).await?;
+        self.backend_models.insert(current, previous.clone());
+        self.online = true;
+
+        say!("{} {}", "✓".bright_green(), tr!("chat.switched_backend",
+            backend = provider.name().bright_cyan(), model = self.executor.get_model().bright_cyan()));
+        self.warn_deterministic_switch(&previous);
+        Ok(())
+    }
+
+    /// Same seed, different weights: replies from before and after a switch are not comparable
+    fn warn_deterministic_switch(&self, previous: &str) {
+        let model = self.executor.get_model();
+        if self.executor.generation().deterministic && model != previous {
+            say!("{} {}", tr!("label.warning").bright_yellow(),
+                tr!("settings.deterministic_switch", model = model.bright_cyan(), previous = previous.bright_cyan()));
+        }
+    }
+
+    fn show_tabs(&self) {
+        say!("\n{}", tr!("tabs.title").bright_yellow().bold());
+
+        for (i, tab) in self.tabs.iter().enumerate() {
+            let (history, model) = if i == self.current {
+                (&self.history, self.executor.get_model())
+            } else {
+                (&tab.history, tab.model.as_str())
+            };
+
+            let title = history.iter()
+                .find(|m| m.role == "user")
+                .map(|m| m.content.chars().take(40).collect::<String>())
+                .unwrap_or_else(|| tr!("tabs.empty").to_string());
+            let turns = history.iter().filter(|m| m.role != "system").count();
+            let marker = if i == self.current { "*".bright_green() } else { " ".normal() };
+
+            say!("  {} {} {} [{}] {}",
+                marker, i + 1, title, model.bright_cyan(), tr!("tabs.messages", count = turns));
+        }
+        say!();
+    }
+
+    /// `/batch <file> [--concurrency n]`: each line is a prompt of its own,
+    /// with up to n asked at once; responses are printed in file order
+    async fn process_batch_file(&self, args: &str) -> Result<()> {
+        let (filename, concurrency) = match args.split_once("--concurrency") {
+            Some((filename, n)) => (filename.trim(), n.trim().parse::<usize>().ok().filter(|n| *n > 0)
+                .context(tr!("batch.concurrency"))?),
+            None => (args, 1),
+        };
+        let content = fs::read_to_string(filename)?;
+        let prompts: Vec<String> = content.lines()
+            .map(|s: &str| s.to_string())
+            .collect();
+
+        if concurrency > 1 {
+            say!("{}", tr!("batch.processing_concurrent", count = prompts.len(), concurrency = concurrency));
+        } else {
+            say!("{}", tr!("batch.processing", count = prompts.len()));
+        }
+
+        // Redrawn on stderr while prompts run; responses go to stdout in order
+        let live = !output::is_quiet() && io::stderr().is_terminal() && !accessibility::enabled();
+        let mut tasks = JoinSet::new();
+        let mut pending = prompts.iter().enumerate();
+        let mut finished = BTreeMap::new();
+        let (mut printed, mut done, mut failed) = (0, 0, 0);
+        loop {
+            while tasks.len() < concurrency && let Some((index, prompt)) = pending.next() {
+                let chat = self.executor.batch_chat(vec![Message {
+                    role: "user".to_string(),
+                    content: prompt.clone(),
+                    images: Vec::new(),
+                    tool_calls: Vec::new(),
+                }]);
+                tasks.spawn(async move { (index, chat.await) });
+            }
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            let (index, result) = joined.context("Batch task panicked")?;
+            finished.insert(index, result);
+            done += 1;
+
+            if live {
+                eprint!("\r\x1b[2K");
+            }
+            while let Some(result) = finished.remove(&printed) {
+                say!("\n[{}/{}] {}", printed + 1, prompts.len(), prompts[printed]);
+                match result {
+                    Ok(response) => say!("{} {}", tr!("batch.response"), response),
+                    Err(e) => {
+                        failed += 1;
+                        say_error!("{} {:#}", tr!("label.error").bright_red(), e);
+                    }
+                }
+                printed += 1;
+            }
+            if live && done < prompts.len() {
+                eprint!("{}", batch_progress(done, (prompts.len() - done).min(concurrency), prompts.len()));
+            }
+        }
+
+        self.print_budget_warning();
+        if failed > 0 {
+            anyhow::bail!(tr!("batch.prompts_failed", failed = failed, count = prompts.len()));
+        }
+        Ok(())
+    }
+
+    async fn compare_models(&self, prompt: &str) {
+        let models = &self.config.compare_models;
+        if models.len() < 2 {
+            say!("{} {}", tr!("label.info").bright_yellow(), tr!("compare.too_few"));
+            say!("  {}", tr!("compare.example"));
+            return;
+        }
+
+        // Compare against the current conversation without changing it
+        let mut messages = self.history.clone();
+        messages.push(Message {
+            role: "user".to_string(),
+            content: prompt.to_string(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        });
+
+        say!("{} {}", "⚙".bright_blue(), tr!("compare.asking", count = models.len()));
+
+        for (model, elapsed, result) in self.executor.compare(models, messages).await {
+            say!("\n{} {} {}",
+                "──".bright_black(),
+                model.bright_cyan().bold(),
+                format!("({:.1}s)", elapsed.as_secs_f64()).bright_black());
+            match result {
+                Ok(response) => say!("{}", response.bright_white()),
+                Err(e) => say_error!("{} {}", tr!("label.error").bright_red(), e),
+            }
+        }
+
+        self.print_budget_warning();
+        say!("\n{}\n", tr!("compare.continue_hint", command = format!("{} <name>", "/model".bright_cyan())));
+    }
+
+    fn show_cache(&self) {
+        match self.executor.response_cache() {
+            Some(cache) => {
+                let ttl = cache.ttl()
+                    .map_or(tr!("cache.no_expiry").to_string(), |ttl| tr!("cache.expiry", seconds = ttl.as_secs()));
+                say!("{} {}", "✓".bright_green(), tr!("cache.on", count = cache.len(), ttl = ttl));
+            }
+            None => {
+                say!("{} {}", "ℹ".bright_blue(), tr!("cache.off"));
+                say!("{}", tr!("cache.enable_hint"));
+            }
+        }
+    }
+
+    /// Shows the budget warning left by the last request, if any
+    fn print_budget_warning(&self) {
+        if let Some(warning) = self.executor.usage().take_warning() {
+            say!("{}", warning);
+        }
+    }
+
+    /// `/set stop "###" "User:"`, `/set num_predict 256`, `/set deterministic on 7`;
+    /// `off` clears any of them
+    fn set_option(&mut self, args: &str) -> Result<()> {
+        let (name, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        let value = value.trim();
+        let generation = self.executor.generation_mut();
+        match name {
+            "stop" if value == "off" => generation.stop.clear(),
+            "stop" => {
+                let sequences = parse_quoted(value)?;
+                if sequences.is_empty() {
+                    anyhow::bail!(tr!("settings.stop_usage"));
+                }
+                generation.stop = sequences;
+            }
+            "num_predict" | "max_tokens" if value == "off" => generation.num_predict = None,
+            "num_predict" | "max_tokens" => {
+                let limit: i64 = value.parse()
+                    .context(tr!("settings.num_predict_usage"))?;
+                if limit <= 0 {
+                    anyhow::bail!(tr!("settings.num_predict_min"));
+                }
+                generation.num_predict = Some(limit);
+            }
+            "deterministic" => {
+                let (switch, seed) = value.split_once(char::is_whitespace).unwrap_or((value, ""));
+                match (switch, seed.trim()) {
+                    ("off", "") => generation.deterministic = false,
+                    ("on", "") => generation.deterministic = true,
+                    ("on", seed) => {
+                        generation.seed = Some(seed.parse().context(tr!("settings.seed_usage"))?);
+                        generation.deterministic = true;
+                    }
+                    _ => anyhow::bail!(tr!("settings.deterministic_usage")),
+                }
+            }
+            _ => anyhow::bail!(tr!("settings.unknown", name = name)),
+        }
+        Ok(())
+    }
+
+    fn show_settings(&self) {
+        let generation = self.executor.generation();
+        let stop = if generation.stop.is_empty() {
+            tr!("state.off").to_string()
+        } else {
+            generation.stop.iter().map(|s| format!("{:?}", s)).collect::<Vec<_>>().join(" ")
+        };
+        let num_predict = generation.num_predict.map_or(tr!("state.off").to_string(), |n| n.to_string());
+        say!("  {} {}", "stop:".bright_cyan(), stop);
+        say!("  {} {}", "num_predict:".bright_cyan(), num_predict);
+        let deterministic = generation.pinned_seed()
+            .map_or(tr!("state.off").to_string(), |seed| tr!("settings.deterministic_on", seed = seed));
+        say!("  {} {}", "deterministic:".bright_cyan(), deterministic);
+    }
+
+    /// Prints (or saves) a summary of the conversation; history is left as is
+    async fn summarize_conversation(&mut self, filename: Option<&str>) -> Result<()> {
+        if !self.history.iter().any(|m| m.role != "system") {
+            say!("{}", tr!("summary.nothing").yellow());
+            return Ok(());
+        }
+
+        let mut messages = self.history.clone();
+        messages.push(Message {
+            role: "user".to_string(),
+            content: SUMMARY_PROMPT.to_string(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        });
+
+        status!("{} {}", "⚙".bright_blue(), tr!("summary.summarizing", count = self.history.len()));
+        let reply = self.executor.complete(messages).await?;
+        self.print_budget_warning();
+        let summary = reply.message.content;
+
+        match filename {
+            Some(filename) => {
+                let document = format!("# {}\n\n_{} · {}_\n\n{}\n",
+                    tr!("summary.document_title"),
+                    self.executor.get_model(),
+                    chrono::Local::now().format("%Y-%m-%d %H:%M"),
+                    summary.trim());
+                fs::write(filename, document)
+                    .with_context(|| tr!("file.write_failed", path = filename))?;
+                say!("{} {}", "✓".bright_green(), tr!("summary.saved", path = filename.bright_cyan()));
+            }
+            None => say!("\n{}\n{}\n", tr!("summary.title").bright_yellow().bold(), summary.trim().bright_white()),
+        }
+        Ok(())
+    }
+
+    fn attach(&mut self, attachment: Result<image::Attachment>) {
+        match attachment {
+            Ok(attachment) => {
+                self.attachments.push(attachment.data);
+                say!("{} {}", "📎".bright_green(), tr!("attach.image", label = attachment.label.bright_cyan()));
+            }
+            Err(e) => say_error!("{} {:#}", tr!("label.error").bright_red(), e),
+        }
+    }
+
+    /// Finds attachments whose file changed since, replacing them with the
+    /// current content when `refresh` is set; returns what to tell the user
+    fn check_attachments(&mut self, refresh: bool) -> Vec<String> {
+        let mut lines = Vec::new();
+        for stale in bundle::stale_attachments(&self.history, &mut self.verified_attachments) {
+            match stale.refreshed {
+                Some(message) if refresh => {
+                    self.history[stale.index] = message;
+                    lines.push(format!("{} {}", "↻".bright_blue(), tr!("attach.refreshed", path = stale.path.bright_cyan())));
+                }
+                Some(_) => lines.push(format!("{} {}",
+                    tr!("label.warning").bright_yellow(), tr!("attach.changed", path = stale.path.bright_cyan()))),
+                None => lines.push(format!("{} {}",
+                    tr!("label.warning").bright_yellow(), tr!("attach.gone", path = stale.path.bright_cyan()))),
+            }
+        }
+        lines
+    }
+
+    fn attach_file(&mut self, path: &str) -> Result<()> {
+        let message = bundle::attach_file(path)?;
+        let tokens = tokens::estimate_message(&message);
+        self.history.push(message);
+        say!("{} {}", "✓".bright_green(),
+            tr!("attach.file", path = path.bright_cyan(), tokens = tokens::format_count(tokens)));
+        Ok(())
+    }
+
+    /// `/bundle load <name>` and `/bundle save <name> [note]`
+    fn bundle(&mut self, args: &str) -> Result<()> {
+        let (action, rest) = args.split_once(' ').unwrap_or((args, ""));
+        let (name, note) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+        if name.is_empty() {
+            anyhow::bail!(tr!("bundle.usage"));
+        }
+
+        match action {
+            "load" => {
+                let bundle = self.config.bundles.get(name).cloned()
+                    .with_context(|| tr!("bundle.unknown", name = name))?;
+                // Every file is read first so a missing one does not leave the bundle half attached
+                let messages = bundle.files.iter()
+                    .map(|path| bundle::attach_file(path))
+                    .collect::<Result<Vec<_>>>()?;
+                let tokens = tokens::estimate(&messages);
+                self.history.extend(messages);
+                if let Some(note) = &bundle.note {
+                    self.history.push(bundle::note_message(note));
+                }
+                let attached = if bundle.note.is_some() { "bundle.attached_with_note" } else { "bundle.attached" };
+                say!("{} {}", "✓".bright_green(), tr!(attached, name = name.bright_cyan(),
+                    count = bundle.files.len(), tokens = tokens::format_count(tokens)));
+            }
+            "save" => {
+                let files = bundle::attached_files(&self.history);
+                if files.is_empty() {
+                    anyhow::bail!(tr!("bundle.nothing_attached"));
+                }
+                let bundle = BundleConfig {
+                    files,
+                    note: (!note.trim().is_empty()).then(|| note.trim().to_string()),
+                };
+                bundle::save(name, &bundle)?;
+                say!("{} {}", "✓".bright_green(), tr!("bundle.saved", name = name.bright_cyan(),
+                    count = bundle.files.len(), path = AppConfig::config_path()?.display()));
+                self.config.bundles.insert(name.to_string(), bundle);
+            }
+            other => anyhow::bail!(tr!("bundle.unknown_action", action = other)),
+        }
+        Ok(())
+    }
+
+    fn list_bundles(&self) {
+        if self.config.bundles.is_empty() {
+            say!("{}", tr!("bundle.none").yellow());
+            say!("{}", tr!("bundle.none_hint"));
+            return;
+        }
+        let mut names: Vec<&String> = self.config.bundles.keys().collect();
+        names.sort();
+        say!("\n{}", tr!("bundle.title").bright_yellow().bold());
+        for name in names {
+            let bundle = &self.config.bundles[name];
+            say!("  {} - {}{}", name.bright_cyan(), bundle.files.join(", "),
+                if bundle.note.is_some() { tr!("bundle.plus_note") } else { "" });
+        }
+        say!("\n{}\n", tr!("bundle.load_hint"));
+    }
+
+    /// Builds the map of the current repository and puts it in place of any older one
+    fn refresh_repo_map(&mut self) -> Result<()> {
+        let max_chars = self.config.repo_map.max_chars.unwrap_or(repo_map::DEFAULT_MAX_CHARS);
+        let map = repo_map::build(Path::new("."), max_chars)?;
+
+        let message = Message {
+            role: "system".to_string(),
+            content: map.text.clone(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        };
+        let tokens = tokens::estimate_message(&message);
+        match self.history.iter().position(|m| m.role == "system" && m.content.starts_with(repo_map::HEADER)) {
+            Some(i) => self.history[i] = message,
+            None => self.history.push(message),
+        }
+        self.repo_map = Some(map.text);
+        say!("{} {}", "✓".bright_green(), tr!("repo_map.added", files = map.files, symbols = map.symbols,
+            tokens = tokens::format_count(tokens)));
+        Ok(())
+    }
+
+    /// Searches past conversations and offers to add the hits to this one as system messages
+    async fn recall(&mut self, query: &str, input: &mut InputReader) -> Result<()> {
+        status!("{} {}", "⚙".bright_blue(), tr!("recall.searching"));
+        let (hits, indexed) = recall::search(query, RECALL_HITS).await?;
+        if indexed > 0 {
+            status!("{} {}", "✓".bright_green(), tr!("recall.indexed", count = indexed));
+        }
+        if hits.is_empty() {
+            say!("{}", tr!("recall.none").yellow());
+            return Ok(());
+        }
+
+        let snippet = |text: &str| {
+            let line = text.trim().lines().next().unwrap_or_default();
+            let cut: String = line.chars().take(100).collect();
+            if cut.len() < line.len() { format!("{}…", cut) } else { cut }
+        };
+        for (i, hit) in hits.iter().enumerate() {
+            say!("\n{} {} {}", format!("[{}]", i + 1).bright_cyan().bold(),
+                format!("{:.2}", hit.score).bright_black(),
+                format!("{} · {}", hit.path.display(), tr!("recall.message", number = hit.exchange.message)).bright_black());
+            say!("  {} {}", format!("{}:", tr!("role.user")).bright_green(), snippet(&hit.exchange.user));
+            say!("  {} {}", tr!("chat.ai").bright_blue(), snippet(&hit.exchange.assistant));
+        }
+        say!();
+
+        if !io::stdin().is_terminal() {
+            return Ok(());
+        }
+        let answer = match input.next(format!("{} ", tr!("recall.prompt").bright_yellow())).await {
+            Input::Line(answer) => answer.trim().to_lowercase(),
+            Input::Interrupted | Input::Eof => return Ok(()),
+        };
+        let selected: Vec<usize> = if answer == "a" || answer == "all" {
+            (0..hits.len()).collect()
+        } else {
+            answer.split([',', ' '])
+                .filter_map(|n| n.trim().parse::<usize>().ok())
+                .filter(|n| (1..=hits.len()).contains(n))
+                .map(|n| n - 1)
+                .collect()
+        };
+
+        for &i in &selected {
+            self.history.push(Message {
+                role: "system".to_string(),
+                content: recall::format_exchange(&hits[i]),
+                images: Vec::new(),
+                tool_calls: Vec::new(),
+            });
+        }
+        if !selected.is_empty() {
+            say!("{} {}", "✓".bright_green(), tr!("recall.added", count = selected.len()));
+        }
+        Ok(())
+    }
+
+    /// Records until Enter is pressed, transcribes locally and asks before the text is sent
+    async fn record_voice(&self, input: &mut InputReader) -> Result<Option<String>> {
+        if !io::stdin().is_terminal() {
+            anyhow::bail!(tr!("voice.needs_terminal"));
+        }
+
+        let recording = voice::start(&self.config.voice)?;
+        let stop = input.next(format!("{} {} ", "🎙".bright_red(), tr!("voice.recording"))).await;
+        let audio = recording.stop().await?;
+        if !matches!(stop, Input::Line(_)) {
+            let _ = fs::remove_file(&audio);
+            say!("{}", tr!("voice.recording_discarded").yellow());
+            return Ok(None);
+        }
+
+        status!("{} {}", "⚙".bright_blue(), tr!("voice.transcribing"));
+        let text = voice::transcribe(&self.config.voice, audio).await?;
+        if text.is_empty() {
+            say!("{}", tr!("voice.no_speech").yellow());
+            return Ok(None);
+        }
+
+        input.print(format!("{} {}", tr!("voice.heard").bright_yellow().bold(), text));
+        match input.next(format!("{} ", tr!("chat.confirm_send").bright_yellow())).await {
+            Input::Line(answer) if matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes") => Ok(Some(text)),
+            _ => {
+                say!("{}", tr!("voice.transcription_discarded").yellow());
+                Ok(None)
+            }
+        }
+    }
+
+    /// Shows the last response, or message `n`, in another language without changing the history
+    async fn translate(&mut self, args: &str) -> Result<()> {
+        // The message number is optional, so a language may contain spaces
+        let (language, index) = match args.rsplit_once(' ') {
+            Some((language, n)) if n.parse::<usize>().is_ok() => (language.trim(), self.message_index(n)?),
+            _ => {
+                let last = self.history.iter().rposition(|m| m.role == "assistant")
+                    .context(tr!("translate.nothing"))?;
+                (args, last)
+            }
+        };
+
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: format!("{}{}\n\n{}", TRANSLATE_PROMPT, language, self.history[index].content),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        }];
+
+        status!("{} {}", "⚙".bright_blue(), tr!("translate.translating", number = index + 1, language = language));
+        let reply = self.executor.complete(messages).await?;
+        self.print_budget_warning();
+
+        say!("\n{} {}\n", tr!("translate.ai", language = language).bright_blue().bold(),
+            reply.message.content.trim().bright_white());
+        Ok(())
+    }
+
+    /// Writes the conversation as an HTML page; `--gist` also uploads it as a secret gist
+    async fn share(&self, args: &str, input: &mut InputReader) -> Result<()> {
+        if !self.history.iter().any(|m| m.role != "system") {
+            say!("{}", tr!("share.nothing").yellow());
+            return Ok(());
+        }
+        let gist = args.split_whitespace().any(|arg| arg == "--gist");
+        let now = chrono::Local::now();
+        let filename = args.split_whitespace().find(|arg| *arg != "--gist")
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("conversation-{}.html", now.format("%Y%m%d-%H%M%S")));
+
+        let model = self.executor.get_model();
+        let html = share::render(&thoughts::exported(&self.history), model, &now.format("%Y-%m-%d %H:%M").to_string());
+        fs::write(&filename, &html)
+            .with_context(|| tr!("file.write_failed", path = filename))?;
+        say!("{} {}", "✓".bright_green(), tr!("share.saved", path = filename.bright_cyan()));
+        if !gist {
+            return Ok(());
+        }
+
+        // Secret gists are unlisted, not private: anyone with the URL can read them
+        if io::stdin().is_terminal() {
+            let answer = match input.next(format!("{} ", tr!("share.confirm_gist").bright_yellow())).await {
+                Input::Line(answer) => answer,
+                Input::Interrupted | Input::Eof => String::new(),
+            };
+            if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                say!("{}", tr!("share.not_uploaded").yellow());
+                return Ok(());
+            }
+        }
+        let name = Path::new(&filename).file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or(filename.clone());
+        let url = github::create_gist(&name, &html, &tr!("share.gist_description", model = model)).await?;
+        say!("{} {}", "✓".bright_green(), tr!("share.shared", url = url.bright_cyan()));
+        Ok(())
+    }
+
+    /// Describes the current branch as a pull request; `--create` opens it with gh
+    async fn pr_description(&mut self, args: &str, input: &mut InputReader) -> Result<()> {
+        let create = args.split_whitespace().any(|arg| arg == "--create");
+        let base = args.split_whitespace().find(|arg| *arg != "--create");
+        let changes = BranchChanges::collect(base)?;
+
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: changes.prompt().to_string(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        }];
+
+        status!("{} {}", "⚙".bright_blue(), tr!("pr.describing", count = changes.commits,
+            branch = changes.branch.bright_cyan(), base = changes.base.bright_cyan()));
+        let reply = self.executor.complete(messages).await?;
+        self.print_budget_warning();
+
+        let (title, body) = pr::split_description(&reply.message.content);
+        if output::is_quiet() {
+            say!("{}\n\n{}", title, body);
+        } else {
+            say!("\n{}\n\n{}\n", title.bright_white().bold(), body);
+        }
+        if !create {
+            return Ok(());
+        }
+        if title.is_empty() {
+            anyhow::bail!(tr!("pr.no_title"));
+        }
+
+        // Publishing is hard to take back, so an interactive user confirms first
+        if io::stdin().is_terminal() {
+            let answer = match input.next(format!("{} ", tr!("pr.confirm_create").bright_yellow())).await {
+                Input::Line(answer) => answer,
+                Input::Interrupted | Input::Eof => String::new(),
+            };
+            if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                say!("{}", tr!("pr.not_created").yellow());
+                return Ok(());
+            }
+        }
+        let url = pr::create(&changes.base, &title, &body)?;
+        say!("{} {}", "✓".bright_green(), tr!("pr.created", url = url.bright_cyan()));
+        Ok(())
+    }
+
+    async fn summarize_path(&self, path: &str, instruction: &str) -> Result<()> {
+        let chunks = mapreduce::collect_chunks(std::path::Path::new(path), DEFAULT_CHUNK_SIZE)?;
+        say!("{} {}", "⚙".bright_blue(), tr!("summarize_dir.mapping", count = chunks.len()));
+
+        let output = self.executor.map_reduce(chunks, instruction).await?;
+        self.print_budget_warning();
+
+        if output.failed > 0 {
+            say_error!("{} {}", tr!("label.warning").bright_yellow(),
+                tr!("summarize_dir.failed_chunks", failed = output.failed, count = output.chunks));
+        }
+        say!("{} {}\n", "AI:".bright_blue().bold(), output.answer.bright_white());
+        Ok(())
+    }
+
+    async fn show_workers(&self) {
+        let statuses = self.executor.worker_status().await;
+
+        say!("\n{}", tr!("workers.title").bright_yellow().bold());
+        say!("{}", accessibility::rule('=').bright_black());
+
+        for status in statuses {
+            let indicator = if status.online {
+                "●".bright_green()
+            } else {
+                "●".bright_red()
+            };
+            let state = if status.online {
+                tr!("workers.models", count = status.models).normal()
+            } else {
+                tr!("workers.offline").bright_red()
+            };
+
+            say!("  {} {} [{}] {}",
+                indicator,
+                status.address.bright_cyan(),
+                status.backend.to_string().bright_magenta(),
+                tr!("workers.active", active = status.active, max = status.max_concurrent, state = state));
         }
 
-        Ok(())
+        say!("{}\n", accessibility::rule('=').bright_black());
     }
 
-    async fn handle_command(&mut self, cmd: &str) -> Result<bool> {
-        match cmd {
-            "/quit" | "/exit" => {
-                println!("{}", "Goodbye!".bright_cyan());
-                return Ok(false);
-            }
-            "/clear" => {
-                self.history.clear();
-                println!("{}", "Conversation history cleared.".yellow());
-            }
-            "/history" => {
-                self.show_history();
-            }
-            "/help" => {
-                self.show_help();
-            }
-            "/model" => {
-                println!("Current model: {}", self.executor.get_model().bright_cyan());
-            }
-            "/mcp-tools" => {
-                self.show_mcp_tools();
-            }
-            cmd if cmd.starts_with("/mcp-call ") => {
-                let rest = cmd.strip_prefix("/mcp-call ").unwrap().trim();
-                let parts: Vec<&str> = rest.splitn(2, ' ').collect();
-                
-                if parts.len() < 2 {
-                    println!("{} Usage: /mcp-call <tool_name> <json_args>", 
-                        "Info:".bright_yellow());
-                    println!("Example: /mcp-call add {{\"a\": 5, \"b\": 3}}");
-                } else {
-                    let tool_name = parts[0];
-                    let args_str = parts[1];
-                    
-                    match serde_json::from_str(args_str) {
-                        Ok(args) => {
-                            if let Err(e) = self.call_mcp_tool(tool_name, args).await {
-                                eprintln!("{} {}", "Error:".bright_red(), e);
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("{} Invalid JSON: {}", "Error:".bright_red(), e);
-                        }
-                    }
-                }
-            }
-            "/mcp-reload" => {
-                if let Err(e) = self.reload_mcp().await {
-                    eprintln!("{} Failed to reload MCP: {}", "Error:".bright_red(), e);
-                } else {
-                    println!("{} MCP configuration reloaded", "✓".bright_green());
-                }
+    /// Takes in MCP servers that finished connecting in the background and
+    /// updates the tool list in the system prompt if it changed, through them
+    /// or through tools used since
+    async fn sync_mcp(&mut self) {
+        let Some(mcp) = &mut self.mcp_manager else {
+            return;
+        };
+        mcp.poll_pending().await;
+        let prompt = mcp.tools_prompt();
+        if let (Some(before), Some(after)) = (&self.tools_prompt, &prompt) && before != after {
+            for message in self.history.iter_mut().filter(|m| m.role == "system" && &m.content == before) {
+                message.content = after.clone();
             }
-            cmd if cmd.starts_with("/model ") => {
-                let model = cmd.strip_prefix("/model ").unwrap().trim();
-                match self.executor.switch_model(model.to_string()).await {
-                    Ok(_) => {
-                        println!("{} Switched to model: {}", "✓".bright_green(), model.bright_cyan());
-                        self.history.clear();
-                    }
+        }
+        self.tools_prompt = prompt;
+    }
+
+    /// Narrows the tools described to the model to those matching the
+    /// message, as "toolSelection" asks
+    async fn select_tools(&mut self, text: &str) {
+        let config = &self.config.tool_selection;
+        let Some(mcp) = &mut self.mcp_manager else {
+            return;
+        };
+        let selection = {
+            let tools = mcp.list_tools();
+            match config.mode {
+                ToolSelection::Off => None,
+                ToolSelection::Keywords => Some(tool_selection::by_keywords(config, text, &tools)),
+                ToolSelection::Model => match tool_selection::by_model(&self.executor, config, text, &tools).await {
+                    Ok(names) => Some(names),
                     Err(e) => {
-                        eprintln!("{} {}", "Error:".bright_red(), e);
+                        say_error!("{} {} {:#}", tr!("label.warning").bright_yellow(), tr!("tools.selection_failed"), e);
+                        None
                     }
-                }
-            }
-            cmd if cmd.starts_with("/save ") => {
-                let filename = cmd.strip_prefix("/save ").unwrap().trim();
-                if let Err(e) = self.save_conversation(filename) {
-                    eprintln!("{} Failed to save: {}", "Error:".bright_red(), e);
-                } else {
-                    println!("{} Conversation saved to {}", "✓".bright_green(), filename.bright_cyan());
-                }
-            }
-            "/save" => {
-                println!("{} Usage: /save <filename>", "Info:".bright_yellow());
-                println!("Example: /save my_chat.json");
-            }
-            cmd if cmd.starts_with("/load ") => {
-                let filename = cmd.strip_prefix("/load ").unwrap().trim();
-                if let Err(e) = self.load_conversation(filename) {
-                    eprintln!("{} Failed to load: {}", "Error:".bright_red(), e);
-                } else {
-                    println!("{} Conversation loaded from {}", "✓".bright_green(), filename.bright_cyan());
-                }
-            }
-            "/load" => {
-                println!("{} Usage: /load <filename>", "Info:".bright_yellow());
-                println!("Example: /load my_chat.json");
-            }
-            cmd if cmd.starts_with("/batch ") => {
-                let filename = cmd.strip_prefix("/batch ").unwrap().trim();
-                if let Err(e) = self.process_batch_file(filename).await {
-                    eprintln!("{} Batch processing failed: {}", "Error:".bright_red(), e);
-                } else {
-                    println!("{} Batch processing complete", "✓".bright_green());
-                }
-            }
-            "/batch" => {
-                println!("{} Usage: /batch <filename>", "Info:".bright_yellow());
-                println!("Example: /batch prompts.txt");
-                println!("\nBatch file format (one prompt per line):");
-                println!("  What is Rust?");
-                println!("  Write hello world in Python");
-                println!("  Explain recursion");
+                },
             }
-            _ => {
-                println!("{} {}", "Unknown command:".bright_red(), cmd);
-                println!("Type {} for available commands", "/help".bright_cyan());
-            }
-        }
-        Ok(true)
+        };
+        mcp.select(selection);
     }
-    
-    async fn process_batch_file(&self, filename: &str) -> Result<()> {
-        let content = fs::read_to_string(filename)?;
-        let prompts: Vec<String> = content.lines()
-            .map(|s: &str| s.to_string())
-            .collect();
-    
-        println!("Processing {} prompts...", prompts.len());
-    
-        for (i, prompt) in prompts.iter().enumerate() {
-            println!("\n[{}/{}] {}", i + 1, prompts.len(), prompt);
-            let response = self.executor.chat(vec![Message {
-                role: "user".to_string(),
-                content: prompt.clone(),
-            }]).await?;
-            println!("Response: {}", response);
+
+    /// What each tool costs in the system prompt and which are left out
+    fn show_tool_budget(&self) {
+        let Some(mcp) = &self.mcp_manager else {
+            say!("{}", tr!("tools.none").yellow());
+            return;
+        };
+        let ranked = mcp.ranked_tools();
+        say!("\n{}", tr!("tools.budget_title").bright_yellow().bold());
+        for tool in &ranked {
+            let mark = if tool.sent { "●".bright_green() } else { "○".bright_black() };
+            let note = match (tool.sent, tool.truncated) {
+                (false, _) if !tool.relevant => format!(" {}", tr!("tools.not_relevant")).bright_black().to_string(),
+                (false, _) => format!(" {}", tr!("tools.omitted")).bright_red().to_string(),
+                (true, true) => format!(" {}", tr!("tools.description_cut")).yellow().to_string(),
+                (true, false) => String::new(),
+            };
+            let server = if tool.server == "builtin" {
+                String::new()
+            } else {
+                format!(" {}", tr!("tools.from", server = tool.server.bright_magenta()))
+            };
+            say!("  {} {} ({}{}) {}{}", mark, tool.tool.name.bright_cyan(),
+                tool.priority.label(), server, tr!("tools.tokens", tokens = tool.tokens), note);
         }
-    
-        Ok(())
+        let sent: Vec<_> = ranked.iter().filter(|t| t.sent).collect();
+        say!("\n{}", tr!("tools.sending", sent = sent.len(), count = ranked.len(),
+            tokens = tokens::format_count(sent.iter().map(|t| t.tokens).sum()),
+            budget = tokens::format_count(mcp.budget_tokens())));
+        say!("{}\n", tr!("tools.budget_hint").bright_black());
     }
 
     fn show_mcp_tools(&self) {
         if let Some(mcp) = &self.mcp_manager {
             let tools = mcp.list_tools();
             if tools.is_empty() {
-                println!("{}", "No MCP tools available.".yellow());
+                say!("{}", tr!("tools.none").yellow());
                 return;
             }
 
-            println!("\n{}", "Available MCP Tools:".bright_yellow().bold());
-            println!("{}", "=".repeat(60).bright_black());
+            say!("\n{}", tr!("tools.title").bright_yellow().bold());
+            say!("{}", accessibility::rule('=').bright_black());
         
             // Group by built-in vs external
             let mut builtin = Vec::new();
             let mut external = Vec::new();
         
-            for (_tool_name, (server_name, tool)) in mcp.get_tools_with_server() {
+            for (server_name, tool) in mcp.get_tools_with_server().values() {
                 if server_name == "builtin" {
                     builtin.push(tool);
                 } else {
@@ -281,47 +2266,155 @@ impl ChatCLI {
             }
         
             if !builtin.is_empty() {
-                println!("\n{}", "Built-in Tools:".bright_blue().bold());
+                say!("\n{}", tr!("tools.builtin").bright_blue().bold());
                 for tool in builtin {
-                    println!("\n  {} {}", "●".bright_green(), tool.name.bright_cyan());
-                    println!("    {}", tool.description);
+                    say!("\n  {} {}", "●".bright_green(), tool.name.bright_cyan());
+                    say!("    {}", tool.description);
                 }
             }
         
             if !external.is_empty() {
-                println!("\n{}", "External MCP Servers:".bright_blue().bold());
+                say!("\n{}", tr!("tools.external").bright_blue().bold());
                 for (server, tool) in external {
-                    println!("\n  {} {} (from {})", 
+                    say!("\n  {} {} ({})", 
                         "●".bright_green(), 
                         tool.name.bright_cyan(),
-                        server.bright_magenta());
-                    println!("    {}", tool.description);
+                        tr!("tools.from", server = server.bright_magenta()));
+                    say!("    {}", tool.description);
                 }
             }
         
-            println!("\n{}\n", "=".repeat(60).bright_black());
-            println!("Use {} <tool> <args> to execute", "/mcp-call".bright_cyan());
+            say!("\n{}\n", accessibility::rule('=').bright_black());
+            say!("{}", tr!("tools.call_hint", command = format!("{} <tool> <args>", "/mcp-call".bright_cyan())));
         }
     }
 
-    async fn call_mcp_tool(&mut self, tool_name: &str, arguments: serde_json::Value) -> Result<()> {
-        if let Some(mcp) = &mut self.mcp_manager {
-            println!("{} Calling tool '{}'...", "⚙".bright_blue(), tool_name);
-            
-            let result = mcp.call_tool(tool_name, arguments).await?;
-            
-            for content in &result.content {
-                if content.content_type == "text" {
-                    println!("{} {}", "✓".bright_green(), content.text);
+    /// Runs a tool and adds its text and images to the conversation as a "tool" message
+    async fn call_mcp_tool(&mut self, tool_name: &str, mut arguments: serde_json::Value, input: &mut InputReader) -> Result<()> {
+        let retries = self.config.tool_retries.unwrap_or(DEFAULT_TOOL_RETRIES);
+        let mut attempt = 0;
+        let result = loop {
+            let Some(mcp) = &mut self.mcp_manager else {
+                anyhow::bail!(tr!("tools.not_initialized"));
+            };
+            // Without a terminal there is nobody to ask, so the call is refused
+            if let Some(request) = mcp.approval_request(tool_name, &arguments)
+                && io::stdin().is_terminal()
+            {
+                for line in approval_panel(&request) {
+                    input.print(line);
+                }
+                let approval = match input.next(format!("{} ", Approval::PROMPT.bright_yellow())).await {
+                    Input::Line(answer) => Approval::parse(&answer),
+                    Input::Interrupted | Input::Eof => Approval::Deny,
+                };
+                mcp.decide(tool_name, approval);
+                if matches!(approval, Approval::Deny | Approval::Never) {
+                    return Err(anyhow::anyhow!(tr!("tools.declined", tool = tool_name)).context(Failure::ToolDenied));
                 }
             }
-        } else {
-            anyhow::bail!("MCP not initialized");
+
+            if accessibility::enabled() {
+                input.print(tr!("tools.request", tool = tool_name, arguments = arguments));
+            } else {
+                input.print(format!("{} {}", "⚙".bright_blue(), tr!("tools.calling", tool = tool_name)));
+            }
+
+            match mcp.call_tool(tool_name, arguments.clone()).await {
+                Ok(result) => break result,
+                Err(e) if attempt < retries && e.downcast_ref::<InvalidArguments>().is_some() => {
+                    attempt += 1;
+                    input.print(format!("{} {:#}; {}", "⟳".bright_yellow(), e,
+                        tr!("tools.repairing", attempt = attempt, retries = retries)));
+                    arguments = self.repair_arguments(tool_name, &arguments, &e).await
+                        .context(tr!("tools.repair_failed", error = format!("{:#}", e)))?;
+                    input.print(format!("  {}", arguments.to_string().bright_black()));
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        let (images, texts): (Vec<_>, Vec<_>) = result.content.into_iter()
+            .filter(|c| c.content_type == "text" || c.content_type == "image")
+            .partition(|c| c.content_type == "image");
+        let images: Vec<String> = images.into_iter().filter_map(|c| c.data).collect();
+        let texts: Vec<String> = texts.into_iter().map(|c| c.text).collect();
+        let max_items = self.config.json_array_items.unwrap_or(tool_format::DEFAULT_ARRAY_ITEMS);
+        // The message the output becomes, for /history --show
+        let index = self.history.len() + 1;
+        for text in &texts {
+            if text.starts_with(thoughts::HEADER) {
+                input.print(thoughts::display(text));
+                continue;
+            }
+            let pretty = tool_format::pretty(text, max_items, true);
+            if accessibility::enabled() {
+                input.print(tr!("tools.result_lines", tool = tool_name, count = text.trim_end().lines().count()));
+            } else {
+                input.print(format!("{} {}", "✓".bright_green(), tr!("tools.result", tool = tool_name).bright_black()));
+            }
+            input.print(collapse_tool_output(pretty.as_deref().unwrap_or(text), index));
         }
+        if !images.is_empty() {
+            input.print(format!("{} {}", "📎".bright_green(), tr!("tools.images", count = images.len())));
+        }
+        let text = texts.join("\n");
+
+        let content = match tool_output::condense(&self.executor, &self.config.tool_summary, tool_name, &text).await {
+            Ok(Some(condensed)) => {
+                status!("{} {}", "✓".bright_green(), tr!("tools.summarized", count = text.chars().count(),
+                    model = condensed.model.bright_cyan(), path = condensed.path.display()));
+                condensed.content
+            }
+            Ok(None) => text,
+            Err(e) => {
+                say_error!("{} {} {:#}", tr!("label.warning").bright_yellow(), tr!("tools.summary_failed"), e);
+                text
+            }
+        };
+        self.history.push(Message {
+            role: "tool".to_string(),
+            content,
+            images,
+            tool_calls: Vec::new(),
+        });
         
         Ok(())
     }
 
+    /// Asks the model for arguments that fit the tool's schema, given the
+    /// ones that were rejected and why
+    async fn repair_arguments(&self, tool_name: &str, arguments: &serde_json::Value, error: &anyhow::Error) -> Result<serde_json::Value> {
+        let (_, tool) = self.mcp_manager.as_ref()
+            .and_then(|mcp| mcp.get_tools_with_server().get(tool_name))
+            .context(tr!("tools.not_found", tool = tool_name))?;
+        let request = self.history.iter().rev()
+            .find(|m| m.role == "user")
+            .map_or(String::new(), |m| format!("\n\nThe user's last message, for what the call is meant to do:\n{}", m.content));
+        let prompt = format!(
+            "The tool '{}' was called with arguments it rejected.\n\nDescription: {}\n\nInput schema:\n{}\n\n\
+             Arguments:\n{}\n\nError: {:#}{}\n\n\
+             Reply with only the corrected arguments as one JSON object, keeping what the call was meant to do.",
+            tool.name, tool.description, serde_json::to_string_pretty(&tool.input_schema)?,
+            arguments, error, request
+        );
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: prompt,
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        }];
+        let reply = self.executor.batch_complete(None, messages, None).await?;
+
+        // The object may come in a code block or after a sentence
+        let content = reply.message.content;
+        let json = content.find('{').zip(content.rfind('}'))
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| &content[start..=end])
+            .context(tr!("tools.repair_no_json"))?;
+        serde_json::from_str(json).context(tr!("tools.repair_invalid_json"))
+    }
+
     async fn reload_mcp(&mut self) -> Result<()> {
         // Shutdown existing MCP connections
         if let Some(mcp) = &mut self.mcp_manager {
@@ -332,7 +2425,7 @@ impl ChatCLI {
         self.mcp_manager = match McpManager::new().await {
             Ok(manager) => Some(manager),
             Err(e) => {
-                eprintln!("{} {}", "Warning:".bright_yellow(), e);
+                say_error!("{} {}", tr!("label.warning").bright_yellow(), e);
                 None
             }
         };
@@ -342,66 +2435,532 @@ impl ChatCLI {
     
 
     fn print_welcome(&self) {
-        println!("\n{}", "=".repeat(60).bright_cyan());
-        println!("{}", "  AI Chat CLI - Powered by Repartir".bright_cyan().bold());
-        println!("{}", "=".repeat(60).bright_cyan());
-        println!("\n{}", "Commands:".bright_yellow().bold());
-        println!("  {} - Show this help message", "/help".bright_cyan());
-        println!("  {} - Clear conversation history", "/clear".bright_cyan());
-        println!("  {} - Show conversation history", "/history".bright_cyan());
-        println!("  {} - List available MCP tools", "/mcp-tools".bright_cyan());
-        println!("  {} <t> <a> - Call MCP tool", "/mcp-call".bright_cyan());
-        println!("  {} - Reload MCP configuration", "/mcp-reload".bright_cyan());
-        println!("  {} - Show current model", "/model".bright_cyan());
-        println!("  {} <name> - Switch to different model", "/model".bright_cyan());
-        println!("  {} - Exit the chat", "/quit".bright_cyan());
-        println!("\n{}\n", "Start chatting! (Ctrl+C to interrupt, /quit to exit)".bright_white());
+        if accessibility::enabled() {
+            say!("\nAI Chat CLI - Powered by Repartir");
+        } else {
+            say!("\n{}", "=".repeat(60).bright_cyan());
+            say!("{}", "  AI Chat CLI - Powered by Repartir".bright_cyan().bold());
+            say!("{}", "=".repeat(60).bright_cyan());
+        }
+        say!("\n{}", tr!("welcome.commands").bright_yellow().bold());
+        print_commands();
+        say!("\n{}\n", tr!("welcome.start").bright_white());
     }
 
     fn show_help(&self) {
-        println!("\n{}", "Available Commands:".bright_yellow().bold());
-        println!("  {} - Show this help message", "/help".bright_cyan());
-        println!("  {} - Clear conversation history", "/clear".bright_cyan());
-        println!("  {} - Show conversation history", "/history".bright_cyan());
-        println!("  {} - List available MCP tools", "/mcp-tools".bright_cyan());
-        println!("  {} <t> <a> - Call MCP tool", "/mcp-call".bright_cyan());
-        println!("  {} - Reload MCP configuration", "/mcp-reload".bright_cyan());
-        println!("  {} - Show current model", "/model".bright_cyan());
-        println!("  {} <name> - Switch to different model", "/model".bright_cyan());
-        println!("  {} - Exit the chat\n", "/quit".bright_cyan());
-    }
-
-    fn show_history(&self) {
+        say!("\n{}", tr!("help.title").bright_yellow().bold());
+        print_commands();
+        say!();
+    }
+
+    fn show_history(&self, args: &str) {
         if self.history.is_empty() {
-            println!("{}", "No conversation history yet.".yellow());
+            say!("{}", tr!("history.empty").yellow());
             return;
         }
 
-        println!("\n{}", "Conversation History:".bright_yellow().bold());
-        println!("{}", "-".repeat(60).bright_black());
-        
-        for (i, msg) in self.history.iter().enumerate() {
-            let role = if msg.role == "user" {
-                "You".bright_green().bold()
+        let options = match HistoryOptions::parse(args) {
+            Ok(options) => options,
+            Err(e) => {
+                say_error!("{} {}", tr!("label.error").bright_red(), e);
+                say!("{} /history [--page <n>] [--role user|assistant|system|tool] [--show <n>]", tr!("label.usage"));
+                return;
+            }
+        };
+
+        if let Some(index) = options.show {
+            match self.history.get(index.wrapping_sub(1)) {
+                Some(msg) => {
+                    say!("\n{} [{}] {}", role_label(&msg.role), index,
+                        format!("({})", tr!("history.tokens", tokens = tokens::estimate_message(msg))).bright_black());
+                    say!("{}\n", msg.content);
+                }
+                None => say_error!("{} {}", tr!("label.error").bright_red(),
+                    tr!("history.no_message", number = index, count = self.history.len())),
+            }
+            return;
+        }
+
+        // Indices always refer to the full history so they match other commands
+        let matching: Vec<(usize, &Message)> = self.history.iter()
+            .enumerate()
+            .filter(|(_, m)| options.role.as_ref().is_none_or(|role| &m.role == role))
+            .map(|(i, m)| (i + 1, m))
+            .collect();
+
+        if matching.is_empty() {
+            say!("{}", tr!("history.no_match").yellow());
+            return;
+        }
+
+        let pages = matching.len().div_ceil(HISTORY_PAGE_SIZE);
+        // Without --page, show the most recent messages
+        let page = options.page.unwrap_or(pages).clamp(1, pages);
+        let start = (page - 1) * HISTORY_PAGE_SIZE;
+
+        say!("\n{}", tr!("history.title").bright_yellow().bold());
+        say!("{}", accessibility::rule('-').bright_black());
+
+        for (index, msg) in matching.iter().skip(start).take(HISTORY_PAGE_SIZE) {
+            let estimate = tokens::estimate_message(msg);
+            let chars = msg.content.chars().count();
+            let lines = msg.content.trim_end().lines().count();
+
+            let content = if thoughts::is_thought(msg) && !thoughts::shown() {
+                format!("{} {}", thoughts::summary(&msg.content).bright_black(),
+                    format!("(/history --show {})", index).bright_black())
+            } else if msg.role == "tool" || msg.role == "system" {
+                // Collapsed to its first line
+                let first: String = msg.content.lines().next().unwrap_or_default().chars().take(HISTORY_PREVIEW_CHARS).collect();
+                let rest = if lines > 1 || chars > first.chars().count() {
+                    format!(" ({})", tr!("history.more_lines", count = lines.saturating_sub(1), number = index)).bright_black().to_string()
+                } else {
+                    String::new()
+                };
+                format!("{}{}", first, rest)
+            } else if chars > HISTORY_PREVIEW_CHARS {
+                let preview: String = msg.content.chars().take(HISTORY_PREVIEW_CHARS).collect();
+                format!("{}… {}", render_reply(preview.trim_end()),
+                    format!("({})", tr!("history.more_chars", count = chars - HISTORY_PREVIEW_CHARS, number = index)).bright_black())
+            } else if msg.role == "assistant" {
+                render_message(msg)
             } else {
-                "AI".bright_blue().bold()
+                msg.content.clone()
             };
-            
-            println!("{} [{}]: {}", role, i + 1, msg.content);
+
+            let pin = if self.pinned.contains(&(index - 1)) { " 📌" } else { "" };
+            let header = format!("{} [{}]{} {}:", role_label(&msg.role), index, pin,
+                format!("~{}", tokens::format_count(estimate)).bright_black());
+            match msg.role.as_str() {
+                // Context, not conversation: indented and dimmed
+                "system" => say!("  {} {}", header, content.bright_black()),
+                "tool" => say!("  {} {}", header, content),
+                _ => say!("{} {}", header, content),
+            }
+        }
+
+        say!("{}", accessibility::rule('-').bright_black());
+        let total: usize = matching.iter().map(|(_, m)| tokens::estimate_message(m)).sum();
+        say!("{}\n", tr!("history.page", page = page, pages = pages, count = matching.len(),
+            tokens = tokens::format_count(total)).bright_black());
+    }
+
+    /// Drops messages for which `keep` is false, renumbering the pins
+    fn retain_messages(&mut self, keep: impl Fn(usize, &Message) -> bool) {
+        let mut pinned = BTreeSet::new();
+        let mut kept = Vec::with_capacity(self.history.len());
+
+        for (i, msg) in std::mem::take(&mut self.history).into_iter().enumerate() {
+            if keep(i, &msg) {
+                if self.pinned.contains(&i) {
+                    pinned.insert(kept.len());
+                }
+                kept.push(msg);
+            }
+        }
+
+        self.history = kept;
+        self.pinned = pinned;
+    }
+
+    /// Resolves a 1-based message number as shown by /history
+    fn message_index(&self, arg: &str) -> Result<usize> {
+        let n: usize = arg.parse().context(tr!("history.invalid_number", number = arg))?;
+        if n == 0 || n > self.history.len() {
+            anyhow::bail!(tr!("history.no_message", number = n, count = self.history.len()));
+        }
+        Ok(n - 1)
+    }
+
+    /// Saves the conversation and the file journal position under a name
+    fn checkpoint(&mut self, name: Option<&str>) {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => (self.checkpoints.len() + 1..)
+                .map(|n| n.to_string())
+                .find(|n| !self.checkpoints.iter().any(|c| &c.name == n))
+                .unwrap(),
+        };
+        self.checkpoints.retain(|c| c.name != name);
+        self.checkpoints.push(Checkpoint {
+            name: name.clone(),
+            history: self.history.clone(),
+            pinned: self.pinned.clone(),
+            journal: journal::mark(),
+        });
+        say!("{} {}", "✓".bright_green(),
+            tr!("checkpoint.saved", name = name.bright_cyan(), count = self.history.len(), command = format!("/rollback {}", name)));
+    }
+
+    /// Undoes the file edits since a checkpoint and restores its conversation
+    fn rollback(&mut self, name: &str) -> Result<()> {
+        let index = self.checkpoints.iter().position(|c| c.name == name)
+            .with_context(|| tr!("checkpoint.unknown", name = name))?;
+
+        let files = journal::rollback(self.checkpoints[index].journal)?;
+        self.checkpoints.truncate(index + 1);
+        let checkpoint = &self.checkpoints[index];
+        self.history = checkpoint.history.clone();
+        self.pinned = checkpoint.pinned.clone();
+
+        say!("{} {}", "✓".bright_green(), tr!("checkpoint.rolled_back", name = name.bright_cyan(), count = self.history.len()));
+        for file in &files {
+            say!("  {} {}", "↺".bright_yellow(), file.display());
+        }
+        if files.is_empty() {
+            say!("{}", tr!("checkpoint.no_files").bright_black());
+        }
+        Ok(())
+    }
+
+    /// Opens a message in $VISUAL/$EDITOR and replaces it with the saved text
+    fn edit_message(&mut self, arg: &str) -> Result<()> {
+        let index = self.message_index(arg)?;
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string());
+
+        let path = std::env::temp_dir().join(format!("ai-chat-msg-{}.md", uuid::Uuid::new_v4()));
+        fs::write(&path, &self.history[index].content)
+            .context(tr!("edit.temp_failed"))?;
+
+        // The editor command may carry its own flags, e.g. "code --wait"
+        let mut parts = editor.split_whitespace();
+        let program = parts.next().context(tr!("edit.no_editor"))?;
+        let status = std::process::Command::new(program)
+            .args(parts)
+            .arg(&path)
+            .status()
+            .with_context(|| tr!("edit.start_failed", editor = editor));
+
+        let edited = fs::read_to_string(&path);
+        let _ = fs::remove_file(&path);
+
+        if !status?.success() {
+            anyhow::bail!(tr!("edit.editor_failed", number = index + 1));
+        }
+        let edited = edited.context(tr!("edit.read_failed"))?;
+        let edited = edited.trim_end();
+
+        if edited.is_empty() {
+            anyhow::bail!(tr!("edit.empty", number = index + 1));
+        }
+        if edited == self.history[index].content {
+            say!("{}", tr!("edit.unchanged").yellow());
+            return Ok(());
         }
-        println!("{}\n", "-".repeat(60).bright_black());
+
+        self.history[index].content = edited.to_string();
+        say!("{} {}", "✓".bright_green(),
+            tr!("edit.updated", role = role_label(&self.history[index].role), number = index + 1));
+        Ok(())
     }
 }
 
-// Update Drop implementation
-impl Drop for ChatCLI {
-    fn drop(&mut self) {
-        if let Some(mcp) = &mut self.mcp_manager {
-            // Spawn blocking task to shutdown MCP
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async {
-                mcp.shutdown().await;
-            });
+/// How often an offline session checks whether Ollama is back
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a reply may take before the live status line appears, and how often it is redrawn
+const FOOTER_DELAY: Duration = Duration::from_millis(300);
+const FOOTER_INTERVAL: Duration = Duration::from_millis(150);
+/// Shorter streams, such as a local model answering in one burst, give meaningless rates
+const MIN_RATE_WINDOW: Duration = Duration::from_millis(250);
+const SPINNER: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+const SUMMARY_PROMPT: &str = "Summarize our conversation so far in a few short paragraphs or bullet points. \
+Cover the goals, decisions made, key facts and any open questions. Reply with the summary only.";
+
+/// Past exchanges offered by /recall
+const RECALL_HITS: usize = 5;
+
+/// Times the model may fix rejected tool arguments unless "toolRetries" says otherwise
+const DEFAULT_TOOL_RETRIES: usize = 2;
+
+const TRANSLATE_PROMPT: &str = "Translate the text below. Keep Markdown formatting, code blocks, \
+commands and names unchanged, and reply with the translation only.\nTarget language: ";
+
+const HISTORY_PAGE_SIZE: usize = 10;
+
+/// Longer messages are cut in /history; `--show <n>` prints one in full
+const HISTORY_PREVIEW_CHARS: usize = 300;
+
+/// Lines of tool output shown before the rest is collapsed
+const TOOL_PREVIEW_LINES: usize = 6;
+
+/// The commands listed by the welcome banner and /help: name, arguments and
+/// the catalog key of the description
+const COMMANDS: &[(&str, &str, &str)] = &[
+    ("/help", "", "command.help"),
+    ("/clear", "[--all]", "command.clear"),
+    ("/history", "[--page n] [--role r] [--show n]", "command.history"),
+    ("/edit-msg", "<n>", "command.edit_msg"),
+    ("/delete-msg", "<n>", "command.delete_msg"),
+    ("/pin", "<n>", "command.pin"),
+    ("/unpin", "<n>", "command.unpin"),
+    ("/checkpoint", "[name]", "command.checkpoint"),
+    ("/rollback", "<name>", "command.rollback"),
+    ("/mcp-tools", "", "command.mcp_tools"),
+    ("/tools prune", "", "command.tools_prune"),
+    ("/tools stats", "", "command.tools_stats"),
+    ("/mcp-call", "<t> <a>", "command.mcp_call"),
+    ("/mcp-reload", "", "command.mcp_reload"),
+    ("/workers", "", "command.workers"),
+    ("/show-thoughts", "[off]", "command.show_thoughts"),
+    ("/stats", "", "command.stats"),
+    ("/cache", "[clear]", "command.cache"),
+    ("/reconnect", "", "command.reconnect"),
+    ("/confirm", "", "command.confirm"),
+    ("/agent", "", "command.agent"),
+    ("/new", "[model]", "command.new"),
+    ("/tabs", "", "command.tabs"),
+    ("/switch", "<n>", "command.switch"),
+    ("/summarize", "[--to f]", "command.summarize"),
+    ("/summarize-dir", "<p> [i]", "command.summarize_dir"),
+    ("/translate", "<lang> [n]", "command.translate"),
+    ("/share", "[f] [--gist]", "command.share"),
+    ("/pr-description", "[base] [--create]", "command.pr_description"),
+    ("/recall", "<query>", "command.recall"),
+    ("/tag", "<tag> [tag ...]", "command.tag"),
+    ("/sessions", "[--tag t]", "command.sessions"),
+    ("/attach", "<file>", "command.attach"),
+    ("/refresh", "", "command.refresh"),
+    ("/bundle", "[load|save <name>]", "command.bundle"),
+    ("/repo-map", "", "command.repo_map"),
+    ("/why", "[question]", "command.why"),
+    ("/voice", "", "command.voice"),
+    ("/image", "<file>", "command.image"),
+    ("/paste-image", "", "command.paste_image"),
+    ("/compare", "<prompt>", "command.compare"),
+    ("/export-training", "<f> [fmt]", "command.export_training"),
+    ("/model", "", "command.model"),
+    ("/model", "<name>", "command.model_switch"),
+    ("/set", "[stop|num_predict|deterministic <v>]", "command.set"),
+    ("/backend", "[ollama|anthropic]", "command.backend"),
+    ("/quit", "", "command.quit"),
+];
+
+#[derive(Default)]
+struct HistoryOptions {
+    page: Option<usize>,
+    role: Option<String>,
+    show: Option<usize>,
+}
+
+impl HistoryOptions {
+    fn parse(args: &str) -> Result<Self> {
+        let mut options = Self::default();
+        let mut words = args.split_whitespace();
+
+        while let Some(flag) = words.next() {
+            let mut value = || words.next().context(tr!("history.needs_value", flag = flag));
+            match flag {
+                "--page" => options.page = Some(value()?.parse().context(tr!("history.invalid_page"))?),
+                "--show" => options.show = Some(value()?.parse().context(tr!("history.invalid_show"))?),
+                "--role" => {
+                    let role = value()?;
+                    if !matches!(role, "user" | "assistant" | "system" | "tool") {
+                        anyhow::bail!(tr!("history.unknown_role", role = role));
+                    }
+                    options.role = Some(role.to_string());
+                }
+                other => anyhow::bail!(tr!("history.unknown_option", option = other)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// `45s`, `12m 04s` or `1h 02m`
+fn format_duration(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Width of the /batch progress bar in cells
+const BATCH_BAR_WIDTH: usize = 24;
+
+/// `[██████░░░░░░] 4/10 done, 2 running`
+fn batch_progress(done: usize, running: usize, total: usize) -> String {
+    let filled = done * BATCH_BAR_WIDTH / total.max(1);
+    let bar = format!("{}{}", "█".repeat(filled), "░".repeat(BATCH_BAR_WIDTH - filled));
+    format!("[{}] {}", bar.bright_blue(), tr!("batch.progress", done = done, total = total, running = running))
+        .bright_black().to_string()
+}
+
+/// Draws an approval request as a bordered panel sized to the terminal, or
+/// as plain lines in accessible mode
+fn approval_panel(request: &ApprovalRequest) -> Vec<String> {
+    if accessibility::enabled() {
+        let mut lines = vec![tr!("approval.needed", tool = request.tool, server = request.server)];
+        for (heading, body) in request.sections() {
+            lines.push(heading.to_string());
+            lines.extend(body.into_iter().map(|line| format!("  {}", line)));
+        }
+        return lines;
+    }
+
+    let columns = ratatui::crossterm::terminal::size().map_or(80, |(w, _)| w as usize);
+    let title = format!(" {} ", tr!("approval.title", tool = request.tool, server = request.server));
+
+    let mut body = Vec::new();
+    for (i, (heading, lines)) in request.sections().into_iter().enumerate() {
+        if i > 0 {
+            body.push((String::new(), false));
+        }
+        body.push((heading.to_string(), true));
+        body.extend(lines.into_iter().map(|line| (format!("  {}", line), false)));
+    }
+
+    let longest = body.iter().map(|(l, _)| l.chars().count())
+        .chain([title.chars().count()])
+        .max()
+        .unwrap_or(0);
+    let width = longest.min(columns.saturating_sub(4)).max(20);
+
+    let border = |s: &str| s.bright_yellow().to_string();
+    let mut panel = vec![format!("{}{}{}", border("╭─"), title.bright_white().bold(),
+        border(&format!("{}╮", "─".repeat((width + 1).saturating_sub(title.chars().count())))))];
+
+    for (line, heading) in body {
+        let chars: Vec<char> = line.chars().collect();
+        let chunks: Vec<String> = if chars.is_empty() {
+            vec![String::new()]
+        } else {
+            chars.chunks(width).map(|c| c.iter().collect()).collect()
+        };
+        for chunk in chunks {
+            let padding = " ".repeat(width - chunk.chars().count());
+            let text = if heading { chunk.bright_cyan().bold() } else { chunk.normal() };
+            panel.push(format!("{} {}{} {}", border("│"), text, padding, border("│")));
+        }
+    }
+
+    panel.push(border(&format!("╰{}╯", "─".repeat(width + 2))));
+    panel
+}
+
+/// Splits `"###" "\n\nUser:" END` into its parts; quotes allow spaces and
+/// the escapes \n, \t, \" and \\
+fn parse_quoted(text: &str) -> Result<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut part = String::new();
+        if c != '"' {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                part.push(c);
+            }
+            parts.push(part);
+            continue;
+        }
+        chars.next();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('n') => part.push('\n'),
+                    Some('t') => part.push('\t'),
+                    Some(other) => part.push(other),
+                    None => anyhow::bail!(tr!("settings.unfinished_escape", text = text)),
+                },
+                Some(c) => part.push(c),
+                None => anyhow::bail!(tr!("settings.missing_quote", text = text)),
+            }
         }
+        if part.is_empty() {
+            anyhow::bail!(tr!("settings.empty_sequence", text = text));
+        }
+        parts.push(part);
+    }
+    Ok(parts)
+}
+
+fn print_commands() {
+    for (command, arguments, description) in COMMANDS {
+        let arguments = if arguments.is_empty() { String::new() } else { format!(" {}", arguments) };
+        say!("  {}{} - {}", command.bright_cyan(), arguments, i18n::text(description));
+    }
+}
+
+/// `❯ You`, `◆ AI`, `▪ System` or `⚒ Tool`; context and tool output are
+/// dimmer than the conversation itself. Accessible mode names the role only.
+fn role_label(role: &str) -> ColoredString {
+    if accessibility::enabled() {
+        return match role {
+            "user" => tr!("role.user"),
+            "assistant" => tr!("role.assistant"),
+            "system" => tr!("role.system"),
+            "tool" => tr!("role.tool"),
+            other => other,
+        }.normal();
+    }
+    match role {
+        "user" => format!("❯ {}", tr!("role.user")).bright_green().bold(),
+        "assistant" => "◆ AI".bright_blue().bold(),
+        "system" => format!("▪ {}", tr!("role.system")).magenta(),
+        "tool" => format!("⚒ {}", tr!("role.tool")).yellow(),
+        other => other.normal().bold(),
+    }
+}
+
+/// A reply with the tool call it asks for, if any, shown as one line,
+/// e.g. `⚒ read_file {"path":"notes.txt"}`, instead of its JSON block
+fn render_reply(content: &str) -> String {
+    let Some((block, call)) = agent::find_tool_call(content) else {
+        return content.to_string();
+    };
+    let request = tool_request(&call.name, &call.arguments);
+    let before = content[..block.start].trim_end();
+    let after = content[block.end..].trim_start();
+    [before, &request, after].iter()
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `render_reply` for a whole message, with the calls it made through `tool_calls`
+fn render_message(message: &Message) -> String {
+    let calls = message.tool_calls.iter()
+        .map(|call| tool_request(&call.function.name, &call.function.arguments));
+    std::iter::once(render_reply(message.content.trim()))
+        .chain(calls)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn tool_request(name: &str, arguments: &serde_json::Value) -> String {
+    if accessibility::enabled() {
+        tr!("tools.request", tool = name, arguments = arguments)
+    } else {
+        format!("{} {} {}", "⚒".yellow(), name.yellow().bold(), arguments.to_string().bright_black())
+    }
+}
+
+/// The first `TOOL_PREVIEW_LINES` lines of tool output, under a gutter,
+/// with a note on how to see the rest
+fn collapse_tool_output(text: &str, index: usize) -> String {
+    let lines: Vec<&str> = text.trim_end().lines().collect();
+    let accessible = accessibility::enabled();
+    let mut shown: Vec<String> = lines.iter()
+        .take(TOOL_PREVIEW_LINES)
+        .map(|line| if accessible { format!("  {}", line) } else { format!("  {} {}", "│".yellow(), line) })
+        .collect();
+    let hidden = lines.len().saturating_sub(TOOL_PREVIEW_LINES);
+    if hidden > 0 && accessible {
+        shown.push(format!("  {}", tr!("tools.hidden_lines", count = hidden, number = index)));
+    } else if hidden > 0 {
+        shown.push(format!("  {} {}", "╰".yellow(),
+            tr!("tools.more_lines", count = hidden, number = index).bright_black()));
     }
+    shown.join("\n")
 }
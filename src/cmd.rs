@@ -0,0 +1,81 @@
+//! Single shell commands from a description, for the `cmd` subcommand.
+//!
+//! The model is asked for exactly one command line, which is checked
+//! against the same policy rules as the bash tool, shown, and only run once
+//! the user confirms. Without a terminal to confirm on, the command is only
+//! printed, so it can be used as `$(ai-chat-cli -q cmd "...")`.
+
+use anyhow::{Context, Result};
+use colored::*;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::process::Command;
+
+use crate::args::CmdArgs;
+use crate::executor::AIExecutor;
+use crate::ollama::Message;
+use crate::output;
+use crate::policy::Policy;
+
+const PROMPT: &str = "Reply with exactly one shell command that does what is described below, \
+on a single line. No explanation, no Markdown, no code fences, no placeholder values the user \
+would have to fill in unless unavoidable.";
+
+pub async fn run(executor: &AIExecutor, args: CmdArgs) -> Result<()> {
+    let shell = std::env::var("SHELL").ok()
+        .and_then(|shell| shell.rsplit('/').next().map(str::to_string))
+        .unwrap_or_else(|| "sh".to_string());
+    let messages = vec![Message {
+        role: "user".to_string(),
+        content: format!("{}\n\nOS: {}\nShell: {}\nTask: {}", PROMPT, std::env::consts::OS, shell, args.description.join(" ")),
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    }];
+
+    let reply = executor.batch_complete(args.model.as_deref(), messages, None).await?;
+    let command = extract_command(&reply.message.content)
+        .context("The model did not reply with a command")?;
+
+    // Refused commands are not even offered
+    Policy::load()?.check_rules("bash", &serde_json::json!({ "command": command }))?;
+
+    if output::is_quiet() || !io::stdin().is_terminal() {
+        println!("{}", command);
+        return Ok(());
+    }
+
+    println!("\n  {} {}\n", "$".bright_black(), command.bright_white().bold());
+    print!("{} ", "Run it? [y/N]".bright_yellow());
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        return Ok(());
+    }
+
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+    let status = Command::new(&shell).arg("-c").arg(&command).status()
+        .with_context(|| format!("Failed to run {}", shell))?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// The command in a reply, in case the model wrapped it in a code block or
+/// a `$` prompt despite being asked not to
+fn extract_command(reply: &str) -> Option<String> {
+    let reply = reply.trim();
+    let body = match reply.find("```") {
+        Some(start) => {
+            let block = &reply[start + 3..];
+            // Skip the language tag line
+            let block = block.split_once('\n').map_or(block, |(_, rest)| rest);
+            block.split("```").next().unwrap_or(block)
+        }
+        None => reply,
+    };
+    body.lines()
+        .map(|line| line.trim().trim_start_matches("$ ").trim_matches('`').trim())
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
@@ -0,0 +1,746 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::distributed::Backend;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppConfig {
+    /// Remote workers added to the distributed inference pool
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workers: Vec<WorkerConfig>,
+
+    /// Preferred backend per model name
+    #[serde(rename = "modelBackends", default, skip_serializing_if = "HashMap::is_empty")]
+    pub model_backends: HashMap<String, Backend>,
+
+    /// Preferred backend for batch jobs, overriding the per-model preference
+    #[serde(rename = "batchBackend", skip_serializing_if = "Option::is_none")]
+    pub batch_backend: Option<Backend>,
+
+    /// Models queried side by side by /compare
+    #[serde(rename = "compareModels", default, skip_serializing_if = "Vec::is_empty")]
+    pub compare_models: Vec<String>,
+
+    /// Show a preview of each request and ask before sending it
+    #[serde(rename = "confirmBeforeSend", default, skip_serializing_if = "std::ops::Not::not")]
+    pub confirm_before_send: bool,
+
+    /// What happens when an attached file changed on disk since it was attached
+    #[serde(rename = "staleAttachments", default)]
+    pub stale_attachments: StaleAttachments,
+
+    /// Whether the last conversation in the working directory is picked up on start
+    #[serde(rename = "autoResume", default)]
+    pub auto_resume: AutoResume,
+
+    /// Template of the system message describing the tools, with {tools},
+    /// {mode} and {usage} filled in; empty to leave the tools out
+    #[serde(rename = "toolInstructions", skip_serializing_if = "Option::is_none")]
+    pub tool_instructions: Option<String>,
+
+    /// How tools reach the model: described in the system prompt, or sent
+    /// through Ollama's tool-calling API
+    #[serde(rename = "toolCalling", default)]
+    pub tool_calling: ToolCalling,
+
+    /// Where chat replies come from; switched for the session with /backend
+    #[serde(rename = "chatBackend", default)]
+    pub chat_backend: Provider,
+
+    /// Key, model and reply length for the Anthropic backend
+    #[serde(default)]
+    pub anthropic: AnthropicConfig,
+
+    /// Limit on how much of the context the tool descriptions may take
+    #[serde(rename = "toolBudget", default)]
+    pub tool_budget: ToolBudgetConfig,
+
+    /// Describing only the tools that match each message
+    #[serde(rename = "toolSelection", default)]
+    pub tool_selection: ToolSelectionConfig,
+
+    /// Reshaping of JSON tool results, by tool name
+    #[serde(rename = "toolFormatters", default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_formatters: HashMap<String, ToolFormatterConfig>,
+
+    /// Elements of a JSON array shown in tool results before the rest are counted (default 10)
+    #[serde(rename = "jsonArrayItems", skip_serializing_if = "Option::is_none")]
+    pub json_array_items: Option<usize>,
+
+    /// The model calling tools itself in the REPL
+    #[serde(default)]
+    pub agent: AgentConfig,
+
+    /// Times the model may fix tool arguments that do not fit the schema (default 2, 0 to never)
+    #[serde(rename = "toolRetries", skip_serializing_if = "Option::is_none")]
+    pub tool_retries: Option<usize>,
+
+    /// Shortcuts for commands, e.g. "/q" -> "/quit"
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub aliases: HashMap<String, String>,
+
+    /// Line editor settings
+    #[serde(default)]
+    pub editor: EditorConfig,
+
+    /// Named sets of files and a note, attached together by /bundle load
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub bundles: HashMap<String, BundleConfig>,
+
+    /// Prompts run on a cron schedule by the `schedule` subcommand
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedules: Vec<ScheduleConfig>,
+
+    /// Price per model name (or name prefix); unlisted models are free
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub pricing: HashMap<String, ModelPrice>,
+
+    /// Cache of chat replies for identical requests
+    #[serde(rename = "responseCache", default)]
+    pub response_cache: CacheConfig,
+
+    /// Encryption of saved conversations
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+
+    /// Model options for chat replies; /set changes them for the session
+    #[serde(default)]
+    pub generation: GenerationConfig,
+
+    /// Loading the model ahead of the first prompt, and how long Ollama keeps it
+    #[serde(rename = "warmUp", default)]
+    pub warm_up: WarmUpConfig,
+
+    /// Clean-up applied to replies before they are shown and stored
+    #[serde(rename = "responseFilters", default)]
+    pub response_filters: ResponseFiltersConfig,
+
+    /// Language the model is asked to reply in, e.g. "German"
+    #[serde(rename = "responseLanguage", skip_serializing_if = "Option::is_none")]
+    pub response_language: Option<String>,
+
+    /// Language of the CLI's own messages, e.g. "ko"; LC_ALL, LC_MESSAGES or LANG when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+
+    /// Recorder and whisper.cpp setup for /voice
+    #[serde(default)]
+    pub voice: VoiceConfig,
+
+    /// Desktop notifications when long replies finish
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+
+    /// Display and export of the model's `think` tool reasoning
+    #[serde(default)]
+    pub thoughts: ThoughtsConfig,
+
+    /// Output for screen readers (--accessible) and the bell after replies
+    #[serde(default)]
+    pub accessibility: AccessibilityConfig,
+
+    /// Refusing tools that change anything (--read-only)
+    #[serde(rename = "readOnly", default)]
+    pub read_only: ReadOnlyConfig,
+
+    /// Map of the repository added as context at session start
+    #[serde(rename = "repoMap", default)]
+    pub repo_map: RepoMapConfig,
+
+    /// Language servers queried by the get_diagnostics tool, by name
+    #[serde(rename = "languageServers", default, skip_serializing_if = "HashMap::is_empty")]
+    pub language_servers: HashMap<String, LanguageServerConfig>,
+
+    /// Access to the GitHub API for the github_* tools
+    #[serde(default)]
+    pub github: GithubConfig,
+
+    /// Embedding index of past conversations for /recall
+    #[serde(default)]
+    pub recall: RecallConfig,
+
+    /// Condensing large tool results before they enter the conversation
+    #[serde(rename = "toolSummary", default)]
+    pub tool_summary: ToolSummaryConfig,
+
+    /// Command run after tool calls that change files, with failures fed back to the model
+    #[serde(default)]
+    pub verify: VerifyConfig,
+
+    /// Which values the env tool masks
+    #[serde(default)]
+    pub env: EnvConfig,
+
+    /// Recent shell commands sent by /why; off unless enabled
+    #[serde(rename = "shellHistory", default)]
+    pub shell_history: ShellHistoryConfig,
+
+    /// Spending limit in USD per calendar month; chat is refused once reached
+    #[serde(rename = "monthlyBudget", skip_serializing_if = "Option::is_none")]
+    pub monthly_budget: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerConfig {
+    /// Address of the worker's Ollama API (e.g. "192.168.1.100:11434")
+    pub address: String,
+
+    /// Backend the worker executes tasks on
+    #[serde(default)]
+    pub backend: Backend,
+
+    /// Maximum number of tasks dispatched to the worker at once
+    #[serde(rename = "maxConcurrent", default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShellHistoryConfig {
+    /// Lets /why read the shell history
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Number of recent commands sent (default 10)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commands: Option<usize>,
+
+    /// History file to read instead of the shell's usual one
+    #[serde(rename = "historyFile", skip_serializing_if = "Option::is_none")]
+    pub history_file: Option<String>,
+
+    /// Terminal log with the commands' output, e.g. written by `script -qf`
+    #[serde(rename = "outputLog", skip_serializing_if = "Option::is_none")]
+    pub output_log: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleConfig {
+    /// Text files attached in this order; relative paths resolve from the working directory
+    #[serde(default)]
+    pub files: Vec<String>,
+
+    /// Added as a system message after the files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Serve chat replies from the cache; batch runs always use it unless --no-cache
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Age after which a cached reply is ignored; unlimited when unset
+    #[serde(rename = "ttlSeconds", default, skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WarmUpConfig {
+    /// Load the model in the background at startup and after /model, as if --warm-up were given
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Sent as Ollama's keep_alive with every request, e.g. "30m", or -1 to keep the model loaded
+    #[serde(rename = "keepAlive", skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// Encrypt sessions written by /save, signal shutdown and import;
+    /// encrypted files are always readable given the passphrase
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Where the passphrase is kept, e.g. "keyring:sessions"; asked for when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passphrase: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolSummaryConfig {
+    /// Also enabled for one run with --summarize-tool-output
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Summarizer model, ideally a small one; the chat model when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Outputs up to this many characters are kept as they are (default 4000)
+    #[serde(rename = "thresholdChars", skip_serializing_if = "Option::is_none")]
+    pub threshold_chars: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolBudgetConfig {
+    /// Estimated tokens the tool list may take in the system prompt (default 2000)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<usize>,
+
+    /// Longer descriptions are cut to this many characters (default 300)
+    #[serde(rename = "descriptionChars", skip_serializing_if = "Option::is_none")]
+    pub description_chars: Option<usize>,
+
+    /// Tools described ahead of the others, after recently used ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowlist: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolFormatterConfig {
+    /// jq-style path to the values to show, e.g. ".items[]"; the whole result when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub select: Option<String>,
+
+    /// Line per value with {path} placeholders, e.g. "#{number} {title}"; compact JSON when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// Values beyond this many are counted instead of shown
+    #[serde(rename = "maxItems", skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolSelectionConfig {
+    #[serde(default)]
+    pub mode: ToolSelection,
+
+    /// Model that picks the tools in "model" mode, ideally a small one; the chat model when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Most tools described for one message (default 8)
+    #[serde(rename = "maxTools", skip_serializing_if = "Option::is_none")]
+    pub max_tools: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolSelection {
+    /// Every tool that fits the budget is described
+    #[default]
+    Off,
+    /// Tools sharing words with the message
+    Keywords,
+    /// Tools a model picks for the message
+    Model,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolCalling {
+    /// The tools prompt lists the tools and the model replies with ```tool blocks
+    #[default]
+    Prompt,
+    /// Tool definitions go in the request's `tools` and calls come back as `tool_calls`
+    Native,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// The local Ollama instance
+    #[default]
+    Ollama,
+    /// Claude through the Anthropic Messages API
+    Anthropic,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 2] = [Provider::Ollama, Provider::Anthropic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::Ollama => "ollama",
+            Provider::Anthropic => "anthropic",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|provider| provider.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnthropicConfig {
+    /// API key, e.g. "keyring:anthropic"; ANTHROPIC_API_KEY when unset
+    #[serde(rename = "apiKey", skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+
+    /// API root, for a proxy or gateway (default: https://api.anthropic.com)
+    #[serde(rename = "apiUrl", skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+
+    /// Model used on this backend until /model picks another
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Reply length limit the API requires, unless "generation.numPredict" is set (default: 4096)
+    #[serde(rename = "maxTokens", skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerationConfig {
+    /// Text that ends a reply as soon as the model produces it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+
+    /// Maximum tokens per reply (Ollama's num_predict); unlimited when unset
+    #[serde(rename = "numPredict", skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i64>,
+
+    /// Temperature 0 and a fixed seed, so the same prompt gives the same reply
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deterministic: bool,
+
+    /// Seed used in deterministic mode (default 42)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+}
+
+impl GenerationConfig {
+    pub const DEFAULT_SEED: i64 = 42;
+
+    /// The seed replies are pinned to, or `None` outside deterministic mode
+    pub fn pinned_seed(&self) -> Option<i64> {
+        self.deterministic.then(|| self.seed.unwrap_or(Self::DEFAULT_SEED))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponseFiltersConfig {
+    /// Drop an opening "Sure!", "Certainly," and the like
+    #[serde(rename = "stripPreamble", default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_preamble: bool,
+
+    /// Drop closing sentences that apologize
+    #[serde(rename = "stripApologies", default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_apologies: bool,
+
+    /// Keep at most one blank line between paragraphs
+    #[serde(rename = "collapseBlankLines", default, skip_serializing_if = "std::ops::Not::not")]
+    pub collapse_blank_lines: bool,
+
+    /// Regex replacements applied in order, after the filters above
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replacements: Vec<ReplacementConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplacementConfig {
+    pub pattern: String,
+
+    /// May refer to groups of the pattern as $1 or ${name}
+    #[serde(default)]
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GithubConfig {
+    /// API token, e.g. "keyring:github"; GITHUB_TOKEN or GH_TOKEN when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+
+    /// API root for GitHub Enterprise, e.g. "https://github.example.com/api/v3"
+    #[serde(rename = "apiUrl", skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EnvConfig {
+    /// More names whose values are masked, as globs like "*_DSN" (case-insensitive)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mask: Vec<String>,
+
+    /// Names shown as they are even when they look secret, e.g. "SSH_AUTH_SOCK"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reveal: Vec<String>,
+
+    /// Mask values that look like credentials whatever their name (default: true)
+    #[serde(rename = "detectValues", skip_serializing_if = "Option::is_none")]
+    pub detect_values: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerifyConfig {
+    /// Shell command, e.g. "cargo check" or "npm run lint"; off when unset.
+    /// Also set for one run with --verify
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+
+    /// Tools whose successful calls are verified (default: edit_file and write_file)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+
+    /// Time limit for the command (default 120)
+    #[serde(rename = "timeoutSeconds", skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VoiceConfig {
+    /// Command recording the microphone to `{output}` until interrupted (default: SoX `rec`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recorder: Option<String>,
+
+    /// whisper.cpp CLI binary (default: `whisper-cli`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub whisper: Option<String>,
+
+    /// Path to a ggml whisper model; required for /voice
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Spoken language, e.g. "de"; whisper's default when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThoughtsConfig {
+    /// Show thoughts in full from the start instead of collapsed
+    #[serde(default)]
+    pub show: bool,
+
+    /// Keep thoughts in /share pages and training exports
+    #[serde(default)]
+    pub export: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Start the REPL in agent mode, as if --agent were given
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Model replies to one message before the loop is stopped (default 10)
+    #[serde(rename = "maxIterations", skip_serializing_if = "Option::is_none")]
+    pub max_iterations: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccessibilityConfig {
+    /// Accessible output for every session, as if --accessible were given
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Ring the terminal bell when a reply is complete
+    #[serde(default)]
+    pub bell: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReadOnlyConfig {
+    /// Read-only for every session, as if --read-only were given
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Shell commands allowed besides the built-in list, e.g. "kubectl top"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+
+    /// MCP tools known not to change anything, though their server does not say so
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Tasks finishing sooner do not notify (default 30)
+    #[serde(rename = "thresholdSeconds", skip_serializing_if = "Option::is_none")]
+    pub threshold_seconds: Option<u64>,
+
+    /// Overrides for one operating system
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub macos: Option<PlatformNotification>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linux: Option<PlatformNotification>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub windows: Option<PlatformNotification>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlatformNotification {
+    /// Turns notifications on or off on this OS only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+
+    /// Sound name, e.g. "Glass" on macOS or "message-new-instant" on Linux
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sound: Option<String>,
+
+    /// How long the notification stays up; the desktop's default when unset
+    #[serde(rename = "timeoutMs", skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepoMapConfig {
+    /// Also enabled for one run with --repo-map
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Size limit of the map; detail is dropped to fit (default 8000)
+    #[serde(rename = "maxChars", skip_serializing_if = "Option::is_none")]
+    pub max_chars: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageServerConfig {
+    /// Server executable, e.g. "rust-analyzer"; it must speak LSP on stdio
+    pub command: String,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+
+    /// File extensions the server handles, e.g. ["rs"]
+    pub extensions: Vec<String>,
+
+    /// LSP language identifier when it differs from the usual one for the extension
+    #[serde(rename = "languageId", skip_serializing_if = "Option::is_none")]
+    pub language_id: Option<String>,
+
+    /// Time allowed for starting up and for publishing diagnostics (default 30)
+    #[serde(rename = "timeoutSeconds", skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+
+    /// Passed to the server as initializationOptions
+    #[serde(rename = "initializationOptions", skip_serializing_if = "Option::is_none")]
+    pub initialization_options: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecallConfig {
+    /// Ollama embedding model (default: nomic-embed-text)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Directories of saved sessions to index besides ~/.ai-chat-cli/sessions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dirs: Vec<String>,
+}
+
+/// USD per million tokens
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ModelPrice {
+    #[serde(default)]
+    pub input: f64,
+    #[serde(default)]
+    pub output: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EditorConfig {
+    /// Editing mode of the prompt
+    #[serde(default)]
+    pub mode: EditorMode,
+
+    /// Key to action, e.g. "Ctrl-P": "previous-history" or "F2": "insert:/history"
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub keybindings: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StaleAttachments {
+    /// Tell the user before sending; /refresh updates the attachments
+    #[default]
+    Warn,
+    /// Replace the attachment with the current content before sending
+    Refresh,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoResume {
+    /// Offer to resume, defaulting to yes
+    #[default]
+    Ask,
+    /// Resume without asking
+    Always,
+    /// Neither resume nor keep conversations per directory
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditorMode {
+    #[default]
+    Emacs,
+    Vi,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    /// Name used in logs and output headings
+    pub name: String,
+
+    /// Cron expression (e.g. "0 9 * * 1-5" for weekdays at 9:00)
+    pub cron: String,
+
+    /// Prompt sent to the model
+    pub prompt: String,
+
+    /// Model to use instead of the default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Shell commands whose output is appended to the prompt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context: Vec<String>,
+
+    /// File the response is appended to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+
+    /// URL the response is POSTed to as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+}
+
+fn default_max_concurrent() -> usize {
+    1
+}
+
+impl AppConfig {
+    pub fn load() -> Result<Self> {
+        let config_path = Self::config_path()?;
+
+        if !config_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&config_path)
+            .context("Failed to read configuration file")?;
+
+        let config: AppConfig = serde_json::from_str(&content)
+            .context("Failed to parse configuration")?;
+
+        Ok(config)
+    }
+
+    /// System prompt asking for replies in the configured "responseLanguage"
+    pub fn language_prompt(&self) -> Option<String> {
+        self.response_language.as_ref().map(|language| format!(
+            "Always reply in {}, whatever language the user writes in.", language))
+    }
+
+    pub fn config_path() -> Result<PathBuf> {
+        let home = dirs::home_dir()
+            .context("Could not find home directory")?;
+
+        Ok(home.join(".ai-chat-cli").join("config.json"))
+    }
+}
@@ -0,0 +1,313 @@
+//! A background process keeping the model, MCP servers and conversation
+//! warm, for quick questions from any terminal or tmux pane.
+//!
+//! `daemon` serves the `--stdio-api` protocol on ~/.ai-chat-cli/daemon.sock,
+//! one client at a time. `attach` is a small REPL speaking that protocol.
+//! Detaching leaves the conversation in the daemon for the next attach.
+
+use anyhow::{Context, Result};
+use colored::*;
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+use crate::accessibility;
+use crate::args::DaemonArgs;
+use crate::config::AppConfig;
+use crate::executor::AIExecutor;
+use crate::lifecycle;
+use crate::output::status;
+use crate::stdio_api::{self, Output, Server};
+
+/// How long `--background` waits for the new daemon to listen
+const STARTUP_TIMEOUT_SECS: u64 = 60;
+
+pub fn socket_path() -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .context("Could not find home directory")?;
+    Ok(home.join(".ai-chat-cli").join("daemon.sock"))
+}
+
+#[cfg(unix)]
+pub async fn run(executor: impl AsyncFnOnce() -> Result<AIExecutor>, config: &AppConfig, args: DaemonArgs) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixStream;
+    use tokio::net::UnixListener;
+
+    let path = socket_path()?;
+    if args.stop {
+        let mut client = Client::connect(&path)?;
+        client.call("shutdown", Value::Null, &mut None).await?;
+        println!("{} Daemon stopped", "✓".bright_green());
+        return Ok(());
+    }
+    if UnixStream::connect(&path).is_ok() {
+        anyhow::bail!("A daemon is already running on {}", path.display());
+    }
+    if args.background {
+        return start_background(&path).await;
+    }
+
+    let executor = executor().await?;
+    // Keeping the model warm is the point of the daemon
+    executor.preload();
+    let mut server = Server::new(executor, None, config).await?;
+    // Left behind by a daemon that did not exit cleanly
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)
+        .context(format!("Failed to listen on {}", path.display()))?;
+    // Whoever can connect can run tools, so only the owner may
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+        .context(format!("Failed to restrict {}", path.display()))?;
+    status!("{} Daemon listening on {}; attach with {}",
+        "✓".bright_green(), path.display(), "ai-chat-cli attach".bright_cyan());
+
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => accepted?.0,
+            _ = tokio::signal::ctrl_c() => break,
+            _ = lifecycle::terminated() => break,
+        };
+        // The protocol is served from a reader thread and blocking writes
+        let stream = stream.into_std()?;
+        stream.set_nonblocking(false)?;
+
+        status!("{} Client attached", "→".bright_blue());
+        let mut requests = stdio_api::read_requests(BufReader::new(stream.try_clone()?));
+        let output = Output::new(stream);
+        let stop = tokio::select! {
+            stop = server.serve(&mut requests, &output) => stop,
+            _ = tokio::signal::ctrl_c() => true,
+            _ = lifecycle::terminated() => true,
+        };
+        status!("{} Client detached", "←".bright_blue());
+        if stop {
+            break;
+        }
+    }
+
+    let _ = std::fs::remove_file(&path);
+    server.shutdown().await;
+    status!("{} Daemon stopped", "✓".bright_green());
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn run(_executor: impl AsyncFnOnce() -> Result<AIExecutor>, _config: &AppConfig, _args: DaemonArgs) -> Result<()> {
+    anyhow::bail!("The daemon needs Unix domain sockets, which this platform does not offer")
+}
+
+/// Starts `ai-chat-cli daemon` in its own process group, logging to
+/// ~/.ai-chat-cli/daemon.log, and waits until it listens
+#[cfg(unix)]
+async fn start_background(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    let log_path = path.with_file_name("daemon.log");
+    let log = std::fs::File::create(&log_path)
+        .context(format!("Failed to create {}", log_path.display()))?;
+    let mut child = Command::new(std::env::current_exe()?)
+        .arg("--plain")
+        .arg("daemon")
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        // Not stopped by Ctrl+C in the terminal it was started from
+        .process_group(0)
+        .spawn()
+        .context("Failed to start the daemon")?;
+
+    for _ in 0..STARTUP_TIMEOUT_SECS * 10 {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            println!("{} Daemon started (pid {}); attach with {}",
+                "✓".bright_green(), child.id(), "ai-chat-cli attach".bright_cyan());
+            return Ok(());
+        }
+        if let Some(status) = child.try_wait()? {
+            anyhow::bail!("The daemon exited with {}; see {}", status, log_path.display());
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    anyhow::bail!("The daemon did not start listening within {}s; see {}", STARTUP_TIMEOUT_SECS, log_path.display())
+}
+
+/// A line-based REPL on the daemon's conversation
+#[cfg(unix)]
+pub async fn attach() -> Result<()> {
+    let mut client = Client::connect(&socket_path()?)?;
+    let mut input = Some(read_lines());
+
+    let info = client.call("initialize", Value::Null, &mut input).await?;
+    println!("{} Attached to the daemon: {} with {} tool(s), {} message(s) so far",
+        "✓".bright_green(), info["model"].as_str().unwrap_or_default().bright_cyan(),
+        info["tools"].as_array().map_or(0, Vec::len), info["messages"]);
+    println!("{}", "/detach leaves the conversation in the daemon, /stop ends it; /clear, /sessions and /load <file> as usual".bright_black());
+
+    loop {
+        print!("\n{} ", "You:".bright_green().bold());
+        std::io::stdout().flush()?;
+        let Some(line) = next_line(&mut input).await else {
+            break;
+        };
+        let result = match line.trim() {
+            "" => continue,
+            "/detach" | "/quit" | "/exit" => break,
+            "/stop" => {
+                client.call("shutdown", Value::Null, &mut input).await?;
+                println!("{} Daemon stopped", "✓".bright_green());
+                return Ok(());
+            }
+            "/clear" => client.call("clear", Value::Null, &mut input).await
+                .map(|_| println!("{} Conversation cleared", "✓".bright_green())),
+            "/sessions" => client.call("listSessions", Value::Null, &mut input).await.map(|sessions| {
+                for session in sessions.as_array().into_iter().flatten() {
+                    println!("  {} {} {}", session["modified"].as_str().unwrap_or_default().get(..16).unwrap_or_default().bright_black(),
+                        session["path"].as_str().unwrap_or_default().bright_cyan(),
+                        format!("({} messages)", session["messages"]).bright_black());
+                }
+            }),
+            line if line.starts_with("/load ") => {
+                let path = line.strip_prefix("/load ").unwrap().trim();
+                client.call("loadSession", json!({ "path": path }), &mut input).await
+                    .map(|loaded| println!("{} Loaded {} message(s)", "✓".bright_green(), loaded["messages"]))
+            }
+            text => {
+                print!("{} ", reply_label());
+                client.call("send", json!({ "text": text }), &mut input).await.map(|_| println!())
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("{} {:#}", "Error:".bright_red(), e);
+        }
+    }
+    println!("{} Detached; the conversation stays in the daemon", "✓".bright_green());
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn attach() -> Result<()> {
+    anyhow::bail!("The daemon needs Unix domain sockets, which this platform does not offer")
+}
+
+struct Client {
+    writer: Box<dyn Write + Send>,
+    messages: mpsc::UnboundedReceiver<Value>,
+    next_id: u64,
+}
+
+impl Client {
+    #[cfg(unix)]
+    fn connect(path: &std::path::Path) -> Result<Self> {
+        let stream = std::os::unix::net::UnixStream::connect(path)
+            .context("No daemon is running; start one with `ai-chat-cli daemon --background`")?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, messages) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            for line in reader.lines().map_while(Result::ok) {
+                if let Ok(message) = serde_json::from_str(&line)
+                    && sender.send(message).is_err()
+                {
+                    break;
+                }
+            }
+        });
+        Ok(Self { writer: Box::new(stream), messages, next_id: 1 })
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        writeln!(self.writer, "{}", json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .context("The daemon went away")?;
+        Ok(id)
+    }
+
+    /// Sends a request and shows its events until the response arrives;
+    /// approvals are asked for on `input`
+    async fn call(&mut self, method: &str, params: Value, input: &mut Option<mpsc::UnboundedReceiver<String>>) -> Result<Value> {
+        let id = self.request(method, params)?;
+        loop {
+            let message = self.messages.recv().await
+                .context("The daemon closed the connection")?;
+            match message["method"].as_str() {
+                Some("event") => show_event(&message["params"]["event"]),
+                Some("approvalRequest") => {
+                    let request = &message["params"];
+                    println!("\n{} {} wants to run {}", "⚠".bright_yellow(),
+                        request["server"].as_str().unwrap_or_default(), request["tool"].as_str().unwrap_or_default().bright_cyan());
+                    println!("  {}", request["arguments"].to_string().bright_black());
+                    print!("{} ", crate::policy::Approval::PROMPT.bright_yellow());
+                    std::io::stdout().flush()?;
+                    let answer = next_line(input).await.unwrap_or_default();
+                    let decision = match crate::policy::Approval::parse(&answer) {
+                        crate::policy::Approval::Once => "yes",
+                        crate::policy::Approval::Always => "always",
+                        crate::policy::Approval::Never => "never",
+                        crate::policy::Approval::Deny => "no",
+                    };
+                    self.request("approve", json!({ "decision": decision }))?;
+                }
+                Some(_) => {}
+                None if message["id"] == json!(id) => {
+                    if let Some(error) = message.get("error") {
+                        anyhow::bail!("{}", error["message"].as_str().unwrap_or("Request failed"));
+                    }
+                    return Ok(message["result"].clone());
+                }
+                // Responses to approvals
+                None => {}
+            }
+        }
+    }
+}
+
+fn show_event(event: &Value) {
+    match event["type"].as_str() {
+        Some("token") => {
+            print!("{}", event["text"].as_str().unwrap_or_default());
+            let _ = std::io::stdout().flush();
+        }
+        Some("tool_call") if accessibility::enabled() => println!("\nTool request: {} {}",
+            event["name"].as_str().unwrap_or_default(), event["arguments"]),
+        Some("tool_result") if accessibility::enabled() => {
+            let outcome = if event["is_error"] == json!(true) { "failed" } else { "succeeded" };
+            let output = event["output"].as_str().unwrap_or_default().trim();
+            println!("Tool result: {}, {} line(s)\n{}", outcome, output.lines().count(), reply_label());
+        }
+        Some("tool_call") => println!("\n{} Calling tool '{}'...", "⚙".bright_blue(), event["name"].as_str().unwrap_or_default()),
+        Some("tool_result") => {
+            let output = event["output"].as_str().unwrap_or_default().trim();
+            let first = output.lines().next().unwrap_or_default();
+            let more = output.lines().count().saturating_sub(1);
+            let more = if more > 0 { format!(" (+{} lines)", more).bright_black().to_string() } else { String::new() };
+            let mark = if event["is_error"] == json!(true) { "✗".bright_red() } else { "✓".bright_green() };
+            print!("{} {}{}\n{} ", mark, first, more, reply_label());
+        }
+        _ => {}
+    }
+}
+
+/// Named in full in accessible mode
+fn reply_label() -> ColoredString {
+    if accessibility::enabled() { "Assistant:".normal() } else { "AI:".bright_blue().bold() }
+}
+
+/// Lines typed at the terminal, read in the background so that approvals
+/// can be answered while a reply streams
+fn read_lines() -> mpsc::UnboundedReceiver<String> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+async fn next_line(input: &mut Option<mpsc::UnboundedReceiver<String>>) -> Option<String> {
+    input.as_mut()?.recv().await
+}
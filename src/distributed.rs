@@ -1,44 +1,256 @@
 use anyhow::Result;
-use repartir::{Pool, task::{Task, Backend}};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::timeout;
 
+use crate::config::WorkerConfig;
+use crate::ollama::{ChatResponse, Message, OllamaClient};
+
+const LOCAL_ADDRESS: &str = "localhost:11434";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Cpu,
+    Gpu,
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Backend::Cpu => write!(f, "cpu"),
+            Backend::Gpu => write!(f, "gpu"),
+        }
+    }
+}
+
+struct Worker {
+    address: String,
+    backend: Backend,
+    max_concurrent: usize,
+    client: OllamaClient,
+    slots: Arc<Semaphore>,
+    // Tasks assigned to this worker that have not finished yet (running or queued)
+    pending: AtomicUsize,
+    // Cleared when the worker cannot be reached, restored by the next successful probe
+    online: AtomicBool,
+}
+
+/// Keeps a worker's pending count accurate for as long as a task is assigned to it
+struct Assignment {
+    worker: Arc<Worker>,
+}
+
+impl Drop for Assignment {
+    fn drop(&mut self) {
+        self.worker.pending.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkerStatus {
+    pub address: String,
+    pub backend: Backend,
+    pub max_concurrent: usize,
+    pub active: usize,
+    pub online: bool,
+    pub models: usize,
+}
+
+#[derive(Clone)]
 pub struct DistributedAI {
-    pool: Pool,
+    workers: Vec<Arc<Worker>>,
 }
 
 impl DistributedAI {
-    pub async fn new() -> Result<Self> {
-        // Configure for distributed execution
-        let pool = Pool::builder()
-            .cpu_workers(4)
-            // In future versions, add remote workers:
-            // .remote_worker("192.168.1.100:8080")
-            // .remote_worker("192.168.1.101:8080")
-            .build()?;
-
-        Ok(Self { pool })
-    }
-
-    pub async fn parallel_inference(&self, prompts: Vec<String>) -> Result<Vec<String>> {
-        let mut results = Vec::new();
-
-        for prompt in prompts {
-            let task = Task::builder()
-                .binary("/bin/sh")
-                .arg("-c")
-                .arg(format!(
-                    "curl -s http://localhost:11434/api/generate -d '{{\"model\":\"llama3.2:1b\",\"prompt\":\"{}\",\"stream\":false}}'",
-                    prompt.replace('\'', "'\\''")
-                ))
-                .backend(Backend::Cpu)
-                .build()?;
-
-            let result = self.pool.submit(task).await?;
-            
-            if result.is_success() {
-                results.push(result.stdout_str()?.to_string());
+    pub fn new(cpu_workers: usize, remote_workers: &[WorkerConfig]) -> Result<Self> {
+        // The local Ollama instance is always part of the pool
+        let mut workers = vec![Self::build_worker(LOCAL_ADDRESS, Backend::Cpu, cpu_workers)?];
+
+        for config in remote_workers {
+            workers.push(Self::build_worker(&config.address, config.backend, config.max_concurrent)?);
+        }
+
+        Ok(Self { workers })
+    }
+
+    fn build_worker(address: &str, backend: Backend, max_concurrent: usize) -> Result<Arc<Worker>> {
+        if address.trim().is_empty() {
+            anyhow::bail!("Worker address must not be empty");
+        }
+        if max_concurrent == 0 {
+            anyhow::bail!("Worker '{}' must allow at least one concurrent task", address);
+        }
+
+        let base_url = if address.starts_with("http://") || address.starts_with("https://") {
+            address.trim_end_matches('/').to_string()
+        } else {
+            format!("http://{}", address.trim_end_matches('/'))
+        };
+
+        Ok(Arc::new(Worker {
+            address: address.to_string(),
+            backend,
+            max_concurrent,
+            client: OllamaClient::with_base_url(base_url),
+            slots: Arc::new(Semaphore::new(max_concurrent)),
+            pending: AtomicUsize::new(0),
+            online: AtomicBool::new(true),
+        }))
+    }
+
+    /// Picks the backend tasks will actually run on, falling back to any
+    /// worker when none of the requested kind is reachable
+    fn resolve_backend(&self, backend: Option<Backend>) -> Option<Backend> {
+        let backend = backend?;
+        let available = self.workers.iter()
+            .any(|w| w.backend == backend && w.online.load(Ordering::SeqCst));
+
+        if available {
+            Some(backend)
+        } else {
+            eprintln!("{} No {} worker available, falling back to any worker",
+                "Warning:".bright_yellow(), backend);
+            None
+        }
+    }
+
+    /// Assigns the next task to the worker with the lowest load relative to its capacity
+    fn assign(&self, backend: Option<Backend>) -> Assignment {
+        let eligible = |w: &&Arc<Worker>| backend.is_none_or(|b| w.backend == b);
+        let online: Vec<&Arc<Worker>> = self.workers.iter()
+            .filter(eligible)
+            .filter(|w| w.online.load(Ordering::SeqCst))
+            .collect();
+
+        // When every candidate looks offline, try them anyway and let the error surface
+        let candidates = if online.is_empty() {
+            self.workers.iter().filter(eligible).collect()
+        } else {
+            online
+        };
+
+        let worker = candidates.into_iter()
+            .min_by_key(|w| w.pending.load(Ordering::SeqCst) * 1000 / w.max_concurrent)
+            .expect("pool always contains the local worker")
+            .clone();
+
+        worker.pending.fetch_add(1, Ordering::SeqCst);
+        Assignment { worker }
+    }
+
+    async fn run(
+        assignment: Assignment,
+        model: String,
+        messages: Vec<Message>,
+        options: Option<serde_json::Value>,
+    ) -> Result<ChatResponse> {
+        let worker = &assignment.worker;
+        let _slot = worker.slots.clone().acquire_owned().await?;
+
+        match worker.client.complete(&model, messages, options).await {
+            Ok(response) => {
+                worker.online.store(true, Ordering::SeqCst);
+                Ok(response)
+            }
+            Err(e) => {
+                if is_connect_error(&e) {
+                    worker.online.store(false, Ordering::SeqCst);
+                }
+                Err(e.context(format!("Worker '{}' failed", worker.address)))
             }
         }
+    }
 
-        Ok(results)
+    /// Runs a conversation on the pool, preferring workers with the given backend
+    pub async fn complete(
+        &self,
+        model: &str,
+        messages: Vec<Message>,
+        options: Option<serde_json::Value>,
+        backend: Option<Backend>,
+    ) -> Result<ChatResponse> {
+        let backend = self.resolve_backend(backend);
+        Self::run(self.assign(backend), model.to_string(), messages, options).await
     }
+
+    /// Runs independent single-turn prompts across the pool, returning results in input order
+    pub async fn parallel_inference(&self, model: &str, prompts: Vec<String>, backend: Option<Backend>) -> Vec<Result<ChatResponse>> {
+        let backend = self.resolve_backend(backend);
+        let mut tasks = JoinSet::new();
+
+        for (index, prompt) in prompts.into_iter().enumerate() {
+            let assignment = self.assign(backend);
+            let model = model.to_string();
+            let messages = vec![Message {
+                role: "user".to_string(),
+                content: prompt,
+                images: Vec::new(),
+                tool_calls: Vec::new(),
+            }];
+
+            tasks.spawn(async move {
+                (index, Self::run(assignment, model, messages, None).await)
+            });
+        }
+
+        let mut results: Vec<Option<Result<ChatResponse>>> = (0..tasks.len()).map(|_| None).collect();
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok((index, result)) => results[index] = Some(result),
+                Err(e) => eprintln!("Distributed task panicked: {}", e),
+            }
+        }
+
+        results.into_iter()
+            .map(|r| r.unwrap_or_else(|| Err(anyhow::anyhow!("Task did not complete"))))
+            .collect()
+    }
+
+    /// Probes every worker and reports its current load and reachability
+    pub async fn status(&self) -> Vec<WorkerStatus> {
+        let mut probes = JoinSet::new();
+
+        for (index, worker) in self.workers.iter().enumerate() {
+            let worker = worker.clone();
+            probes.spawn(async move {
+                let models = timeout(Duration::from_secs(3), worker.client.list_models()).await;
+                let models = match models {
+                    Ok(Ok(models)) => Some(models.len()),
+                    _ => None,
+                };
+                worker.online.store(models.is_some(), Ordering::SeqCst);
+
+                (index, WorkerStatus {
+                    address: worker.address.clone(),
+                    backend: worker.backend,
+                    max_concurrent: worker.max_concurrent,
+                    active: worker.max_concurrent - worker.slots.available_permits(),
+                    online: models.is_some(),
+                    models: models.unwrap_or(0),
+                })
+            });
+        }
+
+        let mut statuses = Vec::new();
+        while let Some(Ok(status)) = probes.join_next().await {
+            statuses.push(status);
+        }
+        statuses.sort_by_key(|(index, _)| *index);
+
+        statuses.into_iter().map(|(_, status)| status).collect()
+    }
+}
+
+fn is_connect_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_connect() || e.is_timeout())
+    })
 }
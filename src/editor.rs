@@ -0,0 +1,113 @@
+use anyhow::{Context, Result};
+use colored::*;
+use rustyline::{At, Cmd, Config, DefaultEditor, EditMode, KeyCode, KeyEvent, Modifiers, Movement, Word};
+
+use crate::config::{EditorConfig, EditorMode};
+
+/// Builds the line editor with the configured editing mode and key bindings
+pub fn build(config: &EditorConfig) -> Result<DefaultEditor> {
+    let edit_mode = match config.mode {
+        EditorMode::Emacs => EditMode::Emacs,
+        EditorMode::Vi => EditMode::Vi,
+    };
+
+    let mut rl = DefaultEditor::with_config(Config::builder().edit_mode(edit_mode).build())?;
+
+    for (key, action) in &config.keybindings {
+        // A bad binding shouldn't keep the chat from starting
+        match parse_key(key).and_then(|key| Ok((key, parse_action(action)?))) {
+            Ok((key, cmd)) => {
+                rl.bind_sequence(key, cmd);
+            }
+            Err(e) => eprintln!("{} Ignoring key binding '{}': {:#}", "Warning:".bright_yellow(), key, e),
+        }
+    }
+
+    Ok(rl)
+}
+
+/// Parses keys such as "Ctrl-P", "Alt-f", "F2" or "Ctrl-Left"
+fn parse_key(spec: &str) -> Result<KeyEvent> {
+    let mut modifiers = Modifiers::NONE;
+    let mut parts: Vec<&str> = spec.split('-').collect();
+
+    // "Ctrl--" binds the minus key itself
+    if spec.ends_with("--") {
+        parts.truncate(parts.len() - 2);
+        parts.push("-");
+    }
+
+    let name = parts.pop().filter(|name| !name.is_empty()).context("Missing key name")?;
+
+    for modifier in parts {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" | "c" => Modifiers::CTRL,
+            "alt" | "meta" | "m" => Modifiers::ALT,
+            "shift" | "s" => Modifiers::SHIFT,
+            other => anyhow::bail!("Unknown modifier '{}'", other),
+        };
+    }
+
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyEvent::normalize(KeyEvent::new(c, modifiers)));
+    }
+
+    let code = match name.to_lowercase().as_str() {
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "tab" => KeyCode::Tab,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "backspace" => KeyCode::Backspace,
+        f if f.starts_with('f') => KeyCode::F(f[1..].parse().context("Invalid function key")?),
+        other => anyhow::bail!("Unknown key '{}'", other),
+    };
+
+    Ok(KeyEvent::normalize(KeyEvent(code, modifiers)))
+}
+
+/// Maps readline-style command names to editor commands; "insert:<text>"
+/// types the given text, e.g. a slash command
+fn parse_action(action: &str) -> Result<Cmd> {
+    if let Some(text) = action.strip_prefix("insert:") {
+        return Ok(Cmd::Insert(1, text.to_string()));
+    }
+
+    Ok(match action {
+        "accept-line" => Cmd::AcceptLine,
+        "previous-history" => Cmd::PreviousHistory,
+        "next-history" => Cmd::NextHistory,
+        "beginning-of-history" => Cmd::BeginningOfHistory,
+        "end-of-history" => Cmd::EndOfHistory,
+        "reverse-search-history" => Cmd::ReverseSearchHistory,
+        "forward-search-history" => Cmd::ForwardSearchHistory,
+        "history-search-backward" => Cmd::HistorySearchBackward,
+        "history-search-forward" => Cmd::HistorySearchForward,
+        "beginning-of-line" => Cmd::Move(Movement::BeginningOfLine),
+        "end-of-line" => Cmd::Move(Movement::EndOfLine),
+        "backward-char" => Cmd::Move(Movement::BackwardChar(1)),
+        "forward-char" => Cmd::Move(Movement::ForwardChar(1)),
+        "backward-word" => Cmd::Move(Movement::BackwardWord(1, Word::Emacs)),
+        "forward-word" => Cmd::Move(Movement::ForwardWord(1, At::AfterEnd, Word::Emacs)),
+        "kill-line" => Cmd::Kill(Movement::EndOfLine),
+        "backward-kill-line" => Cmd::Kill(Movement::BeginningOfLine),
+        "kill-whole-line" => Cmd::Kill(Movement::WholeLine),
+        "backward-kill-word" => Cmd::Kill(Movement::BackwardWord(1, Word::Emacs)),
+        "kill-word" => Cmd::Kill(Movement::ForwardWord(1, At::AfterEnd, Word::Emacs)),
+        "clear-screen" => Cmd::ClearScreen,
+        "complete" => Cmd::Complete,
+        "undo" => Cmd::Undo(1),
+        "transpose-chars" => Cmd::TransposeChars,
+        "noop" => Cmd::Noop,
+        other => anyhow::bail!("Unknown action '{}'", other),
+    })
+}
@@ -0,0 +1,167 @@
+//! Optional encryption of saved conversations, and of the other files that
+//! keep their content: the recall index, cached responses, the offline queue
+//! and saved tool outputs.
+//!
+//! Files are sealed with XChaCha20-Poly1305 under a key derived from a
+//! passphrase with Argon2id. The passphrase comes from
+//! `AI_CHAT_CLI_PASSPHRASE`, the OS keychain (`"passphrase": "keyring:<name>"`)
+//! or is asked for on the terminal, once per run.
+//! Encrypted files are recognized by their header and decrypted on read
+//! whether or not encryption is enabled, so turning it off never locks
+//! anyone out of existing sessions.
+
+use anyhow::{Context, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use zeroize::Zeroizing;
+
+use crate::config::EncryptionConfig;
+use crate::secrets;
+
+const MAGIC: &[u8] = b"AICHAT-ENC1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const PASSPHRASE_ENV: &str = "AI_CHAT_CLI_PASSPHRASE";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Configured passphrase reference, e.g. "keyring:sessions"
+static SOURCE: Mutex<Option<String>> = Mutex::new(None);
+/// Entered or read from the environment on first use
+static PASSPHRASE: Mutex<Option<Zeroizing<String>>> = Mutex::new(None);
+/// Derived keys by salt; Argon2 is deliberately slow, so each salt is derived once
+static KEYS: Mutex<Option<HashMap<[u8; SALT_LEN], Key>>> = Mutex::new(None);
+/// Salt used for every file written by this process
+static WRITE_SALT: OnceLock<[u8; SALT_LEN]> = OnceLock::new();
+
+/// Applies the "encryption" config for the whole process
+pub fn init(config: &EncryptionConfig) {
+    ENABLED.store(config.enabled, Ordering::Relaxed);
+    *SOURCE.lock().unwrap() = config.passphrase.clone();
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Writes `contents`, encrypted when enabled in the config
+pub fn write(path: &Path, contents: &str) -> Result<()> {
+    let data = if is_enabled() {
+        encrypt(contents.as_bytes())?
+    } else {
+        contents.as_bytes().to_vec()
+    };
+
+    Ok(fs::write(path, data)?)
+}
+
+/// Reads a file written by `write`, decrypting it if needed
+pub fn read_to_string(path: &Path) -> Result<String> {
+    let data = fs::read(path)?;
+
+    let plain = if is_encrypted(&data) {
+        decrypt(&data)?
+    } else {
+        data
+    };
+
+    String::from_utf8(plain).context("File is not valid UTF-8")
+}
+
+fn encrypt(plain: &[u8]) -> Result<Vec<u8>> {
+    let salt = *WRITE_SALT.get_or_init(|| {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        salt
+    });
+    let cipher = XChaCha20Poly1305::new(&key_for(&salt, true)?);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let sealed = cipher.encrypt(&nonce, plain)
+        .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+
+    let mut data = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + sealed.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&sealed);
+    Ok(data)
+}
+
+fn decrypt(data: &[u8]) -> Result<Vec<u8>> {
+    let body = &data[MAGIC.len()..];
+    if body.len() < SALT_LEN + NONCE_LEN {
+        anyhow::bail!("Encrypted file is truncated");
+    }
+
+    let (salt, rest) = body.split_at(SALT_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(&key_for(salt.try_into()?, false)?);
+
+    cipher.decrypt(XNonce::from_slice(nonce), sealed).map_err(|_| {
+        // Let a mistyped passphrase be entered again on the next attempt
+        *PASSPHRASE.lock().unwrap() = None;
+        if let Some(keys) = KEYS.lock().unwrap().as_mut() {
+            keys.clear();
+        }
+        anyhow::anyhow!("Wrong passphrase or corrupted file")
+    })
+}
+
+/// `confirm` asks twice when prompting, so files are not sealed with a typo
+fn key_for(salt: &[u8; SALT_LEN], confirm: bool) -> Result<Key> {
+    let mut keys = KEYS.lock().unwrap();
+    let keys = keys.get_or_insert_with(HashMap::new);
+    if let Some(key) = keys.get(salt) {
+        return Ok(*key);
+    }
+
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase(confirm)?.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+    keys.insert(*salt, key);
+    Ok(key)
+}
+
+fn passphrase(confirm: bool) -> Result<Zeroizing<String>> {
+    let mut stored = PASSPHRASE.lock().unwrap();
+    if let Some(passphrase) = stored.as_ref() {
+        return Ok(passphrase.clone());
+    }
+
+    let source = SOURCE.lock().unwrap().clone();
+    let passphrase = match (std::env::var(PASSPHRASE_ENV), source) {
+        (Ok(passphrase), _) => Zeroizing::new(passphrase),
+        (Err(_), Some(source)) => Zeroizing::new(secrets::resolve(&source)?),
+        (Err(_), None) => prompt(confirm)?,
+    };
+    if passphrase.is_empty() {
+        anyhow::bail!("The session passphrase must not be empty");
+    }
+
+    *stored = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+/// Reads from the terminal, so this also works when stdin is piped
+fn prompt(confirm: bool) -> Result<Zeroizing<String>> {
+    let read = |prompt: &str| rpassword::prompt_password(prompt)
+        .map(Zeroizing::new)
+        .context(format!("No terminal to ask for the passphrase; set {}", PASSPHRASE_ENV));
+
+    let passphrase = read("Session passphrase: ")?;
+    if confirm && *read("Repeat passphrase: ")? != *passphrase {
+        anyhow::bail!("Passphrases do not match");
+    }
+    Ok(passphrase)
+}
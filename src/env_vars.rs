@@ -0,0 +1,238 @@
+//! The env tool: environment variables as the commands the tools start
+//! would see them, with secret-looking values masked, so that looking at
+//! the environment does not put tokens into the conversation.
+//!
+//! A value is masked when the variable's name matches a secret-looking
+//! pattern (TOKEN, SECRET, PASSWORD, API_KEY, ...) or one of "env.mask",
+//! unless "env.reveal" lists it, and also when the value itself looks like a
+//! credential: a known token prefix, a JWT, a private key, or the password
+//! of a URL. Variables set or unset through the tool last for the session
+//! and apply to bash, cargo and verification commands. Such calls need the
+//! user's approval, and variables that make programs load or run other code
+//! (`PATH`, `LD_PRELOAD`, `GIT_*`, `RUSTC_WRAPPER`, ...) cannot be changed.
+
+use anyhow::Result;
+use regex::Regex;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::sync::{LazyLock, Mutex};
+use tokio::process::Command;
+
+use crate::config::EnvConfig;
+
+/// Names whose values are secrets, as case-insensitive globs
+const SECRET_NAMES: &[&str] = &[
+    "*TOKEN*", "*SECRET*", "*PASSWORD*", "*PASSWD*", "*PASSPHRASE*", "*CREDENTIAL*",
+    "*API_KEY*", "*APIKEY*", "*ACCESS_KEY*", "*PRIVATE_KEY*", "*_KEY", "*_AUTH", "*COOKIE*",
+];
+
+/// Starts of values issued as credentials, whatever the variable is called
+const SECRET_PREFIXES: &[&str] = &[
+    "ghp_", "gho_", "ghu_", "ghs_", "ghr_", "github_pat_", "glpat-", "sk-", "xoxb-", "xoxp-",
+    "xoxa-", "xoxs-", "AKIA", "ASIA", "AIza", "hf_", "npm_",
+];
+
+/// Names the tool may not set or unset: they decide which programs and
+/// libraries commands load, or make git, cargo and shells run a command
+const PROTECTED_NAMES: &[&str] = &[
+    "PATH", "LD_*", "DYLD_*", "BASH_ENV", "ENV", "SHELLOPTS", "BASHOPTS", "PROMPT_COMMAND", "IFS",
+    "PAGER", "*_PAGER", "EDITOR", "VISUAL", "*_EDITOR", "BROWSER", "*ASKPASS*", "GIT_*", "SSH_*",
+    "CARGO_*", "RUSTC*", "RUSTDOC*", "RUSTFLAGS", "RUSTUP_*", "CC", "CXX", "LD", "AR", "CFLAGS",
+    "CXXFLAGS", "LDFLAGS", "PKG_CONFIG*", "PYTHON*", "NODE_OPTIONS", "NODE_PATH", "PERL5*",
+    "RUBYOPT", "RUBYLIB", "HOME", "XDG_CONFIG_HOME", "TMPDIR",
+];
+
+/// `scheme://user:password@`, the password to be masked
+static URL_PASSWORD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b([a-z][a-z0-9+.-]*://[^:/@\s]+:)([^@\s]+)@").unwrap()
+});
+
+static SETTINGS: Mutex<Option<EnvConfig>> = Mutex::new(None);
+/// Session overrides by name: a value to set, or `None` to unset
+static OVERRIDES: Mutex<BTreeMap<String, Option<String>>> = Mutex::new(BTreeMap::new());
+
+pub fn init(config: &EnvConfig) {
+    *SETTINGS.lock().unwrap() = Some(config.clone());
+}
+
+/// Sets (`Some`) or unsets (`None`) variables for every command the tools
+/// start from now on; nothing changes when one of them is refused
+pub fn change(changes: &[(String, Option<String>)]) -> Result<()> {
+    for (name, value) in changes {
+        check_name(name)?;
+        if value.as_deref().is_some_and(|value| value.contains('\0')) {
+            anyhow::bail!("The value of {} contains a NUL byte", name);
+        }
+    }
+    OVERRIDES.lock().unwrap().extend(changes.iter().cloned());
+    Ok(())
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(['=', '\0']) {
+        anyhow::bail!("Invalid environment variable name: {:?}", name);
+    }
+    if is_protected(name) {
+        anyhow::bail!("{} decides which programs commands run, so it cannot be changed", name);
+    }
+    Ok(())
+}
+
+fn is_protected(name: &str) -> bool {
+    PROTECTED_NAMES.iter().any(|pattern| glob_match(pattern, name))
+}
+
+/// The first variable an env call would set or unset that may not be changed
+pub fn protected_change(arguments: &Value) -> Option<String> {
+    let set = arguments["set"].as_object().into_iter().flat_map(|set| set.keys().map(String::as_str));
+    let unset = arguments["unset"].as_array().into_iter().flatten().filter_map(Value::as_str);
+    set.chain(unset).find(|name| is_protected(name)).map(String::from)
+}
+
+/// Whether an env call sets or unsets variables rather than only listing them
+pub fn changes(arguments: &Value) -> bool {
+    arguments["set"].as_object().is_some_and(|set| !set.is_empty())
+        || arguments["unset"].as_array().is_some_and(|unset| !unset.is_empty())
+}
+
+/// Gives a command about to be started the session's overrides
+pub fn apply(command: &mut Command) {
+    for (name, value) in OVERRIDES.lock().unwrap().iter() {
+        match value {
+            Some(value) => command.env(name, value),
+            None => command.env_remove(name),
+        };
+    }
+}
+
+pub struct Variable {
+    pub name: String,
+    /// `None` when the variable is not set
+    pub value: Option<String>,
+    pub masked: bool,
+    /// Set or unset for this session through the tool
+    pub overridden: bool,
+}
+
+/// The variables named in `names`, set or not, or those whose names match
+/// `pattern`, or all of them, in name order and with secrets masked
+pub fn variables(names: &[String], pattern: Option<&str>) -> Vec<Variable> {
+    let overrides = OVERRIDES.lock().unwrap().clone();
+    let mut environment: BTreeMap<String, String> = std::env::vars_os()
+        .map(|(name, value)| (name.to_string_lossy().to_string(), value.to_string_lossy().to_string()))
+        .collect();
+    for (name, value) in &overrides {
+        match value {
+            Some(value) => environment.insert(name.clone(), value.clone()),
+            None => environment.remove(name),
+        };
+    }
+
+    let selected: Vec<String> = if !names.is_empty() {
+        names.to_vec()
+    } else {
+        environment.keys()
+            .filter(|name| pattern.is_none_or(|pattern| glob_match(pattern, name)))
+            .cloned()
+            .collect()
+    };
+
+    let settings = SETTINGS.lock().unwrap().clone().unwrap_or_default();
+    selected.into_iter()
+        .map(|name| {
+            let value = environment.get(&name).cloned();
+            let masked_value = value.as_deref().and_then(|value| mask(&settings, &name, value));
+            Variable {
+                overridden: overrides.contains_key(&name),
+                masked: masked_value.is_some(),
+                value: masked_value.or(value),
+                name,
+            }
+        })
+        .collect()
+}
+
+/// The value as it may be shown, or `None` when it can be shown as it is
+fn mask(settings: &EnvConfig, name: &str, value: &str) -> Option<String> {
+    if value.is_empty() || settings.reveal.iter().any(|pattern| glob_match(pattern, name)) {
+        return None;
+    }
+    let hidden = || Some(format!("****** ({} chars)", value.chars().count()));
+    if SECRET_NAMES.iter().any(|pattern| glob_match(pattern, name))
+        || settings.mask.iter().any(|pattern| glob_match(pattern, name))
+    {
+        return hidden();
+    }
+    if !settings.detect_values.unwrap_or(true) {
+        return None;
+    }
+
+    let word = value.trim();
+    let issued = word.len() >= 16
+        && !word.contains(char::is_whitespace)
+        && SECRET_PREFIXES.iter().any(|prefix| word.starts_with(prefix));
+    let is_jwt = word.starts_with("eyJ") && word.split('.').count() == 3;
+    if issued || is_jwt || word.starts_with("-----BEGIN") {
+        return hidden();
+    }
+    // Only the password of a connection URL is hidden, so the host stays readable
+    URL_PASSWORD.is_match(value)
+        .then(|| URL_PASSWORD.replace_all(value, "${1}******@").to_string())
+}
+
+/// `*` matching any run of characters, ignoring ASCII case
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_ascii_uppercase();
+    let name = name.to_ascii_uppercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if parts.len() == 1 {
+        return name == pattern;
+    }
+    if name.len() < first.len() + last.len() || !name.starts_with(first) || !name.ends_with(last) {
+        return false;
+    }
+
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// `NAME=value` lines, noting masked, overridden and missing variables
+pub fn variables_text(variables: &[Variable]) -> String {
+    if variables.is_empty() {
+        return "No matching environment variables".to_string();
+    }
+    variables.iter()
+        .map(|variable| {
+            let mut line = match &variable.value {
+                Some(value) => format!("{}={}", variable.name, value),
+                None => format!("{} is not set", variable.name),
+            };
+            if variable.masked {
+                line.push_str("  [masked]");
+            }
+            if variable.overridden {
+                line.push_str(if variable.value.is_some() { "  [set for this session]" } else { "  [unset for this session]" });
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn variables_json(variables: &[Variable]) -> Value {
+    variables.iter()
+        .map(|variable| json!({
+            "name": variable.name,
+            "value": variable.value,
+            "masked": variable.masked,
+            "override": variable.overridden,
+        }))
+        .collect()
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+use std::process::ExitCode;
+
+/// Failure categories scripts can tell apart by exit code.
+///
+/// Attach one as the outermost context of an error, e.g.
+/// `.context(Failure::Connection)`, so it prefixes the message; anything
+/// else exits with the general code 1. Clap reports usage errors with 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Ollama or a worker could not be reached
+    Connection,
+    /// The requested model is not installed
+    ModelMissing,
+    /// A tool call was refused by policy or by the user
+    ToolDenied,
+    /// A configured usage budget has been used up
+    BudgetExhausted,
+    /// The model request failed or returned an error
+    Generation,
+}
+
+impl Failure {
+    pub fn code(self) -> u8 {
+        match self {
+            Failure::Connection => 3,
+            Failure::ModelMissing => 4,
+            Failure::ToolDenied => 5,
+            Failure::BudgetExhausted => 6,
+            Failure::Generation => 7,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Failure::Connection => "connection failed",
+            Failure::ModelMissing => "model missing",
+            Failure::ToolDenied => "tool denied",
+            Failure::BudgetExhausted => "budget exhausted",
+            Failure::Generation => "generation failed",
+        };
+        f.write_str(text)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+pub fn exit_code(error: &anyhow::Error) -> ExitCode {
+    let code = error.downcast_ref::<Failure>().map_or(1, |failure| failure.code());
+    ExitCode::from(code)
+}
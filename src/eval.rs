@@ -0,0 +1,232 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Instant;
+
+use crate::args::EvalArgs;
+use crate::executor::AIExecutor;
+use crate::ollama::Message;
+
+#[derive(Debug, Deserialize)]
+pub struct EvalSuite {
+    /// Model under test (overridden by --model)
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Model that scores the responses (overridden by --judge, defaults to the target model)
+    #[serde(default)]
+    pub judge: Option<String>,
+    /// System prompt applied to every case
+    #[serde(default)]
+    pub system: Option<String>,
+    pub cases: Vec<EvalCase>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EvalCase {
+    pub name: String,
+    pub prompt: String,
+    /// Plain-language requirements the response must satisfy
+    pub criteria: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Verdict {
+    pass: bool,
+    #[serde(default)]
+    score: Option<f64>,
+    #[serde(default)]
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CaseReport {
+    pub name: String,
+    pub pass: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    pub reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    pub latency_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EvalReport {
+    pub model: String,
+    pub judge: String,
+    pub passed: usize,
+    pub total: usize,
+    pub pass_rate: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_score: Option<f64>,
+    pub cases: Vec<CaseReport>,
+}
+
+pub async fn run(executor: &AIExecutor, args: EvalArgs) -> Result<()> {
+    let content = fs::read_to_string(&args.suite)
+        .context(format!("Failed to read eval suite: {}", args.suite.display()))?;
+    let suite: EvalSuite = serde_yaml::from_str(&content)
+        .context("Failed to parse eval suite")?;
+
+    if suite.cases.is_empty() {
+        anyhow::bail!("Eval suite has no cases");
+    }
+
+    let model = args.model.or(suite.model)
+        .unwrap_or_else(|| executor.get_model().to_string());
+    let judge = args.judge.or(suite.judge)
+        .unwrap_or_else(|| model.clone());
+
+    println!("{} Evaluating {} case(s) on {} (judge: {})",
+        "⚙".bright_blue(), suite.cases.len(), model.bright_cyan(), judge.bright_magenta());
+
+    let mut cases = Vec::new();
+    for case in &suite.cases {
+        let report = run_case(executor, &model, &judge, suite.system.as_deref(), case).await;
+
+        let marker = if report.pass { "✓".bright_green() } else { "✗".bright_red() };
+        println!("  {} {} ({} ms)", marker, report.name.bright_white(), report.latency_ms);
+        if !report.pass {
+            println!("    {}", report.reason.bright_black());
+        }
+
+        cases.push(report);
+    }
+
+    let passed = cases.iter().filter(|c| c.pass).count();
+    let total = cases.len();
+    let scores: Vec<f64> = cases.iter().filter_map(|c| c.score).collect();
+    let mean_score = (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64);
+
+    let report = EvalReport {
+        model,
+        judge,
+        passed,
+        total,
+        pass_rate: passed as f64 / total as f64 * 100.0,
+        mean_score,
+        cases,
+    };
+
+    print!("\n{} {}/{} passed ({:.1}%)", "Score:".bright_yellow().bold(),
+        report.passed, report.total, report.pass_rate);
+    match report.mean_score {
+        Some(score) => println!(", mean judge score {:.1}/10", score),
+        None => println!(),
+    }
+
+    if let Some(path) = &args.out {
+        fs::write(path, serde_json::to_string_pretty(&report)?)
+            .context(format!("Failed to write report: {}", path.display()))?;
+        println!("{} Report written to {}", "✓".bright_green(), path.display());
+    }
+
+    if report.passed < report.total {
+        anyhow::bail!("{} of {} eval case(s) failed", report.total - report.passed, report.total);
+    }
+
+    Ok(())
+}
+
+async fn run_case(
+    executor: &AIExecutor,
+    model: &str,
+    judge: &str,
+    system: Option<&str>,
+    case: &EvalCase,
+) -> CaseReport {
+    let mut messages = Vec::new();
+    if let Some(system) = system {
+        messages.push(Message {
+            role: "system".to_string(),
+            content: system.to_string(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        });
+    }
+    messages.push(Message {
+        role: "user".to_string(),
+        content: case.prompt.clone(),
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    });
+
+    let started = Instant::now();
+    let response = executor.batch_complete(Some(model), messages, None).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let response = match response {
+        Ok(response) => response.message.content,
+        Err(e) => {
+            return CaseReport {
+                name: case.name.clone(),
+                pass: false,
+                score: None,
+                reason: format!("Model error: {:#}", e),
+                response: None,
+                latency_ms,
+            };
+        }
+    };
+
+    let verdict = judge_response(executor, judge, case, &response).await
+        .unwrap_or_else(|e| Verdict {
+            pass: false,
+            score: None,
+            reason: format!("Judge error: {:#}", e),
+        });
+
+    CaseReport {
+        name: case.name.clone(),
+        pass: verdict.pass,
+        score: verdict.score,
+        reason: verdict.reason,
+        response: Some(response),
+        latency_ms,
+    }
+}
+
+/// Asks the judge model whether a response meets every criterion of the case
+async fn judge_response(executor: &AIExecutor, judge: &str, case: &EvalCase, response: &str) -> Result<Verdict> {
+    let criteria: String = case.criteria.iter()
+        .map(|c| format!("- {}\n", c))
+        .collect();
+
+    let prompt = format!(
+        "You are grading an AI assistant's response.\n\n\
+         Prompt:\n{}\n\n\
+         Response:\n{}\n\n\
+         Criteria (all must hold to pass):\n{}\n\
+         Reply with only a JSON object: \
+         {{\"pass\": true or false, \"score\": 0-10, \"reason\": \"one sentence\"}}",
+        case.prompt, response, criteria
+    );
+
+    let messages = vec![Message {
+        role: "user".to_string(),
+        content: prompt,
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    }];
+
+    let reply = executor.batch_complete(Some(judge), messages, Some(serde_json::json!({"temperature": 0})))
+        .await?
+        .message
+        .content;
+
+    parse_verdict(&reply)
+}
+
+/// Extracts the verdict object even when the judge wraps it in prose or code fences
+pub fn parse_verdict<T: DeserializeOwned>(reply: &str) -> Result<T> {
+    let start = reply.find('{');
+    let end = reply.rfind('}');
+
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&reply[start..=end])
+            .context(format!("Judge returned an invalid verdict: {}", reply.trim())),
+        _ => anyhow::bail!("Judge did not return a verdict: {}", reply.trim()),
+    }
+}
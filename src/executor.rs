@@ -1,43 +1,587 @@
 use anyhow::Result;
-use crate::ollama::{Message, OllamaClient};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use crate::backend::{self, ChatBackend};
+use crate::cache::{CachedResponse, ResponseCache};
+use crate::config::{AppConfig, GenerationConfig, Provider};
+use crate::error::Failure;
+use crate::distributed::{Backend, DistributedAI, WorkerStatus};
+use crate::mapreduce::{self, Chunk, MapReduceOutput};
+use crate::ollama::{ChatResponse, Message};
+use crate::tokens;
+use crate::usage::UsageTracker;
 
 pub struct AIExecutor {
-    ollama: OllamaClient,
+    /// Where chat replies come from
+    backend: Arc<dyn ChatBackend>,
+    provider: Provider,
+    pool: DistributedAI,
     model: String,
+    model_backends: HashMap<String, Backend>,
+    batch_backend: Option<Backend>,
+    cache_ttl: Option<Duration>,
+    /// Opt-in cache for chat replies
+    cache: Option<ResponseCache>,
+    /// Stop sequences and token limit for chat replies
+    generation: GenerationConfig,
+    /// Load models as soon as they are switched to
+    warm_up: bool,
+    /// Every request sent is checked against the budget and counted here
+    usage: Arc<Mutex<UsageTracker>>,
 }
 
 impl AIExecutor {
-    pub async fn new(model: String, _cpu_workers: usize) -> Result<Self> {
-        // Note: In a production distributed system, you would initialize
-        // Repartir pool here and use it to distribute AI inference tasks
-        // across multiple workers/machines. For this demo, we're focusing
-        // on the local Ollama integration.
-        
-        let ollama = OllamaClient::new();
+    pub async fn new(model: String, cpu_workers: usize, config: &AppConfig) -> Result<Self> {
+        // Interactive chat goes to the configured backend; the pool spreads
+        // independent tasks across local and remote Ollama workers.
+        let backend = backend::connect(config.chat_backend, config)?;
+        Self::with_backend(backend, model, cpu_workers, config)
+    }
+
+    /// Like `new`, with chat replies from `backend` instead of the one
+    /// configured, e.g. a stand-in answering from a script
+    pub fn with_backend(backend: Arc<dyn ChatBackend>, model: String, cpu_workers: usize, config: &AppConfig) -> Result<Self> {
+        let pool = DistributedAI::new(cpu_workers, &config.workers)?;
+
+        let cache_ttl = config.response_cache.ttl_seconds.map(Duration::from_secs);
+        let cache = if config.response_cache.enabled {
+            Some(ResponseCache::open(cache_ttl)?)
+        } else {
+            None
+        };
 
         Ok(Self {
-            ollama,
+            backend,
+            provider: config.chat_backend,
+            pool,
             model,
+            model_backends: config.model_backends.clone(),
+            batch_backend: config.batch_backend,
+            cache_ttl,
+            cache,
+            generation: config.generation.clone(),
+            warm_up: config.warm_up.enabled,
+            usage: Arc::new(Mutex::new(UsageTracker::load(config))),
         })
     }
 
     pub async fn chat(&self, messages: Vec<Message>) -> Result<String> {
-        // Execute AI inference through Ollama
-        let response = self.ollama.chat(&self.model, messages).await?;
+        // Execute AI inference through the backend
+        let response = self.complete(messages).await?;
+        Ok(response.message.content)
+    }
+
+    /// Like `chat`, but returns the token counts along with the reply
+    pub async fn complete(&self, messages: Vec<Message>) -> Result<ChatResponse> {
+        let options = self.options();
+        let key = self.cache.as_ref().map(|_| ResponseCache::key(&self.model, options.as_ref(), &messages));
+        if let Some(cached) = key.as_deref().and_then(|key| self.cached(key)) {
+            return Ok(cached);
+        }
+
+        let prompt_estimate = tokens::estimate(&messages);
+        let request = self.backend.chat(&self.model, messages, options);
+        let response = metered(&self.usage, &self.model, prompt_estimate, request).await?;
+        self.store(key.as_deref(), &response);
+        Ok(response)
+    }
+
+    /// Like `complete`, calling `on_token` as pieces of the reply arrive and
+    /// offering the model `tools` to call through the backend's tool-calling
+    /// API; cached replies arrive whole, without calls
+    pub async fn complete_with_tools(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<serde_json::Value>>,
+        mut on_token: impl FnMut(&str) + Send,
+    ) -> Result<ChatResponse> {
+        let options = self.options();
+        // The cache keeps only the text of a reply, not the tools it called
+        let key = self.cache.as_ref()
+            .filter(|_| tools.is_none())
+            .map(|_| ResponseCache::key(&self.model, options.as_ref(), &messages));
+        if let Some(cached) = key.as_deref().and_then(|key| self.cached(key)) {
+            return Ok(cached);
+        }
+
+        let prompt_estimate = tokens::estimate(&messages);
+        let request = self.backend.stream(&self.model, messages, options, tools, &mut on_token);
+        let response = metered(&self.usage, &self.model, prompt_estimate, request).await?;
+        self.store(key.as_deref(), &response);
         Ok(response)
     }
 
+    /// The reply cached under `key`, if the cache is on and has one
+    fn cached(&self, key: &str) -> Option<ChatResponse> {
+        let cached = self.cache.as_ref()?.get(key)?;
+        Some(ChatResponse {
+            message: Message {
+                role: "assistant".to_string(),
+                content: cached.response,
+                images: Vec::new(),
+                tool_calls: Vec::new(),
+            },
+            done: true,
+            prompt_eval_count: cached.prompt_tokens,
+            eval_count: cached.completion_tokens,
+            cached: true,
+        })
+    }
+
+    /// Caches `response` under `key`, when there is one
+    fn store(&self, key: Option<&str>, response: &ChatResponse) {
+        let (Some(cache), Some(key)) = (&self.cache, key) else {
+            return;
+        };
+        // A failed write only costs a future cache hit
+        let _ = cache.put(key, &CachedResponse {
+            response: response.message.content.clone(),
+            prompt_tokens: response.prompt_eval_count,
+            completion_tokens: response.eval_count,
+        });
+    }
+
+    /// Stop sequences and token limit applied to chat replies
+    pub fn generation(&self) -> &GenerationConfig {
+        &self.generation
+    }
+
+    pub fn generation_mut(&mut self) -> &mut GenerationConfig {
+        &mut self.generation
+    }
+
+    /// Ollama options for chat replies, or `None` when nothing is set
+    fn options(&self) -> Option<serde_json::Value> {
+        let mut options = serde_json::Map::new();
+        if !self.generation.stop.is_empty() {
+            options.insert("stop".to_string(), serde_json::json!(self.generation.stop));
+        }
+        if let Some(num_predict) = self.generation.num_predict {
+            options.insert("num_predict".to_string(), serde_json::json!(num_predict));
+        }
+        if let Some(seed) = self.generation.pinned_seed() {
+            options.insert("seed".to_string(), serde_json::json!(seed));
+            options.insert("temperature".to_string(), serde_json::json!(0));
+        }
+        (!options.is_empty()).then_some(serde_json::Value::Object(options))
+    }
+
+    /// Token counts and cost so far, with the budget
+    pub fn usage(&self) -> MutexGuard<'_, UsageTracker> {
+        self.usage.lock().unwrap()
+    }
+
+    /// Fails with `Failure::BudgetExhausted` once this month's budget is used up
+    pub fn check_budget(&self) -> Result<()> {
+        self.usage().check_budget()
+    }
+
+    /// The chat cache, when enabled with "responseCache" in config.json
+    pub fn response_cache(&self) -> Option<&ResponseCache> {
+        self.cache.as_ref()
+    }
+
+    /// TTL for cached responses, shared by batch runs
+    pub fn cache_ttl(&self) -> Option<Duration> {
+        self.cache_ttl
+    }
+
+    /// Runs a batch item on the worker pool using the batch or per-model backend
+    /// preference; the future borrows nothing, so many can be spawned at once
+    pub fn batch_chat(&self, messages: Vec<Message>) -> impl Future<Output = Result<String>> + Send + 'static {
+        let usage = self.usage.clone();
+        let model = self.model.clone();
+        let tasks = self.tasks(self.task_backend());
+        async move {
+            let prompt_estimate = tokens::estimate(&messages);
+            let request = tasks.complete(&model, messages, None);
+            let response = metered(&usage, &model, prompt_estimate, request).await?;
+            Ok(response.message.content)
+        }
+    }
+
+    /// Runs a batch item with its own model and options, defaulting to the current model
+    pub async fn batch_complete(
+        &self,
+        model: Option<&str>,
+        messages: Vec<Message>,
+        options: Option<serde_json::Value>,
+    ) -> Result<ChatResponse> {
+        let model = model.unwrap_or(&self.model);
+        let tasks = self.tasks(self.batch_backend.or_else(|| self.backend_for(model)));
+        let prompt_estimate = tokens::estimate(&messages);
+        let request = tasks.complete(model, messages, options);
+        metered(&self.usage, model, prompt_estimate, request).await
+    }
+
+    /// Code to insert between `prefix` and `suffix`, from a FIM-capable model
+    /// (default: the current one) with the chat replies' stop sequences and token limit
+    pub async fn fill_in_middle(&self, model: Option<&str>, prefix: &str, suffix: &str) -> Result<String> {
+        let model = model.unwrap_or(&self.model);
+        self.check_budget()?;
+        let code = self.backend.fill_in_middle(model, prefix, suffix, self.options()).await?;
+        let prompt_tokens = tokens::estimate_text(prefix) + tokens::estimate_text(suffix);
+        self.usage().record(model, prompt_tokens as u64, tokens::estimate_text(&code) as u64);
+        Ok(code)
+    }
+
+    /// Sends the same conversation to several models at once, returning results in model order
+    pub async fn compare(&self, models: &[String], messages: Vec<Message>) -> Vec<(String, Duration, Result<String>)> {
+        let mut tasks = JoinSet::new();
+
+        for (index, model) in models.iter().enumerate() {
+            let usage = self.usage.clone();
+            let model = model.clone();
+            let messages = messages.clone();
+            let runner = self.tasks(self.backend_for(&model));
+
+            tasks.spawn(async move {
+                let started = Instant::now();
+                let prompt_estimate = tokens::estimate(&messages);
+                let request = runner.complete(&model, messages, None);
+                let result = metered(&usage, &model, prompt_estimate, request).await
+                    .map(|response| response.message.content);
+                (index, model, started.elapsed(), result)
+            });
+        }
+
+        let mut results = Vec::new();
+        while let Some(Ok(result)) = tasks.join_next().await {
+            results.push(result);
+        }
+        results.sort_by_key(|(index, ..)| *index);
+
+        results.into_iter()
+            .map(|(_, model, elapsed, result)| (model, elapsed, result))
+            .collect()
+    }
+
+    /// Fans chunks out across the worker pool and reduces the partial results
+    pub async fn map_reduce(&self, chunks: Vec<Chunk>, instruction: &str) -> Result<MapReduceOutput> {
+        mapreduce::run(&self.tasks(self.task_backend()), &self.usage, &self.model, chunks, instruction).await
+    }
+
+    /// Map-reduce on another model, e.g. a small one that condenses tool output
+    pub async fn map_reduce_on(&self, model: &str, chunks: Vec<Chunk>, instruction: &str) -> Result<MapReduceOutput> {
+        mapreduce::run(&self.tasks(self.backend_for(model)), &self.usage, model, chunks, instruction).await
+    }
+
+    /// Where independent tasks run: the worker pool, preferring workers with
+    /// `backend`, when chatting with Ollama; the chat backend otherwise, since
+    /// the pool's workers do not know another provider's models
+    fn tasks(&self, backend: Option<Backend>) -> Tasks {
+        match self.provider {
+            Provider::Ollama => Tasks::Pool(self.pool.clone(), backend),
+            _ => Tasks::Chat(self.backend.clone()),
+        }
+    }
+
+    fn task_backend(&self) -> Option<Backend> {
+        self.batch_backend.or_else(|| self.backend_for(&self.model))
+    }
+
+    fn backend_for(&self, model: &str) -> Option<Backend> {
+        self.model_backends.get(model).copied().or_else(|| {
+            // Allow "llama3" to match "llama3:8b", preferring the most specific entry
+            self.model_backends.iter()
+                .filter(|(name, _)| model.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, backend)| *backend)
+        })
+    }
+
+    pub async fn worker_status(&self) -> Vec<WorkerStatus> {
+        self.pool.status().await
+    }
+
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        self.backend.list_models().await
+    }
+
+    pub async fn has_model(&self, model: &str) -> Result<bool> {
+        self.backend.has_model(model).await
+    }
+
+    pub async fn context_window(&self, model: &str) -> Result<Option<usize>> {
+        self.backend.context_window(model).await
+    }
+
     pub fn get_model(&self) -> &str {
         &self.model
     }
 
+    /// Sets a model that was already verified, e.g. when restoring a conversation
+    pub fn set_model(&mut self, model: String) {
+        self.model = model;
+    }
+
+    /// The backend chat replies come from
+    pub fn provider(&self) -> Provider {
+        self.provider
+    }
+
+    /// Sends chat replies to another backend from now on, with `model`,
+    /// once the backend lists it
+    pub async fn switch_backend(&mut self, provider: Provider, backend: Arc<dyn ChatBackend>, model: String) -> Result<()> {
+        if !backend.has_model(&model).await? {
+            let models = backend.list_models().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Model '{}' not found on the {} backend. Available: {:?}", model, backend.name(), models)
+                .context(Failure::ModelMissing));
+        }
+        self.backend = backend;
+        self.provider = provider;
+        self.model = model;
+        if self.warm_up {
+            self.preload();
+        }
+        Ok(())
+    }
+
     pub async fn switch_model(&mut self, model: String) -> Result<()> {
         // Verify model exists before switching
-        let models = self.ollama.list_models().await?;
-        if !models.iter().any(|m| m.starts_with(&model)) {
-            anyhow::bail!("Model '{}' not found. Available: {:?}", model, models);
+        if !self.backend.has_model(&model).await? {
+            let models = self.backend.list_models().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Model '{}' not found. Available: {:?}", model, models)
+                .context(Failure::ModelMissing));
         }
         self.model = model;
+        if self.warm_up {
+            self.preload();
+        }
         Ok(())
     }
+
+    /// Starts loading the current model in the background; a failure shows
+    /// up again on the first real request
+    pub fn preload(&self) {
+        let backend = self.backend.clone();
+        let model = self.model.clone();
+        tokio::spawn(async move {
+            let _ = backend.preload(&model).await;
+        });
+    }
+}
+
+/// Runs batch items, comparisons and map-reduce rounds
+#[derive(Clone)]
+pub enum Tasks {
+    /// Spread over the Ollama workers, preferring those with the backend
+    Pool(DistributedAI, Option<Backend>),
+    /// Sent to the chat backend, one request per task
+    Chat(Arc<dyn ChatBackend>),
+}
+
+impl Tasks {
+    pub async fn complete(&self, model: &str, messages: Vec<Message>, options: Option<serde_json::Value>) -> Result<ChatResponse> {
+        match self {
+            Tasks::Pool(pool, backend) => pool.complete(model, messages, options, *backend).await,
+            Tasks::Chat(chat) => chat.chat(model, messages, options).await,
+        }
+    }
+
+    /// Runs independent single-turn prompts at once, returning results in input order
+    pub async fn parallel_inference(&self, model: &str, prompts: Vec<String>) -> Vec<Result<ChatResponse>> {
+        let chat = match self {
+            Tasks::Pool(pool, backend) => return pool.parallel_inference(model, prompts, *backend).await,
+            Tasks::Chat(chat) => chat,
+        };
+
+        let mut tasks = JoinSet::new();
+        for (index, prompt) in prompts.into_iter().enumerate() {
+            let chat = chat.clone();
+            let model = model.to_string();
+            let messages = vec![Message {
+                role: "user".to_string(),
+                content: prompt,
+                images: Vec::new(),
+                tool_calls: Vec::new(),
+            }];
+            tasks.spawn(async move { (index, chat.chat(&model, messages, None).await) });
+        }
+
+        let mut results: Vec<Option<Result<ChatResponse>>> = (0..tasks.len()).map(|_| None).collect();
+        while let Some(Ok((index, result))) = tasks.join_next().await {
+            results[index] = Some(result);
+        }
+        results.into_iter()
+            .map(|r| r.unwrap_or_else(|| Err(anyhow::anyhow!("Task did not complete"))))
+            .collect()
+    }
+}
+
+/// Sends `request` once the budget allows and counts its reply
+async fn metered(
+    usage: &Mutex<UsageTracker>,
+    model: &str,
+    prompt_estimate: usize,
+    request: impl Future<Output = Result<ChatResponse>>,
+) -> Result<ChatResponse> {
+    usage.lock().unwrap().check_budget()?;
+    let response = request.await?;
+    usage.lock().unwrap().record_reply(model, prompt_estimate, &response);
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use serde_json::{Value, json};
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::ollama::{FunctionCall, ToolCall};
+
+    /// Answers with the replies it was given, in order, counting the requests
+    #[derive(Default)]
+    struct Scripted {
+        replies: Mutex<VecDeque<Message>>,
+        requests: AtomicUsize,
+    }
+
+    impl Scripted {
+        fn new(replies: Vec<Message>) -> Arc<Self> {
+            Arc::new(Self { replies: Mutex::new(replies.into()), ..Default::default() })
+        }
+
+        fn next(&self) -> Result<ChatResponse> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let message = self.replies.lock().unwrap().pop_front()
+                .ok_or_else(|| anyhow::anyhow!("The script has no more replies"))?;
+            Ok(ChatResponse {
+                message,
+                done: true,
+                prompt_eval_count: Some(12),
+                eval_count: Some(3),
+                cached: false,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl ChatBackend for Scripted {
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        async fn chat(&self, _model: &str, _messages: Vec<Message>, _options: Option<Value>) -> Result<ChatResponse> {
+            self.next()
+        }
+
+        async fn stream(
+            &self,
+            _model: &str,
+            _messages: Vec<Message>,
+            _options: Option<Value>,
+            _tools: Option<Vec<Value>>,
+            on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+        ) -> Result<ChatResponse> {
+            let response = self.next()?;
+            for word in response.message.content.split_inclusive(' ') {
+                on_token(word);
+            }
+            Ok(response)
+        }
+
+        async fn list_models(&self) -> Result<Vec<String>> {
+            Ok(vec!["scripted".to_string()])
+        }
+    }
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: content.to_string(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        }
+    }
+
+    /// An executor on `backend` whose cache lives in a fresh temporary
+    /// directory and whose usage is not saved
+    fn executor(backend: Arc<Scripted>, config: &AppConfig) -> (AIExecutor, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("ai-chat-cli-test-{}", uuid::Uuid::new_v4()));
+        let mut executor = AIExecutor::with_backend(backend, "scripted".to_string(), 1, config).unwrap();
+        executor.cache = Some(ResponseCache::at(dir.clone(), None).unwrap());
+        executor.usage = Arc::new(Mutex::new(UsageTracker::at(None, config)));
+        (executor, dir)
+    }
+
+    #[tokio::test]
+    async fn complete_serves_a_repeated_request_from_the_cache() {
+        let backend = Scripted::new(vec![message("assistant", "Paris"), message("assistant", "Lyon")]);
+        let (executor, dir) = executor(backend.clone(), &AppConfig::default());
+        let question = vec![message("user", "Capital of France?")];
+
+        let first = executor.complete(question.clone()).await.unwrap();
+        let second = executor.complete(question).await.unwrap();
+        let other = executor.complete(vec![message("user", "Another city?")]).await.unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+
+        assert_eq!((first.message.content.as_str(), first.cached), ("Paris", false));
+        assert_eq!((second.message.content.as_str(), second.cached), ("Paris", true));
+        assert_eq!(other.message.content, "Lyon");
+        assert_eq!(backend.requests.load(Ordering::SeqCst), 2);
+        // Cached replies are free
+        let usage = executor.usage().session_total();
+        assert_eq!((usage.requests, usage.prompt_tokens, usage.completion_tokens), (2, 24, 6));
+    }
+
+    #[tokio::test]
+    async fn complete_with_tools_streams_and_returns_calls_uncached() {
+        let mut call = message("assistant", "");
+        call.tool_calls.push(ToolCall {
+            function: FunctionCall { name: "read_file".to_string(), arguments: json!({"path": "Cargo.toml"}) },
+        });
+        let backend = Scripted::new(vec![call, message("assistant", "It is a Rust crate")]);
+        let (executor, dir) = executor(backend.clone(), &AppConfig::default());
+        let tools = Some(vec![json!({"type": "function", "function": {"name": "read_file"}})]);
+        let history = vec![message("user", "What is this project?")];
+
+        let first = executor.complete_with_tools(history.clone(), tools.clone(), |_| {}).await.unwrap();
+        let mut streamed = String::new();
+        let second = executor.complete_with_tools(history, tools, |piece| streamed.push_str(piece)).await.unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+
+        assert_eq!(first.message.tool_calls[0].function.name, "read_file");
+        // With tools offered, the same request goes to the backend again
+        assert!(!second.cached);
+        assert_eq!(streamed, "It is a Rust crate");
+        assert_eq!(backend.requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn spent_budget_refuses_requests_before_the_backend() {
+        let config = AppConfig { monthly_budget: Some(0.0), ..Default::default() };
+        let backend = Scripted::new(vec![message("assistant", "unused")]);
+        let (executor, dir) = executor(backend.clone(), &config);
+
+        let error = executor.complete_with_tools(vec![message("user", "Hello")], None, |_| {}).await.unwrap_err();
+        let _ = std::fs::remove_dir_all(dir);
+
+        assert_eq!(error.downcast_ref::<Failure>(), Some(&Failure::BudgetExhausted));
+        assert_eq!(backend.requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn tasks_go_to_the_chat_backend_when_it_is_not_ollama() {
+        let config = AppConfig { chat_backend: Provider::Anthropic, ..Default::default() };
+        let backend = Scripted::new(vec![
+            message("assistant", "Reply"),
+            message("assistant", "Notes"),
+            message("assistant", "Summary"),
+        ]);
+        let (executor, dir) = executor(backend.clone(), &config);
+
+        let reply = executor.batch_complete(None, vec![message("user", "Hello")], None).await.unwrap();
+        let chunks = vec![Chunk { source: "notes.txt".to_string(), text: "Some notes".to_string() }];
+        let output = executor.map_reduce(chunks, "Summarize").await.unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+
+        assert_eq!(reply.message.content, "Reply");
+        assert_eq!((output.answer.as_str(), output.chunks, output.failed), ("Summary", 1, 0));
+        assert_eq!(backend.requests.load(Ordering::SeqCst), 3);
+    }
 }
@@ -0,0 +1,104 @@
+//! One-shot explanations of failing commands, for the `explain` subcommand.
+//!
+//! The command runs with its output going to the terminal as usual while
+//! the end of its stderr is kept. If it fails, or cannot be started at all,
+//! the model is asked why and only its answer is printed. The process then
+//! exits with the command's own code, so a shell alias around it behaves
+//! like the command.
+
+use anyhow::{Context, Result};
+use colored::*;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+use crate::args::ExplainArgs;
+use crate::executor::AIExecutor;
+use crate::filters;
+use crate::ollama::Message;
+use crate::text::tail;
+
+/// End of stderr sent to the model; the error is almost always at the bottom
+const MAX_STDERR_CHARS: usize = 8000;
+/// What shells exit with for a command that does not exist
+const NOT_FOUND: i32 = 127;
+
+const PROMPT: &str = "The shell command below failed. Explain briefly why, then suggest a fix, \
+with the corrected command if there is one. Answer in plain text for a terminal: no headings, \
+at most a few short paragraphs.";
+
+pub async fn run(executor: &AIExecutor, args: ExplainArgs) -> Result<()> {
+    let (code, stderr) = execute(&args.command)?;
+    if code == 0 {
+        return Ok(());
+    }
+
+    let shown = args.command.join(" ");
+    let stderr = tail(stderr.trim_end(), MAX_STDERR_CHARS);
+    let cwd = std::env::current_dir().map(|dir| dir.display().to_string()).unwrap_or_default();
+    let messages = vec![Message {
+        role: "user".to_string(),
+        content: format!("{}\n\nCommand: {}\nExit code: {}\nWorking directory: {}\nOS: {}\n\nStderr:\n```\n{}\n```",
+            PROMPT, shown, code, cwd, std::env::consts::OS,
+            if stderr.is_empty() { "(empty)" } else { stderr }),
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    }];
+
+    // Even without an explanation the exit code stays the command's
+    match executor.batch_complete(args.model.as_deref(), messages, None).await {
+        Ok(reply) => println!("{}", filters::apply(&reply.message.content).trim()),
+        Err(e) => eprintln!("{} {:#}", "Error:".bright_red().bold(), e),
+    }
+    io::stdout().flush()?;
+    std::process::exit(code);
+}
+
+/// Runs the command and returns its exit code and stderr, which is also
+/// passed through to ours as it arrives. A single argument is a shell
+/// command line; several are a program and its arguments.
+fn execute(command: &[String]) -> Result<(i32, String)> {
+    let mut process = match command {
+        [line] => {
+            let mut process = Command::new("sh");
+            process.arg("-c").arg(line);
+            process
+        }
+        [program, args @ ..] => {
+            let mut process = Command::new(program);
+            process.args(args);
+            process
+        }
+        [] => anyhow::bail!("No command given; usage: ai-chat-cli explain -- <command>"),
+    };
+
+    let mut child = match process.stdin(Stdio::inherit()).stdout(Stdio::inherit()).stderr(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let message = format!("{}: command not found", command[0]);
+            eprintln!("{}", message);
+            return Ok((NOT_FOUND, message));
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to run {}", command[0])),
+    };
+
+    let mut stderr = Vec::new();
+    let mut pipe = child.stderr.take().context("No stderr for the command")?;
+    let mut buffer = [0; 4096];
+    loop {
+        let read = pipe.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        io::stderr().write_all(&buffer[..read])?;
+        stderr.extend_from_slice(&buffer[..read]);
+    }
+
+    let status = child.wait()?;
+    #[cfg(unix)]
+    let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+    #[cfg(not(unix))]
+    let signal: Option<i32> = None;
+    // Killed by a signal: report it the way shells do
+    let code = status.code().or(signal.map(|signal| 128 + signal)).unwrap_or(1);
+    Ok((code, String::from_utf8_lossy(&stderr).to_string()))
+}
@@ -0,0 +1,155 @@
+//! The stat tool: what `ls -la` and `file` would tell about one path,
+//! as JSON.
+//!
+//! Symlinks are described as links, with their target, and the rest of the
+//! metadata is that of the file they point to. The MIME type is guessed from
+//! the first bytes of the file, then from its extension.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde_json::{Value, json};
+use std::fs::{self, Metadata};
+use std::io::Read;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Bytes read to tell text from binary content
+const SNIFF_BYTES: usize = 8192;
+
+pub fn stat(path: &Path) -> Result<Value> {
+    let link = fs::symlink_metadata(path)
+        .context(format!("Failed to stat {}", path.display()))?;
+    let symlink_target = link.file_type().is_symlink()
+        .then(|| fs::read_link(path).ok())
+        .flatten();
+    // A dangling link is still worth describing
+    let metadata = fs::metadata(path).unwrap_or(link.clone());
+
+    let kind = if link.file_type().is_symlink() {
+        "symlink"
+    } else if metadata.is_dir() {
+        "dir"
+    } else if metadata.is_file() {
+        "file"
+    } else {
+        "other"
+    };
+    let (owner, group) = owner(&metadata);
+
+    Ok(json!({
+        "path": path,
+        "type": kind,
+        "size": metadata.len(),
+        "permissions": permissions(&metadata),
+        "owner": owner,
+        "group": group,
+        "modified": metadata.modified().ok().map(timestamp),
+        "changed": changed(&metadata),
+        "accessed": metadata.accessed().ok().map(timestamp),
+        "symlinkTarget": symlink_target,
+        "mimeType": metadata.is_file().then(|| mime_type(path)),
+    }))
+}
+
+fn timestamp(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).to_rfc3339()
+}
+
+/// `rw-r--r-- (644)`
+#[cfg(unix)]
+fn permissions(metadata: &Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = metadata.permissions().mode() & 0o777;
+    let flags: String = (0..9).rev()
+        .map(|bit| if mode & (1 << bit) == 0 { '-' } else { ['x', 'w', 'r'][bit % 3] })
+        .collect();
+    format!("{} ({:o})", flags, mode)
+}
+
+#[cfg(not(unix))]
+fn permissions(metadata: &Metadata) -> String {
+    if metadata.permissions().readonly() { "read-only" } else { "read-write" }.to_string()
+}
+
+/// User and group names, or their ids when the system does not list them
+#[cfg(unix)]
+fn owner(metadata: &Metadata) -> (Option<String>, Option<String>) {
+    use std::os::unix::fs::MetadataExt;
+
+    let name = |file: &str, id: u32| {
+        fs::read_to_string(file).ok()
+            .and_then(|entries| entries.lines()
+                .map(|line| line.split(':').collect::<Vec<_>>())
+                .find(|fields| fields.get(2) == Some(&id.to_string().as_str()))
+                .map(|fields| fields[0].to_string()))
+            .unwrap_or_else(|| id.to_string())
+    };
+    (Some(name("/etc/passwd", metadata.uid())), Some(name("/etc/group", metadata.gid())))
+}
+
+#[cfg(not(unix))]
+fn owner(_metadata: &Metadata) -> (Option<String>, Option<String>) {
+    (None, None)
+}
+
+/// When the file's metadata last changed; its creation time where there is no such thing
+#[cfg(unix)]
+fn changed(metadata: &Metadata) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    DateTime::from_timestamp(metadata.ctime(), metadata.ctime_nsec() as u32)
+        .map(|time| time.with_timezone(&Local).to_rfc3339())
+}
+
+#[cfg(not(unix))]
+fn changed(metadata: &Metadata) -> Option<String> {
+    metadata.created().ok().map(timestamp)
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let mut head = Vec::new();
+    if let Ok(file) = fs::File::open(path) {
+        let _ = file.take(SNIFF_BYTES as u64).read_to_end(&mut head);
+    }
+
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG", "image/png"),
+        (b"\xFF\xD8\xFF", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"%PDF", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1F\x8B", "application/gzip"),
+        (b"\x7FELF", "application/x-executable"),
+    ];
+    if let Some((_, mime)) = MAGIC.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return mime;
+    }
+
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    let by_extension = match extension.as_str() {
+        "json" => "application/json",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "xml" => "application/xml",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "tar" => "application/x-tar",
+        "wasm" => "application/wasm",
+        _ => "",
+    };
+    if !by_extension.is_empty() {
+        return by_extension;
+    }
+    match std::str::from_utf8(&head) {
+        Ok(_) => "text/plain",
+        // Only cut in the middle of a character at the end of the sample
+        Err(e) if e.error_len().is_none() => "text/plain",
+        Err(_) => "application/octet-stream",
+    }
+}
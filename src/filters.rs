@@ -0,0 +1,119 @@
+//! Clean-up of chat replies before they are shown and kept in the history.
+//!
+//! Every filter is off unless enabled under "responseFilters" in
+//! config.json. The built-in ones only touch the opening and closing prose
+//! of a reply and the blank lines between paragraphs, never the inside of
+//! code blocks; custom regex replacements apply to the whole reply.
+
+use colored::*;
+use regex::Regex;
+use std::sync::Mutex;
+
+use crate::config::ResponseFiltersConfig;
+
+/// Filler a reply opens with, such as "Sure! " or "Certainly, "
+const PREAMBLE: &str = r"(?i)^\s*(?:sure(?: thing)?|certainly|of course|absolutely|great question|good question|happy to help|no problem)\s*[!.,:]+\s*";
+/// A closing sentence or paragraph apologizing, possibly several in a row
+const APOLOGY: &str = r"(?i)(?:^|\n|[.!?]\s+)\s*(?:i apologi[sz]e|apologies|sorry|i'm sorry|i am sorry|my apologies)\b[^\n]*$";
+
+/// Compiled filters, or `None` while all are off
+static FILTERS: Mutex<Option<Filters>> = Mutex::new(None);
+
+struct Filters {
+    preamble: Option<Regex>,
+    apology: Option<Regex>,
+    collapse_blank_lines: bool,
+    replacements: Vec<(Regex, String)>,
+}
+
+/// Applies the "responseFilters" config for the whole process; invalid
+/// patterns are reported and skipped
+pub fn init(config: &ResponseFiltersConfig) {
+    let mut replacements = Vec::new();
+    for replacement in &config.replacements {
+        match Regex::new(&replacement.pattern) {
+            Ok(pattern) => replacements.push((pattern, replacement.replacement.clone())),
+            Err(e) => eprintln!("{} Ignoring response filter '{}': {}",
+                "Warning:".bright_yellow(), replacement.pattern, e),
+        }
+    }
+
+    let filters = Filters {
+        preamble: config.strip_preamble.then(|| Regex::new(PREAMBLE).unwrap()),
+        apology: config.strip_apologies.then(|| Regex::new(APOLOGY).unwrap()),
+        collapse_blank_lines: config.collapse_blank_lines,
+        replacements,
+    };
+    let active = filters.preamble.is_some() || filters.apology.is_some()
+        || filters.collapse_blank_lines || !filters.replacements.is_empty();
+    *FILTERS.lock().unwrap() = active.then_some(filters);
+}
+
+/// The reply with the configured filters applied in order: preamble,
+/// apologies, replacements, blank lines
+pub fn apply(reply: &str) -> String {
+    let filters = FILTERS.lock().unwrap();
+    let Some(filters) = filters.as_ref() else {
+        return reply.to_string();
+    };
+    let mut text = reply.to_string();
+
+    if let Some(preamble) = &filters.preamble
+        && let Some(found) = preamble.find(&text)
+        && found.end() < text.len()
+    {
+        text = capitalize(&text[found.end()..]);
+    }
+
+    if let Some(apology) = &filters.apology {
+        // A reply ending in a code block has no closing prose to strip
+        while !text.trim_end().ends_with("```") && let Some(found) = apology.find(text.trim_end()) {
+            if found.start() == 0 {
+                break;
+            }
+            // Keep the punctuation ending the previous sentence
+            let end = text[found.start()..].find(|c: char| !matches!(c, '.' | '!' | '?')).map_or(found.start(), |i| found.start() + i);
+            text.truncate(end);
+            text = text.trim_end().to_string();
+        }
+    }
+
+    for (pattern, replacement) in &filters.replacements {
+        text = pattern.replace_all(&text, replacement.as_str()).to_string();
+    }
+
+    if filters.collapse_blank_lines {
+        text = collapse_blank_lines(&text);
+    }
+    text
+}
+
+/// At most one blank line between paragraphs, leaving code blocks as they are
+fn collapse_blank_lines(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    let mut in_code = false;
+    let mut blank_run = 0;
+    for line in text.trim_matches('\n').lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if line.trim().is_empty() && !in_code {
+            blank_run += 1;
+            if blank_run > 1 {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
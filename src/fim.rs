@@ -0,0 +1,117 @@
+//! Fill-in-the-middle completion for the `fim` subcommand.
+//!
+//! The file is split at the requested position and the text before and after
+//! it go to the model as prefix and suffix, which needs a model whose
+//! template supports insertion (qwen2.5-coder, codellama:code, starcoder2,
+//! deepseek-coder). The proposal is printed and, with --apply, written into
+//! the file once confirmed.
+
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use crate::args::FimArgs;
+use crate::executor::AIExecutor;
+use crate::output::{self, status};
+use crate::text::{head, tail};
+
+/// Text sent on either side of the insertion point; models do best with the nearby code anyway
+const MAX_PREFIX_CHARS: usize = 12_000;
+const MAX_SUFFIX_CHARS: usize = 4_000;
+/// Lines of the file shown around the proposal
+const PREVIEW_LINES: usize = 3;
+
+pub async fn run(executor: &AIExecutor, args: FimArgs) -> Result<()> {
+    let text = fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read {}", args.file.display()))?;
+    let at = offset(&text, args.line, args.column)?;
+    let (prefix, suffix) = text.split_at(at);
+
+    let model = args.model.as_deref().unwrap_or(executor.get_model());
+    status!("{} Completing {}:{}:{} with {}...", "⚙".bright_blue(),
+        args.file.display(), args.line, args.column, model.bright_cyan());
+    let mut completion = executor.fill_in_middle(args.model.as_deref(), tail(prefix, MAX_PREFIX_CHARS), head(suffix, MAX_SUFFIX_CHARS))
+        .await
+        .context("Completion failed; the model needs a template that supports insertion (suffix)")?;
+
+    if completion.trim().is_empty() {
+        status!("{}", "The model proposed nothing to insert".yellow());
+        return Ok(());
+    }
+    // Whole lines inserted at the start of a line keep the line below on its own
+    if args.column == 1 && !suffix.is_empty() && !completion.ends_with('\n') {
+        completion.push('\n');
+    }
+
+    if !args.apply {
+        if output::is_quiet() {
+            print!("{}", completion);
+        } else {
+            preview(prefix, &completion, suffix);
+        }
+        return Ok(());
+    }
+
+    preview(prefix, &completion, suffix);
+    if !args.yes && !confirm(&format!("Insert into {}? [y/N]", args.file.display()))? {
+        println!("{}", "Not applied".bright_black());
+        return Ok(());
+    }
+    fs::write(&args.file, format!("{}{}{}", prefix, completion, suffix))
+        .with_context(|| format!("Failed to write {}", args.file.display()))?;
+    println!("{} Inserted {} line(s) into {}", "✓".bright_green(),
+        completion.lines().count(), args.file.display().to_string().bright_cyan());
+    Ok(())
+}
+
+/// Byte offset of a 1-based line and column; the line after the last one is the end of the file
+fn offset(text: &str, line: usize, column: usize) -> Result<usize> {
+    if line == 0 || column == 0 {
+        anyhow::bail!("Lines and columns count from 1");
+    }
+    let mut start = 0;
+    for _ in 1..line {
+        match text[start..].find('\n') {
+            Some(end) => start += end + 1,
+            None if start < text.len() => start = text.len(),
+            None => anyhow::bail!("The file has only {} line(s)", text.lines().count()),
+        }
+    }
+    let line_text = text[start..].split('\n').next().unwrap_or_default();
+    let within = line_text.char_indices().map(|(i, _)| i).chain([line_text.len()])
+        .nth(column - 1)
+        .with_context(|| format!("Line {} has only {} character(s)", line, line_text.chars().count()))?;
+    Ok(start + within)
+}
+
+/// The file around the insertion point, with the lines the completion touches in green
+fn preview(prefix: &str, completion: &str, suffix: &str) {
+    let result = format!("{}{}{}", prefix, completion, suffix);
+    let first = prefix.matches('\n').count();
+    let last = first + completion.trim_end_matches('\n').matches('\n').count();
+
+    println!();
+    for (i, line) in result.lines().enumerate()
+        .skip(first.saturating_sub(PREVIEW_LINES))
+        .take_while(|(i, _)| *i <= last + PREVIEW_LINES)
+    {
+        if (first..=last).contains(&i) {
+            println!("{} {}", format!("{:>5}+", i + 1).bright_green(), line.bright_green());
+        } else {
+            println!("{}  {}", format!("{:>5}", i + 1).bright_black(), line);
+        }
+    }
+    println!();
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!("Confirming needs a terminal; pass --yes to apply without asking");
+    }
+    print!("{} ", question.bright_yellow());
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
@@ -0,0 +1,262 @@
+//! GitHub issues and pull requests for the github_* tools, over the REST API.
+//!
+//! The token comes from "github.token" in config.json (usually a
+//! `keyring:` reference) or from GITHUB_TOKEN / GH_TOKEN. Public
+//! repositories can be read without one, at a lower rate limit. The
+//! repository defaults to the one the origin remote points to.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::{json, Value};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::GithubConfig;
+use crate::secrets;
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+const TIMEOUT: Duration = Duration::from_secs(30);
+const PAGE_SIZE: usize = 100;
+/// Pages fetched at most for one listing; the issues endpoint also returns pull requests
+const MAX_PAGES: usize = 5;
+const MAX_BODY_CHARS: usize = 8000;
+const MAX_COMMENT_CHARS: usize = 2000;
+const MAX_FILES: usize = 100;
+
+static SETTINGS: Mutex<Option<GithubConfig>> = Mutex::new(None);
+
+/// Applies the "github" config for the whole process
+pub fn init(config: &GithubConfig) {
+    *SETTINGS.lock().unwrap() = Some(config.clone());
+}
+
+/// What a listing returns
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Issues,
+    PullRequests,
+}
+
+/// Open, closed or all issues or pull requests, newest activity first
+pub async fn list(repo: Option<&str>, kind: Kind, state: &str, labels: Option<&str>, limit: usize) -> Result<String> {
+    let (api, repo) = (Api::new()?, repo_name(repo)?);
+    let path = match kind {
+        Kind::Issues => format!("/repos/{}/issues", repo),
+        Kind::PullRequests => format!("/repos/{}/pulls", repo),
+    };
+
+    let mut found = Vec::new();
+    for page in 1..=MAX_PAGES {
+        let mut query = vec![
+            ("state", state.to_string()),
+            ("sort", "updated".to_string()),
+            ("per_page", PAGE_SIZE.to_string()),
+            ("page", page.to_string()),
+        ];
+        if let Some(labels) = labels.filter(|_| kind == Kind::Issues) {
+            query.push(("labels", labels.to_string()));
+        }
+        let items = api.get(&path, &query).await?;
+        let items = items.as_array().context("Unexpected response from GitHub")?;
+        found.extend(items.iter()
+            .filter(|item| kind == Kind::PullRequests || item.get("pull_request").is_none())
+            .cloned());
+        if found.len() >= limit || items.len() < PAGE_SIZE {
+            break;
+        }
+    }
+    found.truncate(limit);
+
+    let noun = if kind == Kind::Issues { "issue" } else { "pull request" };
+    if found.is_empty() {
+        return Ok(format!("No {} {}s in {}", state, noun, repo));
+    }
+    let mut text = format!("{} {} {}(s) in {}, recently updated first:", found.len(), state, noun, repo);
+    for item in &found {
+        text.push('\n');
+        text.push_str(&summary_line(item));
+    }
+    Ok(text)
+}
+
+/// An issue or pull request with its description and comments; pull requests
+/// also get their branches and changed files
+pub async fn read(repo: Option<&str>, number: u64, with_comments: bool) -> Result<String> {
+    let (api, repo) = (Api::new()?, repo_name(repo)?);
+    let issue = api.get(&format!("/repos/{}/issues/{}", repo, number), &[]).await?;
+    let is_pull = issue.get("pull_request").is_some();
+
+    let mut text = format!("{} #{}: {}\nState: {}, by @{}, created {}, updated {}\n{}",
+        if is_pull { "Pull request" } else { "Issue" }, number, str_field(&issue, "title"),
+        str_field(&issue, "state"), str_field(&issue["user"], "login"),
+        date(&issue, "created_at"), date(&issue, "updated_at"), str_field(&issue, "html_url"));
+    let labels = names(&issue["labels"], "name");
+    if !labels.is_empty() {
+        text.push_str(&format!("\nLabels: {}", labels.join(", ")));
+    }
+    let assignees = names(&issue["assignees"], "login");
+    if !assignees.is_empty() {
+        text.push_str(&format!("\nAssignees: {}", assignees.join(", ")));
+    }
+
+    if is_pull {
+        let pull = api.get(&format!("/repos/{}/pulls/{}", repo, number), &[]).await?;
+        text.push_str(&format!("\nBranch: {} -> {}{}{}, {} file(s) changed, +{} -{}",
+            str_field(&pull["head"], "label"), str_field(&pull["base"], "ref"),
+            if pull["draft"].as_bool() == Some(true) { ", draft" } else { "" },
+            if pull["merged"].as_bool() == Some(true) { ", merged" } else { "" },
+            pull["changed_files"], pull["additions"], pull["deletions"]));
+
+        let files = api.get(&format!("/repos/{}/pulls/{}/files", repo, number),
+            &[("per_page", MAX_FILES.to_string())]).await?;
+        for file in files.as_array().into_iter().flatten() {
+            text.push_str(&format!("\n  {} (+{} -{})", str_field(file, "filename"), file["additions"], file["deletions"]));
+        }
+    }
+
+    let body = issue["body"].as_str().unwrap_or_default().trim();
+    text.push_str(&format!("\n\n{}", if body.is_empty() { "(no description)".to_string() } else { truncate(body, MAX_BODY_CHARS) }));
+
+    let count = issue["comments"].as_u64().unwrap_or(0);
+    if with_comments && count > 0 {
+        let comments = api.get(&format!("/repos/{}/issues/{}/comments", repo, number),
+            &[("per_page", PAGE_SIZE.to_string())]).await?;
+        text.push_str(&format!("\n\nComments ({}):", count));
+        for comment in comments.as_array().into_iter().flatten() {
+            text.push_str(&format!("\n\n@{} on {}:\n{}", str_field(&comment["user"], "login"),
+                date(comment, "created_at"), truncate(str_field(comment, "body").trim(), MAX_COMMENT_CHARS)));
+        }
+    } else if count > 0 {
+        text.push_str(&format!("\n\n{} comment(s) not shown", count));
+    }
+    Ok(text)
+}
+
+/// Posts a comment on an issue or pull request and returns its URL
+pub async fn comment(repo: Option<&str>, number: u64, body: &str) -> Result<String> {
+    let (api, repo) = (Api::new()?, repo_name(repo)?);
+    let comment = api.post(&format!("/repos/{}/issues/{}/comments", repo, number), &json!({ "body": body })).await?;
+    Ok(format!("Comment posted on {}#{}: {}", repo, number, str_field(&comment, "html_url")))
+}
+
+/// Uploads `content` as a secret gist and returns its URL
+pub async fn create_gist(filename: &str, content: &str, description: &str) -> Result<String> {
+    let api = Api::new()?;
+    let gist = api.post("/gists", &json!({
+        "description": description,
+        "public": false,
+        "files": { filename: { "content": content } },
+    })).await?;
+    Ok(str_field(&gist, "html_url").to_string())
+}
+
+struct Api {
+    client: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl Api {
+    fn new() -> Result<Self> {
+        let config = SETTINGS.lock().unwrap().clone().unwrap_or_default();
+        let token = match &config.token {
+            Some(token) => Some(secrets::resolve(token)?),
+            None => std::env::var("GITHUB_TOKEN").or_else(|_| std::env::var("GH_TOKEN")).ok(),
+        };
+        let client = reqwest::Client::builder()
+            .timeout(TIMEOUT)
+            .user_agent(concat!("ai-chat-cli/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+
+        Ok(Self {
+            client,
+            base_url: config.api_url.unwrap_or_else(|| DEFAULT_API_URL.to_string()).trim_end_matches('/').to_string(),
+            token: token.filter(|t| !t.is_empty()),
+        })
+    }
+
+    async fn get(&self, path: &str, query: &[(&str, String)]) -> Result<Value> {
+        self.send(path, self.client.get(format!("{}{}", self.base_url, path)).query(query)).await
+    }
+
+    async fn post(&self, path: &str, body: &Value) -> Result<Value> {
+        self.send(path, self.client.post(format!("{}{}", self.base_url, path)).json(body)).await
+    }
+
+    async fn send(&self, path: &str, request: reqwest::RequestBuilder) -> Result<Value> {
+        let mut request = request
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.context("Failed to reach the GitHub API")?;
+
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if status.is_success() {
+            return Ok(body);
+        }
+        let message = body["message"].as_str().unwrap_or("no details");
+        let hint = match status.as_u16() {
+            401 => "; check the token in \"github.token\" or GITHUB_TOKEN",
+            403 | 404 if self.token.is_none() => "; private repositories, comments, gists and higher rate limits need a token",
+            _ => "",
+        };
+        anyhow::bail!("GitHub returned {} for {}: {}{}", status, path, message, hint)
+    }
+}
+
+/// The repository given, or else the one of the origin remote
+fn repo_name(repo: Option<&str>) -> Result<String> {
+    match repo.filter(|r| !r.trim().is_empty()) {
+        Some(repo) => Ok(repo.trim().to_string()),
+        None => origin_repo().context("No repository given and the origin remote is not on GitHub; pass repo as owner/name"),
+    }
+}
+
+/// owner/name of the origin remote, from an https or ssh URL
+fn origin_repo() -> Option<String> {
+    let output = Command::new("git").args(["remote", "get-url", "origin"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let pattern = Regex::new(r"[:/]([^/:]+)/([^/]+?)(?:\.git)?/?$").ok()?;
+    let captures = pattern.captures(&url)?;
+    Some(format!("{}/{}", &captures[1], &captures[2]))
+}
+
+/// `#12 [open] Title (bug, ui) @user, updated 2026-10-01, 3 comment(s)`
+fn summary_line(item: &Value) -> String {
+    let labels = names(&item["labels"], "name");
+    let labels = if labels.is_empty() { String::new() } else { format!(" ({})", labels.join(", ")) };
+    let draft = if item["draft"].as_bool() == Some(true) { " draft" } else { "" };
+    let comments = item["comments"].as_u64().map(|n| format!(", {} comment(s)", n)).unwrap_or_default();
+    format!("#{} [{}{}] {}{} @{}, updated {}{}", item["number"], str_field(item, "state"), draft,
+        str_field(item, "title"), labels, str_field(&item["user"], "login"), date(item, "updated_at"), comments)
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value[key].as_str().unwrap_or_default()
+}
+
+/// The day of an ISO timestamp
+fn date<'a>(value: &'a Value, key: &str) -> &'a str {
+    let timestamp = str_field(value, key);
+    timestamp.get(..10).unwrap_or(timestamp)
+}
+
+fn names(list: &Value, key: &str) -> Vec<String> {
+    list.as_array().into_iter().flatten()
+        .filter_map(|item| item[key].as_str().map(str::to_string))
+        .collect()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}\n... (truncated)", &text[..end]),
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,111 @@
+//! The `run` subcommand: the agent loop without a user, for CI bots and
+//! editor integrations.
+//!
+//! With `--output-format jsonl`, stdout carries nothing but one JSON event
+//! per line, ending with a "final" or "error" event. Tools that need approval
+//! are refused, since there is nobody to ask, and the model is told so.
+
+use anyhow::{Context, Result};
+use colored::*;
+
+use crate::accessibility;
+use crate::agent::{self, Event};
+use crate::args::{RunArgs, RunOutputFormat};
+use crate::config::AppConfig;
+use crate::executor::AIExecutor;
+use crate::mcp_manager::McpManager;
+use crate::ollama::Message;
+use crate::output;
+use crate::policy::Approval;
+use crate::repo_map;
+
+pub async fn run(mut executor: AIExecutor, config: &AppConfig, args: RunArgs) -> Result<()> {
+    let jsonl = args.output_format == RunOutputFormat::Jsonl;
+    let quiet = output::is_quiet();
+    // Status lines would corrupt the stream, or end up in the answer
+    output::init(true, true);
+    if let Some(model) = args.model {
+        executor.set_model(model);
+    }
+
+    let report = |event: Event| match event {
+        // Whole turns are reported, not their pieces
+        Event::Token { .. } => {}
+        event if jsonl => emit(&event),
+        event if !quiet => print_event(&event),
+        _ => {}
+    };
+    let result = work(&executor, config, &args.task, args.max_iterations, report).await;
+    match &result {
+        Err(e) if jsonl => emit(&Event::Error { message: format!("{:#}", e) }),
+        Ok(answer) if !jsonl => println!("{}", answer.trim()),
+        _ => {}
+    }
+    result.map(|_| ())
+}
+
+async fn work(executor: &AIExecutor, config: &AppConfig, task: &str, max_iterations: usize, report: impl Fn(Event) + Sync) -> Result<String> {
+    let mut mcp = McpManager::new().await
+        .context("Failed to initialize tools")?;
+    mcp.set_agent_mode(true);
+
+    let mut history: Vec<Message> = config.language_prompt().into_iter()
+        .chain(repo_map::startup_context(&config.repo_map))
+        .chain(mcp.tools_prompt())
+        .map(|content| Message {
+            role: "system".to_string(),
+            content,
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        })
+        .collect();
+    history.push(Message {
+        role: "user".to_string(),
+        content: task.to_string(),
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    });
+
+    report(Event::Start { task: task.to_string(), model: executor.get_model().to_string() });
+    // Nobody is there to approve a call
+    let answer = agent::run(executor, &mut mcp, &mut history, max_iterations, &report, async |_| Approval::Deny).await;
+    mcp.shutdown().await;
+    answer
+}
+
+fn emit(event: &Event) {
+    println!("{}", serde_json::to_string(event).unwrap_or_default());
+}
+
+/// Progress for people watching a text run, on stderr so stdout holds only the answer
+fn print_event(event: &Event) {
+    if accessibility::enabled() {
+        return announce_event(event);
+    }
+    match event {
+        Event::Start { model, .. } => eprintln!("{} Working with {}", "⚙".bright_blue(), model.bright_cyan()),
+        Event::ToolCall { iteration, name, arguments } => eprintln!("{} [{}] {} {}",
+            "⚙".bright_blue(), iteration, name.bright_cyan(), arguments.to_string().bright_black()),
+        Event::ToolResult { output, is_error: true, .. } => eprintln!("  {} {}", "✗".bright_red(), first_line(output)),
+        Event::ToolResult { output, elapsed_ms, .. } => eprintln!("  {} {} {}",
+            "✓".bright_green(), first_line(output), format!("({}ms)", elapsed_ms).bright_black()),
+        Event::Token { .. } | Event::Turn { .. } | Event::Final { .. } | Event::Error { .. } => {}
+    }
+}
+
+/// `print_event` as announcements for accessible mode
+fn announce_event(event: &Event) {
+    match event {
+        Event::Start { model, .. } => eprintln!("Working with {}", model),
+        Event::ToolCall { iteration, name, arguments } => eprintln!("Tool request: {} {} (step {})", name, arguments, iteration),
+        Event::ToolResult { output, is_error: true, .. } => eprintln!("Tool result: failed. {}", first_line(output)),
+        Event::ToolResult { output, elapsed_ms, .. } => eprintln!("Tool result: succeeded in {}ms. {}", elapsed_ms, first_line(output)),
+        Event::Token { .. } | Event::Turn { .. } | Event::Final { .. } | Event::Error { .. } => {}
+    }
+}
+
+fn first_line(text: &str) -> String {
+    let lines = text.trim().lines().count();
+    let first = text.trim().lines().next().unwrap_or_default();
+    if lines > 1 { format!("{} (+{} lines)", first, lines - 1) } else { first.to_string() }
+}
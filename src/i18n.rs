@@ -0,0 +1,104 @@
+//! The catalog of user-facing messages, in English, Korean and Japanese.
+//! It covers startup and everything the interactive chat prints; the
+//! subcommands are English.
+//!
+//! The locale is "locale" in config.json, or else the language of LC_ALL,
+//! LC_MESSAGES or LANG, so `LANG=ko_KR.UTF-8` is enough. The built-in
+//! catalogs live in locales/; a ~/.ai-chat-cli/locales/<locale>.json adds
+//! a language or replaces some of a built-in one's messages. Messages a
+//! catalog lacks are shown in English.
+//!
+//! Placeholders are named, e.g. "Using model: {model}", because the word
+//! order differs between languages. Command names, tool output and the
+//! model's replies are never translated.
+
+use colored::*;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const BUILT_IN: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.json")),
+    ("ko", include_str!("../locales/ko.json")),
+    ("ja", include_str!("../locales/ja.json")),
+];
+
+struct Catalog {
+    messages: HashMap<String, String>,
+    english: HashMap<String, String>,
+}
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// Picks the locale for the whole process; `locale` is the configured one
+pub fn init(locale: Option<&str>) {
+    let locale = locale.map(str::to_string)
+        .or_else(locale_from_env)
+        .map(|locale| language(&locale))
+        .unwrap_or_else(|| "en".to_string());
+    let _ = CATALOG.set(load(&locale));
+}
+
+/// The message for `key` in the current locale
+pub fn text(key: &'static str) -> &'static str {
+    let catalog = CATALOG.get_or_init(|| load("en"));
+    catalog.messages.get(key)
+        .or_else(|| catalog.english.get(key))
+        .map_or(key, String::as_str)
+}
+
+/// `template` with each `{name}` replaced by its value
+pub fn fill(template: &str, values: &[(&str, String)]) -> String {
+    values.iter().fold(template.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+/// A message from the catalog, e.g. `tr!("startup.using_model", model = name)`
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::i18n::text($key)
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::fill($crate::i18n::text($key), &[$((stringify!($name), $value.to_string())),+])
+    };
+}
+
+pub(crate) use tr;
+
+/// The first locale variable set, as the C library reads them
+fn locale_from_env() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// "ko_KR.UTF-8" -> "ko"; "C" and "POSIX" are English
+fn language(locale: &str) -> String {
+    let language = locale.split(['_', '-', '.', '@']).next().unwrap_or_default().to_lowercase();
+    match language.as_str() {
+        "" | "c" | "posix" => "en".to_string(),
+        _ => language,
+    }
+}
+
+fn load(locale: &str) -> Catalog {
+    let english = parse(BUILT_IN[0].1).unwrap_or_default();
+    let mut messages = BUILT_IN.iter()
+        .find(|(name, _)| *name == locale)
+        .and_then(|(_, catalog)| parse(catalog).ok())
+        .unwrap_or_default();
+
+    if let Some(path) = dirs::home_dir().map(|home| home.join(".ai-chat-cli").join("locales").join(format!("{}.json", locale)))
+        && let Ok(content) = std::fs::read_to_string(&path)
+    {
+        match parse(&content) {
+            Ok(custom) => messages.extend(custom),
+            Err(e) => eprintln!("{} Ignoring {}: {}", "Warning:".bright_yellow(), path.display(), e),
+        }
+    }
+    Catalog { messages, english }
+}
+
+fn parse(catalog: &str) -> serde_json::Result<HashMap<String, String>> {
+    serde_json::from_str(catalog)
+}
@@ -0,0 +1,72 @@
+//! Images attached to prompts for vision models, from files (/image) or the
+//! system clipboard (/paste-image). Ollama takes them base64-encoded.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::fs;
+use std::path::Path;
+
+/// Larger files are almost certainly not meant for a chat prompt
+const MAX_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+pub struct Attachment {
+    /// Base64 of the encoded image file
+    pub data: String,
+    /// Shown when the image is attached, e.g. "screenshot.png" or "1280×720 from clipboard"
+    pub label: String,
+}
+
+/// Reads a PNG, JPEG, GIF or WebP file
+pub fn from_file(path: &Path) -> Result<Attachment> {
+    let size = fs::metadata(path)
+        .context(format!("Failed to read {}", path.display()))?
+        .len();
+    if size > MAX_FILE_BYTES {
+        anyhow::bail!("{} is too large ({} MB)", path.display(), size / (1024 * 1024));
+    }
+
+    let bytes = fs::read(path)
+        .context(format!("Failed to read {}", path.display()))?;
+    if !is_image(&bytes) {
+        anyhow::bail!("{} is not a PNG, JPEG, GIF or WebP image", path.display());
+    }
+
+    Ok(Attachment {
+        data: STANDARD.encode(&bytes),
+        label: path.display().to_string(),
+    })
+}
+
+/// Takes the image on the clipboard and encodes it as PNG
+pub fn from_clipboard() -> Result<Attachment> {
+    let mut clipboard = arboard::Clipboard::new()
+        .map_err(|e| anyhow::anyhow!("Could not open the clipboard: {}", e))?;
+    let image = clipboard.get_image().map_err(|e| match e {
+        arboard::Error::ContentNotAvailable => anyhow::anyhow!("The clipboard holds no image"),
+        e => anyhow::anyhow!("Could not read the clipboard: {}", e),
+    })?;
+
+    let mut png = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png, image.width as u32, image.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&image.bytes)
+            .context("Failed to encode the clipboard image")?;
+    }
+
+    Ok(Attachment {
+        data: STANDARD.encode(&png),
+        label: format!("{}×{} from clipboard", image.width, image.height),
+    })
+}
+
+/// Checks the magic bytes of the formats vision models accept
+fn is_image(bytes: &[u8]) -> bool {
+    matches!(bytes,
+        [0x89, b'P', b'N', b'G', ..]
+        | [0xFF, 0xD8, 0xFF, ..]
+        | [b'G', b'I', b'F', b'8', ..]
+        | [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..])
+}
@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use crate::args::ImportArgs;
+use crate::ollama::Message;
+use crate::session::Session;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImportSource {
+    /// ChatGPT data export (zip or conversations.json)
+    Chatgpt,
+    /// Claude data export (zip or conversations.json)
+    Claude,
+}
+
+impl ImportSource {
+    fn name(&self) -> &'static str {
+        match self {
+            ImportSource::Chatgpt => "chatgpt",
+            ImportSource::Claude => "claude",
+        }
+    }
+}
+
+pub fn run(args: ImportArgs) -> Result<()> {
+    let json = read_conversations(&args.path)?;
+    let conversations: Vec<Value> = serde_json::from_str(&json)
+        .context("conversations.json must contain an array of conversations")?;
+
+    let sessions: Vec<Session> = conversations.iter()
+        .filter_map(|c| match args.from {
+            ImportSource::Chatgpt => parse_chatgpt(c),
+            ImportSource::Claude => parse_claude(c),
+        })
+        .collect();
+
+    fs::create_dir_all(&args.out_dir)
+        .context(format!("Failed to create {}", args.out_dir.display()))?;
+
+    for (i, session) in sessions.iter().enumerate() {
+        let slug = slugify(session.title.as_deref().unwrap_or("untitled"));
+        let path = args.out_dir.join(format!("{}-{:04}-{}.json", args.from.name(), i + 1, slug));
+        session.save(&path)?;
+    }
+
+    println!("{} Imported {} of {} conversation(s) into {}",
+        "✓".bright_green(), sessions.len(), conversations.len(), args.out_dir.display());
+    println!("Continue one with {}", "/load <file>".bright_cyan());
+
+    Ok(())
+}
+
+/// Reads conversations.json from an export archive, or directly if given the JSON file
+fn read_conversations(path: &Path) -> Result<String> {
+    if path.extension().is_some_and(|ext| ext == "json") {
+        return fs::read_to_string(path)
+            .context(format!("Failed to read {}", path.display()));
+    }
+
+    let file = File::open(path)
+        .context(format!("Failed to open {}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .context("Export is neither a zip archive nor a JSON file")?;
+
+    let name = archive.file_names()
+        .find(|n| n.rsplit('/').next() == Some("conversations.json"))
+        .map(String::from)
+        .context("Export archive does not contain conversations.json")?;
+
+    let mut json = String::new();
+    archive.by_name(&name)?.read_to_string(&mut json)?;
+    Ok(json)
+}
+
+fn session(title: Option<&str>, source: ImportSource, messages: Vec<Message>) -> Option<Session> {
+    if messages.is_empty() {
+        return None;
+    }
+
+    Some(Session {
+        title: title.map(String::from),
+        tags: vec!["imported".to_string(), source.name().to_string()],
+        messages,
+        ..Default::default()
+    })
+}
+
+/// ChatGPT stores each conversation as a tree of nodes; the visible thread
+/// is the path from `current_node` back to the root
+fn parse_chatgpt(conversation: &Value) -> Option<Session> {
+    let mapping = conversation["mapping"].as_object()?;
+    let mut node = conversation["current_node"].as_str();
+    let mut messages = Vec::new();
+
+    while let Some(id) = node {
+        let entry = mapping.get(id)?;
+        let message = &entry["message"];
+
+        let role = message["author"]["role"].as_str().unwrap_or("");
+        let hidden = message["metadata"]["is_visually_hidden_from_conversation"].as_bool() == Some(true);
+
+        if matches!(role, "user" | "assistant" | "system") && !hidden {
+            let content: Vec<&str> = message["content"]["parts"].as_array()
+                .map(|parts| parts.iter().filter_map(|p| p.as_str()).collect())
+                .unwrap_or_default();
+            let content = content.join("\n");
+
+            if !content.trim().is_empty() {
+                messages.push(Message {
+                    role: role.to_string(),
+                    content,
+                    images: Vec::new(),
+                    tool_calls: Vec::new(),
+                });
+            }
+        }
+
+        node = entry["parent"].as_str();
+    }
+
+    messages.reverse();
+    session(conversation["title"].as_str(), ImportSource::Chatgpt, messages)
+}
+
+fn parse_claude(conversation: &Value) -> Option<Session> {
+    let messages = conversation["chat_messages"].as_array()?
+        .iter()
+        .filter_map(|m| {
+            let role = match m["sender"].as_str()? {
+                "human" => "user",
+                "assistant" => "assistant",
+                _ => return None,
+            };
+
+            let mut content = m["text"].as_str().unwrap_or("").to_string();
+            if content.trim().is_empty() {
+                content = m["content"].as_array()
+                    .map(|blocks| {
+                        blocks.iter()
+                            .filter(|b| b["type"] == "text")
+                            .filter_map(|b| b["text"].as_str())
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    })
+                    .unwrap_or_default();
+            }
+
+            (!content.trim().is_empty()).then(|| Message {
+                role: role.to_string(),
+                content,
+                images: Vec::new(),
+                tool_calls: Vec::new(),
+            })
+        })
+        .collect();
+
+    session(conversation["name"].as_str(), ImportSource::Claude, messages)
+}
+
+fn slugify(title: &str) -> String {
+    let slug: String = title.chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+
+    let slug = slug.split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    let slug: String = slug.chars().take(40).collect();
+    if slug.is_empty() { "untitled".to_string() } else { slug }
+}
@@ -0,0 +1,76 @@
+//! Files changed by the built-in editing tools, so the changes can be undone.
+//!
+//! Before `edit_file`, `write_file` or `extract_archive` touches a file, its
+//! contents (or the fact that it did not exist) are recorded here. A
+//! `/checkpoint` remembers how far the journal went, and `/rollback` puts
+//! every file changed since back the way it was. Changes made through
+//! `bash`, `cargo_fmt` or MCP servers are not seen.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+struct Entry {
+    seq: u64,
+    path: PathBuf,
+    /// `None` when the file was created
+    before: Option<Vec<u8>>,
+}
+
+struct Journal {
+    entries: Vec<Entry>,
+    next: u64,
+}
+
+static JOURNAL: Mutex<Journal> = Mutex::new(Journal { entries: Vec::new(), next: 0 });
+
+/// Records `path` as it is now, before a tool writes to it
+pub fn record(path: &Path) {
+    let before = fs::read(path).ok();
+    let mut journal = JOURNAL.lock().unwrap();
+    let seq = journal.next;
+    journal.next += 1;
+    journal.entries.push(Entry { seq, path: path.to_path_buf(), before });
+}
+
+/// Files changed since the session started and not rolled back, each once
+pub fn changed_files() -> Vec<PathBuf> {
+    let journal = JOURNAL.lock().unwrap();
+    let mut files: Vec<PathBuf> = Vec::new();
+    for entry in &journal.entries {
+        if !files.contains(&entry.path) {
+            files.push(entry.path.clone());
+        }
+    }
+    files
+}
+
+/// The position of the journal now, for a later `rollback`
+pub fn mark() -> u64 {
+    JOURNAL.lock().unwrap().next
+}
+
+/// Restores every file changed since `mark`, latest change first, and
+/// returns the files, each once. Files that did not exist are removed.
+pub fn rollback(mark: u64) -> Result<Vec<PathBuf>> {
+    let mut journal = JOURNAL.lock().unwrap();
+    let mut restored: Vec<PathBuf> = Vec::new();
+    // An entry stays until its file is restored, so a failed rollback can be retried
+    while let Some(entry) = journal.entries.last().filter(|entry| entry.seq >= mark) {
+        match &entry.before {
+            Some(content) => fs::write(&entry.path, content)
+                .context(format!("Failed to restore {}", entry.path.display()))?,
+            None => if entry.path.exists() {
+                fs::remove_file(&entry.path)
+                    .context(format!("Failed to remove {}", entry.path.display()))?;
+            },
+        }
+        let entry = journal.entries.pop().unwrap();
+        if !restored.contains(&entry.path) {
+            restored.push(entry.path);
+        }
+    }
+    restored.reverse();
+    Ok(restored)
+}
@@ -0,0 +1,83 @@
+//! Orderly shutdown for the interactive chat.
+//!
+//! Exit paths call into here instead of relying on `Drop`, which cannot
+//! await and used to spin up a second runtime inside the running one.
+//! SIGTERM and SIGHUP take the same path as `/quit`, so MCP servers are
+//! not left running when the process is killed or its terminal closes.
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use crate::mcp_manager::McpManager;
+
+/// Terminal settings captured before the line editor touches them.
+///
+/// Readline runs on its own thread and may still hold the terminal in raw
+/// mode when the chat ends (e.g. `/quit` sent from the queue while a
+/// prompt is open), so the original mode is put back explicitly.
+pub struct TerminalState {
+    #[cfg(unix)]
+    saved: Option<libc::termios>,
+}
+
+impl TerminalState {
+    pub fn capture() -> Self {
+        #[cfg(unix)]
+        {
+            let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+            // SAFETY: tcgetattr only writes into the provided struct and
+            // reports failure (e.g. stdin is not a terminal) through -1
+            let saved = unsafe {
+                (libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) == 0)
+                    .then(|| termios.assume_init())
+            };
+            Self { saved }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+
+    pub fn restore(&self) {
+        #[cfg(unix)]
+        if let Some(termios) = &self.saved {
+            // SAFETY: the struct came from tcgetattr on the same descriptor
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, termios);
+            }
+        }
+    }
+}
+
+/// Stops MCP servers and hands the terminal back in its original state
+pub async fn shutdown(mcp_manager: Option<McpManager>, terminal: &TerminalState) {
+    if let Some(mut mcp) = mcp_manager {
+        mcp.shutdown().await;
+    }
+    terminal.restore();
+}
+
+/// Resolves with the signal's name once the process is asked to stop
+pub async fn terminated() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let (Ok(mut term), Ok(mut hup)) = (signal(SignalKind::terminate()), signal(SignalKind::hangup())) else {
+            return std::future::pending().await;
+        };
+        tokio::select! {
+            _ = term.recv() => "SIGTERM",
+            _ = hup.recv() => "SIGHUP",
+        }
+    }
+    #[cfg(not(unix))]
+    std::future::pending().await
+}
+
+/// Where conversations open at the time of a signal are saved
+pub fn sessions_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .context("Could not find home directory")?;
+
+    Ok(home.join(".ai-chat-cli").join("sessions"))
+}
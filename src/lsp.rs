@@ -0,0 +1,382 @@
+//! Bridge to language servers for the get_diagnostics tool.
+//!
+//! Servers are configured under "languageServers" in config.json, each with
+//! the file extensions it handles. A server is started on the first request
+//! for one of its files, rooted at the file's git repository, and kept
+//! running until shutdown. The file is (re)sent to the server as it is on
+//! disk, so an edit made by a tool is seen immediately; diagnostics are
+//! pulled when the server supports it and otherwise awaited from its
+//! publishDiagnostics notifications.
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{Notify, oneshot};
+
+use crate::config::LanguageServerConfig;
+use crate::repo_map;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Servers often publish in rounds (syntax first, then a full check); later
+/// rounds are awaited until none has come for this long
+const SETTLE: Duration = Duration::from_millis(1500);
+
+static SETTINGS: Mutex<Option<HashMap<String, LanguageServerConfig>>> = Mutex::new(None);
+/// Running servers by configured name and project root
+static SERVERS: tokio::sync::Mutex<Vec<LanguageServer>> = tokio::sync::Mutex::const_new(Vec::new());
+
+/// Applies the "languageServers" config for the whole process
+pub fn init(config: &HashMap<String, LanguageServerConfig>) {
+    *SETTINGS.lock().unwrap() = Some(config.clone());
+}
+
+/// State shared with the task reading the server's output
+#[derive(Default)]
+struct Shared {
+    pending: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
+    /// Latest published diagnostics by file path
+    published: Mutex<HashMap<PathBuf, Vec<Value>>>,
+    updated: Notify,
+}
+
+struct LanguageServer {
+    name: String,
+    root: PathBuf,
+    process: Child,
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    shared: Arc<Shared>,
+    next_id: u64,
+    /// Open documents and their version
+    versions: HashMap<PathBuf, i64>,
+    /// Whether the server answers textDocument/diagnostic requests
+    pull: bool,
+    timeout: Duration,
+}
+
+/// Diagnostics of the file at `path`, formatted one per line
+pub async fn diagnostics(path: &Path) -> Result<String> {
+    let path = std::fs::canonicalize(path)
+        .context(format!("Failed to read {}", path.display()))?;
+    let extension = path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
+    let (name, config) = SETTINGS.lock().unwrap().iter().flatten()
+        .find(|(_, config)| config.extensions.iter().any(|e| e.trim_start_matches('.') == extension))
+        .map(|(name, config)| (name.clone(), config.clone()))
+        .with_context(|| format!(
+            "No language server configured for .{} files; add one under \"languageServers\" in ~/.ai-chat-cli/config.json",
+            extension))?;
+    let root = repo_map::project_root(&path);
+    let text = std::fs::read_to_string(&path)
+        .context(format!("Failed to read {}", path.display()))?;
+
+    let mut servers = SERVERS.lock().await;
+    let index = match servers.iter().position(|s| s.name == name && s.root == root) {
+        Some(index) => index,
+        None => {
+            servers.push(LanguageServer::start(&name, &config, &root).await?);
+            servers.len() - 1
+        }
+    };
+    let server = &mut servers[index];
+
+    match server.check(&path, &text, config.language_id.as_deref()).await {
+        Ok(Some(items)) => Ok(format_diagnostics(&path, &root, items)),
+        Ok(None) => Ok(format!("Language server '{}' published no diagnostics for {} within {}s; it may still be indexing, try again shortly",
+            name, path.display(), server.timeout.as_secs())),
+        Err(e) => {
+            // A server that died or stopped answering is started afresh next time
+            let mut server = servers.remove(index);
+            let _ = server.process.kill().await;
+            Err(e)
+        }
+    }
+}
+
+/// Asks running servers to exit; called on shutdown
+pub async fn shutdown() {
+    for mut server in SERVERS.lock().await.drain(..) {
+        let _ = tokio::time::timeout(Duration::from_secs(2), server.request("shutdown", Value::Null)).await;
+        let _ = server.notify("exit", Value::Null).await;
+        if tokio::time::timeout(Duration::from_millis(500), server.process.wait()).await.is_err() {
+            let _ = server.process.kill().await;
+        }
+    }
+}
+
+impl LanguageServer {
+    async fn start(name: &str, config: &LanguageServerConfig, root: &Path) -> Result<Self> {
+        let mut process = Command::new(&config.command)
+            .args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start language server '{}' ({})", name, config.command))?;
+
+        let stdin = Arc::new(tokio::sync::Mutex::new(process.stdin.take().context("Failed to get stdin")?));
+        let stdout = process.stdout.take().context("Failed to get stdout")?;
+        let shared = Arc::new(Shared::default());
+        tokio::spawn(read_messages(stdout, stdin.clone(), shared.clone()));
+
+        let mut server = Self {
+            name: name.to_string(),
+            root: root.to_path_buf(),
+            process,
+            stdin,
+            shared,
+            next_id: 1,
+            versions: HashMap::new(),
+            pull: false,
+            timeout: config.timeout_seconds.map(Duration::from_secs).unwrap_or(DEFAULT_TIMEOUT),
+        };
+
+        let params = json!({
+            "processId": std::process::id(),
+            "rootUri": file_uri(root),
+            "workspaceFolders": [{"uri": file_uri(root), "name": root.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()}],
+            "clientInfo": {"name": "ai-chat-cli", "version": env!("CARGO_PKG_VERSION")},
+            "capabilities": {
+                "textDocument": {
+                    "synchronization": {"didSave": true},
+                    "publishDiagnostics": {"relatedInformation": false},
+                    "diagnostic": {"dynamicRegistration": false}
+                },
+                "workspace": {"configuration": true, "workspaceFolders": true}
+            },
+            "initializationOptions": config.initialization_options.clone().unwrap_or(Value::Null),
+        });
+        let result = tokio::time::timeout(server.timeout, server.request("initialize", params)).await
+            .map_err(|_| anyhow::anyhow!("Language server '{}' did not answer initialize within {}s", name, server.timeout.as_secs()))??;
+        server.pull = !result["capabilities"]["diagnosticProvider"].is_null();
+        server.notify("initialized", json!({})).await?;
+        Ok(server)
+    }
+
+    /// Sends the current text of `path` and collects its diagnostics; `None`
+    /// when a push-only server published nothing in time
+    async fn check(&mut self, path: &Path, text: &str, language_id: Option<&str>) -> Result<Option<Vec<Value>>> {
+        let uri = file_uri(path);
+        self.shared.published.lock().unwrap().remove(path);
+
+        match self.versions.get_mut(path) {
+            Some(version) => {
+                *version += 1;
+                let params = json!({
+                    "textDocument": {"uri": uri, "version": *version},
+                    "contentChanges": [{"text": text}],
+                });
+                self.notify("textDocument/didChange", params).await?;
+            }
+            None => {
+                let language_id = language_id.map(str::to_string).unwrap_or_else(|| language_of(path));
+                let params = json!({
+                    "textDocument": {"uri": uri, "languageId": language_id, "version": 1, "text": text},
+                });
+                self.notify("textDocument/didOpen", params).await?;
+                self.versions.insert(path.to_path_buf(), 1);
+            }
+        }
+        // Some servers, rust-analyzer among them, only run the full check on save
+        self.notify("textDocument/didSave", json!({"textDocument": {"uri": uri}, "text": text})).await?;
+
+        if self.pull {
+            let timeout = self.timeout;
+            let request = self.request("textDocument/diagnostic", json!({"textDocument": {"uri": uri}}));
+            let result = tokio::time::timeout(timeout, request).await
+                .map_err(|_| anyhow::anyhow!("Language server '{}' did not answer within {}s", self.name, self.timeout.as_secs()))??;
+            let items = result["items"].as_array().cloned().unwrap_or_default();
+            // Servers that pull only syntax errors may still publish the rest
+            let published = self.await_published(path, SETTLE).await;
+            return Ok(Some(if items.is_empty() { published.unwrap_or_default() } else { items }));
+        }
+
+        Ok(self.await_published(path, self.timeout).await)
+    }
+
+    /// Waits up to `timeout` for the first publication for `path`, then for later ones until they settle
+    async fn await_published(&self, path: &Path, timeout: Duration) -> Option<Vec<Value>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut latest = None;
+        loop {
+            let updated = self.shared.updated.notified();
+            if let Some(items) = self.shared.published.lock().unwrap().get(path) {
+                latest = Some(items.clone());
+            }
+            let wait = if latest.is_some() {
+                SETTLE.min(deadline.saturating_duration_since(tokio::time::Instant::now()))
+            } else {
+                deadline.saturating_duration_since(tokio::time::Instant::now())
+            };
+            if tokio::time::timeout(wait, updated).await.is_err() {
+                return latest;
+            }
+        }
+    }
+
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let (tx, rx) = oneshot::channel();
+        self.shared.pending.lock().unwrap().insert(id, tx);
+
+        write_message(&self.stdin, &json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})).await?;
+        let response = rx.await
+            .map_err(|_| anyhow::anyhow!("Language server '{}' exited", self.name))?;
+        if let Some(error) = response.get("error") {
+            anyhow::bail!("Language server '{}' failed {}: {}", self.name, method, error["message"].as_str().unwrap_or("unknown error"));
+        }
+        Ok(response["result"].clone())
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        write_message(&self.stdin, &json!({"jsonrpc": "2.0", "method": method, "params": params})).await
+    }
+}
+
+async fn write_message(stdin: &tokio::sync::Mutex<ChildStdin>, message: &Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    let mut stdin = stdin.lock().await;
+    stdin.write_all(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).as_bytes()).await?;
+    stdin.flush().await?;
+    Ok(())
+}
+
+/// Routes responses to their requests, stores published diagnostics and
+/// answers the server's own requests so it does not wait on us
+async fn read_messages(stdout: ChildStdout, stdin: Arc<tokio::sync::Mutex<ChildStdin>>, shared: Arc<Shared>) {
+    let mut reader = BufReader::new(stdout);
+    while let Ok(Some(message)) = read_message(&mut reader).await {
+        let method = message["method"].as_str();
+        match (message.get("id"), method) {
+            (Some(id), None) => {
+                if let Some(sender) = id.as_u64().and_then(|id| shared.pending.lock().unwrap().remove(&id)) {
+                    let _ = sender.send(message);
+                }
+            }
+            (Some(id), Some(method)) => {
+                // Settings are left to the server's defaults; everything else is acknowledged
+                let result = match method {
+                    "workspace/configuration" => Value::Array(vec![Value::Null; message["params"]["items"].as_array().map_or(0, Vec::len)]),
+                    _ => Value::Null,
+                };
+                let _ = write_message(&stdin, &json!({"jsonrpc": "2.0", "id": id, "result": result})).await;
+            }
+            (None, Some("textDocument/publishDiagnostics")) => {
+                let params = &message["params"];
+                if let Some(path) = params["uri"].as_str().and_then(uri_path) {
+                    let items = params["diagnostics"].as_array().cloned().unwrap_or_default();
+                    shared.published.lock().unwrap().insert(path, items);
+                    shared.updated.notify_waiters();
+                }
+            }
+            _ => {}
+        }
+    }
+    // Wakes requests waiting on a server that is gone
+    shared.pending.lock().unwrap().clear();
+}
+
+async fn read_message(reader: &mut BufReader<ChildStdout>) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let mut body = vec![0; length.context("Message without Content-Length")?];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+fn format_diagnostics(path: &Path, root: &Path, mut items: Vec<Value>) -> String {
+    let display = path.strip_prefix(root).unwrap_or(path).display().to_string();
+    if items.is_empty() {
+        return format!("No problems found in {}", display);
+    }
+
+    items.sort_by_key(|item| (item["severity"].as_u64().unwrap_or(1), item["range"]["start"]["line"].as_u64().unwrap_or(0)));
+    let mut counts = [0usize; 4];
+    let lines: Vec<String> = items.iter().map(|item| {
+        let severity = item["severity"].as_u64().unwrap_or(1).clamp(1, 4) as usize;
+        counts[severity - 1] += 1;
+        let start = &item["range"]["start"];
+        let code = match &item["code"] {
+            Value::String(code) => format!(" [{}]", code),
+            Value::Number(code) => format!(" [{}]", code),
+            _ => String::new(),
+        };
+        let source = item["source"].as_str().map(|s| format!(" ({})", s)).unwrap_or_default();
+        format!("{}:{}:{} {}: {}{}{}", display,
+            start["line"].as_u64().unwrap_or(0) + 1, start["character"].as_u64().unwrap_or(0) + 1,
+            ["error", "warning", "info", "hint"][severity - 1],
+            item["message"].as_str().unwrap_or_default().replace('\n', " "), code, source)
+    }).collect();
+
+    format!("{} error(s), {} warning(s), {} other in {}:\n{}", counts[0], counts[1], counts[2] + counts[3], display, lines.join("\n"))
+}
+
+/// LSP language identifier for the extension of `path`
+fn language_of(path: &Path) -> String {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "ts" | "mts" => "typescript",
+        "tsx" => "typescriptreact",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "javascriptreact",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "kt" | "kts" => "kotlin",
+        "rb" => "ruby",
+        "sh" => "shellscript",
+        "md" => "markdown",
+        other => other,
+    }
+    .to_string()
+}
+
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] == b'%' && let Some(byte) = std::str::from_utf8(encoded.get(i + 1..i + 3)?).ok()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            bytes.push(byte);
+            i += 3;
+        } else {
+            bytes.push(encoded[i]);
+            i += 1;
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
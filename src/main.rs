@@ -1,84 +1,297 @@
+mod ab;
+mod accessibility;
+mod agent;
+mod anthropic;
+mod archive;
+mod args;
+mod backend;
+mod batch;
+mod bundle;
+mod cache;
+mod cargo_tools;
+mod checksums;
 mod cli;
+mod cmd;
+mod config;
+mod daemon;
+mod error;
+mod eval;
+mod github;
+mod headless;
+mod i18n;
+mod distributed;
+mod editor;
+mod encryption;
+mod env_vars;
 mod executor;
+mod explain;
+mod file_info;
+mod filters;
+mod fim;
+mod image;
+mod import;
+mod journal;
+mod lifecycle;
+mod lsp;
+mod mapreduce;
+mod navigation;
+mod notify;
 mod ollama;
+mod output;
+mod policy;
+mod pr;
+mod prefix_cache;
+mod pull;
+mod read_only;
+mod queue;
+mod recall;
+mod replay;
+mod repo_map;
+mod resume;
+mod schedule;
+mod secrets;
+mod session;
+mod share;
+mod shell_history;
+mod stdio_api;
+mod symbols;
+mod text;
+mod thoughts;
+mod tokens;
+mod tool_format;
+mod tool_output;
+mod tool_selection;
+mod tool_stats;
+mod training;
+mod tui;
+mod usage;
+mod verify;
+mod voice;
+mod watch;
+mod mcp_cache;
 mod mcp_config;
 mod mcp_client;
 mod mcp_manager;
 mod builtin_tools;
 
 use anyhow::{Context, Result};
+use args::{Args, Command};
+use clap::Parser;
 use colored::*;
+use config::AppConfig;
 use executor::AIExecutor;
 use cli::ChatCLI;
 use mcp_manager::McpManager;
+use i18n::tr;
+use output::status;
+use std::io::{IsTerminal, Write};
+use std::process::ExitCode;
+use std::sync::Arc;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    // Anything but JSON-RPC on stdout would break the protocol
+    output::init(args.quiet || args.stdio_api, args.plain);
+
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{} {:#}", tr!("label.error").bright_red().bold(), e);
+            error::exit_code(&e)
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<()> {
     // Configuration
-    let model = "llama3.2:1b";
     let cpu_workers = 6;
 
-    println!("{}", "Initializing AI Chat CLI...".bright_cyan());
+    let mut config = AppConfig::load().unwrap_or_else(|e| {
+        // Without a config the locale comes from the environment alone
+        i18n::init(None);
+        eprintln!("{} {:#}", tr!("label.warning").bright_yellow(), e);
+        AppConfig::default()
+    });
+    let mut model = backend::default_model(config.chat_backend, &config);
+    if args.summarize_tool_output {
+        config.tool_summary.enabled = true;
+    }
+    if args.repo_map {
+        config.repo_map.enabled = true;
+    }
+    if args.verify.is_some() {
+        config.verify.command = args.verify.clone();
+    }
+    if args.read_only {
+        config.read_only.enabled = true;
+    }
+    if args.warm_up {
+        config.warm_up.enabled = true;
+    }
+    if args.agent {
+        config.agent.enabled = true;
+    }
+    if args.accessible {
+        config.accessibility.enabled = true;
+    }
+    i18n::init(config.locale.as_deref());
+    accessibility::init(&config.accessibility);
+    encryption::init(&config.encryption);
+    notify::init(&config.notifications);
+    filters::init(&config.response_filters);
+    thoughts::init(&config.thoughts);
+    read_only::init(&config.read_only);
+    recall::init(&config.recall);
+    lsp::init(&config.language_servers);
+    github::init(&config.github);
+    verify::init(&config.verify);
+    env_vars::init(&config.env);
+
+    if let Some(command) = args.command {
+        return run_command(command, &model, cpu_workers, &config).await;
+    }
+    if args.tui && accessibility::enabled() {
+        anyhow::bail!(tr!("startup.tui_accessible"));
+    }
+
+    status!("{}", tr!("startup.initializing").bright_cyan());
 
     // Check if Ollama is running
-    let client = ollama::OllamaClient::new();
-    match client.list_models().await {
-        Ok(models) => {
-            println!("{} {}", "✓".bright_green(), "Connected to Ollama".bright_white());
+    let client = ollama::OllamaClient::new().with_keep_alive(config.warm_up.keep_alive.clone());
+    let online = match config.chat_backend {
+        // A bad key or model shows up on the first request
+        config::Provider::Anthropic => {
+            status!("{} {}", "✓".bright_green(), tr!("startup.using_backend", backend = "anthropic".bright_cyan(), model = model.bright_cyan()));
+            true
+        }
+        config::Provider::Ollama => match client.list_models().await {
+            Ok(models) => {
+                status!("{} {}", "✓".bright_green(), tr!("startup.connected").bright_white());
             
-            if !models.iter().any(|m| m.starts_with(model)) {
-                eprintln!(
-                    "{} Model '{}' not found. Available models: {:?}",
-                    "Warning:".bright_yellow(),
-                    model,
-                    models
-                );
-                eprintln!("\nInstall the model with: {}", 
-                    format!("ollama pull {}", model).bright_cyan());
-                std::process::exit(1);
-            }
+                if !models.iter().any(|m| m.starts_with(model.as_str())) {
+                    model = pull::resolve_missing(&client, &model, &models, args.yes).await?;
+                }
             
-            println!("{} Using model: {}", "✓".bright_green(), model.bright_cyan());
-        }
-        Err(e) => {
-            eprintln!("{} {}", "Error:".bright_red().bold(), e);
-            eprintln!("\n{}", "Make sure Ollama is running:".bright_yellow());
-            eprintln!("  {}", "ollama serve".bright_cyan());
-            std::process::exit(1);
-        }
-    }
+                status!("{} {}", "✓".bright_green(), tr!("startup.using_model", model = model.bright_cyan()));
+                // Loads while the tools start
+                if config.warm_up.enabled {
+                    client.preload_in_background(&model);
+                    status!("{} {}", "⏳".bright_blue(), tr!("startup.loading_model", model = model.bright_cyan()));
+                }
+                true
+            }
+            // Interactively, tools and config are still usable while Ollama is down;
+            // with --queue-on-failure, piped prompts are queued instead
+            Err(e) if std::io::stdin().is_terminal() || args.queue_on_failure => {
+                eprintln!("{} {:#}", tr!("label.warning").bright_yellow(), e);
+                eprintln!("{} {}", "ℹ".bright_blue(), tr!("startup.offline", command = "ollama serve".bright_cyan()));
+                false
+            }
+            Err(e) => {
+                eprintln!("{}", tr!("startup.ollama_hint").bright_yellow());
+                eprintln!("  {}", "ollama serve".bright_cyan());
+                return Err(e);
+            }
+        },
+    };
 
     // Initialize MCP
     let mcp_manager = match McpManager::new().await {
         Ok(manager) => {
             if manager.has_tools() {
                 let tool_count = manager.list_tools().len();
-                println!("{} Loaded {} MCP tool(s)", 
-                    "✓".bright_green(), tool_count);
+                status!("{} {}", "✓".bright_green(), tr!("startup.tools_loaded", count = tool_count));
                 Some(manager)
             } else {
-                println!("{} No MCP tools configured (create ~/.ai-chat-cli/mcp.json)", 
-                    "ℹ".bright_blue());
+                status!("{} {}", "ℹ".bright_blue(), tr!("startup.no_tools"));
                 None
             }
         }
         Err(e) => {
-            eprintln!("{} Failed to initialize MCP: {}", 
-                "Warning:".bright_yellow(), e);
+            eprintln!("{} {}", tr!("label.warning").bright_yellow(), tr!("startup.mcp_failed", error = e));
             None
         }
     };
 
+    if read_only::enabled() {
+        status!("{} {}", "🔒".bright_yellow(), tr!("startup.read_only"));
+    }
+
     // Create executor
-    let executor = AIExecutor::new(model.to_string(), cpu_workers)
+    let executor = AIExecutor::new(model, cpu_workers, &config)
         .await
-        .context("Failed to create AI executor")?;
+        .context(tr!("startup.executor_failed"))?;
 
-    println!("{} AI executor ready", "✓".bright_green());
+    status!("{} {}", "✓".bright_green(), tr!("startup.executor_ready"));
+    if !config.workers.is_empty() {
+        status!("{} {}", "✓".bright_green(), tr!("startup.workers", count = config.workers.len()));
+    }
+
+    if args.stdio_api {
+        return stdio_api::run(executor, mcp_manager, &config).await;
+    }
+
+    if args.tui {
+        let context = config.language_prompt().into_iter()
+            .chain(repo_map::startup_context(&config.repo_map))
+            .collect();
+        let json_array_items = config.json_array_items.unwrap_or(tool_format::DEFAULT_ARRAY_ITEMS);
+        return tui::run(executor, mcp_manager, context, json_array_items).await;
+    }
 
     // Create and run CLI
-    let mut cli = ChatCLI::new(executor, mcp_manager);
-    cli.run().await?;
+    let mut cli = ChatCLI::new(executor, mcp_manager, config, online);
+    if args.queue_on_failure {
+        cli.set_offline_queue(queue::OfflineQueue::open()?);
+    }
+    let result = tokio::select! {
+        result = cli.run() => result,
+        signal = lifecycle::terminated() => {
+            // The terminal may already be gone after SIGHUP, so nothing here may panic on output
+            let _ = writeln!(std::io::stderr(), "\n{} {}", "⚠".bright_yellow(), tr!("startup.signal", signal = signal));
+            match cli.save_open_conversations() {
+                Ok(saved) => for path in saved {
+                    let _ = writeln!(std::io::stderr(), "{} {}", "✓".bright_green(), tr!("chat.saved", file = path.display()));
+                },
+                Err(e) => {
+                    let _ = writeln!(std::io::stderr(), "{} {} {:#}", tr!("label.warning").bright_yellow(),
+                        tr!("startup.save_failed"), e);
+                }
+            }
+            Ok(())
+        }
+    };
+    cli.shutdown().await;
 
-    Ok(())
+    result
+}
+
+/// Runs a non-interactive subcommand
+async fn run_command(command: Command, model: &str, cpu_workers: usize, config: &AppConfig) -> Result<()> {
+    // Only commands that talk to a model build an executor
+    let executor = || async {
+        AIExecutor::new(model.to_string(), cpu_workers, config)
+            .await
+            .context(tr!("startup.executor_failed"))
+    };
+
+    match command {
+        Command::Batch(batch_args) => batch::run(Arc::new(executor().await?), batch_args).await,
+        Command::Eval(eval_args) => eval::run(&executor().await?, eval_args).await,
+        Command::Replay(replay_args) => replay::run(&executor().await?, replay_args).await,
+        Command::Ab(ab_args) => ab::run(&executor().await?, ab_args).await,
+        Command::ExportTraining(export_args) => training::export(export_args),
+        Command::Import(import_args) => import::run(import_args),
+        Command::Schedule(schedule_args) => schedule::run(&executor().await?, config, schedule_args).await,
+        Command::Watch(watch_args) => watch::run(&executor().await?, watch_args).await,
+        Command::FlushQueue(flush_args) => queue::flush(&executor().await?, flush_args).await,
+        Command::Secret(secret_args) => secrets::run(secret_args),
+        Command::Fim(fim_args) => fim::run(&executor().await?, fim_args).await,
+        Command::Explain(explain_args) => explain::run(&executor().await?, explain_args).await,
+        Command::Cmd(cmd_args) => cmd::run(&executor().await?, cmd_args).await,
+        Command::Run(run_args) => headless::run(executor().await?, config, run_args).await,
+        Command::Daemon(daemon_args) => daemon::run(executor, config, daemon_args).await,
+        Command::Attach => daemon::attach().await,
+    }
 }
@@ -0,0 +1,205 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::executor::Tasks;
+use crate::tokens;
+use crate::usage::UsageTracker;
+
+/// Maximum characters per chunk, sized to fit small-context models with room for the prompt
+pub const DEFAULT_CHUNK_SIZE: usize = 6000;
+
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub source: String,
+    pub text: String,
+}
+
+pub struct MapReduceOutput {
+    pub answer: String,
+    pub chunks: usize,
+    pub failed: usize,
+}
+
+/// Splits a file, or every readable text file under a directory, into line-aligned chunks
+pub fn collect_chunks(path: &Path, chunk_size: usize) -> Result<Vec<Chunk>> {
+    let mut chunks = Vec::new();
+
+    if path.is_dir() {
+        collect_dir(path, chunk_size, &mut chunks)?;
+    } else {
+        let content = fs::read_to_string(path)
+            .context(format!("Failed to read file: {}", path.display()))?;
+        split_text(&path.display().to_string(), &content, chunk_size, &mut chunks);
+    }
+
+    Ok(chunks)
+}
+
+fn collect_dir(dir: &Path, chunk_size: usize, chunks: &mut Vec<Chunk>) -> Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .context(format!("Failed to read directory: {}", dir.display()))?
+        .filter_map(|e| e.ok())
+        .collect();
+    entries.sort_by_key(|e| e.path());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_str()) {
+            continue;
+        }
+
+        if path.is_dir() {
+            collect_dir(&path, chunk_size, chunks)?;
+        } else if let Ok(content) = fs::read_to_string(&path) {
+            // Binary and non-UTF-8 files are skipped
+            split_text(&path.display().to_string(), &content, chunk_size, chunks);
+        }
+    }
+
+    Ok(())
+}
+
+/// Appends `content` as line-aligned chunks labeled with `source`
+pub fn split_text(source: &str, content: &str, chunk_size: usize, chunks: &mut Vec<Chunk>) {
+    let mut current = String::new();
+
+    for line in content.lines() {
+        if !current.is_empty() && current.len() + line.len() + 1 > chunk_size {
+            chunks.push(Chunk { source: source.to_string(), text: std::mem::take(&mut current) });
+        }
+        // Hard-wrap single lines longer than a whole chunk
+        let mut line = line;
+        while line.len() > chunk_size {
+            let mut split = chunk_size;
+            while !line.is_char_boundary(split) {
+                split -= 1;
+            }
+            chunks.push(Chunk { source: source.to_string(), text: line[..split].to_string() });
+            line = &line[split..];
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+
+    if !current.trim().is_empty() {
+        chunks.push(Chunk { source: source.to_string(), text: current });
+    }
+}
+
+fn map_prompt(instruction: &str, chunk: &Chunk) -> String {
+    format!(
+        "You are processing one part of a larger document set.\n\
+         Task: {}\n\n\
+         Source: {}\n\
+         ---\n{}\n---\n\n\
+         Write concise notes on this part only, keeping facts needed for the task.",
+        instruction, chunk.source, chunk.text
+    )
+}
+
+fn reduce_prompt(instruction: &str, partials: &[String]) -> String {
+    let mut prompt = format!(
+        "Combine the following partial results into a single answer.\nTask: {}\n\n",
+        instruction
+    );
+    for (i, partial) in partials.iter().enumerate() {
+        prompt.push_str(&format!("--- Part {} ---\n{}\n\n", i + 1, partial.trim()));
+    }
+    prompt
+}
+
+/// Groups partial results so each reduce prompt stays within the chunk budget,
+/// always pairing at least two results so every round makes progress
+fn group_partials(partials: Vec<String>, chunk_size: usize) -> Vec<Vec<String>> {
+    let mut groups: Vec<Vec<String>> = Vec::new();
+    let mut size = 0;
+
+    for partial in partials {
+        let fits = size + partial.len() <= chunk_size;
+        match groups.last_mut() {
+            Some(group) if fits || group.len() < 2 => {
+                size += partial.len();
+                group.push(partial);
+            }
+            _ => {
+                size = partial.len();
+                groups.push(vec![partial]);
+            }
+        }
+    }
+
+    groups
+}
+
+/// Maps every chunk at once, then reduces the partial results
+/// until one answer remains; each round waits for the budget and is counted
+pub async fn run(
+    tasks: &Tasks,
+    usage: &Mutex<UsageTracker>,
+    model: &str,
+    chunks: Vec<Chunk>,
+    instruction: &str,
+) -> Result<MapReduceOutput> {
+    if chunks.is_empty() {
+        anyhow::bail!("No readable text found");
+    }
+
+    let prompts = chunks.iter().map(|c| map_prompt(instruction, c)).collect();
+    let mapped = metered_round(tasks, usage, model, prompts).await?;
+
+    let total = mapped.len();
+    let mut partials: Vec<String> = mapped.into_iter().filter_map(|r| r.ok()).collect();
+    let failed = total - partials.len();
+
+    if partials.is_empty() {
+        anyhow::bail!("All {} map tasks failed", total);
+    }
+
+    loop {
+        let groups = group_partials(partials, DEFAULT_CHUNK_SIZE);
+        let final_round = groups.len() == 1;
+        let prompts = groups.iter().map(|g| reduce_prompt(instruction, g)).collect();
+
+        partials = metered_round(tasks, usage, model, prompts).await?
+            .into_iter()
+            .collect::<Result<Vec<_>>>()
+            .context("Reduce step failed")?;
+
+        if final_round {
+            break;
+        }
+    }
+
+    Ok(MapReduceOutput {
+        answer: partials.remove(0),
+        chunks: total,
+        failed,
+    })
+}
+
+/// Runs one round of prompts once the budget allows, counting every reply
+async fn metered_round(
+    tasks: &Tasks,
+    usage: &Mutex<UsageTracker>,
+    model: &str,
+    prompts: Vec<String>,
+) -> Result<Vec<Result<String>>> {
+    usage.lock().unwrap().check_budget()?;
+    let estimates: Vec<usize> = prompts.iter().map(|prompt| tokens::estimate_text(prompt) + 4).collect();
+    let replies = tasks.parallel_inference(model, prompts).await;
+
+    let mut usage = usage.lock().unwrap();
+    Ok(replies.into_iter()
+        .zip(estimates)
+        .map(|(reply, estimate)| reply.map(|reply| {
+            usage.record_reply(model, estimate, &reply);
+            reply.message.content
+        }))
+        .collect())
+}
@@ -0,0 +1,56 @@
+//! Tool lists of MCP servers remembered between runs.
+//!
+//! Starting a server and asking for its tools can take seconds, mostly for
+//! `npx` servers. The list is cached under a hash of the server's
+//! configuration, so a changed command, URL or environment starts fresh. A
+//! cached server has its tools registered at once while it connects in the
+//! background, and the cache is rewritten from the list it reports.
+
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cache::digest;
+use crate::mcp_client::Tool;
+use crate::mcp_config::McpServerConfig;
+
+/// Hash of everything that decides which server is started
+pub fn key(config: &McpServerConfig) -> String {
+    let config = json!({
+        "command": config.command,
+        "args": config.args,
+        "env": sorted(&config.env),
+        "httpUrl": config.http_url,
+        "headers": sorted(&config.headers),
+    });
+    digest(&config.to_string())
+}
+
+/// The tools the server reported last time, if any
+pub fn load(key: &str) -> Option<Vec<Tool>> {
+    let content = fs::read_to_string(path(key).ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn save(key: &str, tools: &[Tool]) -> Result<()> {
+    let path = path(key)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .context("Failed to create MCP cache directory")?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(tools)?)
+        .context(format!("Failed to write {}", path.display()))
+}
+
+/// Map order must not change the key
+fn sorted(map: &Option<HashMap<String, String>>) -> Option<BTreeMap<&String, &String>> {
+    map.as_ref().map(|m| m.iter().collect())
+}
+
+fn path(key: &str) -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .context("Could not find home directory")?;
+    Ok(home.join(".ai-chat-cli").join("cache").join("mcp").join(format!("{}.json", key)))
+}
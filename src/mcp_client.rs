@@ -13,6 +13,22 @@ pub struct Tool {
     pub description: String,
     #[serde(rename = "inputSchema")]
     pub input_schema: serde_json::Value,
+    /// What the server says about the tool's behavior
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolAnnotations {
+    /// The tool only reads and changes nothing
+    #[serde(rename = "readOnlyHint", default)]
+    pub read_only_hint: bool,
+}
+
+impl Tool {
+    pub fn read_only_hint(&self) -> bool {
+        self.annotations.as_ref().is_some_and(|a| a.read_only_hint)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,7 +42,37 @@ pub struct ToolCallResult {
 pub struct Content {
     #[serde(rename = "type")]
     pub content_type: String,
+    /// Empty for "image" content
+    #[serde(default)]
     pub text: String,
+    /// Base64 payload of "image" content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// Context of errors caused by arguments that do not fit the tool's input
+/// schema, which the model may be able to fix
+#[derive(Debug, Clone, Copy)]
+pub struct InvalidArguments;
+
+impl std::fmt::Display for InvalidArguments {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid arguments")
+    }
+}
+
+impl std::error::Error for InvalidArguments {}
+
+/// The result of a tools/call response, or the JSON-RPC error it carries
+fn call_result(response: &serde_json::Value) -> Result<ToolCallResult> {
+    if let Some(error) = response.get("error") {
+        let message = anyhow::anyhow!("MCP server error: {}", error["message"].as_str().unwrap_or("unknown error"));
+        // -32602 is JSON-RPC's "Invalid params"
+        return Err(if error["code"].as_i64() == Some(-32602) { message.context(InvalidArguments) } else { message });
+    }
+    Ok(serde_json::from_value(response["result"].clone())?)
 }
 
 #[derive(Debug)]
@@ -66,6 +112,22 @@ impl McpClient {
             McpClient::Http(_) => Ok(()),
         }
     }
+
+    /// Whether a stdio server process is still running; HTTP servers keep
+    /// no connection open and are always reported alive
+    pub fn is_alive(&mut self) -> bool {
+        match self {
+            McpClient::Stdio(client) => matches!(client.process.try_wait(), Ok(None)),
+            McpClient::Http(_) => true,
+        }
+    }
+
+    pub fn transport(&self) -> &'static str {
+        match self {
+            McpClient::Stdio(_) => "stdio",
+            McpClient::Http(_) => "http",
+        }
+    }
 }
 
 // STDIO Client Implementation
@@ -81,7 +143,9 @@ impl StdioClient {
         cmd.args(&args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::null());
+            .stderr(Stdio::null())
+            // Backstop for exits that skip the shutdown path
+            .kill_on_drop(true);
 
         for (key, value) in env {
             cmd.env(key, value);
@@ -192,9 +256,7 @@ impl StdioClient {
         self.request_id += 1;
 
         let response = self.send_request(request).await?;
-        
-        let result: ToolCallResult = serde_json::from_value(response["result"].clone())?;
-        Ok(result)
+        call_result(&response)
     }
 
     async fn shutdown(&mut self) -> Result<()> {
@@ -293,8 +355,6 @@ impl HttpClient {
         });
 
         let response = self.send_request(request).await?;
-        
-        let result: ToolCallResult = serde_json::from_value(response["result"].clone())?;
-        Ok(result)
+        call_result(&response)
     }
 }
@@ -1,32 +1,175 @@
 use anyhow::{Context, Result};
 use colored::*;
 use std::collections::HashMap;
+use std::time::Instant;
+use tokio::task::JoinHandle;
 
 use crate::builtin_tools::BuiltinToolRegistry;
-use crate::mcp_client::{McpClient, Tool, ToolCallResult};
+use crate::config::{AppConfig, ToolBudgetConfig, ToolCalling};
+use crate::error::Failure;
+use crate::mcp_cache;
+use crate::mcp_client::{InvalidArguments, McpClient, Tool, ToolCallResult};
 use crate::mcp_config::{McpConfig, McpServerConfig};
+use crate::output::status;
+use crate::policy::{Approval, ApprovalRequest, Policy};
+use crate::secrets;
+use crate::tool_format::Formatter;
+use crate::tool_stats;
+
+/// Health snapshot of a connected MCP server
+#[derive(Debug, Clone)]
+pub struct ServerStatus {
+    pub name: String,
+    pub transport: &'static str,
+    pub tools: usize,
+    pub alive: bool,
+}
+
+/// Used when config.json sets no "toolInstructions"
+const DEFAULT_TOOL_INSTRUCTIONS: &str = "SYSTEM: You have access to these MCP tools:\n\n{tools}\n\n{usage}";
+
+/// {usage} in agent mode; the block format is what `agent::parse_tool_call` reads
+const AGENT_USAGE: &str = "To use a tool, reply with only a fenced block like this and nothing after it:\n\n\
+```tool\n{\"name\": \"read_file\", \"arguments\": {\"path\": \"src/main.rs\"}}\n```\n\n\
+The result comes back in the next message. Call one tool at a time. When you have what you need, \
+reply with the final answer and no tool block.";
+
+const DEFAULT_BUDGET_TOKENS: usize = 2000;
+const DEFAULT_DESCRIPTION_CHARS: usize = 300;
+
+/// Why a tool is ranked where it is in the tools prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    RecentlyUsed,
+    Allowlisted,
+    Builtin,
+    Other,
+}
+
+impl Priority {
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::RecentlyUsed => "recently used",
+            Priority::Allowlisted => "allowlisted",
+            Priority::Builtin => "built-in",
+            Priority::Other => "other",
+        }
+    }
+}
+
+/// A tool's place in the tools prompt
+pub struct RankedTool<'a> {
+    pub tool: &'a Tool,
+    pub server: &'a str,
+    pub priority: Priority,
+    /// Its description, cut if too long
+    pub description: String,
+    /// Its line in the prompt
+    pub line: String,
+    pub truncated: bool,
+    pub tokens: usize,
+    /// Whether it was picked for the current message
+    pub relevant: bool,
+    /// Whether it is relevant and fit in the budget
+    pub sent: bool,
+}
+
+/// How tools get called, which decides what the model is told about them
+#[derive(Debug, Clone, Copy)]
+enum ToolMode {
+    /// Only the user runs tools, with /mcp-call
+    Manual,
+    /// The model calls tools itself through the agent loop
+    Agent,
+}
+
+impl ToolMode {
+    /// Value of {mode} in the instructions template
+    fn name(self) -> &'static str {
+        match self {
+            ToolMode::Manual => "manual",
+            ToolMode::Agent => "agent",
+        }
+    }
+
+    /// Value of {usage}: how the model should go about using the tools
+    fn usage(self) -> &'static str {
+        match self {
+            ToolMode::Manual => "When relevant, tell users they can execute these with /mcp-call <tool> <args>",
+            ToolMode::Agent => AGENT_USAGE,
+        }
+    }
+}
 
 pub struct McpManager {
     clients: HashMap<String, McpClient>,
     tools: HashMap<String, (String, Tool)>, // tool_name -> (server_name, tool)
     builtin_tools: BuiltinToolRegistry,
+    policy: Policy,
+    /// Servers still connecting in the background, registered with their cached tools
+    pending: HashMap<String, JoinHandle<Connection>>,
+    /// server name -> hash of its configuration, naming its tool cache
+    cache_keys: HashMap<String, String>,
+    /// "toolInstructions" from config.json
+    instructions: String,
+    mode: ToolMode,
+    /// "toolCalling" from config.json
+    calling: ToolCalling,
+    budget: ToolBudgetConfig,
+    /// Tools called this session, most recent first
+    recent: Vec<String>,
+    /// Tools picked for the current message by "toolSelection"; all when `None`
+    selection: Option<Vec<String>>,
+    /// "toolFormatters" from config.json, by tool name
+    formatters: HashMap<String, Formatter>,
 }
 
+/// A connected server and the tools it reported
+type Connection = Result<(McpClient, Vec<Tool>)>;
+
 impl McpManager {
     pub async fn new() -> Result<Self> {
         let config = McpConfig::load()?;
+        let app_config = AppConfig::load()?;
         let mut manager = Self {
             clients: HashMap::new(),
             tools: HashMap::new(),
             builtin_tools: BuiltinToolRegistry::new(),
+            policy: Policy::load()?,
+            pending: HashMap::new(),
+            cache_keys: HashMap::new(),
+            instructions: app_config.tool_instructions
+                .unwrap_or_else(|| DEFAULT_TOOL_INSTRUCTIONS.to_string()),
+            mode: ToolMode::Manual,
+            calling: app_config.tool_calling,
+            budget: app_config.tool_budget,
+            recent: Vec::new(),
+            selection: None,
+            formatters: HashMap::new(),
         };
 
+        for (tool, formatter) in &app_config.tool_formatters {
+            match Formatter::new(formatter) {
+                Ok(formatter) => {
+                    manager.formatters.insert(tool.clone(), formatter);
+                }
+                Err(e) => eprintln!("{} Ignoring the formatter for '{}': {:#}",
+                    "Warning:".bright_yellow(), tool, e),
+            }
+        }
+
+        if manager.policy.is_custom() {
+            status!("{} Guardrail policy loaded from {}",
+                "✓".bright_green(), Policy::path()?.display());
+        }
+
         // Add built-in tools first
         for tool in manager.builtin_tools.list_tools() {
             let mcp_tool = Tool {
                 name: tool.name.clone(),
                 description: tool.description.clone(),
                 input_schema: tool.input_schema.clone(),
+                annotations: None,
             };
             manager.tools.insert(
                 tool.name.clone(),
@@ -34,22 +177,32 @@ impl McpManager {
             );
         }
         
-        println!("{} Loaded {} built-in tools", 
+        status!("{} Loaded {} built-in tools", 
             "✓".bright_green(), 
             manager.builtin_tools.list_tools().len());
 
-        // Connect to configured MCP servers
+        // Servers start in parallel; those with cached tools are not waited for
+        let mut connecting = Vec::new();
         for (name, server_config) in config.mcp_servers {
-            if let Err(e) = manager.connect_server(&name, &server_config).await {
-                eprintln!("{} Failed to connect to MCP server '{}': {}", 
-                    "Warning:".bright_yellow(), name, e);
-                continue;
+            let key = mcp_cache::key(&server_config);
+            let handle = tokio::spawn(connect(server_config));
+            manager.cache_keys.insert(name.clone(), key.clone());
+            match mcp_cache::load(&key) {
+                Some(tools) => {
+                    status!("{} Registered {} cached tool(s) from MCP server {}; connecting in the background",
+                        "✓".bright_green(), tools.len(), name.bright_cyan());
+                    manager.register(&name, tools);
+                    manager.pending.insert(name, handle);
+                }
+                None => connecting.push((name, handle)),
+            }
+        }
+        for (name, handle) in connecting {
+            let connection = join(handle).await;
+            if manager.finish(&name, connection) {
+                status!("{} Connected to MCP server: {}", "✓".bright_green(), name.bright_cyan());
             }
-            println!("{} Connected to MCP server: {}", "✓".bright_green(), name.bright_cyan());
         }
-
-        // Discover tools from external servers
-        manager.discover_tools().await?;
 
         Ok(manager)
     }
@@ -58,72 +211,142 @@ impl McpManager {
         &self.tools
     }
 
+    /// Details to show the user if the policy wants this call approved first
+    pub fn approval_request(&self, name: &str, arguments: &serde_json::Value) -> Option<ApprovalRequest> {
+        let (server_name, tool) = self.tools.get(name)?;
+        // A call read-only mode refuses is not worth approving
+        if crate::read_only::check(name, server_name, arguments, tool.read_only_hint()).is_err() {
+            return None;
+        }
+        self.policy.approval_request(name, server_name, arguments)
+    }
+
+    /// Records the user's answer to an approval request
+    pub fn decide(&mut self, name: &str, approval: Approval) {
+        self.policy.decide(name, approval);
+    }
+
     pub async fn call_tool(&mut self, name: &str, arguments: serde_json::Value) -> Result<ToolCallResult> {
-        let (server_name, _) = self.tools.get(name)
+        // A cached tool can only run once its server is up
+        if let Some((server_name, _)) = self.tools.get(name)
+            && let Some(handle) = self.pending.remove(server_name)
+        {
+            let server_name = server_name.clone();
+            let connection = join(handle).await;
+            self.finish(&server_name, connection);
+        }
+        let (server_name, tool) = self.tools.get(name)
             .context(format!("Tool '{}' not found", name))?;
-        
+        check_arguments(&tool.input_schema, &arguments)
+            .map_err(|problems| anyhow::anyhow!("{}", problems.join("; ")).context(InvalidArguments))?;
+        crate::read_only::check(name, server_name, &arguments, tool.read_only_hint())
+            .map_err(|reason| anyhow::anyhow!("Blocked by read-only mode: {}", reason).context(Failure::ToolDenied))?;
+        let server_name = server_name.clone();
+
+        self.policy.check(name, &arguments)?;
+        self.recent.retain(|recent| recent != name);
+        self.recent.insert(0, name.to_string());
+
+        let started = Instant::now();
+        let result = self.execute(name, &server_name, arguments).await;
+        let failed = result.as_ref().map_or(true, |result| result.is_error == Some(true));
+        if let Err(e) = tool_stats::record(name, &server_name, started.elapsed(), failed) {
+            eprintln!("{} {:#}", "Warning:".bright_yellow(), e);
+        }
+        let mut result = result?;
+
+        // Results that are not JSON, and errors, are left as they are
+        if result.is_error != Some(true) && let Some(formatter) = self.formatters.get(name) {
+            for content in result.content.iter_mut().filter(|c| c.content_type == "text") {
+                if let Some(text) = formatter.apply(&content.text) {
+                    content.text = text;
+                }
+            }
+        }
+
+        // A failed call changed nothing, so there is nothing to verify
+        if result.is_error != Some(true) && let Some(text) = crate::verify::after_tool_call(name).await {
+            result.content.push(crate::mcp_client::Content {
+                content_type: "text".to_string(),
+                text,
+                data: None,
+                mime_type: None,
+            });
+        }
+        Ok(result)
+    }
+
+    async fn execute(&mut self, name: &str, server_name: &str, arguments: serde_json::Value) -> Result<ToolCallResult> {
         // Handle built-in tools
         if server_name == "builtin" {
             let result = self.builtin_tools.execute(name, arguments).await?;
             
             // Convert BuiltinToolResult to ToolCallResult
-            return Ok(ToolCallResult {
+            Ok(ToolCallResult {
                 content: result.content.into_iter().map(|c| {
                     crate::mcp_client::Content {
                         content_type: c.content_type,
                         text: c.text,
+                        data: c.data,
+                        mime_type: c.mime_type,
                     }
                 }).collect(),
                 is_error: result.is_error,
-            });
+            })
+        } else {
+            // Handle external MCP server tools
+            let client = self.clients.get_mut(server_name)
+                .context(format!("Server '{}' not connected", server_name))?;
+
+            client.call_tool(name, arguments).await
         }
-        
-        // Handle external MCP server tools
-        let client = self.clients.get_mut(server_name)
-            .context(format!("Server '{}' not connected", server_name))?;
-        
-        client.call_tool(name, arguments).await
     }
 
-    async fn connect_server(&mut self, name: &str, config: &McpServerConfig) -> Result<()> {
-        let client = if config.is_stdio() {
-            let command = config.command.clone().unwrap();
-            let args = config.args.clone().unwrap_or_default();
-            let env = config.env.clone().unwrap_or_default();
-            
-            McpClient::connect_stdio(command, args, env).await?
-        } else if config.is_http() {
-            let url = config.http_url.clone().unwrap();
-            let headers = config.headers.clone().unwrap_or_default();
-            
-            McpClient::connect_http(url, headers).await?
-        } else {
-            anyhow::bail!("Server configuration must specify either command or httpUrl");
-        };
-
-        self.clients.insert(name.to_string(), client);
-        Ok(())
+    /// Takes in servers that finished connecting in the background
+    pub async fn poll_pending(&mut self) {
+        let finished: Vec<String> = self.pending.iter()
+            .filter(|(_, handle)| handle.is_finished())
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in finished {
+            if let Some(handle) = self.pending.remove(&name) {
+                let connection = join(handle).await;
+                self.finish(&name, connection);
+            }
+        }
     }
 
-    async fn discover_tools(&mut self) -> Result<()> {
-        for (server_name, client) in &mut self.clients {
-            match client.list_tools().await {
-                Ok(tools) => {
-                    for tool in tools {
-                        self.tools.insert(
-                            tool.name.clone(),
-                            (server_name.clone(), tool)
-                        );
-                    }
-                }
-                Err(e) => {
-                    eprintln!("{} Failed to list tools from '{}': {}", 
-                        "Warning:".bright_yellow(), server_name, e);
+    /// Records a server's connection and caches its tools; on failure its
+    /// cached tools are withdrawn. Returns whether it connected.
+    fn finish(&mut self, name: &str, connection: Connection) -> bool {
+        match connection {
+            Ok((client, tools)) => {
+                self.clients.insert(name.to_string(), client);
+                if let Err(e) = mcp_cache::save(&self.cache_key(name), &tools) {
+                    eprintln!("{} {:#}", "Warning:".bright_yellow(), e);
                 }
+                self.register(name, tools);
+                true
             }
+            Err(e) => {
+                eprintln!("{} Failed to connect to MCP server '{}': {:#}",
+                    "Warning:".bright_yellow(), name, e);
+                self.tools.retain(|_, (server, _)| server != name);
+                false
+            }
+        }
+    }
+
+    /// Replaces the tools registered for a server
+    fn register(&mut self, server_name: &str, tools: Vec<Tool>) {
+        self.tools.retain(|_, (server, _)| server != server_name);
+        for tool in tools {
+            self.tools.insert(tool.name.clone(), (server_name.to_string(), tool));
         }
+    }
 
-        Ok(())
+    fn cache_key(&self, name: &str) -> String {
+        self.cache_keys.get(name).cloned().unwrap_or_default()
     }
 
     pub fn list_tools(&self) -> Vec<&Tool> {
@@ -134,11 +357,210 @@ impl McpManager {
         !self.tools.is_empty()
     }
 
+    /// All tools in the order they are considered for the prompt: recently
+    /// used, allowlisted, built-in, then the rest, each group by name. Tools
+    /// are sent in that order until the token budget is spent. Tools that
+    /// read-only mode refuses are left out.
+    pub fn ranked_tools(&self) -> Vec<RankedTool<'_>> {
+        let max_chars = self.budget.description_chars.unwrap_or(DEFAULT_DESCRIPTION_CHARS);
+        let mut ranked: Vec<RankedTool> = self.tools.values()
+            .filter(|(server, tool)| !crate::read_only::refuses(&tool.name, server, tool.read_only_hint()))
+            .map(|(server, tool)| {
+                let priority = if self.recent.contains(&tool.name) {
+                    Priority::RecentlyUsed
+                } else if self.budget.allowlist.contains(&tool.name) {
+                    Priority::Allowlisted
+                } else if server == "builtin" {
+                    Priority::Builtin
+                } else {
+                    Priority::Other
+                };
+                let description = tool.description.split_whitespace().collect::<Vec<_>>().join(" ");
+                let truncated = description.chars().count() > max_chars;
+                let description = if truncated {
+                    format!("{}…", description.chars().take(max_chars).collect::<String>().trim_end())
+                } else {
+                    description
+                };
+                let line = format!("- {}: {}", tool.name, &description);
+                // Recently used and allowlisted tools stay whatever the message is about
+                let relevant = priority < Priority::Builtin
+                    || self.selection.as_ref().is_none_or(|selection| selection.contains(&tool.name));
+                RankedTool {
+                    tool, server, priority, tokens: line.chars().count().div_ceil(4), description, line, truncated, relevant, sent: false,
+                }
+            })
+            .collect();
+        let recency = |name: &String| self.recent.iter().position(|recent| recent == name);
+        ranked.sort_by(|a, b| (a.priority, recency(&a.tool.name), &a.tool.name)
+            .cmp(&(b.priority, recency(&b.tool.name), &b.tool.name)));
+
+        let mut remaining = self.budget_tokens();
+        for tool in &mut ranked {
+            // A smaller tool further down may still fit
+            if tool.relevant && tool.tokens <= remaining {
+                remaining -= tool.tokens;
+                tool.sent = true;
+            }
+        }
+        ranked
+    }
+
+    /// Limits the tools described to those picked for the current message, or lifts the limit
+    pub fn select(&mut self, selection: Option<Vec<String>>) {
+        self.selection = selection;
+    }
+
+    pub fn budget_tokens(&self) -> usize {
+        self.budget.tokens.unwrap_or(DEFAULT_BUDGET_TOKENS)
+    }
+
+    /// Whether the model calls tools itself; changes what `tools_prompt` tells it
+    pub fn set_agent_mode(&mut self, agent: bool) {
+        self.mode = if agent { ToolMode::Agent } else { ToolMode::Manual };
+    }
+
+    /// System prompt advertising the available tools to the model, from the
+    /// "toolInstructions" template; none when they go through `native_tools`
+    pub fn tools_prompt(&self) -> Option<String> {
+        if !self.has_tools() || self.instructions.trim().is_empty() || self.calling == ToolCalling::Native {
+            return None;
+        }
+
+        let ranked = self.ranked_tools();
+        let sent = Self::sent_by_name(&ranked);
+        let mut tools: Vec<&str> = sent.iter().map(|t| t.line.as_str()).collect();
+        let left_out = ranked.len() - tools.len();
+        let note = format!("({} more tool(s) not listed)", left_out);
+        if left_out > 0 {
+            tools.push(&note);
+        }
+        // Tools last, so their descriptions are never taken for variables
+        Some(self.instructions
+            .replace("{mode}", self.mode.name())
+            .replace("{usage}", self.mode.usage())
+            .replace("{tools}", &tools.join("\n")))
+    }
+
+    /// Definitions of the tools for the `tools` of a chat request when
+    /// "toolCalling" is "native": the same tools the prompt would list
+    pub fn native_tools(&self) -> Option<Vec<serde_json::Value>> {
+        if !self.has_tools() || self.calling != ToolCalling::Native {
+            return None;
+        }
+        let ranked = self.ranked_tools();
+        Some(Self::sent_by_name(&ranked).iter()
+            .map(|t| serde_json::json!({
+                "type": "function",
+                "function": {
+                    "name": t.tool.name,
+                    "description": t.description,
+                    "parameters": t.tool.input_schema,
+                },
+            }))
+            .collect())
+    }
+
+    /// The tools that fit the budget, by name rather than rank, so using a
+    /// tool does not change the request and the backend's prompt cache stays valid
+    fn sent_by_name<'a>(ranked: &'a [RankedTool<'a>]) -> Vec<&'a RankedTool<'a>> {
+        let mut sent: Vec<&RankedTool> = ranked.iter().filter(|t| t.sent).collect();
+        sent.sort_by_key(|t| &t.tool.name);
+        sent
+    }
+
+    /// "builtin" and the configured servers, connected or still connecting
+    pub fn server_names(&self) -> Vec<String> {
+        std::iter::once("builtin".to_string())
+            .chain(self.clients.keys().cloned())
+            .chain(self.pending.keys().cloned())
+            .collect()
+    }
+
+    pub fn server_status(&mut self) -> Vec<ServerStatus> {
+        let tools = &self.tools;
+        let count_tools = |server: &str| tools.values().filter(|(s, _)| s == server).count();
+
+        let mut servers: Vec<ServerStatus> = self.clients.iter_mut()
+            .map(|(name, client)| ServerStatus {
+                name: name.clone(),
+                transport: client.transport(),
+                tools: count_tools(name),
+                alive: client.is_alive(),
+            })
+            .collect();
+        // Not connected yet, but their cached tools are offered
+        servers.extend(self.pending.keys().map(|name| ServerStatus {
+            name: name.clone(),
+            transport: "connecting",
+            tools: count_tools(name),
+            alive: true,
+        }));
+        servers.sort_by(|a, b| a.name.cmp(&b.name));
+
+        servers.insert(0, ServerStatus {
+            name: "builtin".to_string(),
+            transport: "local",
+            tools: count_tools("builtin"),
+            alive: true,
+        });
+
+        servers
+    }
+
     pub async fn shutdown(&mut self) {
+        // Dropping a connecting server kills its process
+        for (_, handle) in self.pending.drain() {
+            handle.abort();
+        }
         for (name, client) in &mut self.clients {
             if let Err(e) = client.shutdown().await {
                 eprintln!("Failed to shutdown MCP server '{}': {}", name, e);
             }
         }
+        // Started on demand by the get_diagnostics tool
+        crate::lsp::shutdown().await;
     }
 }
+
+/// Everything about `arguments` that breaks the tool's input schema, each
+/// with where in the arguments it is, so a call that would fail never runs.
+/// A schema that is itself invalid lets every call through.
+fn check_arguments(schema: &serde_json::Value, arguments: &serde_json::Value) -> Result<(), Vec<String>> {
+    let Ok(validator) = jsonschema::validator_for(schema) else {
+        return Ok(());
+    };
+    let problems: Vec<String> = validator.iter_errors(arguments)
+        .map(|error| match error.instance_path().as_str() {
+            "" => error.to_string(),
+            path => format!("{}: {}", path, error),
+        })
+        .collect();
+    if problems.is_empty() { Ok(()) } else { Err(problems) }
+}
+
+/// Starts a server and asks for its tools
+async fn connect(config: McpServerConfig) -> Connection {
+    let mut client = if config.is_stdio() {
+        let command = config.command.clone().unwrap();
+        let args = config.args.clone().unwrap_or_default();
+        let env = secrets::resolve_map(config.env.clone().unwrap_or_default())?;
+
+        McpClient::connect_stdio(command, args, env).await?
+    } else if config.is_http() {
+        let url = config.http_url.clone().unwrap();
+        let headers = secrets::resolve_map(config.headers.clone().unwrap_or_default())?;
+
+        McpClient::connect_http(url, headers).await?
+    } else {
+        anyhow::bail!("Server configuration must specify either command or httpUrl");
+    };
+
+    let tools = client.list_tools().await
+        .context("Failed to list tools")?;
+    Ok((client, tools))
+}
+
+async fn join(handle: JoinHandle<Connection>) -> Connection {
+    handle.await.context("MCP connection task failed")?
+}
@@ -0,0 +1,175 @@
+//! Where a symbol is defined and where it is used, for the find_definition
+//! and find_references tools.
+//!
+//! Definitions come from the same symbol index as the repo map, so they are
+//! real declarations rather than any line that happens to contain the name.
+//! References are whole-identifier matches outside comments and string
+//! literals, in the source files of the repository.
+
+use anyhow::Result;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::repo_map;
+use crate::symbols::{self, Symbol};
+
+/// Definitions of `name` in the repository containing `path`. A qualified
+/// name such as `ChatCLI::new` or `Shape.area` only matches members of that type.
+pub fn find_definition(path: &Path, name: &str) -> Result<String> {
+    let (container, member) = split_qualified(name);
+    let (root, files) = repo_map::project_files(path)?;
+    let index = symbols::index(&root, &files);
+
+    let mut found = Vec::new();
+    for file in &files {
+        let Some(symbols) = index.get(file) else {
+            continue;
+        };
+        let source = fs::read_to_string(root.join(file)).unwrap_or_default();
+        let lines: Vec<&str> = source.lines().collect();
+
+        for (symbol, parent) in with_parents(symbols) {
+            if !defines(symbol, member) || container.is_some_and(|c| parent.is_none_or(|p| type_name(&p.name) != c)) {
+                continue;
+            }
+            let range = if symbol.end_line > symbol.line {
+                format!("{}-{}", symbol.line, symbol.end_line)
+            } else {
+                symbol.line.to_string()
+            };
+            let parent = parent.map(|p| format!(" (in {} {})", p.kind, p.name)).unwrap_or_default();
+            let text = lines.get(symbol.line - 1).map(|line| line.trim()).unwrap_or_default();
+            found.push(format!("{}:{} {} {}{}\n  {}", file, range, symbol.kind, symbol.name, parent, text));
+        }
+    }
+
+    if found.is_empty() {
+        return Ok(format!("No definition of '{}' found in {} ({} source files searched). Try find_references or grep.",
+            name, root.display(), index.len()));
+    }
+    Ok(format!("{} definition(s) of '{}':\n{}", found.len(), name, found.join("\n")))
+}
+
+/// Lines using `name` in the repository containing `path`, definitions marked with `*`
+pub fn find_references(path: &Path, name: &str, limit: usize) -> Result<String> {
+    let (_, member) = split_qualified(name);
+    let (root, files) = repo_map::project_files(path)?;
+    let index = symbols::index(&root, &files);
+    let pattern = Regex::new(&format!(r"(?:^|[^\w$]){}(?:$|[^\w$])", regex::escape(member)))?;
+
+    let mut text = String::new();
+    let (mut shown, mut total, mut in_files) = (0, 0, 0);
+    for file in &files {
+        let file_path = Path::new(file);
+        if !symbols::is_supported(file_path) && !index.contains_key(file) {
+            continue;
+        }
+        if fs::metadata(root.join(file)).is_ok_and(|m| m.len() > symbols::MAX_PARSED_BYTES) {
+            continue;
+        }
+        let Ok(source) = fs::read_to_string(root.join(file)) else {
+            continue;
+        };
+        let definitions: HashSet<usize> = index.get(file).into_iter().flatten()
+            .filter(|symbol| defines(symbol, member))
+            .map(|symbol| symbol.line)
+            .collect();
+        let comment = symbols::line_comment(file_path).unwrap_or("//");
+        // Rust uses ' for lifetimes, so only double quotes delimit its strings
+        let quotes: &[char] = if file.ends_with(".rs") { &['"'] } else { &['"', '\'', '`'] };
+
+        let mut matches = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let is_reference = pattern.find_iter(line)
+                .any(|found| in_code(&line[..found.start()], comment, quotes));
+            if is_reference {
+                matches.push((i + 1, line.trim()));
+            }
+        }
+        if matches.is_empty() {
+            continue;
+        }
+
+        total += matches.len();
+        in_files += 1;
+        if shown >= limit {
+            continue;
+        }
+        text.push_str(&format!("{}\n", file));
+        for (line, content) in matches {
+            if shown >= limit {
+                break;
+            }
+            let marker = if definitions.contains(&line) { "*" } else { " " };
+            text.push_str(&format!("{} {}: {}\n", marker, line, content));
+            shown += 1;
+        }
+    }
+
+    if total == 0 {
+        return Ok(format!("No references to '{}' found in {}", member, root.display()));
+    }
+    let mut header = format!("{} reference(s) to '{}' in {} file(s), definitions marked with *", total, member, in_files);
+    if shown < total {
+        header.push_str(&format!("; showing the first {}", shown));
+    }
+    Ok(format!("{}:\n{}", header, text))
+}
+
+/// `Type::member` or `Type.member` into the type and the member
+fn split_qualified(name: &str) -> (Option<&str>, &str) {
+    match name.rsplit_once("::").or_else(|| name.rsplit_once('.')) {
+        Some((container, member)) => (Some(type_name(container)), member),
+        None => (None, name),
+    }
+}
+
+/// Whether `symbol` defines `name`; Rust impl blocks count for the type they implement
+fn defines(symbol: &Symbol, name: &str) -> bool {
+    if symbol.kind == "impl" {
+        return symbol.name.split(" for ").last().is_some_and(|target| type_name(target) == name);
+    }
+    symbol.name == name
+}
+
+/// `std::fmt::Display` -> `Display`, `Cache<K, V>` -> `Cache`
+fn type_name(name: &str) -> &str {
+    let name = name.split('<').next().unwrap_or(name).trim();
+    name.rsplit("::").next().unwrap_or(name).rsplit('.').next().unwrap_or(name)
+}
+
+/// Each symbol with the innermost definition containing it
+fn with_parents(symbols: &[Symbol]) -> Vec<(&Symbol, Option<&Symbol>)> {
+    let mut open: Vec<&Symbol> = Vec::new();
+    symbols.iter().map(|symbol| {
+        while open.last().is_some_and(|parent| parent.end_line < symbol.line) {
+            open.pop();
+        }
+        let parent = open.last().copied();
+        if symbol.end_line > symbol.line {
+            open.push(symbol);
+        }
+        (symbol, parent)
+    }).collect()
+}
+
+/// Whether code ending in `before` continues outside comments and string literals
+fn in_code(before: &str, comment: &str, quotes: &[char]) -> bool {
+    let mut open: Option<char> = None;
+    let mut chars = before.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match open {
+            Some(_) if c == '\\' => {
+                chars.next();
+            }
+            Some(quote) if c == quote => open = None,
+            Some(_) => {}
+            None if quotes.contains(&c) => open = Some(c),
+            None if before[i..].starts_with(comment) => return false,
+            None => {}
+        }
+    }
+    open.is_none()
+}
@@ -0,0 +1,114 @@
+//! Desktop notifications when a long reply or batch run finishes.
+//!
+//! Off unless "notifications" is enabled in config.json. A notification is
+//! only sent when the task took longer than the threshold and the terminal
+//! does not have focus; when focus cannot be determined (no X11 `WINDOWID`,
+//! tmux, ssh), it is sent anyway.
+
+#[cfg(unix)]
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::{NotificationConfig, PlatformNotification};
+
+const APP_NAME: &str = "ai-chat-cli";
+const DEFAULT_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Settings for this OS, or `None` while notifications are off
+static SETTINGS: Mutex<Option<Settings>> = Mutex::new(None);
+
+struct Settings {
+    threshold: Duration,
+    platform: PlatformNotification,
+}
+
+/// Applies the "notifications" config for the whole process
+pub fn init(config: &NotificationConfig) {
+    let platform = match std::env::consts::OS {
+        "macos" => config.macos.clone(),
+        "windows" => config.windows.clone(),
+        _ => config.linux.clone(),
+    }
+    .unwrap_or_default();
+
+    let enabled = platform.enabled.unwrap_or(config.enabled);
+    *SETTINGS.lock().unwrap() = enabled.then(|| Settings {
+        threshold: config.threshold_seconds.map(Duration::from_secs).unwrap_or(DEFAULT_THRESHOLD),
+        platform,
+    });
+}
+
+/// Notifies that a task started at `started` is done, if it ran long enough to be worth it
+pub fn finished(started: Instant, summary: &str, body: &str) {
+    let settings = SETTINGS.lock().unwrap();
+    let Some(settings) = settings.as_ref() else {
+        return;
+    };
+    if started.elapsed() < settings.threshold || terminal_focused() == Some(true) {
+        return;
+    }
+
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .appname(APP_NAME)
+        .summary(summary)
+        .body(&preview(body));
+    if let Some(sound) = &settings.platform.sound {
+        notification.sound_name(sound);
+    }
+    if let Some(timeout) = settings.platform.timeout_ms {
+        notification.timeout(notify_rust::Timeout::Milliseconds(timeout));
+    }
+
+    // Showing may wait on D-Bus or the notification center; a failure is not worth reporting
+    std::thread::spawn(move || {
+        let _ = notification.show();
+    });
+}
+
+/// First line of a reply, short enough for a notification bubble
+fn preview(text: &str) -> String {
+    let line = text.trim().lines().next().unwrap_or_default();
+    if line.chars().count() > 120 {
+        format!("{}…", line.chars().take(119).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// Whether the terminal window is in front; `None` when that cannot be told
+#[cfg(target_os = "macos")]
+fn terminal_focused() -> Option<bool> {
+    let terminal = std::env::var("TERM_PROGRAM").ok()?;
+    let output = Command::new("osascript")
+        .args(["-e", "tell application \"System Events\" to get name of first application process whose frontmost is true"])
+        .output()
+        .ok()?;
+    let frontmost = String::from_utf8_lossy(&output.stdout).trim().to_lowercase();
+
+    // TERM_PROGRAM names differ from process names: Apple_Terminal, iTerm.app, vscode
+    let terminal = terminal.to_lowercase();
+    let terminal = match terminal.as_str() {
+        "vscode" => "code",
+        name => name.trim_start_matches("apple_").trim_end_matches(".app"),
+    };
+    Some(frontmost.contains(terminal))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn terminal_focused() -> Option<bool> {
+    // Set by most X11 terminal emulators; compared with the active window
+    let window: u64 = std::env::var("WINDOWID").ok()?.parse().ok()?;
+    let output = Command::new("xdotool").arg("getactivewindow").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let active: u64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    Some(active == window)
+}
+
+#[cfg(not(unix))]
+fn terminal_focused() -> Option<bool> {
+    None
+}
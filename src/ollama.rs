@@ -1,45 +1,149 @@
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+use crate::backend::ChatBackend;
+use crate::error::Failure;
+
+/// The model chatted with unless another is chosen
+pub const DEFAULT_MODEL: &str = "llama3.2:1b";
+
+/// Tokens Ollama gives a model that sets no `num_ctx`, whatever it was
+/// trained on; older servers used 2048
+const DEFAULT_NUM_CTX: usize = 4096;
+
 #[derive(Debug, Serialize)]
 pub struct ChatRequest {
     pub model: String,
     pub messages: Vec<Message>,
     pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<serde_json::Value>,
+    /// How long Ollama keeps the model loaded afterwards, e.g. "30m"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<serde_json::Value>,
+    /// Tools the model may call, as {"type": "function", "function": {...}} definitions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<serde_json::Value>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
     pub content: String,
+    /// Base64-encoded images for vision models
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+    /// Calls the model made through the `tools` of the request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// One entry of a reply's `tool_calls`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub function: FunctionCall,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: serde_json::Value,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ChatResponse {
     pub message: Message,
-    // Note: 'done' field exists in API but we don't need it for non-streaming
-    #[allow(dead_code)]
+    /// False on all but the last piece of a streamed reply
     pub done: bool,
+    /// Number of tokens in the prompt
+    #[serde(default)]
+    pub prompt_eval_count: Option<u64>,
+    /// Number of tokens in the response
+    #[serde(default)]
+    pub eval_count: Option<u64>,
+    /// Served from the response cache rather than the model
+    #[serde(skip)]
+    pub cached: bool,
 }
 
+#[derive(Clone)]
 pub struct OllamaClient {
     base_url: String,
     client: reqwest::Client,
+    keep_alive: Option<serde_json::Value>,
 }
 
 impl OllamaClient {
     pub fn new() -> Self {
+        Self::with_base_url("http://localhost:11434".to_string())
+    }
+
+    pub fn with_base_url(base_url: String) -> Self {
         Self {
-            base_url: "http://localhost:11434".to_string(),
+            base_url,
             client: reqwest::Client::new(),
+            keep_alive: None,
         }
     }
 
-    pub async fn chat(&self, model: &str, messages: Vec<Message>) -> Result<String> {
+    /// Sends `keep_alive` with every chat request, overriding Ollama's default of 5 minutes
+    pub fn with_keep_alive(mut self, keep_alive: Option<serde_json::Value>) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Loads a model into memory without generating anything, so the next
+    /// request does not wait for it
+    pub async fn preload(&self, model: &str) -> Result<()> {
+        let request = ChatRequest {
+            model: model.to_string(),
+            messages: Vec::new(),
+            stream: false,
+            options: None,
+            keep_alive: self.keep_alive.clone(),
+            tools: None,
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send request to Ollama")
+            .context(Failure::Connection)?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+        Ok(())
+    }
+
+    /// `preload` on a background task; a failure shows up again on the first real request
+    pub fn preload_in_background(&self, model: &str) {
+        let client = self.clone();
+        let model = model.to_string();
+        tokio::spawn(async move {
+            let _ = client.preload(&model).await;
+        });
+    }
+
+    /// Sends a chat request with optional model options and returns the full response
+    pub async fn complete(
+        &self,
+        model: &str,
+        messages: Vec<Message>,
+        options: Option<serde_json::Value>,
+    ) -> Result<ChatResponse> {
         let request = ChatRequest {
             model: model.to_string(),
             messages,
             stream: false,
+            options,
+            keep_alive: self.keep_alive.clone(),
+            tools: None,
         };
 
         let response = self
@@ -48,11 +152,11 @@ impl OllamaClient {
             .json(&request)
             .send()
             .await
-            .context("Failed to send request to Ollama")?;
+            .context("Failed to send request to Ollama")
+            .context(Failure::Connection)?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Ollama API error: {}", error_text);
+            return Err(api_error(response).await);
         }
 
         let chat_response: ChatResponse = response
@@ -60,7 +164,115 @@ impl OllamaClient {
             .await
             .context("Failed to parse Ollama response")?;
 
-        Ok(chat_response.message.content)
+        Ok(chat_response)
+    }
+
+    /// Like `complete`, but streams the reply, calling `on_token` with each
+    /// piece as it arrives, and offers the model `tools` to call
+    pub async fn stream_complete(
+        &self,
+        model: &str,
+        messages: Vec<Message>,
+        options: Option<serde_json::Value>,
+        tools: Option<Vec<serde_json::Value>>,
+        mut on_token: impl FnMut(&str),
+    ) -> Result<ChatResponse> {
+        let request = ChatRequest {
+            model: model.to_string(),
+            messages,
+            stream: true,
+            options,
+            keep_alive: self.keep_alive.clone(),
+            tools,
+        };
+
+        let mut response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send request to Ollama")
+            .context(Failure::Connection)?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        // Pieces arrive as newline-delimited JSON; the last one, marked done, carries the counts
+        let mut content = String::new();
+        // Tool calls come whole, in whichever piece the model finished them
+        let mut tool_calls = Vec::new();
+        let mut buffer = Vec::new();
+        while let Some(chunk) = response.chunk().await.context("Reply interrupted").context(Failure::Connection)? {
+            buffer.extend_from_slice(&chunk);
+
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let value: serde_json::Value = match serde_json::from_slice(&line) {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                };
+
+                if let Some(error) = value["error"].as_str() {
+                    return Err(anyhow::anyhow!("Ollama API error: {}", error).context(Failure::Generation));
+                }
+                let mut piece: ChatResponse = serde_json::from_value(value)
+                    .context("Failed to parse Ollama response")?;
+                if !piece.message.content.is_empty() {
+                    on_token(&piece.message.content);
+                    content.push_str(&piece.message.content);
+                }
+                tool_calls.append(&mut piece.message.tool_calls);
+                if piece.done {
+                    return Ok(ChatResponse {
+                        message: Message { content, tool_calls, ..piece.message },
+                        ..piece
+                    });
+                }
+            }
+        }
+
+        Err(anyhow::anyhow!("Reply ended before it was complete").context(Failure::Connection))
+    }
+
+    /// Asks a fill-in-the-middle model for the text between `prefix` and
+    /// `suffix`, through /api/generate
+    pub async fn fill_in_middle(
+        &self,
+        model: &str,
+        prefix: &str,
+        suffix: &str,
+        options: Option<serde_json::Value>,
+    ) -> Result<String> {
+        let mut request = serde_json::json!({
+            "model": model,
+            "prompt": prefix,
+            "suffix": suffix,
+            "stream": false,
+        });
+        if let Some(options) = options {
+            request["options"] = options;
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/api/generate", self.base_url))
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send request to Ollama")
+            .context(Failure::Connection)?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let data: serde_json::Value = response
+            .json()
+            .await
+            .context("Failed to parse Ollama response")?;
+        Ok(data["response"].as_str().unwrap_or_default().to_string())
     }
 
     pub async fn list_models(&self) -> Result<Vec<String>> {
@@ -69,7 +281,8 @@ impl OllamaClient {
             .get(format!("{}/api/tags", self.base_url))
             .send()
             .await
-            .context("Failed to list models")?;
+            .context("Failed to list models")
+            .context(Failure::Connection)?;
 
         let data: serde_json::Value = response.json().await?;
         
@@ -84,4 +297,174 @@ impl OllamaClient {
 
         Ok(models)
     }
+
+    /// Context window a model is run with: its `num_ctx` parameter from
+    /// /api/show, or else the server's default (`OLLAMA_CONTEXT_LENGTH`, or
+    /// 4096), never more than the length it was trained on
+    pub async fn context_window(&self, model: &str) -> Result<Option<usize>> {
+        let response = self
+            .client
+            .post(format!("{}/api/show", self.base_url))
+            .json(&serde_json::json!({ "model": model }))
+            .send()
+            .await
+            .context("Failed to query model details")
+            .context(Failure::Connection)?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let data: serde_json::Value = response.json().await?;
+
+        let num_ctx = data["parameters"].as_str().and_then(|params| {
+            params.lines()
+                .filter_map(|line| line.trim().strip_prefix("num_ctx"))
+                .find_map(|value| value.trim().parse().ok())
+        });
+
+        let trained = data["model_info"].as_object().and_then(|info| {
+            info.iter()
+                .find(|(key, _)| key.ends_with(".context_length"))
+                .and_then(|(_, value)| value.as_u64())
+                .map(|n| n as usize)
+        });
+
+        // The trained length is only an upper bound: without num_ctx, Ollama
+        // truncates prompts to its default context size
+        let default = std::env::var("OLLAMA_CONTEXT_LENGTH").ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(DEFAULT_NUM_CTX);
+        let window = num_ctx.unwrap_or(default);
+        Ok(Some(trained.map_or(window, |trained| window.min(trained))))
+    }
+
+    /// Embeds each input with an embedding model such as nomic-embed-text
+    pub async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let response = self
+            .client
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&serde_json::json!({ "model": model, "input": inputs }))
+            .send()
+            .await
+            .context("Failed to request embeddings")
+            .context(Failure::Connection)?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        #[derive(Deserialize)]
+        struct EmbedResponse {
+            embeddings: Vec<Vec<f32>>,
+        }
+        let data: EmbedResponse = response
+            .json()
+            .await
+            .context("Failed to parse embeddings")?;
+
+        if data.embeddings.len() != inputs.len() {
+            anyhow::bail!("Expected {} embeddings, got {}", inputs.len(), data.embeddings.len());
+        }
+        Ok(data.embeddings)
+    }
+
+    /// Downloads a model, reporting each progress update as it streams in
+    pub async fn pull_model(&self, model: &str, mut on_progress: impl FnMut(&PullProgress)) -> Result<()> {
+        let mut response = self
+            .client
+            .post(format!("{}/api/pull", self.base_url))
+            .json(&serde_json::json!({ "model": model, "stream": true }))
+            .send()
+            .await
+            .context("Failed to start model download")
+            .context(Failure::Connection)?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        // Progress arrives as newline-delimited JSON that may span chunks
+        let mut buffer = Vec::new();
+        while let Some(chunk) = response.chunk().await.context("Model download interrupted")? {
+            buffer.extend_from_slice(&chunk);
+
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let value: serde_json::Value = match serde_json::from_slice(&line) {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                };
+
+                if let Some(error) = value["error"].as_str() {
+                    anyhow::bail!("Failed to pull '{}': {}", model, error);
+                }
+                if let Ok(progress) = serde_json::from_value::<PullProgress>(value) {
+                    on_progress(&progress);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChatBackend for OllamaClient {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    async fn chat(&self, model: &str, messages: Vec<Message>, options: Option<serde_json::Value>) -> Result<ChatResponse> {
+        self.complete(model, messages, options).await
+    }
+
+    async fn stream(
+        &self,
+        model: &str,
+        messages: Vec<Message>,
+        options: Option<serde_json::Value>,
+        tools: Option<Vec<serde_json::Value>>,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<ChatResponse> {
+        self.stream_complete(model, messages, options, tools, |text| on_token(text)).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        OllamaClient::list_models(self).await
+    }
+
+    async fn context_window(&self, model: &str) -> Result<Option<usize>> {
+        OllamaClient::context_window(self, model).await
+    }
+
+    async fn preload(&self, model: &str) -> Result<()> {
+        OllamaClient::preload(self, model).await
+    }
+
+    async fn fill_in_middle(&self, model: &str, prefix: &str, suffix: &str, options: Option<serde_json::Value>) -> Result<String> {
+        OllamaClient::fill_in_middle(self, model, prefix, suffix, options).await
+    }
+}
+
+/// One line of the progress stream returned by /api/pull
+#[derive(Debug, Deserialize)]
+pub struct PullProgress {
+    pub status: String,
+    #[serde(default)]
+    pub total: Option<u64>,
+    #[serde(default)]
+    pub completed: Option<u64>,
+}
+
+/// Ollama answers 404 when the requested model is not installed
+async fn api_error(response: reqwest::Response) -> anyhow::Error {
+    let failure = if response.status() == reqwest::StatusCode::NOT_FOUND {
+        Failure::ModelMissing
+    } else {
+        Failure::Generation
+    };
+
+    let error_text = response.text().await.unwrap_or_default();
+    anyhow::anyhow!("Ollama API error: {}", error_text).context(failure)
 }
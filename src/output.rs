@@ -0,0 +1,91 @@
+use rustyline::ExternalPrinter;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+/// Prints above the REPL's line editor; only on a terminal
+static PRINTER: Mutex<Option<Box<dyn ExternalPrinter + Send>>> = Mutex::new(None);
+/// Whether the REPL's prompt is open and waiting for a line
+static PROMPT_OPEN: AtomicBool = AtomicBool::new(false);
+
+/// Applies the --quiet / --plain flags for the whole process
+pub fn init(quiet: bool, plain: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+
+    // Quiet output is meant for pipelines, so it is always plain as well
+    if quiet || plain {
+        colored::control::set_override(false);
+    }
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Hands over the REPL's printer, used while its prompt is open
+pub fn set_printer(printer: Option<Box<dyn ExternalPrinter + Send>>) {
+    *PRINTER.lock().unwrap() = printer;
+}
+
+pub fn set_prompt_open(open: bool) {
+    PROMPT_OPEN.store(open, Ordering::Relaxed);
+}
+
+/// Whether lines are printed above an open prompt rather than after it
+pub fn prompt_open() -> bool {
+    PROMPT_OPEN.load(Ordering::Relaxed) && PRINTER.lock().unwrap().is_some()
+}
+
+/// Prints `text` above the open prompt without disturbing what is being
+/// typed; false when no prompt is open
+pub fn above_prompt(text: &str) -> bool {
+    if !PROMPT_OPEN.load(Ordering::Relaxed) {
+        return false;
+    }
+    match PRINTER.lock().unwrap().as_mut() {
+        Some(printer) => printer.print(text.to_string()).is_ok(),
+        None => false,
+    }
+}
+
+/// Prints a line, above the prompt while one is open
+pub fn line(text: String) {
+    if !above_prompt(&text) {
+        println!("{}", text);
+    }
+}
+
+/// Like `line`, on stderr when no prompt is open
+pub fn error_line(text: String) {
+    if !above_prompt(&text) {
+        eprintln!("{}", text);
+    }
+}
+
+/// Prints a banner or progress line, unless --quiet is set
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            $crate::output::line(format!($($arg)*));
+        }
+    };
+}
+
+/// `println!` for the REPL, keeping an open prompt intact
+macro_rules! say {
+    () => {
+        $crate::output::line(String::new())
+    };
+    ($($arg:tt)*) => {
+        $crate::output::line(format!($($arg)*))
+    };
+}
+
+/// `eprintln!` for the REPL, keeping an open prompt intact
+macro_rules! say_error {
+    ($($arg:tt)*) => {
+        $crate::output::error_line(format!($($arg)*))
+    };
+}
+
+pub(crate) use {say, say_error, status};
@@ -0,0 +1,431 @@
+//! Guardrails for tool calls, loaded from ~/.ai-chat-cli/policy.yaml.
+//!
+//! Every call goes through `McpManager::call_tool`, which asks the policy
+//! before dispatching, so the same rules cover built-in and external tools.
+//! Tools listed under `require_approval` additionally need a yes from the
+//! user, asked for by the frontend through an `ApprovalRequest`.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::Failure;
+
+/// Commands refused when the policy file does not list its own
+const DEFAULT_FORBIDDEN: &[&str] = &["rm -rf /", "dd if=", "mkfs", "format", "> /dev/"];
+
+/// Tools that ask before every call even without a policy file
+const ALWAYS_APPROVE: &[&str] = &["screenshot", "github_comment"];
+
+/// Argument names whose values are treated as file system paths
+const PATH_KEYS: &[&str] = &["path", "file", "dir", "directory", "source", "destination"];
+
+#[derive(Debug, Default, Deserialize)]
+struct PolicyFile {
+    /// Regular expressions matched against shell commands
+    #[serde(default)]
+    forbidden_commands: Option<Vec<String>>,
+    /// Paths no tool may read or modify, including everything below them
+    #[serde(default)]
+    protected_paths: Vec<String>,
+    #[serde(default)]
+    network: NetworkRules,
+    /// Files that may be deleted per session
+    #[serde(default)]
+    max_deletions: Option<usize>,
+    /// Tools that only run after the user approves the call ("*" for all)
+    #[serde(default)]
+    require_approval: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct NetworkRules {
+    /// Hosts URLs may point to; when set, every other host is refused
+    #[serde(default)]
+    allow: Vec<String>,
+    /// Hosts URLs may never point to ("*" for all)
+    #[serde(default)]
+    deny: Vec<String>,
+}
+
+/// The user's answer to an approval request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Approval {
+    /// Run this call only
+    Once,
+    /// Refuse this call only
+    Deny,
+    /// Run every call of this tool for the rest of the session
+    Always,
+    /// Refuse every call of this tool for the rest of the session
+    Never,
+}
+
+impl Approval {
+    pub const PROMPT: &str = "Allow? [y]es / [n]o / [a]lways / ne[v]er";
+
+    /// Parses an answer to `PROMPT`; anything unrecognized refuses
+    pub fn parse(answer: &str) -> Self {
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => Approval::Once,
+            "a" | "always" => Approval::Always,
+            "v" | "never" => Approval::Never,
+            _ => Approval::Deny,
+        }
+    }
+}
+
+/// Everything the user needs to judge a tool call before approving it
+#[derive(Debug, Clone)]
+pub struct ApprovalRequest {
+    pub tool: String,
+    pub server: String,
+    /// Exact shell command, for bash
+    pub command: Option<String>,
+    /// Files and directories the call refers to
+    pub paths: Vec<PathBuf>,
+    /// Arguments as pretty-printed JSON
+    pub arguments: String,
+}
+
+impl ApprovalRequest {
+    /// Titled blocks of lines, in display order; empty blocks are left out
+    pub fn sections(&self) -> Vec<(&'static str, Vec<String>)> {
+        let mut sections = Vec::new();
+        if let Some(command) = &self.command {
+            sections.push(("Command", command.lines().map(String::from).collect()));
+        }
+        if !self.paths.is_empty() {
+            sections.push(("Affected paths", self.paths.iter().map(|p| p.display().to_string()).collect()));
+        }
+        sections.push(("Arguments", self.arguments.lines().map(String::from).collect()));
+        sections
+    }
+}
+
+pub struct Policy {
+    forbidden_commands: Vec<Regex>,
+    protected_paths: Vec<PathBuf>,
+    network: NetworkRules,
+    max_deletions: Option<usize>,
+    deletions: usize,
+    require_approval: Vec<String>,
+    /// Always/Never answers, kept for the session
+    remembered: HashMap<String, Approval>,
+    /// Tools approved for their next call only
+    approved_once: HashSet<String>,
+    url: Regex,
+    /// Whether rules came from a policy file rather than the defaults
+    custom: bool,
+}
+
+impl Policy {
+    pub fn path() -> Result<PathBuf> {
+        let home = dirs::home_dir()
+            .context("Could not find home directory")?;
+        Ok(home.join(".ai-chat-cli").join("policy.yaml"))
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        let (file, custom) = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .context(format!("Failed to read policy: {}", path.display()))?;
+            let file: PolicyFile = serde_yaml::from_str(&content)
+                .context(format!("Invalid policy file: {}", path.display()))?;
+            (file, true)
+        } else {
+            (PolicyFile::default(), false)
+        };
+
+        let forbidden_commands = match file.forbidden_commands {
+            Some(patterns) => patterns.iter()
+                .map(|p| Regex::new(p).context(format!("Invalid forbidden_commands pattern: {}", p)))
+                .collect::<Result<_>>()?,
+            None => DEFAULT_FORBIDDEN.iter()
+                .map(|p| Regex::new(&regex::escape(p)).unwrap())
+                .collect(),
+        };
+
+        Ok(Self {
+            forbidden_commands,
+            protected_paths: file.protected_paths.iter().map(|p| resolve(p)).collect(),
+            network: file.network,
+            max_deletions: file.max_deletions,
+            deletions: 0,
+            require_approval: file.require_approval,
+            remembered: HashMap::new(),
+            approved_once: HashSet::new(),
+            url: Regex::new(r#"(?i)\b[a-z][a-z0-9+.-]*://(?:[^/\s@'"]*@)?([^/\s:'"?#]+)"#).unwrap(),
+            custom,
+        })
+    }
+
+    pub fn is_custom(&self) -> bool {
+        self.custom
+    }
+
+    /// Refuses the call with `Failure::ToolDenied` if it breaks a rule or
+    /// lacks approval; allowed deletions count towards `max_deletions`
+    pub fn check(&mut self, tool: &str, arguments: &Value) -> Result<()> {
+        let denied = |reason: String| anyhow::anyhow!(reason).context(Failure::ToolDenied);
+
+        let deletions = self.evaluate(tool, arguments)
+            .map_err(|reason| denied(format!("Blocked by policy: {}", reason)))?;
+
+        if self.requires_approval(tool, arguments) {
+            match self.remembered.get(tool) {
+                Some(Approval::Always) => {}
+                Some(Approval::Never) => return Err(denied(format!("'{}' was refused for this session", tool))),
+                _ if self.approved_once.remove(tool) => {}
+                _ => return Err(denied(format!("'{}' requires approval", tool))),
+            }
+        }
+
+        self.deletions += deletions;
+        Ok(())
+    }
+
+    /// Like `check`, but only the rules: for callers that ask the user
+    /// themselves before every call, such as the `cmd` subcommand
+    pub fn check_rules(&self, tool: &str, arguments: &Value) -> Result<()> {
+        self.evaluate(tool, arguments)
+            .map(|_| ())
+            .map_err(|reason| anyhow::anyhow!("Blocked by policy: {}", reason).context(Failure::ToolDenied))
+    }
+
+    /// What to show the user before `check` is called, or None when no
+    /// approval is needed (or the call will be refused regardless)
+    pub fn approval_request(&self, tool: &str, server: &str, arguments: &Value) -> Option<ApprovalRequest> {
+        if !self.requires_approval(tool, arguments) || self.remembered.contains_key(tool)
+            || self.evaluate(tool, arguments).is_err()
+        {
+            return None;
+        }
+
+        let command = arguments["command"].as_str().filter(|_| tool == "bash");
+        let mut strings = Vec::new();
+        collect_strings(None, arguments, &mut strings);
+
+        let mut paths: Vec<PathBuf> = strings.iter()
+            .filter(|(key, _)| key.as_deref().is_some_and(is_path_key))
+            .map(|(_, value)| resolve(value))
+            .collect();
+        if let Some(command) = command {
+            paths.extend(command_paths(command));
+        }
+        paths.dedup();
+
+        Some(ApprovalRequest {
+            tool: tool.to_string(),
+            server: server.to_string(),
+            command: command.map(String::from),
+            paths,
+            arguments: serde_json::to_string_pretty(arguments).unwrap_or_default(),
+        })
+    }
+
+    pub fn decide(&mut self, tool: &str, approval: Approval) {
+        match approval {
+            Approval::Once => {
+                self.approved_once.insert(tool.to_string());
+            }
+            Approval::Deny => {}
+            Approval::Always | Approval::Never => {
+                self.remembered.insert(tool.to_string(), approval);
+            }
+        }
+    }
+
+    fn requires_approval(&self, tool: &str, arguments: &Value) -> bool {
+        ALWAYS_APPROVE.contains(&tool)
+            // What the model sets reaches every command the tools run later
+            || tool == "env" && crate::env_vars::changes(arguments)
+            || self.require_approval.iter().any(|t| t == "*" || t == tool)
+    }
+
+    /// Returns the number of deletions the call makes, or why it is refused
+    fn evaluate(&self, tool: &str, arguments: &Value) -> std::result::Result<usize, String> {
+        let command = arguments["command"].as_str().filter(|_| tool == "bash");
+
+        if tool == "env"
+            && let Some(name) = crate::env_vars::protected_change(arguments)
+        {
+            return Err(format!("{} decides which programs commands run, so env cannot change it", name));
+        }
+        if let Some(command) = command
+            && let Some(rule) = self.forbidden_commands.iter().find(|r| r.is_match(command))
+        {
+            return Err(format!("command matches forbidden pattern '{}'", rule.as_str()));
+        }
+
+        let mut strings = Vec::new();
+        collect_strings(None, arguments, &mut strings);
+
+        for (key, value) in &strings {
+            let is_path = key.as_deref().is_some_and(is_path_key);
+            if is_path && let Some(protected) = self.protected(&resolve(value)) {
+                return Err(format!("{} is protected", protected.display()));
+            }
+
+            for host in self.url.captures_iter(value).map(|c| c[1].to_lowercase()) {
+                if !self.host_allowed(&host) {
+                    return Err(format!("network access to {} is not allowed", host));
+                }
+            }
+        }
+
+        if let Some(command) = command {
+            for protected in &self.protected_paths {
+                if mentions_path(command, protected) {
+                    return Err(format!("{} is protected", protected.display()));
+                }
+            }
+            if let Some(protected) = command_paths(command).iter().find_map(|path| self.protected(path)) {
+                return Err(format!("{} is protected", protected.display()));
+            }
+            // `$HOME/...` is expanded by the shell, after the words are resolved here
+            if ["$HOME", "${HOME}"].iter().any(|var| command.contains(var))
+                && let Some(home) = dirs::home_dir()
+                && let Some(protected) = self.protected_paths.iter().find(|p| p.starts_with(&home))
+            {
+                return Err(format!("{} is protected, so commands may not use $HOME", protected.display()));
+            }
+        }
+
+        let deletions = match command {
+            Some(command) => count_deletions(command),
+            None if tool.contains("delete") || tool.contains("remove") => 1,
+            None => 0,
+        };
+        if let Some(max) = self.max_deletions
+            && self.deletions + deletions > max
+        {
+            return Err(format!("more than {} file deletion(s) this session ({} so far)", max, self.deletions));
+        }
+
+        Ok(deletions)
+    }
+
+    fn protected(&self, path: &Path) -> Option<&PathBuf> {
+        let real = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.protected_paths.iter()
+            .find(|p| path.starts_with(p) || real.starts_with(p))
+    }
+
+    fn host_allowed(&self, host: &str) -> bool {
+        if self.network.deny.iter().any(|p| host_matches(p, host))
+            && !self.network.allow.iter().any(|p| host_matches(p, host))
+        {
+            return false;
+        }
+        self.network.allow.is_empty() || self.network.allow.iter().any(|p| host_matches(p, host))
+    }
+}
+
+/// Gathers every string in the arguments along with the key it is stored under
+fn collect_strings(key: Option<&str>, value: &Value, out: &mut Vec<(Option<String>, String)>) {
+    match value {
+        Value::String(s) => out.push((key.map(str::to_string), s.clone())),
+        Value::Array(items) => items.iter().for_each(|v| collect_strings(key, v, out)),
+        Value::Object(map) => map.iter().for_each(|(k, v)| collect_strings(Some(k), v, out)),
+        _ => {}
+    }
+}
+
+fn is_path_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    PATH_KEYS.iter().any(|p| key.contains(p))
+}
+
+/// Words of a shell command that name existing files or look like paths,
+/// relative ones taken from the directory the last `cd` before them moved to
+fn command_paths(command: &str) -> Vec<PathBuf> {
+    let mut dir = std::env::current_dir().unwrap_or_default();
+    let mut paths = Vec::new();
+    for segment in command.split(['\n', ';', '&', '|']) {
+        let words: Vec<&str> = segment.split_whitespace()
+            .map(|word| word.trim_matches(|c| matches!(c, '\'' | '"' | '>' | '<' | '(' | ')')))
+            .filter(|word| !word.is_empty())
+            .collect();
+        if let ["cd", target, ..] = words.as_slice() {
+            dir = resolve_in(&dir, target);
+        }
+        paths.extend(words.iter()
+            .filter(|word| !word.starts_with('-') && !word.contains("://"))
+            .filter(|word| word.contains('/') || word.starts_with('~') || dir.join(word).exists())
+            .map(|word| resolve_in(&dir, word)));
+    }
+    paths
+}
+
+/// Expands `~` and makes the path absolute, resolving `.` and `..` lexically
+fn resolve(path: &str) -> PathBuf {
+    resolve_in(&std::env::current_dir().unwrap_or_default(), path)
+}
+
+/// `resolve` for a path relative to `dir` instead of the working directory
+fn resolve_in(dir: &Path, path: &str) -> PathBuf {
+    let expanded = match path.strip_prefix('~') {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest.trim_start_matches('/')))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    };
+    let absolute = if expanded.is_absolute() {
+        expanded
+    } else {
+        dir.join(expanded)
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Whether a shell command refers to the path, absolutely or via `~`
+fn mentions_path(command: &str, path: &Path) -> bool {
+    let absolute = path.to_string_lossy();
+    if command.contains(absolute.as_ref()) {
+        return true;
+    }
+    dirs::home_dir()
+        .and_then(|home| path.strip_prefix(home).ok().map(|rel| format!("~/{}", rel.display())))
+        .is_some_and(|tilde| command.contains(&tilde))
+}
+
+/// Number of operands passed to rm, rmdir, unlink and shred in a shell command
+fn count_deletions(command: &str) -> usize {
+    command.split(['\n', ';', '&', '|'])
+        .map(|segment| {
+            let mut words = segment.split_whitespace()
+                .skip_while(|w| *w == "sudo" || w.contains('='));
+            match words.next() {
+                Some("rm" | "rmdir" | "unlink" | "shred") => words.filter(|w| !w.starts_with('-')).count(),
+                _ => 0,
+            }
+        })
+        .sum()
+}
+
+/// "*" matches any host, "*.example.com" any subdomain, anything else exactly
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    match pattern.strip_prefix("*.") {
+        _ if pattern == "*" => true,
+        Some(domain) => host == domain || host.ends_with(&format!(".{}", domain)),
+        None => host == pattern,
+    }
+}
@@ -0,0 +1,127 @@
+//! Pull request descriptions for /pr-description.
+//!
+//! The commits and diff of the current branch since it left the base branch
+//! are put into a prompt asking for a title, a summary and a test plan. The
+//! result can be handed to `gh pr create`.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Larger diffs are cut; the commit messages and the file stat still cover the rest
+const MAX_DIFF_CHARS: usize = 40_000;
+const TEMPLATES: &[&str] = &[
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    "PULL_REQUEST_TEMPLATE.md",
+    "docs/pull_request_template.md",
+];
+
+const PROMPT: &str = "Write a pull request description for the changes below. \
+Reply with the title on the first line: plain text, under 72 characters, no prefix like \"Title:\". \
+Then a blank line and the body in Markdown. Start the body with one or two sentences on what the \
+change does and why, then a \"## Summary\" section with short bullet points and a \"## Test plan\" \
+section saying how to verify the change. Describe only what the commits and diff show.";
+
+/// The current branch compared with its base
+pub struct BranchChanges {
+    pub branch: String,
+    pub base: String,
+    pub commits: usize,
+    prompt: String,
+}
+
+impl BranchChanges {
+    /// Collects the commits and diff of HEAD since it diverged from `base`
+    /// (default: the remote's default branch, main or master)
+    pub fn collect(base: Option<&str>) -> Result<Self> {
+        let base = match base {
+            Some(base) => base.to_string(),
+            None => default_base().context("Could not tell the base branch; pass it, e.g. /pr-description main")?,
+        };
+        let branch = git(&["rev-parse", "--abbrev-ref", "HEAD"])?.trim().to_string();
+        let fork_point = git(&["merge-base", &base, "HEAD"])
+            .with_context(|| format!("No common history with '{}'", base))?;
+        let range = format!("{}..HEAD", fork_point.trim());
+
+        let log = git(&["log", "--reverse", "--format=%h %s%n%b", &range])?;
+        let commits = git(&["rev-list", "--count", &range])?.trim().parse().unwrap_or(0);
+        if commits == 0 {
+            anyhow::bail!("'{}' has no commits that are not on '{}'", branch, base);
+        }
+        let stat = git(&["diff", "--stat", &range])?;
+        let mut diff = git(&["diff", &range])?;
+        if diff.len() > MAX_DIFF_CHARS {
+            let end = (0..=MAX_DIFF_CHARS).rev().find(|&i| diff.is_char_boundary(i)).unwrap_or(0);
+            diff.truncate(end);
+            diff.push_str("\n... (diff truncated)\n");
+        }
+
+        let mut prompt = PROMPT.to_string();
+        if let Some(template) = template() {
+            prompt.push_str(&format!(" Use the sections of the repository's pull request template instead of \
+                Summary and Test plan, skipping any that do not apply:\n\n{}", template));
+        }
+        prompt.push_str(&format!("\n\nBranch {} onto {}, {} commit(s):\n{}\n\nFiles changed:\n{}\n```diff\n{}```",
+            branch, base, commits, log.trim(), stat.trim_end(), diff));
+
+        Ok(Self { branch, base, commits, prompt })
+    }
+
+    pub fn prompt(&self) -> &str {
+        &self.prompt
+    }
+}
+
+/// Title and body of a generated description
+pub fn split_description(text: &str) -> (String, String) {
+    let text = text.trim();
+    let (title, body) = text.split_once('\n').unwrap_or((text, ""));
+    let title = title.trim().trim_start_matches('#').trim().trim_matches('*').trim();
+    (title.to_string(), body.trim().to_string())
+}
+
+/// Opens the pull request with `gh pr create` and returns its URL
+pub fn create(base: &str, title: &str, body: &str) -> Result<String> {
+    // Only the branch part, gh wants "main" rather than "origin/main"
+    let base = base.split_once('/').map_or(base, |(_, branch)| branch);
+    let mut child = Command::new("gh")
+        .args(["pr", "create", "--base", base, "--title", title, "--body-file", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run gh; is the GitHub CLI installed?")?;
+    child.stdin.take().context("No stdin for gh")?.write_all(body.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!("gh pr create failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).output().context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn default_base() -> Option<String> {
+    if let Ok(head) = git(&["symbolic-ref", "--short", "refs/remotes/origin/HEAD"]) {
+        return Some(head.trim().to_string());
+    }
+    ["main", "master"].into_iter()
+        .find(|branch| git(&["rev-parse", "--verify", "--quiet", branch]).is_ok())
+        .map(str::to_string)
+}
+
+fn template() -> Option<String> {
+    let root = git(&["rev-parse", "--show-toplevel"]).ok()?;
+    TEMPLATES.iter()
+        .find_map(|file| std::fs::read_to_string(Path::new(root.trim()).join(file)).ok())
+        .filter(|text| !text.trim().is_empty())
+}
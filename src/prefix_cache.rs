@@ -0,0 +1,98 @@
+//! Keeping requests friendly to prompt caching. Ollama reuses the part of
+//! the previous prompt that is unchanged and hosted APIs cache prefixes,
+//! so a request is only quick to start when its beginning is identical to
+//! an earlier one.
+//!
+//! The conversation only grows at the end, and the tools prompt lists tools
+//! by name so that it only changes with the set of tools described. With tool
+//! selection that set changes with every message; sent in its place at the
+//! start, the tools prompt would make the whole conversation after it be
+//! evaluated again, so `arrange` sends it just before the latest message.
+
+use colored::*;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use crate::accessibility;
+use crate::ollama::Message;
+use crate::tokens;
+
+/// The messages of a request: `history` in order, except that the system
+/// message holding `volatile` goes right before the last user message
+pub fn arrange(history: &[Message], volatile: Option<&str>) -> Vec<Message> {
+    let is_volatile = |m: &Message| m.role == "system" && Some(m.content.as_str()) == volatile;
+    let mut messages: Vec<Message> = history.iter().filter(|m| !is_volatile(m)).cloned().collect();
+    if let Some(moved) = history.iter().find(|m| is_volatile(m)) {
+        let at = messages.iter().rposition(|m| m.role == "user").unwrap_or(messages.len());
+        messages.insert(at, moved.clone());
+    }
+    messages
+}
+
+/// How much of each request repeats the one before, for /stats
+#[derive(Debug, Default)]
+pub struct PrefixTracker {
+    /// The messages sent last time, then the reply, hashed
+    previous: Vec<u64>,
+    requests: usize,
+    prompt_tokens: usize,
+    reused_tokens: usize,
+    /// Prompt tokens the backend reports evaluating, without those it reused
+    evaluated_tokens: u64,
+    first_tokens: Vec<Duration>,
+}
+
+impl PrefixTracker {
+    /// Records a request about to be sent, returning the estimated tokens
+    /// at its start that are unchanged from the previous one
+    pub fn sent(&mut self, request: &[Message]) -> usize {
+        let hashes: Vec<u64> = request.iter().map(hash).collect();
+        let unchanged = hashes.iter().zip(&self.previous).take_while(|(a, b)| a == b).count();
+        let reused = tokens::estimate(&request[..unchanged]);
+
+        self.requests += 1;
+        self.prompt_tokens += tokens::estimate(request);
+        self.reused_tokens += reused;
+        self.previous = hashes;
+        reused
+    }
+
+    /// Records the reply to the last request; it is part of the next prefix
+    pub fn replied(&mut self, reply: &Message, evaluated: Option<u64>, first_token: Option<Duration>) {
+        self.previous.push(hash(reply));
+        self.evaluated_tokens += evaluated.unwrap_or_default();
+        self.first_tokens.extend(first_token);
+    }
+
+    pub fn print_stats(&self) {
+        println!("{}", "Prompt prefix:".bright_yellow().bold());
+        println!("{}", accessibility::rule('-').bright_black());
+        if self.requests == 0 {
+            println!("  No requests in this session yet\n");
+            return;
+        }
+
+        println!("  {:<24} {}", "Requests", self.requests);
+        println!("  {:<24} ~{} of ~{} prompt tokens ({}%)", "Unchanged prefix",
+            tokens::format_count(self.reused_tokens), tokens::format_count(self.prompt_tokens),
+            self.reused_tokens * 100 / self.prompt_tokens.max(1));
+        if self.evaluated_tokens > 0 {
+            println!("  {:<24} {} prompt tokens", "Evaluated by the model", tokens::format_count(self.evaluated_tokens as usize));
+        }
+        if !self.first_tokens.is_empty() {
+            let average = self.first_tokens.iter().sum::<Duration>() / self.first_tokens.len() as u32;
+            println!("  {:<24} {:.2}s on average", "Time to first token", average.as_secs_f64());
+        }
+        println!();
+    }
+}
+
+fn hash(message: &Message) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (&message.role, &message.content, &message.images).hash(&mut hasher);
+    if !message.tool_calls.is_empty() {
+        serde_json::to_string(&message.tool_calls).unwrap_or_default().hash(&mut hasher);
+    }
+    hasher.finish()
+}
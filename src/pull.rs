@@ -0,0 +1,109 @@
+//! Getting a missing model in place at startup: pull it or pick another.
+
+use anyhow::Result;
+use colored::*;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use crate::error::Failure;
+use crate::ollama::{OllamaClient, PullProgress};
+use crate::output::{self, status};
+
+/// Returns the model to chat with when `model` is not installed.
+///
+/// With `yes` the model is pulled without asking. Otherwise the user
+/// chooses on a terminal; non-interactive runs fail as before.
+pub async fn resolve_missing(client: &OllamaClient, model: &str, available: &[String], yes: bool) -> Result<String> {
+    let missing = || {
+        anyhow::anyhow!("Model '{}' not found. Available models: {:?}", model, available)
+            .context(Failure::ModelMissing)
+    };
+
+    if yes {
+        pull(client, model).await?;
+        return Ok(model.to_string());
+    }
+
+    if !io::stdin().is_terminal() {
+        eprintln!("Install the model with: {} (or pass {} to pull it automatically)",
+            format!("ollama pull {}", model).bright_cyan(), "--yes".bright_cyan());
+        return Err(missing());
+    }
+
+    eprintln!("{} Model '{}' is not installed.", "⚠".bright_yellow(), model.bright_cyan());
+    eprintln!("  {} Pull {}", "[p]".bright_cyan(), model);
+    for (i, name) in available.iter().enumerate() {
+        eprintln!("  {} Use {}", format!("[{}]", i + 1).bright_cyan(), name);
+    }
+    eprintln!("  {} Quit", "[q]".bright_cyan());
+
+    loop {
+        eprint!("Choice [p]: ");
+        io::stderr().flush()?;
+
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer)? == 0 {
+            return Err(missing());
+        }
+
+        match answer.trim().to_lowercase().as_str() {
+            "" | "p" => {
+                pull(client, model).await?;
+                return Ok(model.to_string());
+            }
+            "q" => return Err(missing()),
+            choice => match choice.parse::<usize>() {
+                Ok(n) if (1..=available.len()).contains(&n) => return Ok(available[n - 1].clone()),
+                _ => eprintln!("Enter p, q or a number from the list"),
+            },
+        }
+    }
+}
+
+async fn pull(client: &OllamaClient, model: &str) -> Result<()> {
+    status!("{} Pulling {}...", "⬇".bright_blue(), model.bright_cyan());
+
+    // Redraw one line on a terminal; elsewhere log each new stage once
+    let live = io::stderr().is_terminal();
+    let mut last_status = String::new();
+
+    client.pull_model(model, |progress| {
+        if output::is_quiet() {
+            return;
+        }
+        if live {
+            eprint!("\r  {:<70}", describe(progress));
+        } else if progress.status != last_status {
+            eprintln!("  {}", progress.status);
+            last_status = progress.status.clone();
+        }
+    }).await?;
+
+    if live && !output::is_quiet() {
+        eprintln!();
+    }
+    status!("{} Pulled {}", "✓".bright_green(), model.bright_cyan());
+    Ok(())
+}
+
+fn describe(progress: &PullProgress) -> String {
+    match (progress.total, progress.completed) {
+        (Some(total), Some(completed)) if total > 0 => format!("{} {:>3}% ({} / {})",
+            progress.status, completed * 100 / total, format_bytes(completed), format_bytes(total)),
+        _ => progress.status.clone(),
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
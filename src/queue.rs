@@ -0,0 +1,228 @@
+//! Prompts that failed while the backend was down, kept for `flush-queue`.
+//!
+//! Batch runs and piped chat sessions started with `--queue-on-failure`
+//! append failed requests to ~/.ai-chat-cli/queue.jsonl; `flush-queue`
+//! retries them and keeps whatever still fails. The queue is encrypted like
+//! sessions when encryption is enabled.
+
+use anyhow::{Context, Result};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::args::FlushQueueArgs;
+use crate::encryption;
+use crate::error::Failure;
+use crate::executor::AIExecutor;
+use crate::ollama::Message;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedPrompt {
+    #[serde(rename = "queuedAt")]
+    pub queued_at: String,
+    /// Where the prompt came from: "batch" or "chat"
+    pub source: String,
+    /// Batch item id, echoed back in flush results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    pub model: String,
+    /// Full request, including any system prompt and earlier turns
+    pub messages: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<serde_json::Value>,
+    /// Why the original attempt failed
+    pub error: String,
+}
+
+impl QueuedPrompt {
+    pub fn new(source: &str, model: &str, messages: Vec<Message>, error: String) -> Self {
+        Self {
+            queued_at: chrono::Local::now().to_rfc3339(),
+            source: source.to_string(),
+            id: None,
+            model: model.to_string(),
+            messages,
+            options: None,
+            error,
+        }
+    }
+
+    fn prompt(&self) -> &str {
+        self.messages.iter().rev()
+            .find(|m| m.role == "user")
+            .map_or("", |m| m.content.as_str())
+    }
+}
+
+#[derive(Serialize)]
+struct FlushResult<'a> {
+    #[serde(rename = "queuedAt")]
+    queued_at: &'a str,
+    source: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<&'a serde_json::Value>,
+    model: &'a str,
+    prompt: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub struct OfflineQueue {
+    path: PathBuf,
+    /// Batch items fail concurrently; appends must not interleave
+    writes: Mutex<()>,
+}
+
+impl OfflineQueue {
+    pub fn open() -> Result<Self> {
+        let home = dirs::home_dir()
+            .context("Could not find home directory")?;
+        let dir = home.join(".ai-chat-cli");
+        fs::create_dir_all(&dir)
+            .context("Failed to create ~/.ai-chat-cli")?;
+
+        Ok(Self {
+            path: dir.join("queue.jsonl"),
+            writes: Mutex::new(()),
+        })
+    }
+
+    /// Adds a prompt; the file is rewritten, since an encrypted one cannot be appended to
+    pub fn push(&self, prompt: &QueuedPrompt) -> Result<()> {
+        let _guard = self.writes.lock().unwrap();
+        let mut prompts = self.load()?;
+        prompts.push(prompt.clone());
+        self.write(&prompts)
+    }
+
+    fn load(&self) -> Result<Vec<QueuedPrompt>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = encryption::read_to_string(&self.path)
+            .context(format!("Failed to read queue: {}", self.path.display()))?;
+
+        content.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).context("Invalid entry in queue.jsonl"))
+            .collect()
+    }
+
+    fn save(&self, prompts: &[QueuedPrompt]) -> Result<()> {
+        let _guard = self.writes.lock().unwrap();
+        self.write(prompts)
+    }
+
+    /// Replaces the queue, encrypted when enabled in the config
+    fn write(&self, prompts: &[QueuedPrompt]) -> Result<()> {
+        let mut content = String::new();
+        for prompt in prompts {
+            content.push_str(&serde_json::to_string(prompt)?);
+            content.push('\n');
+        }
+        encryption::write(&self.path, &content)
+            .context(format!("Failed to write queue: {}", self.path.display()))
+    }
+}
+
+/// Retries every queued prompt, printing responses and keeping failures queued
+pub async fn flush(executor: &AIExecutor, args: FlushQueueArgs) -> Result<()> {
+    let queue = OfflineQueue::open()?;
+    let prompts = queue.load()?;
+
+    if args.list {
+        for (i, prompt) in prompts.iter().enumerate() {
+            println!("{} {} {} {}", format!("[{}]", i + 1).bright_black(), prompt.queued_at.bright_black(),
+                prompt.model.bright_cyan(), preview(prompt.prompt()));
+        }
+        eprintln!("{} {} prompt(s) queued", "ℹ".bright_blue(), prompts.len());
+        return Ok(());
+    }
+
+    if prompts.is_empty() {
+        eprintln!("{} Queue is empty", "ℹ".bright_blue());
+        return Ok(());
+    }
+
+    // Check once up front instead of failing every prompt in turn
+    if let Err(e) = executor.list_models().await {
+        eprintln!("{} Ollama is still unreachable; {} prompt(s) kept in the queue",
+            "ℹ".bright_blue(), prompts.len());
+        return Err(e);
+    }
+
+    let mut out = match &args.out {
+        Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)
+            .context(format!("Failed to open output file: {}", path.display()))?),
+        None => None,
+    };
+
+    eprintln!("{} Sending {} queued prompt(s)...", "⚙".bright_blue(), prompts.len());
+
+    let mut remaining = Vec::new();
+    for (i, prompt) in prompts.iter().enumerate() {
+        let result = executor
+            .batch_complete(Some(&prompt.model), prompt.messages.clone(), prompt.options.clone())
+            .await;
+
+        eprintln!("\n{} {} {} {}", format!("[{}/{}]", i + 1, prompts.len()).bright_black(),
+            prompt.source.bright_black(), prompt.model.bright_cyan(), preview(prompt.prompt()));
+
+        let (response, error) = match result {
+            Ok(response) => {
+                if out.is_none() {
+                    println!("{}", response.message.content);
+                }
+                (Some(response.message.content), None)
+            }
+            Err(e) => {
+                eprintln!("{} {:#}", "Error:".bright_red(), e);
+                remaining.push(prompt.clone());
+                (None, Some(format!("{:#}", e)))
+            }
+        };
+
+        if let Some(file) = &mut out {
+            let result = FlushResult {
+                queued_at: &prompt.queued_at,
+                source: &prompt.source,
+                id: prompt.id.as_ref(),
+                model: &prompt.model,
+                prompt: prompt.prompt(),
+                response,
+                error,
+            };
+            writeln!(file, "{}", serde_json::to_string(&result)?)?;
+        }
+    }
+
+    let sent = prompts.len() - remaining.len();
+
+    // Keep anything queued by other processes while this flush was running
+    let added = queue.load()?.into_iter().skip(prompts.len());
+    remaining.extend(added);
+    queue.save(&remaining)?;
+
+    eprintln!("\n{} {} sent, {} still queued", "✓".bright_green(), sent, remaining.len());
+
+    if remaining.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("{} queued prompt(s) could not be sent", remaining.len())
+            .context(Failure::Generation))
+    }
+}
+
+fn preview(prompt: &str) -> String {
+    let line = prompt.lines().next().unwrap_or("");
+    if line.chars().count() > 60 || prompt.lines().count() > 1 {
+        format!("{}…", line.chars().take(60).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
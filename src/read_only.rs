@@ -0,0 +1,273 @@
+//! Read-only sessions, for exploring machines where nothing may change.
+//!
+//! With --read-only (or "readOnly.enabled"), `McpManager::call_tool` refuses
+//! every tool that writes: `edit_file`, `write_file`, `extract_archive`,
+//! `github_comment`, `cargo_fmt` with `apply`, `env` with `set` or `unset`,
+//! and `bash` unless each command of the line is on a list of commands that
+//! only look. External MCP tools run only when their server marks them with
+//! `readOnlyHint` or "readOnly.tools" lists them. A command may not start
+//! with variable assignments, which could make it run another program
+//! through `PAGER` or `GIT_EXTERNAL_DIFF`, nor give the options or operands
+//! with which a listed command writes.
+
+use regex::Regex;
+use std::sync::Mutex;
+
+use crate::config::ReadOnlyConfig;
+
+/// Built-in tools that always change something
+const WRITING_TOOLS: &[&str] = &["edit_file", "write_file", "extract_archive", "github_comment"];
+
+/// Shell commands that only read; an entry of several words must match the
+/// start of the command, so "git log" allows `git log -p` but not `git push`
+const COMMANDS: &[&str] = &[
+    "ls", "cat", "head", "tail", "wc", "grep", "egrep", "fgrep", "rg", "find", "file", "stat",
+    "du", "df", "pwd", "echo", "printf", "which", "whereis", "type", "whoami", "id", "uname",
+    "hostname", "uptime", "free", "ps", "tree", "diff", "cmp", "sort", "uniq", "cut", "tr", "nl",
+    "sed", "jq", "basename", "dirname", "realpath", "readlink", "md5sum", "sha1sum", "sha256sum",
+    "date", "lsof", "ss", "netstat", "journalctl", "true",
+    "git status", "git log", "git diff", "git show", "git blame", "git ls-files", "git rev-parse",
+    "systemctl status", "docker ps", "docker logs", "docker images", "docker inspect",
+    "kubectl get", "kubectl describe", "kubectl logs",
+];
+
+/// Options that make an otherwise reading command write or run something.
+/// A one-letter option also matches inside a cluster, as `-i` in `sed -ni`.
+const WRITING_OPTIONS: &[(&str, &[&str])] = &[
+    ("find", &["-delete", "-exec", "-ok", "-fprint", "-fls"]),
+    ("sed", &["-i", "--in-place"]),
+    ("sort", &["-o", "--output", "--compress-program"]),
+    ("git", &["-o", "--output"]),
+    ("tree", &["-o", "--output"]),
+    ("rg", &["--pre", "--pre-glob"]),
+    ("journalctl", &["--vacuum-size", "--vacuum-time", "--vacuum-files", "--rotate"]),
+    ("date", &["-s", "--set"]),
+    ("hostname", &["-F", "--file", "-b", "--boot"]),
+];
+
+/// Options that take the next word as their value, which is then no operand
+const VALUE_OPTIONS: &[(&str, &[&str])] = &[
+    ("uniq", &["-f", "-s", "-w"]),
+    ("date", &["-d", "--date", "-r", "--reference", "-f", "--file"]),
+];
+
+/// Redirections that write nowhere that matters
+const HARMLESS_REDIRECTS: &[&str] = &["2>&1", "1>&2", ">&2", "2>/dev/null", ">/dev/null"];
+
+static READ_ONLY: Mutex<Option<ReadOnlyConfig>> = Mutex::new(None);
+
+/// Turns read-only mode on for the whole process when the config enables it
+pub fn init(config: &ReadOnlyConfig) {
+    *READ_ONLY.lock().unwrap() = config.enabled.then(|| config.clone());
+}
+
+pub fn enabled() -> bool {
+    READ_ONLY.lock().unwrap().is_some()
+}
+
+/// Why read-only mode refuses the call, if it does. `read_only_hint` is what
+/// the tool's server says about it; built-in tools are judged here.
+pub fn check(tool: &str, server: &str, arguments: &serde_json::Value, read_only_hint: bool) -> Result<(), String> {
+    let guard = READ_ONLY.lock().unwrap();
+    let Some(config) = guard.as_ref() else {
+        return Ok(());
+    };
+
+    if server != "builtin" {
+        return if read_only_hint || config.tools.iter().any(|t| t == tool) {
+            Ok(())
+        } else {
+            Err(format!("'{}' may change things; its server does not mark it read-only", tool))
+        };
+    }
+    match tool {
+        "bash" => command_allowed(arguments["command"].as_str().unwrap_or_default(), &config.commands),
+        "cargo_fmt" if arguments["apply"].as_bool() == Some(true) => Err("cargo_fmt may only check formatting".to_string()),
+        "env" if crate::env_vars::changes(arguments) => Err("env may only list variables".to_string()),
+        _ if WRITING_TOOLS.contains(&tool) => Err(format!("'{}' changes files", tool)),
+        _ => Ok(()),
+    }
+}
+
+/// Whether the tool is refused whatever its arguments, so it need not be offered
+pub fn refuses(tool: &str, server: &str, read_only_hint: bool) -> bool {
+    !matches!(tool, "bash" | "cargo_fmt" | "env") && check(tool, server, &serde_json::Value::Null, read_only_hint).is_err()
+}
+
+/// Every command of a pipeline or list must be a reading one, with no
+/// substitutions and no redirection into files
+fn command_allowed(command: &str, extra: &[String]) -> Result<(), String> {
+    if ["$(", "`", "<(", ">("].iter().any(|s| command.contains(s)) {
+        return Err("command substitution is not allowed".to_string());
+    }
+    let mut stripped = command.to_string();
+    for redirect in HARMLESS_REDIRECTS {
+        stripped = stripped.replace(redirect, " ");
+    }
+    if stripped.contains('>') {
+        return Err("output redirection is not allowed".to_string());
+    }
+
+    let separators = Regex::new(r"\|\|?|&&?|;|\n").unwrap();
+    for part in separators.split(&stripped) {
+        let words: Vec<&str> = part.split_whitespace().collect();
+        let Some(program) = words.first() else {
+            continue;
+        };
+        // `GIT_EXTERNAL_DIFF=... git diff` or `PAGER=... git log` runs any program
+        if program.split_once('=').is_some_and(|(name, _)| !name.is_empty() && !name.contains('/')) {
+            return Err(format!("variable assignments are not allowed: '{}'", part.trim()));
+        }
+        let listed = COMMANDS.iter().copied().chain(extra.iter().map(String::as_str))
+            .any(|allowed| {
+                let allowed: Vec<&str> = allowed.split_whitespace().collect();
+                words.starts_with(&allowed)
+            });
+        if !listed {
+            return Err(format!("'{}' is not a read-only command", part.trim()));
+        }
+        if let Some((_, options)) = WRITING_OPTIONS.iter().find(|(command, _)| command == program)
+            && let Some(option) = words[1..].iter().find(|word| options.iter().any(|o| option_matches(word, o)))
+        {
+            return Err(format!("{} {} may change files", program, option));
+        }
+        if *program == "sed" && words[1..].iter().any(|word| !word.starts_with('-') && sed_writes(word)) {
+            return Err(format!("'{}' may write files or run commands", part.trim()));
+        }
+        if operands_write(program, &words[1..]) {
+            return Err(format!("'{}' may change files or the system", part.trim()));
+        }
+    }
+    Ok(())
+}
+
+/// Whether `word` gives `option`: itself, with a value attached, or for a
+/// one-letter option, as one of the letters of a cluster like `-uo`. A long
+/// option takes its value after `=`, so `--pre` does not match `--pretty`.
+fn option_matches(word: &str, option: &str) -> bool {
+    if option.starts_with("--") {
+        return word.strip_prefix(option).is_some_and(|rest| rest.is_empty() || rest.starts_with('='));
+    }
+    if word.starts_with(option) {
+        return true;
+    }
+    match option.strip_prefix('-') {
+        Some(letter) if letter.len() == 1 && !option.starts_with("--") => {
+            word.strip_prefix('-')
+                .filter(|cluster| !cluster.starts_with('-'))
+                .is_some_and(|cluster| cluster.contains(letter))
+        }
+        _ => false,
+    }
+}
+
+/// Whether the operands make the command write: `uniq IN OUT` writes OUT,
+/// `hostname NAME` and `date MMDDhhmm` set the system's name and clock
+fn operands_write(program: &str, args: &[&str]) -> bool {
+    let takes_value = VALUE_OPTIONS.iter()
+        .find(|(command, _)| *command == program)
+        .map_or(&[][..], |(_, options)| *options);
+    let mut operands = Vec::new();
+    let mut options_done = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if options_done || *arg == "-" || !arg.starts_with('-') {
+            operands.push(arg.trim_matches(|c| c == '\'' || c == '"'));
+        } else if *arg == "--" {
+            options_done = true;
+        } else if takes_value.contains(arg) {
+            // A quoted value such as "next friday" spans several words
+            let value = args.next().copied().unwrap_or_default();
+            if let Some(quote) = value.chars().next().filter(|c| *c == '\'' || *c == '"')
+                && (value.len() == 1 || !value.ends_with(quote))
+            {
+                args.by_ref().find(|word| word.ends_with(quote));
+            }
+        }
+    }
+    match program {
+        "uniq" => operands.len() > 1,
+        // Only the first operand counts; the rest belong to a quoted +FORMAT
+        "hostname" | "date" => operands.first().is_some_and(|operand| !operand.starts_with('+')),
+        _ => false,
+    }
+}
+
+/// Whether a sed script may use the `w`, `W`, `r`, `R` or `e` commands or
+/// flags, which write files, read them in or run commands; judged by the
+/// letter standing alone, so some harmless scripts are refused too
+fn sed_writes(script: &str) -> bool {
+    let script = script.trim_matches(|c| c == '\'' || c == '"');
+    let chars: Vec<char> = script.chars().collect();
+    chars.iter().enumerate().any(|(i, c)| {
+        matches!(c, 'w' | 'W' | 'r' | 'R' | 'e')
+            && (i == 0 || !(chars[i - 1].is_ascii_alphabetic() || chars[i - 1] == '_'))
+            && chars.get(i + 1).is_none_or(|next| !(next.is_ascii_alphanumeric() || *next == '_'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(command: &str) -> bool {
+        command_allowed(command, &[]).is_ok()
+    }
+
+    #[test]
+    fn refuses_commands_that_write() {
+        for command in [
+            "sed -ni 's/a/b/' notes.txt",
+            "sed 's/a/b/w out.txt' notes.txt",
+            "PAGER=vim git log",
+            "rg --pre ./run.sh TODO",
+            "rg --pre=./run.sh TODO",
+            "find . -execdir rm {} +",
+            "uniq a.txt victim.txt",
+            "uniq -c - victim.txt",
+            "uniq -- a.txt victim.txt",
+            "hostname evil",
+            "date 01011200",
+            "date -s tomorrow",
+            "sort -uo out.txt in.txt",
+            "cat notes.txt > copy.txt",
+            "ls $(rm -rf x)",
+        ] {
+            assert!(!allowed(command), "{} was allowed", command);
+        }
+    }
+
+    #[test]
+    fn allows_commands_that_only_read() {
+        for command in [
+            "sed -n 1,5p notes.txt",
+            "git log --pretty=oneline | head",
+            "rg --pretty TODO src",
+            "find . -name '*.rs'",
+            "uniq -c notes.txt",
+            "uniq -f 2 notes.txt",
+            "hostname",
+            "date +%s",
+            "date '+%Y-%m-%d %H:%M'",
+            "date -d 'next friday' +%F",
+            "ls 2>/dev/null",
+        ] {
+            assert!(allowed(command), "{} was refused", command);
+        }
+    }
+
+    #[test]
+    fn matches_options_in_clusters_and_with_values() {
+        assert!(option_matches("-ni", "-i"));
+        assert!(option_matches("--output=x", "--output"));
+        assert!(!option_matches("--pretty", "--pre"));
+        assert!(!option_matches("--include", "-i"));
+    }
+
+    #[test]
+    fn finds_writing_sed_commands() {
+        assert!(sed_writes("'1w out.txt'"));
+        assert!(sed_writes("e"));
+        assert!(!sed_writes("'s/word/text/'"));
+    }
+}
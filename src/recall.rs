@@ -0,0 +1,264 @@
+//! Semantic recall over past conversations.
+//!
+//! Every user message and the reply to it form an exchange, embedded with an
+//! Ollama embedding model. The index covers ~/.ai-chat-cli/sessions/, the
+//! directories under "recall.dirs" and every file written by /save. New or
+//! changed files are indexed before each search. The index goes through the
+//! session encryption, so encrypted conversations do not leak into it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use crate::config::RecallConfig;
+use crate::encryption;
+use crate::lifecycle;
+use crate::ollama::OllamaClient;
+use crate::session::Session;
+
+const DEFAULT_MODEL: &str = "nomic-embed-text";
+/// Characters of an exchange that are embedded; enough to capture the topic
+const EMBED_CHARS: usize = 2000;
+const EMBED_BATCH: usize = 32;
+
+static SETTINGS: Mutex<Option<RecallConfig>> = Mutex::new(None);
+/// Serializes index updates between the /recall command and the recall tool
+static INDEX_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    model: String,
+    files: BTreeMap<PathBuf, IndexedFile>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexedFile {
+    /// Modification time when indexed; 0 for files registered but not yet indexed
+    modified: u64,
+    exchanges: Vec<Exchange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exchange {
+    /// Number of the user message, as shown by /history after /load
+    pub message: usize,
+    pub user: String,
+    pub assistant: String,
+    vector: Vec<f32>,
+}
+
+pub struct Hit {
+    pub path: PathBuf,
+    pub exchange: Exchange,
+    pub score: f32,
+}
+
+/// Applies the "recall" config for the whole process
+pub fn init(config: &RecallConfig) {
+    *SETTINGS.lock().unwrap() = Some(config.clone());
+}
+
+fn settings() -> RecallConfig {
+    SETTINGS.lock().unwrap().clone().unwrap_or_default()
+}
+
+fn index_path() -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .context("Could not find home directory")?;
+    Ok(home.join(".ai-chat-cli").join("recall").join("index.json"))
+}
+
+fn load_index(model: &str) -> Result<Index> {
+    let path = index_path()?;
+    let index: Index = if path.exists() {
+        let json = encryption::read_to_string(&path)
+            .context(format!("Failed to read recall index: {}", path.display()))?;
+        serde_json::from_str(&json).unwrap_or_default()
+    } else {
+        Index::default()
+    };
+
+    // Vectors from another model are not comparable; only the file list survives
+    if index.model == model {
+        return Ok(index);
+    }
+    Ok(Index {
+        model: model.to_string(),
+        files: index.files.into_keys().map(|path| (path, IndexedFile::default())).collect(),
+    })
+}
+
+fn save_index(index: &Index) -> Result<()> {
+    let path = index_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context("Failed to create recall directory")?;
+    }
+    encryption::write(&path, &serde_json::to_string(index)?)
+        .context(format!("Failed to write recall index: {}", path.display()))
+}
+
+/// Adds a saved conversation to the files indexed on the next search
+pub async fn remember(path: &Path) -> Result<()> {
+    let _lock = INDEX_LOCK.lock().await;
+    let model = settings().model.unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    let mut index = load_index(&model)?;
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if index.files.contains_key(&path) {
+        return Ok(());
+    }
+    index.files.insert(path, IndexedFile::default());
+    save_index(&index)
+}
+
+/// Every conversation file the index covers, whether embedded yet or not
+pub async fn session_files() -> Result<Vec<PathBuf>> {
+    let _lock = INDEX_LOCK.lock().await;
+    let settings = settings();
+    let model = settings.model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string());
+
+    let mut files: Vec<PathBuf> = load_index(&model)?.files.into_keys()
+        .filter(|path| path.exists())
+        .collect();
+    for dir in session_dirs(&settings)? {
+        collect_sessions(&dir, &mut files);
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Returns the exchanges closest to `query`, best first, and how many exchanges were newly indexed
+pub async fn search(query: &str, limit: usize) -> Result<(Vec<Hit>, usize)> {
+    let _lock = INDEX_LOCK.lock().await;
+    let settings = settings();
+    let model = settings.model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    let client = OllamaClient::new();
+
+    let mut index = load_index(&model)?;
+    let indexed = refresh(&client, &model, &settings, &mut index).await
+        .context(format!("Failed to index past conversations (is the embedding model installed? ollama pull {})", model))?;
+
+    let query = client.embed(&model, &[query.to_string()]).await?.remove(0);
+    let query = &query;
+    let mut hits: Vec<Hit> = index.files.iter()
+        .flat_map(|(path, file)| file.exchanges.iter().map(move |exchange| Hit {
+            path: path.clone(),
+            score: cosine(query, &exchange.vector),
+            exchange: exchange.clone(),
+        }))
+        .filter(|hit| hit.score > 0.0)
+        .collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit);
+
+    Ok((hits, indexed))
+}
+
+/// Embeds exchanges of new and changed files and drops files that are gone
+async fn refresh(client: &OllamaClient, model: &str, settings: &RecallConfig, index: &mut Index) -> Result<usize> {
+    let mut sources: Vec<PathBuf> = index.files.keys().cloned().collect();
+    for dir in session_dirs(settings)? {
+        collect_sessions(&dir, &mut sources);
+    }
+    sources.sort();
+    sources.dedup();
+
+    let mut indexed = 0;
+    for path in sources {
+        let Some(modified) = modified(&path) else {
+            index.files.remove(&path);
+            continue;
+        };
+        if index.files.get(&path).is_some_and(|file| file.modified == modified) {
+            continue;
+        }
+
+        // Files that are not conversations are remembered as empty, so they are not retried
+        let exchanges = match Session::load(&path) {
+            Ok(session) => embed_exchanges(client, model, &session).await?,
+            Err(_) => Vec::new(),
+        };
+        indexed += exchanges.len();
+        index.files.insert(path, IndexedFile { modified, exchanges });
+        // Saved per file so an interrupted first run keeps its progress
+        save_index(index)?;
+    }
+
+    Ok(indexed)
+}
+
+async fn embed_exchanges(client: &OllamaClient, model: &str, session: &Session) -> Result<Vec<Exchange>> {
+    let messages = &session.messages;
+    let mut exchanges: Vec<Exchange> = messages.iter().enumerate()
+        .filter(|(_, m)| m.role == "user")
+        .map(|(i, m)| Exchange {
+            message: i + 1,
+            user: m.content.clone(),
+            assistant: messages.get(i + 1)
+                .filter(|next| next.role == "assistant")
+                .map(|next| next.content.clone())
+                .unwrap_or_default(),
+            vector: Vec::new(),
+        })
+        .collect();
+
+    for batch in exchanges.chunks_mut(EMBED_BATCH) {
+        let texts: Vec<String> = batch.iter()
+            .map(|e| format!("User: {}\nAssistant: {}", e.user, e.assistant).chars().take(EMBED_CHARS).collect())
+            .collect();
+        for (exchange, vector) in batch.iter_mut().zip(client.embed(model, &texts).await?) {
+            exchange.vector = vector;
+        }
+    }
+
+    Ok(exchanges)
+}
+
+fn session_dirs(settings: &RecallConfig) -> Result<Vec<PathBuf>> {
+    let mut dirs = vec![lifecycle::sessions_dir()?];
+    dirs.extend(settings.dirs.iter().map(|dir| expand_home(dir)));
+    Ok(dirs)
+}
+
+fn collect_sessions(dir: &Path, sources: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_sessions(&path, sources);
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            sources.push(fs::canonicalize(&path).unwrap_or(path));
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some((modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64).max(1))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 { 0.0 } else { dot / denominator }
+}
+
+/// An exchange as context for the model
+pub fn format_exchange(hit: &Hit) -> String {
+    format!("From a past conversation ({}, message {}):\nUser: {}\nAssistant: {}",
+        hit.path.display(), hit.exchange.message, hit.exchange.user.trim(), hit.exchange.assistant.trim())
+}
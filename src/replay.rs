@@ -0,0 +1,123 @@
+use anyhow::Result;
+use colored::*;
+
+use crate::args::ReplayArgs;
+use crate::executor::AIExecutor;
+use crate::session::Session;
+
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Line-level diff based on the longest common subsequence
+pub fn line_diff<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] = length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    diff.extend(old[i..].iter().map(|l| DiffLine::Removed(l)));
+    diff.extend(new[j..].iter().map(|l| DiffLine::Added(l)));
+
+    diff
+}
+
+pub async fn run(executor: &AIExecutor, args: ReplayArgs) -> Result<()> {
+    let transcript = Session::load(&args.session)?.messages;
+
+    let model = args.model.unwrap_or_else(|| executor.get_model().to_string());
+    let turns: Vec<usize> = transcript.iter()
+        .enumerate()
+        .filter(|(_, m)| m.role == "user")
+        .map(|(i, _)| i)
+        .collect();
+
+    println!("{} Replaying {} user turn(s) from {} against {}",
+        "⚙".bright_blue(), turns.len(), args.session.display(), model.bright_cyan());
+
+    let mut unchanged = 0;
+    let mut failed = 0;
+
+    for (turn, &index) in turns.iter().enumerate() {
+        // Each turn sees the original conversation so differences do not compound
+        let context = transcript[..=index].to_vec();
+        let golden = transcript.get(index + 1)
+            .filter(|m| m.role == "assistant")
+            .map(|m| m.content.as_str())
+            .unwrap_or("");
+
+        println!("\n{} {}", format!("Turn {}:", turn + 1).bright_yellow().bold(),
+            preview(&transcript[index].content).bright_white());
+
+        let response = match executor.batch_complete(Some(&model), context, None).await {
+            Ok(response) => response.message.content,
+            Err(e) => {
+                failed += 1;
+                eprintln!("  {} {:#}", "Error:".bright_red(), e);
+                continue;
+            }
+        };
+
+        let diff = line_diff(golden, &response);
+        let same = diff.iter().filter(|l| matches!(l, DiffLine::Same(_))).count();
+        let total = diff.len().max(1);
+
+        if diff.iter().all(|l| matches!(l, DiffLine::Same(_))) {
+            unchanged += 1;
+            println!("  {} unchanged", "=".bright_green());
+            continue;
+        }
+
+        println!("  {} {:.0}% of lines unchanged", "~".bright_yellow(), same as f64 / total as f64 * 100.0);
+        for line in diff {
+            match line {
+                DiffLine::Same(l) if args.full => println!("    {}", l),
+                DiffLine::Same(_) => {}
+                DiffLine::Removed(l) => println!("  {} {}", "-".bright_red(), l.red()),
+                DiffLine::Added(l) => println!("  {} {}", "+".bright_green(), l.green()),
+            }
+        }
+    }
+
+    let changed = turns.len() - unchanged - failed;
+    println!("\n{} {} unchanged, {} changed, {} failed",
+        "Summary:".bright_yellow().bold(), unchanged, changed, failed);
+
+    Ok(())
+}
+
+fn preview(text: &str) -> String {
+    let line = text.lines().next().unwrap_or("");
+    if line.chars().count() > 70 {
+        format!("{}...", line.chars().take(70).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
@@ -0,0 +1,225 @@
+//! A compact map of the project for the model: directories, file names and
+//! the public symbols each file defines.
+//!
+//! With "repoMap" enabled (or --repo-map), the map of the repository the chat
+//! starts in is added as a system message, so the model knows where to look
+//! before reading any file. Files come from `git ls-files` when possible, so
+//! ignored files stay out; symbols come from ctags or the patterns in
+//! `symbols`.
+
+use anyhow::{Context, Result};
+use colored::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::RepoMapConfig;
+use crate::output::status;
+use crate::symbols::{self, Symbol};
+
+pub const DEFAULT_MAX_CHARS: usize = 8000;
+/// Beginning of the system message, used by /repo-map to replace an older map
+pub const HEADER: &str = "Map of the repository";
+
+/// Walking stops here outside git repositories, e.g. when started in $HOME
+const MAX_FILES: usize = 5000;
+const MAX_SYMBOLS_PER_FILE: usize = 15;
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+pub struct RepoMap {
+    pub text: String,
+    pub files: usize,
+    pub symbols: usize,
+}
+
+struct MapFile {
+    name: String,
+    symbols: Vec<String>,
+}
+
+/// Files by directory ("src/", or "./" for the root)
+type Tree = BTreeMap<String, Vec<MapFile>>;
+
+/// Builds the map of the repository containing `path`
+pub fn build(path: &Path, max_chars: usize) -> Result<RepoMap> {
+    let (root, files) = project_files(path)?;
+    let symbols = symbols::index(&root, &files);
+
+    // Only what other files can use; methods and private helpers are found by reading
+    let mut tree = Tree::new();
+    let mut symbol_count = 0;
+    for file in &files {
+        let (dir, name) = match file.rsplit_once('/') {
+            Some((dir, name)) => (format!("{}/", dir), name.to_string()),
+            None => ("./".to_string(), file.clone()),
+        };
+        let names: Vec<String> = symbols.get(file).map(|list| public_names(list)).unwrap_or_default();
+        symbol_count += names.len();
+        tree.entry(dir).or_default().push(MapFile { name, symbols: names });
+    }
+
+    let name = root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let header = format!("{} {} ({} files{}). Files are listed under their directory, with the public symbols they define:",
+        HEADER, name, files.len(), if files.len() >= MAX_FILES { ", listing stopped" } else { "" });
+
+    Ok(RepoMap {
+        text: render(&header, &tree, max_chars),
+        files: files.len(),
+        symbols: symbol_count,
+    })
+}
+
+/// The root of the repository containing `path` and its files relative to it:
+/// from git when possible, otherwise by walking the directory
+pub fn project_files(path: &Path) -> Result<(PathBuf, Vec<String>)> {
+    if let Some(found) = git_files(path) {
+        return Ok(found);
+    }
+    let root = fs::canonicalize(path)
+        .context(format!("Failed to read {}", path.display()))?;
+    let mut files = Vec::new();
+    walk(&root, &root, &mut files);
+    files.sort();
+    Ok((root, files))
+}
+
+/// The map to add at session start, if enabled; problems are reported and skipped
+pub fn startup_context(config: &RepoMapConfig) -> Option<String> {
+    if !config.enabled {
+        return None;
+    }
+    match build(Path::new("."), config.max_chars.unwrap_or(DEFAULT_MAX_CHARS)) {
+        Ok(map) => {
+            status!("{} Repo map: {} files, {} symbols", "✓".bright_green(), map.files, map.symbols);
+            Some(map.text)
+        }
+        Err(e) => {
+            eprintln!("{} Could not build the repo map: {:#}", "Warning:".bright_yellow(), e);
+            None
+        }
+    }
+}
+
+fn public_names(symbols: &[Symbol]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    // Rust module declarations only repeat the file list
+    for symbol in symbols.iter().filter(|s| s.exported && !s.nested && s.kind != "mod") {
+        if !names.contains(&symbol.name) {
+            names.push(symbol.name.clone());
+        }
+    }
+    names
+}
+
+/// Renders the tree, dropping detail until it fits: first the symbols, then the file names
+fn render(header: &str, tree: &Tree, max_chars: usize) -> String {
+    let with_symbols = |file: &MapFile| {
+        if file.symbols.is_empty() {
+            return file.name.clone();
+        }
+        let shown: Vec<&str> = file.symbols.iter().take(MAX_SYMBOLS_PER_FILE).map(String::as_str).collect();
+        let mut line = format!("{}: {}", file.name, shown.join(", "));
+        if file.symbols.len() > MAX_SYMBOLS_PER_FILE {
+            line.push_str(&format!(" (+{})", file.symbols.len() - MAX_SYMBOLS_PER_FILE));
+        }
+        line
+    };
+
+    let detailed = |file_line: &dyn Fn(&MapFile) -> String| {
+        let mut text = format!("{}\n", header);
+        for (dir, files) in tree {
+            text.push_str(&format!("{}\n", dir));
+            for file in files {
+                text.push_str(&format!("  {}\n", file_line(file)));
+            }
+        }
+        text
+    };
+
+    let full = detailed(&with_symbols);
+    if full.len() <= max_chars {
+        return full;
+    }
+    let names_only = detailed(&|file| file.name.clone());
+    if names_only.len() <= max_chars {
+        return names_only;
+    }
+
+    let mut text = format!("{}\n", header);
+    for (dir, files) in tree {
+        let line = format!("{} ({} files)\n", dir, files.len());
+        if text.len() + line.len() > max_chars {
+            text.push_str("…\n");
+            break;
+        }
+        text.push_str(&line);
+    }
+    text
+}
+
+/// The git repository containing `path` (a file or directory), or the directory itself
+pub fn project_root(path: &Path) -> PathBuf {
+    let dir = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+    git_root(dir).unwrap_or_else(|| dir.to_path_buf())
+}
+
+fn git_root(dir: &Path) -> Option<PathBuf> {
+    let output = Command::new("git")
+        .arg("-C").arg(dir)
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// Tracked and untracked-but-not-ignored files, relative to the repository root
+fn git_files(path: &Path) -> Option<(PathBuf, Vec<String>)> {
+    let root = git_root(path)?;
+
+    let output = Command::new("git")
+        .arg("-C").arg(&root)
+        .args(["ls-files", "--cached", "--others", "--exclude-standard"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let mut files: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|file| root.join(file).is_file())
+        .map(str::to_string)
+        .collect();
+    files.sort();
+    files.dedup();
+    files.truncate(MAX_FILES);
+    Some((root, files))
+}
+
+fn walk(root: &Path, dir: &Path, files: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        if files.len() >= MAX_FILES {
+            return;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_str()) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            walk(root, &path, files);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! The last conversation held in each working directory, for auto-resume.
+//!
+//! When an interactive session ends, its conversation is kept under
+//! ~/.ai-chat-cli/sessions/directories/, in a file named after a hash of the
+//! canonical path. The next start in the same directory offers to pick it
+//! up again, as set by "autoResume" in config.json. The directory is also
+//! recorded in the session, so a hash collision is never resumed.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::cache;
+use crate::lifecycle;
+use crate::session::Session;
+
+/// The working directory in its canonical form, the key of its conversation
+pub fn current_dir() -> Result<PathBuf> {
+    let dir = std::env::current_dir().context("Could not tell the working directory")?;
+    Ok(fs::canonicalize(&dir).unwrap_or(dir))
+}
+
+/// The conversation kept for `dir` and when it was saved, if there is one
+/// with more than system context
+pub fn find(dir: &Path) -> Option<(Session, SystemTime)> {
+    let path = file_for(dir).ok()?;
+    let saved = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+    let session = Session::load(&path).ok()?;
+    let matches = session.directory.as_deref() == Some(&*dir.to_string_lossy());
+    (matches && session.messages.iter().any(|m| m.role != "system")).then_some((session, saved))
+}
+
+/// Keeps `session` as the conversation of `dir`
+pub fn save(dir: &Path, mut session: Session) -> Result<()> {
+    let path = file_for(dir)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .context(format!("Failed to create {}", parent.display()))?;
+    }
+    session.directory = Some(dir.to_string_lossy().to_string());
+    session.title.get_or_insert_with(|| format!("Last conversation in {}", dir.display()));
+    session.save(&path)
+}
+
+fn file_for(dir: &Path) -> Result<PathBuf> {
+    let name = cache::digest(&dir.to_string_lossy());
+    Ok(lifecycle::sessions_dir()?.join("directories").join(format!("{}.json", &name[..16])))
+}
@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use colored::*;
+use cron::Schedule;
+use serde_json::json;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::args::ScheduleArgs;
+use crate::builtin_tools::BuiltinToolRegistry;
+use crate::config::{AppConfig, ScheduleConfig};
+use crate::executor::AIExecutor;
+use crate::ollama::Message;
+
+/// Longest a context command may run before it is cut off
+const CONTEXT_TIMEOUT_SECS: u64 = 120;
+
+struct Job<'a> {
+    config: &'a ScheduleConfig,
+    schedule: Schedule,
+    next: Option<DateTime<Local>>,
+}
+
+pub async fn run(executor: &AIExecutor, config: &AppConfig, args: ScheduleArgs) -> Result<()> {
+    if config.schedules.is_empty() {
+        anyhow::bail!("No schedules configured in {}", AppConfig::config_path()?.display());
+    }
+
+    let mut jobs = config.schedules.iter()
+        .map(|job| {
+            let schedule = parse(&job.cron)
+                .context(format!("Invalid cron expression for '{}': {}", job.name, job.cron))?;
+            let next = schedule.upcoming(Local).next();
+            Ok(Job { config: job, schedule, next })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if args.list {
+        for job in &jobs {
+            let next = job.next
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "never".to_string());
+            println!("{:<24} {:<16} next: {}", job.config.name.bright_cyan(), job.config.cron, next);
+        }
+        return Ok(());
+    }
+
+    if let Some(name) = &args.run {
+        let job = jobs.iter()
+            .find(|job| &job.config.name == name)
+            .context(format!("No schedule named '{}'", name))?;
+        return run_job(executor, job.config).await;
+    }
+
+    println!("{} Running {} scheduled job(s), press Ctrl+C to stop",
+        "✓".bright_green(), jobs.len());
+
+    loop {
+        let Some(due) = jobs.iter().filter_map(|job| job.next).min() else {
+            println!("{} No upcoming runs left", "ℹ".bright_blue());
+            return Ok(());
+        };
+
+        let wait = (due - Local::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        let now = Local::now();
+        for job in jobs.iter_mut().filter(|job| job.next.is_some_and(|next| next <= now)) {
+            // A failing job is reported but never stops the daemon
+            if let Err(e) = run_job(executor, job.config).await {
+                eprintln!("{} {}: {:#}", "Error:".bright_red().bold(), job.config.name, e);
+            }
+            job.next = job.schedule.after(&Local::now()).next();
+        }
+    }
+}
+
+/// Accepts standard five-field cron expressions as well as the
+/// seconds-first form understood by the `cron` crate
+fn parse(expression: &str) -> Result<Schedule> {
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+
+    Ok(Schedule::from_str(&expression)?)
+}
+
+async fn run_job(executor: &AIExecutor, job: &ScheduleConfig) -> Result<()> {
+    let started = Local::now();
+    println!("{} Running '{}'", "→".bright_blue(), job.name.bright_cyan());
+
+    let prompt = build_prompt(job).await?;
+    let messages = vec![Message {
+        role: "user".to_string(),
+        content: prompt,
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    }];
+
+    let response = executor.batch_complete(job.model.as_deref(), messages, None).await?;
+    let answer = response.message.content.trim();
+
+    if let Some(path) = &job.output {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(format!("Failed to open {}", path.display()))?;
+        writeln!(file, "## {} ({})\n\n{}\n", job.name, started.format("%Y-%m-%d %H:%M"), answer)?;
+    }
+
+    if let Some(url) = &job.webhook {
+        let payload = json!({
+            "job": job.name,
+            "ranAt": started.to_rfc3339(),
+            "response": answer,
+        });
+
+        reqwest::Client::new()
+            .post(url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .context(format!("Failed to deliver '{}' to webhook", job.name))?;
+    }
+
+    if job.output.is_none() && job.webhook.is_none() {
+        println!("{}\n", answer);
+    }
+
+    println!("{} '{}' finished in {:.1}s", "✓".bright_green(), job.name,
+        (Local::now() - started).num_milliseconds() as f64 / 1000.0);
+
+    Ok(())
+}
+
+/// Appends the output of each context command to the prompt
+async fn build_prompt(job: &ScheduleConfig) -> Result<String> {
+    let mut prompt = job.prompt.clone();
+    if job.context.is_empty() {
+        return Ok(prompt);
+    }
+
+    let tools = BuiltinToolRegistry::new();
+    for command in &job.context {
+        let result = tools.execute("bash", json!({
+            "command": command,
+            "timeout": CONTEXT_TIMEOUT_SECS,
+        })).await?;
+
+        let output: Vec<&str> = result.content.iter().map(|c| c.text.as_str()).collect();
+        prompt.push_str(&format!("\n\n$ {}\n```\n{}\n```", command, output.join("\n").trim_end()));
+    }
+
+    Ok(prompt)
+}
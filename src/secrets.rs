@@ -0,0 +1,94 @@
+//! Secrets kept in the OS credential store (Keychain, Credential Manager,
+//! Secret Service) instead of in config files.
+//!
+//! Config values of the form `keyring:<name>` are replaced by the secret
+//! stored under `<name>`; `ai-chat-cli secret set/get/rm` manage entries.
+
+use anyhow::{Context, Result};
+use colored::*;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Read};
+
+use crate::args::{SecretArgs, SecretCommand};
+
+const SERVICE: &str = "ai-chat-cli";
+const PREFIX: &str = "keyring:";
+
+fn entry(name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, name)
+        .context(format!("Invalid secret name: {}", name))
+}
+
+/// Runs a credential store call on its own thread: the Secret Service
+/// backend deadlocks when called from a thread driving the tokio runtime
+fn isolated<T: Send>(call: impl FnOnce() -> Result<T> + Send) -> Result<T> {
+    std::thread::scope(|scope| scope.spawn(call).join())
+        .map_err(|_| anyhow::anyhow!("Credential store access panicked"))?
+}
+
+fn get(name: &str) -> Result<String> {
+    isolated(|| entry(name)?.get_password().map_err(|e| match e {
+        keyring::Error::NoEntry => anyhow::anyhow!(
+            "No secret named '{}' (add it with: ai-chat-cli secret set {})", name, name),
+        e => anyhow::anyhow!("Failed to read secret '{}': {}", name, e),
+    }))
+}
+
+/// Returns `value` itself, or the stored secret if it is a `keyring:<name>` reference
+pub fn resolve(value: &str) -> Result<String> {
+    match value.strip_prefix(PREFIX) {
+        Some(name) => get(name.trim()),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// Resolves every value of a header or environment map
+pub fn resolve_map(map: HashMap<String, String>) -> Result<HashMap<String, String>> {
+    map.into_iter()
+        .map(|(key, value)| Ok((key, resolve(&value)?)))
+        .collect()
+}
+
+/// Runs the `secret` subcommand
+pub fn run(args: SecretArgs) -> Result<()> {
+    match args.command {
+        SecretCommand::Set { name } => {
+            let value = read_value(&name)?;
+            isolated(|| entry(&name)?.set_password(&value)
+                .map_err(|e| anyhow::anyhow!("Failed to store secret '{}': {}", name, e)))?;
+            eprintln!("{} Stored '{}'; reference it as {}",
+                "✓".bright_green(), name, format!("{}{}", PREFIX, name).bright_cyan());
+            Ok(())
+        }
+        SecretCommand::Get { name } => {
+            println!("{}", get(&name)?);
+            Ok(())
+        }
+        SecretCommand::Rm { name } => {
+            isolated(|| match entry(&name)?.delete_credential() {
+                Ok(()) => Ok(()),
+                Err(keyring::Error::NoEntry) => anyhow::bail!("No secret named '{}'", name),
+                Err(e) => anyhow::bail!("Failed to remove secret '{}': {}", name, e),
+            })?;
+            eprintln!("{} Removed '{}'", "✓".bright_green(), name);
+            Ok(())
+        }
+    }
+}
+
+/// Asks for the value without echoing it, or reads it from piped stdin,
+/// so it never appears in shell history or the process list
+fn read_value(name: &str) -> Result<String> {
+    let value = if io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("Value for '{}': ", name))?
+    } else {
+        let mut value = String::new();
+        io::stdin().read_to_string(&mut value)?;
+        value.trim_end_matches(['\r', '\n']).to_string()
+    };
+
+    if value.is_empty() {
+        anyhow::bail!("The secret value must not be empty");
+    }
+    Ok(value)
+}
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::encryption;
+use crate::ollama::Message;
+
+/// A saved conversation along with its metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Working directory of a conversation kept for auto-resume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// Model the conversation was held with, recorded in deterministic mode and for auto-resume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Seed of deterministic mode, so the replies can be reproduced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// What the session that wrote the conversation did, recorded when it ended
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<SessionStats>,
+    pub messages: Vec<Message>,
+}
+
+/// Summary of a session: printed when quitting and kept with its conversation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStats {
+    /// Messages answered
+    pub turns: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Calls per tool, failed ones included
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tool_calls: BTreeMap<String, u64>,
+    /// Files changed by the built-in editing tools and not rolled back
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files_modified: Vec<String>,
+    pub duration_secs: u64,
+}
+
+/// Sessions are stored either as a bare message array (the original /save
+/// format) or as an object carrying metadata
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SessionFile {
+    Messages(Vec<Message>),
+    Session(Session),
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = encryption::read_to_string(path)
+            .context(format!("Failed to read session: {}", path.display()))?;
+
+        let file: SessionFile = serde_json::from_str(&json)
+            .context(format!("Invalid session file: {}", path.display()))?;
+
+        Ok(match file {
+            SessionFile::Messages(messages) => Session { messages, ..Default::default() },
+            SessionFile::Session(session) => session,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        encryption::write(path, &json)
+            .context(format!("Failed to write session: {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
@@ -0,0 +1,207 @@
+//! The conversation as a single self-contained HTML page, for /share.
+//!
+//! Everything is inline: styles, images as data URLs and code highlighted
+//! here rather than by a script, so the file opens anywhere and can be
+//! mailed or uploaded as a gist. Tool results and system messages are
+//! collapsed, since they are usually long and only sometimes interesting.
+
+use regex::Regex;
+
+use crate::ollama::Message;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "case", "catch", "class", "const", "continue", "crate", "def",
+    "default", "defer", "del", "do", "dyn", "elif", "else", "enum", "except", "export", "extends",
+    "false", "False", "finally", "fn", "for", "from", "func", "function", "go", "if", "impl",
+    "import", "in", "interface", "is", "lambda", "let", "loop", "match", "mod", "move", "mut",
+    "new", "nil", "None", "not", "null", "package", "pass", "pub", "raise", "ref", "return", "self",
+    "Self", "static", "struct", "super", "switch", "this", "throw", "trait", "true", "True", "try",
+    "type", "unsafe", "use", "var", "where", "while", "with", "yield",
+];
+
+/// Languages whose line comments start with #
+const HASH_COMMENTS: &[&str] = &["python", "py", "sh", "bash", "shell", "zsh", "ruby", "rb", "yaml", "yml", "toml", "perl", "r"];
+
+const STYLE: &str = "
+:root { --bg: #fff; --fg: #1f2328; --muted: #656d76; --card: #f6f8fa; --border: #d0d7de;
+  --user: #1a7f37; --ai: #0969da; --kw: #cf222e; --str: #0a3069; --num: #0550ae; --com: #6e7781; }
+@media (prefers-color-scheme: dark) {
+  :root { --bg: #0d1117; --fg: #e6edf3; --muted: #8d96a0; --card: #161b22; --border: #30363d;
+    --user: #3fb950; --ai: #58a6ff; --kw: #ff7b72; --str: #a5d6ff; --num: #79c0ff; --com: #8b949e; }
+}
+body { background: var(--bg); color: var(--fg); font: 15px/1.55 -apple-system, 'Segoe UI', sans-serif;
+  max-width: 860px; margin: 2em auto; padding: 0 1em; }
+header { color: var(--muted); border-bottom: 1px solid var(--border); margin-bottom: 1.5em; }
+.message { margin: 1.2em 0; }
+.role { font-weight: 600; margin-bottom: .3em; }
+.user .role { color: var(--user); }
+.assistant .role { color: var(--ai); }
+.system .role, .tool .role { color: var(--muted); }
+pre { background: var(--card); border: 1px solid var(--border); border-radius: 6px; padding: .8em;
+  overflow-x: auto; font: 13px/1.45 ui-monospace, Menlo, Consolas, monospace; }
+code { font-family: ui-monospace, Menlo, Consolas, monospace; font-size: 90%; }
+p code { background: var(--card); padding: .1em .3em; border-radius: 4px; }
+details summary { cursor: pointer; color: var(--muted); }
+img { max-width: 100%; border-radius: 6px; }
+.kw { color: var(--kw); } .str { color: var(--str); } .num { color: var(--num); } .com { color: var(--com); font-style: italic; }
+";
+
+/// Renders the conversation as a complete HTML document
+pub fn render(history: &[Message], model: &str, created: &str) -> String {
+    let mut body = String::new();
+    for message in history {
+        let label = match message.role.as_str() {
+            "user" => "You",
+            "assistant" => "AI",
+            "system" => "System",
+            "tool" => "Tool",
+            other => other,
+        };
+        let content = if message.role == "assistant" || message.role == "user" {
+            markdown(&message.content)
+        } else {
+            format!("<pre>{}</pre>", escape(message.content.trim_end()))
+        };
+        let images: String = message.images.iter()
+            .map(|data| format!("<p><img src=\"data:image/png;base64,{}\" alt=\"attached image\"></p>", data))
+            .collect();
+
+        body.push_str(&format!("<div class=\"message {}\">\n", escape(&message.role)));
+        if message.role == "tool" || message.role == "system" {
+            let lines = message.content.lines().count();
+            body.push_str(&format!("<details><summary><span class=\"role\">{}</span> ({} line{})</summary>\n{}{}</details>\n",
+                escape(label), lines, if lines == 1 { "" } else { "s" }, content, images));
+        } else {
+            body.push_str(&format!("<div class=\"role\">{}</div>\n{}{}\n", escape(label), content, images));
+        }
+        body.push_str("</div>\n");
+    }
+
+    format!("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+        <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+        <title>Conversation with {model}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+        <header><p>Conversation with <strong>{model}</strong> · {created} · {count} messages</p></header>\n\
+        {body}</body>\n</html>\n",
+        model = escape(model), created = escape(created), count = history.len(), body = body)
+}
+
+/// The Markdown of a chat message: fenced code, headings, lists and
+/// paragraphs, with inline code, bold and links
+fn markdown(text: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<String> = Vec::new();
+    let mut list: Vec<String> = Vec::new();
+    let mut code: Option<(String, Vec<&str>)> = None;
+
+    let flush = |html: &mut String, paragraph: &mut Vec<String>, list: &mut Vec<String>| {
+        if !paragraph.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", paragraph.join("<br>\n")));
+            paragraph.clear();
+        }
+        if !list.is_empty() {
+            html.push_str(&format!("<ul>\n{}</ul>\n", list.iter().map(|item| format!("<li>{}</li>\n", item)).collect::<String>()));
+            list.clear();
+        }
+    };
+
+    for line in text.lines() {
+        if let Some((language, lines)) = &mut code {
+            if line.trim_start().starts_with("```") {
+                html.push_str(&format!("<pre><code>{}</code></pre>\n", highlight(&lines.join("\n"), language)));
+                code = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if let Some(language) = trimmed.strip_prefix("```") {
+            flush(&mut html, &mut paragraph, &mut list);
+            code = Some((language.trim().to_lowercase(), Vec::new()));
+        } else if trimmed.is_empty() {
+            flush(&mut html, &mut paragraph, &mut list);
+        } else if let Some(level) = (1..=6).rev().find(|&n| trimmed.starts_with(&format!("{} ", "#".repeat(n)))) {
+            flush(&mut html, &mut paragraph, &mut list);
+            html.push_str(&format!("<h{0}>{1}</h{0}>\n", level + 1, inline(trimmed[level..].trim())));
+        } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            if !paragraph.is_empty() {
+                flush(&mut html, &mut paragraph, &mut list);
+            }
+            list.push(inline(item));
+        } else {
+            if !list.is_empty() {
+                flush(&mut html, &mut paragraph, &mut list);
+            }
+            paragraph.push(inline(trimmed));
+        }
+    }
+    // An unterminated block still shows, e.g. in an interrupted reply
+    if let Some((language, lines)) = code {
+        html.push_str(&format!("<pre><code>{}</code></pre>\n", highlight(&lines.join("\n"), &language)));
+    }
+    flush(&mut html, &mut paragraph, &mut list);
+    html
+}
+
+fn inline(text: &str) -> String {
+    let code = Regex::new(r"`([^`]+)`").unwrap();
+    let bold = Regex::new(r"\*\*([^*]+)\*\*").unwrap();
+    let link = Regex::new(r#"\[([^\]]+)\]\((https?://[^)\s"]+)\)"#).unwrap();
+
+    // Code spans first, so Markdown inside them stays literal
+    let mut html = String::new();
+    let mut last = 0;
+    for found in code.captures_iter(text) {
+        let whole = found.get(0).unwrap();
+        let before = escape(&text[last..whole.start()]);
+        let before = bold.replace_all(&before, "<strong>$1</strong>");
+        html.push_str(&link.replace_all(&before, "<a href=\"$2\">$1</a>"));
+        html.push_str(&format!("<code>{}</code>", escape(&found[1])));
+        last = whole.end();
+    }
+    let rest = escape(&text[last..]);
+    let rest = bold.replace_all(&rest, "<strong>$1</strong>");
+    html.push_str(&link.replace_all(&rest, "<a href=\"$2\">$1</a>"));
+    html
+}
+
+/// Colors keywords, strings, numbers and comments; good enough for the
+/// common languages without knowing any of them exactly
+fn highlight(code: &str, language: &str) -> String {
+    let comment = if HASH_COMMENTS.contains(&language) { r"#[^\n]*" } else { r"//[^\n]*|/\*[\s\S]*?\*/" };
+    // Rust lifetimes would open a string that never closes, so only character literals count there
+    let single = if language == "rust" || language == "rs" { r"'(?:\\.|[^'\\])'" } else { r"'(?:\\.|[^'\\\n])*'" };
+    let token = Regex::new(&format!(r#"(?P<com>{})|(?P<str>"(?:\\.|[^"\\])*"|{})|(?P<num>\b\d[\w.]*)|(?P<word>\b[A-Za-z_]\w*\b)"#,
+        comment, single)).unwrap();
+
+    let mut html = String::new();
+    let mut last = 0;
+    for found in token.captures_iter(code) {
+        let whole = found.get(0).unwrap();
+        html.push_str(&escape(&code[last..whole.start()]));
+        let class = if found.name("com").is_some() {
+            Some("com")
+        } else if found.name("str").is_some() {
+            Some("str")
+        } else if found.name("num").is_some() {
+            Some("num")
+        } else {
+            KEYWORDS.contains(&whole.as_str()).then_some("kw")
+        };
+        match class {
+            Some(class) => html.push_str(&format!("<span class=\"{}\">{}</span>", class, escape(whole.as_str()))),
+            None => html.push_str(&escape(whole.as_str())),
+        }
+        last = whole.end();
+    }
+    html.push_str(&escape(&code[last..]));
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
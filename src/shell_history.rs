@@ -0,0 +1,130 @@
+//! Recent shell commands as context for /why.
+//!
+//! Off unless "shellHistory.enabled" is set in config.json, since the history
+//! holds whatever was typed at a prompt. Commands come from the bash, zsh or
+//! fish history file. Shells do not keep the output of commands, so it is only
+//! included when "shellHistory.outputLog" points at a terminal log, such as
+//! one written by `script -qf`.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::ShellHistoryConfig;
+
+const DEFAULT_COMMANDS: usize = 10;
+/// End of the terminal log that is sent; the failure is almost always at the bottom
+const MAX_OUTPUT_CHARS: usize = 6000;
+
+/// The /why prompt and how many commands it carries
+pub fn prompt(config: &ShellHistoryConfig, question: Option<&str>) -> Result<(String, usize)> {
+    if !config.enabled {
+        anyhow::bail!("Reading the shell history is off; enable it with \"shellHistory\": {{\"enabled\": true}} in ~/.ai-chat-cli/config.json");
+    }
+
+    let path = history_file(config)?;
+    let text = fs::read(&path)
+        .with_context(|| format!("Failed to read shell history: {}", path.display()))?;
+    let mut commands = parse(&String::from_utf8_lossy(&text));
+    // The command that started this session is not the one to explain
+    while commands.last().is_some_and(|c| c.starts_with("ai-chat-cli")) {
+        commands.pop();
+    }
+    let count = config.commands.unwrap_or(DEFAULT_COMMANDS);
+    let commands = &commands[commands.len().saturating_sub(count)..];
+    if commands.is_empty() {
+        anyhow::bail!("No commands in {}", path.display());
+    }
+
+    let output = match &config.output_log {
+        Some(log) => Some(terminal_output(&expand_home(log))?),
+        None => None,
+    };
+
+    let mut prompt = format!("These are the last {} commands I ran in my shell, oldest first{}. \
+        The most recent failure is probably the last one. Explain what went wrong and how to fix it.",
+        commands.len(), if output.is_some() { ", followed by the end of my terminal output" } else { "" });
+    if let Some(question) = question {
+        prompt.push_str(&format!(" {}", question));
+    }
+    prompt.push_str(&format!("\n\n```\n{}\n```", commands.iter().map(|c| format!("$ {}", c)).collect::<Vec<_>>().join("\n")));
+    if let Some(output) = output {
+        prompt.push_str(&format!("\n\nTerminal output:\n```\n{}\n```", output.trim_end()));
+    }
+    Ok((prompt, commands.len()))
+}
+
+/// The configured file, $HISTFILE, or the usual file of the shell in $SHELL
+fn history_file(config: &ShellHistoryConfig) -> Result<PathBuf> {
+    if let Some(file) = &config.history_file {
+        return Ok(expand_home(file));
+    }
+    if let Ok(file) = std::env::var("HISTFILE") {
+        return Ok(PathBuf::from(file));
+    }
+    let home = dirs::home_dir().context("Could not find home directory")?;
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    Ok(match shell.rsplit('/').next().unwrap_or_default() {
+        "zsh" => home.join(".zsh_history"),
+        "fish" => home.join(".local/share/fish/fish_history"),
+        _ => home.join(".bash_history"),
+    })
+}
+
+/// Commands of a bash, zsh (plain or extended) or fish history, oldest first
+fn parse(text: &str) -> Vec<String> {
+    let zsh_extended = Regex::new(r"^: \d+:\d+;").unwrap();
+    let mut commands: Vec<String> = Vec::new();
+    let mut continued = false;
+
+    for line in text.lines() {
+        if continued && let Some(last) = commands.last_mut() {
+            // zsh stores multi-line commands with a backslash before each newline
+            last.push('\n');
+            last.push_str(line.strip_suffix('\\').unwrap_or(line));
+            continued = line.ends_with('\\');
+            continue;
+        }
+        let command = if let Some(command) = line.strip_prefix("- cmd: ") {
+            command.replace("\\n", "\n").replace("\\\\", "\\")
+        } else if line.starts_with("  when: ") || line.starts_with("  paths:") || line.starts_with("    - ") {
+            // The rest of a fish entry
+            continue;
+        } else if let Some(found) = zsh_extended.find(line) {
+            line[found.end()..].to_string()
+        } else if line.starts_with('#') && line[1..].chars().all(|c| c.is_ascii_digit()) {
+            // bash timestamps written with HISTTIMEFORMAT set
+            continue;
+        } else {
+            line.to_string()
+        };
+
+        continued = command.ends_with('\\');
+        let command = command.strip_suffix('\\').unwrap_or(&command).to_string();
+        if !command.trim().is_empty() {
+            commands.push(command);
+        }
+    }
+    commands
+}
+
+/// The end of a terminal log without escape sequences and carriage returns
+fn terminal_output(path: &std::path::Path) -> Result<String> {
+    let bytes = fs::read(path)
+        .with_context(|| format!("Failed to read terminal log: {}", path.display()))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let escapes = Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[()][0-9A-Za-z]|\x1b[=>]").unwrap();
+    let text = escapes.replace_all(&text, "");
+    let text: String = text.lines().map(|line| line.rsplit('\r').next().unwrap_or(line)).collect::<Vec<_>>().join("\n");
+
+    let start = text.char_indices().rev().nth(MAX_OUTPUT_CHARS - 1).map_or(0, |(i, _)| i);
+    Ok(text[start..].to_string())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,267 @@
+//! `--stdio-api`: JSON-RPC 2.0 over stdin and stdout, one message per line,
+//! for editor plugins that embed the CLI instead of scraping its output.
+//!
+//! Requests are handled one at a time. `send` runs the agent loop on the
+//! message and reports its steps as "event" notifications, token by token,
+//! before answering. When a tool call needs approval, an "approvalRequest"
+//! notification is sent and the loop waits for an `approve` request.
+//!
+//! The `daemon` serves the same protocol on a Unix socket, keeping one
+//! `Server` and its conversation across connections.
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::fs;
+use std::io::{BufRead, Write};
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+use crate::agent;
+use crate::config::AppConfig;
+use crate::executor::AIExecutor;
+use crate::mcp_manager::McpManager;
+use crate::ollama::Message;
+use crate::policy::{Approval, ApprovalRequest};
+use crate::recall;
+use crate::repo_map;
+use crate::session::Session;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+pub struct Request {
+    id: Value,
+    method: String,
+    params: Value,
+}
+
+pub type Requests = mpsc::UnboundedReceiver<Result<Request, String>>;
+
+/// Where responses and notifications are written, one line each
+pub struct Output(Mutex<Box<dyn Write + Send>>);
+
+impl Output {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self(Mutex::new(Box::new(writer)))
+    }
+
+    fn respond(&self, id: Value, result: Value) {
+        self.write(json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+    }
+
+    fn respond_error(&self, id: Value, code: i64, message: &str) {
+        self.write(json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }));
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        self.write(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// A client that went away is noticed when its requests stop
+    fn write(&self, message: Value) {
+        let mut writer = self.0.lock().unwrap();
+        let _ = writeln!(writer, "{}", message);
+        let _ = writer.flush();
+    }
+}
+
+/// The model, tools and conversation behind the protocol
+pub struct Server {
+    executor: AIExecutor,
+    mcp: McpManager,
+    /// System messages every conversation starts with
+    context: Vec<Message>,
+    history: Vec<Message>,
+}
+
+impl Server {
+    pub async fn new(executor: AIExecutor, mcp: Option<McpManager>, config: &AppConfig) -> Result<Self> {
+        let mut mcp = match mcp {
+            Some(mcp) => mcp,
+            None => McpManager::new().await.context("Failed to initialize tools")?,
+        };
+        mcp.set_agent_mode(true);
+        let context: Vec<Message> = config.language_prompt().into_iter()
+            .chain(repo_map::startup_context(&config.repo_map))
+            .chain(mcp.tools_prompt())
+            .map(|content| Message {
+                role: "system".to_string(),
+                content,
+                images: Vec::new(),
+                tool_calls: Vec::new(),
+            })
+            .collect();
+        Ok(Self { executor, mcp, history: context.clone(), context })
+    }
+
+    /// Handles requests until the input ends or `shutdown` is asked for,
+    /// returning whether it was
+    pub async fn serve(&mut self, requests: &mut Requests, output: &Output) -> bool {
+        while let Some(request) = requests.recv().await {
+            let request = match request {
+                Ok(request) => request,
+                Err(error) => {
+                    output.respond_error(Value::Null, PARSE_ERROR, &error);
+                    continue;
+                }
+            };
+
+            let result = match request.method.as_str() {
+                "initialize" => Ok(json!({
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "model": self.executor.get_model(),
+                    "tools": self.mcp.list_tools().iter().map(|tool| &tool.name).collect::<Vec<_>>(),
+                    "messages": self.history.len(),
+                })),
+                "send" => match request.params["text"].as_str() {
+                    Some(text) => self.send(&request, text, requests, output).await,
+                    None => {
+                        output.respond_error(request.id, INVALID_PARAMS, "\"text\" is required");
+                        continue;
+                    }
+                },
+                "approve" => Err(anyhow::anyhow!("No tool call is waiting for approval")),
+                "listSessions" => list_sessions().await,
+                "loadSession" => match request.params["path"].as_str() {
+                    Some(path) => Session::load(std::path::Path::new(path)).map(|session| {
+                        if let Some(model) = &session.model {
+                            self.executor.set_model(model.clone());
+                        }
+                        self.history = session.messages;
+                        json!({ "messages": self.history.len(), "model": self.executor.get_model() })
+                    }),
+                    None => {
+                        output.respond_error(request.id, INVALID_PARAMS, "\"path\" is required");
+                        continue;
+                    }
+                },
+                "clear" => {
+                    self.history = self.context.clone();
+                    Ok(Value::Null)
+                }
+                "shutdown" => {
+                    output.respond(request.id, Value::Null);
+                    return true;
+                }
+                method => {
+                    output.respond_error(request.id, METHOD_NOT_FOUND, &format!("Unknown method: {}", method));
+                    continue;
+                }
+            };
+
+            match result {
+                Ok(result) => output.respond(request.id, result),
+                Err(e) => output.respond_error(request.id, SERVER_ERROR, &format!("{:#}", e)),
+            }
+        }
+        false
+    }
+
+    async fn send(&mut self, request: &Request, text: &str, requests: &mut Requests, output: &Output) -> Result<Value> {
+        self.history.push(Message {
+            role: "user".to_string(),
+            content: text.to_string(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        });
+        let max_iterations = request.params["maxIterations"].as_u64()
+            .map_or(agent::DEFAULT_MAX_ITERATIONS, |n| n as usize);
+        let id = &request.id;
+        let content = agent::run(&self.executor, &mut self.mcp, &mut self.history, max_iterations,
+            |event| output.notify("event", json!({ "id": id, "event": event })),
+            async |approval| wait_for_approval(id, approval, requests, output).await,
+        ).await?;
+        Ok(json!({ "content": content }))
+    }
+
+    pub async fn shutdown(mut self) {
+        self.mcp.shutdown().await;
+    }
+}
+
+pub async fn run(executor: AIExecutor, mcp: Option<McpManager>, config: &AppConfig) -> Result<()> {
+    let mut server = Server::new(executor, mcp, config).await?;
+    let mut requests = read_requests(std::io::BufReader::new(std::io::stdin()));
+    server.serve(&mut requests, &Output::new(std::io::stdout())).await;
+    server.shutdown().await;
+    Ok(())
+}
+
+/// Asks the client about a tool call and waits for its `approve` request;
+/// anything else sent meanwhile is refused as busy. A closed input refuses the call.
+async fn wait_for_approval(id: &Value, request: ApprovalRequest, requests: &mut Requests, output: &Output) -> Approval {
+    output.notify("approvalRequest", json!({
+        "id": id,
+        "tool": request.tool,
+        "server": request.server,
+        "command": request.command,
+        "paths": request.paths,
+        "arguments": serde_json::from_str::<Value>(&request.arguments).unwrap_or_default(),
+    }));
+    while let Some(next) = requests.recv().await {
+        match next {
+            Ok(next) if next.method == "approve" => {
+                let approval = Approval::parse(next.params["decision"].as_str().unwrap_or_default());
+                output.respond(next.id, Value::Null);
+                return approval;
+            }
+            Ok(next) => output.respond_error(next.id, SERVER_ERROR, "Waiting for a tool call to be approved"),
+            Err(error) => output.respond_error(Value::Null, PARSE_ERROR, &error),
+        }
+    }
+    Approval::Deny
+}
+
+/// Saved conversations, most recently changed first
+async fn list_sessions() -> Result<Value> {
+    let mut sessions = Vec::new();
+    for path in recall::session_files().await? {
+        let Ok(session) = Session::load(&path) else {
+            continue;
+        };
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()
+            .map(chrono::DateTime::<chrono::Local>::from);
+        sessions.push((modified, json!({
+            "path": path,
+            "title": session.title,
+            "tags": session.tags,
+            "messages": session.messages.len(),
+            "modified": modified.map(|m| m.to_rfc3339()),
+        })));
+    }
+    sessions.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    Ok(Value::Array(sessions.into_iter().map(|(_, session)| session).collect()))
+}
+
+/// Lines of `input` as requests, read in the background so that `approve`
+/// can arrive while a `send` is running
+pub fn read_requests(input: impl BufRead + Send + 'static) -> Requests {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in input.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let request = serde_json::from_str::<Value>(&line)
+                .map_err(|e| format!("Invalid JSON: {}", e))
+                .and_then(|value| match value["method"].as_str() {
+                    Some(method) => Ok(Request {
+                        id: value["id"].clone(),
+                        method: method.to_string(),
+                        params: value["params"].clone(),
+                    }),
+                    None => Err("Missing \"method\"".to_string()),
+                });
+            if sender.send(request).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
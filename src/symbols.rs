@@ -0,0 +1,518 @@
+//! Symbols defined in source files, for the repo map, code outlines and
+//! symbol search.
+//!
+//! Files in Rust, Python, Go, JavaScript/TypeScript, Java, C# and Ruby are
+//! parsed with tree-sitter, and definitions and imports are read off the
+//! syntax tree with their exact line ranges. Other languages go to Universal
+//! Ctags when it is installed.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use tree_sitter::{Language, Node, Parser};
+
+/// Larger files are usually generated or data
+pub const MAX_PARSED_BYTES: u64 = 512 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    /// "fn", "struct", "class", ... as written in the source, or the ctags kind
+    pub kind: String,
+    /// 1-based
+    pub line: usize,
+    /// Last line of the definition; equal to `line` when unknown
+    pub end_line: usize,
+    /// Visible outside its file or module by the rules of the language
+    pub exported: bool,
+    /// Defined inside another item, such as a method in an impl block or class
+    pub nested: bool,
+}
+
+/// How a language marks definitions that other files can use
+#[derive(Clone, Copy)]
+enum Visibility {
+    /// A modifier such as `pub` or `public`; `pub(crate)` counts, `private` and the like do not
+    Modifier,
+    /// An enclosing `export` statement (JavaScript/TypeScript)
+    Export,
+    /// Names without a leading underscore (Python)
+    NoUnderscore,
+    /// Capitalized names (Go)
+    Capitalized,
+    Always,
+}
+
+struct Grammar {
+    extensions: &'static [&'static str],
+    language: fn() -> Language,
+    /// Node kinds that define something, with the kind shown for them
+    definitions: &'static [(&'static str, &'static str)],
+    /// Node kinds of whole import statements
+    imports: &'static [&'static str],
+    visibility: Visibility,
+    line_comment: &'static str,
+}
+
+const JAVASCRIPT_DEFINITIONS: &[(&str, &str)] = &[
+    ("function_declaration", "function"),
+    ("generator_function_declaration", "function"),
+    ("class_declaration", "class"),
+    ("method_definition", "method"),
+    // Only those holding a function or class, or exported; see `definition`
+    ("variable_declarator", ""),
+];
+
+const TYPESCRIPT_DEFINITIONS: &[(&str, &str)] = &[
+    ("function_declaration", "function"),
+    ("generator_function_declaration", "function"),
+    ("function_signature", "function"),
+    ("class_declaration", "class"),
+    ("abstract_class_declaration", "class"),
+    ("method_definition", "method"),
+    ("method_signature", "method"),
+    ("abstract_method_signature", "method"),
+    ("interface_declaration", "interface"),
+    ("type_alias_declaration", "type"),
+    ("enum_declaration", "enum"),
+    ("internal_module", "namespace"),
+    ("module", "module"),
+    ("variable_declarator", ""),
+];
+
+/// `require` calls and `const x = require(...)` count as imports too
+const JAVASCRIPT_IMPORTS: &[&str] = &["import_statement", "lexical_declaration", "variable_declaration"];
+
+const GRAMMARS: &[Grammar] = &[
+    Grammar {
+        extensions: &["rs"],
+        language: || tree_sitter_rust::LANGUAGE.into(),
+        definitions: &[
+            ("function_item", "fn"),
+            ("function_signature_item", "fn"),
+            ("struct_item", "struct"),
+            ("enum_item", "enum"),
+            ("union_item", "union"),
+            ("trait_item", "trait"),
+            ("impl_item", "impl"),
+            ("mod_item", "mod"),
+            ("type_item", "type"),
+            ("const_item", "const"),
+            ("static_item", "static"),
+            ("macro_definition", "macro"),
+        ],
+        imports: &["use_declaration", "extern_crate_declaration"],
+        visibility: Visibility::Modifier,
+        line_comment: "//",
+    },
+    Grammar {
+        extensions: &["py", "pyi"],
+        language: || tree_sitter_python::LANGUAGE.into(),
+        definitions: &[("function_definition", "def"), ("class_definition", "class")],
+        imports: &["import_statement", "import_from_statement", "future_import_statement"],
+        visibility: Visibility::NoUnderscore,
+        line_comment: "#",
+    },
+    Grammar {
+        extensions: &["go"],
+        language: || tree_sitter_go::LANGUAGE.into(),
+        definitions: &[
+            ("function_declaration", "func"),
+            ("method_declaration", "func"),
+            ("type_spec", "type"),
+            ("type_alias", "type"),
+        ],
+        imports: &["import_declaration"],
+        visibility: Visibility::Capitalized,
+        line_comment: "//",
+    },
+    Grammar {
+        extensions: &["js", "jsx", "mjs", "cjs"],
+        language: || tree_sitter_javascript::LANGUAGE.into(),
+        definitions: JAVASCRIPT_DEFINITIONS,
+        imports: JAVASCRIPT_IMPORTS,
+        visibility: Visibility::Export,
+        line_comment: "//",
+    },
+    Grammar {
+        extensions: &["ts", "mts", "cts"],
+        language: || tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+        definitions: TYPESCRIPT_DEFINITIONS,
+        imports: JAVASCRIPT_IMPORTS,
+        visibility: Visibility::Export,
+        line_comment: "//",
+    },
+    Grammar {
+        extensions: &["tsx"],
+        language: || tree_sitter_typescript::LANGUAGE_TSX.into(),
+        definitions: TYPESCRIPT_DEFINITIONS,
+        imports: JAVASCRIPT_IMPORTS,
+        visibility: Visibility::Export,
+        line_comment: "//",
+    },
+    Grammar {
+        extensions: &["java"],
+        language: || tree_sitter_java::LANGUAGE.into(),
+        definitions: &[
+            ("class_declaration", "class"),
+            ("interface_declaration", "interface"),
+            ("enum_declaration", "enum"),
+            ("record_declaration", "record"),
+            ("annotation_type_declaration", "@interface"),
+            ("method_declaration", "method"),
+            ("constructor_declaration", "constructor"),
+        ],
+        imports: &["import_declaration"],
+        visibility: Visibility::Modifier,
+        line_comment: "//",
+    },
+    Grammar {
+        extensions: &["cs"],
+        language: || tree_sitter_c_sharp::LANGUAGE.into(),
+        definitions: &[
+            ("namespace_declaration", "namespace"),
+            ("file_scoped_namespace_declaration", "namespace"),
+            ("class_declaration", "class"),
+            ("struct_declaration", "struct"),
+            ("interface_declaration", "interface"),
+            ("enum_declaration", "enum"),
+            ("record_declaration", "record"),
+            ("method_declaration", "method"),
+            ("constructor_declaration", "constructor"),
+        ],
+        imports: &["using_directive"],
+        visibility: Visibility::Modifier,
+        line_comment: "//",
+    },
+    Grammar {
+        extensions: &["rb"],
+        language: || tree_sitter_ruby::LANGUAGE.into(),
+        definitions: &[("class", "class"), ("module", "module"), ("method", "def"), ("singleton_method", "def")],
+        // Only `require` and `require_relative`; see `is_import`
+        imports: &["call"],
+        visibility: Visibility::Always,
+        line_comment: "#",
+    },
+];
+
+/// Marker of line comments in the language of `path`, if known
+pub fn line_comment(path: &Path) -> Option<&'static str> {
+    grammar(path).map(|grammar| grammar.line_comment)
+}
+
+/// Whether there is a grammar for the language of `path`
+pub fn is_supported(path: &Path) -> bool {
+    grammar(path).is_some()
+}
+
+fn grammar(path: &Path) -> Option<&'static Grammar> {
+    let extension = path.extension()?.to_str()?;
+    GRAMMARS.iter().find(|grammar| grammar.extensions.contains(&extension))
+}
+
+/// Definitions in each of `files` (relative to `root`) that a grammar or
+/// ctags understands, keyed by file
+pub fn index(root: &Path, files: &[String]) -> HashMap<String, Vec<Symbol>> {
+    let parsed: Vec<String> = files.iter()
+        .filter(|file| fs::metadata(root.join(file)).is_ok_and(|m| m.len() <= MAX_PARSED_BYTES))
+        .cloned()
+        .collect();
+    let (supported, others): (Vec<String>, Vec<String>) = parsed.into_iter()
+        .partition(|file| is_supported(Path::new(file)));
+
+    let mut symbols = ctags(root, &others).unwrap_or_default();
+    for file in supported {
+        if let Ok(source) = fs::read_to_string(root.join(&file)) {
+            let extracted = extract(Path::new(&file), &source);
+            symbols.insert(file, extracted);
+        }
+    }
+    symbols
+}
+
+/// Definitions in `source`, in file order; empty for unknown languages
+pub fn extract(path: &Path, source: &str) -> Vec<Symbol> {
+    parse(path, source).map(|(definitions, _)| definitions).unwrap_or_default()
+}
+
+/// Imports and definitions of a file with their line ranges, nested by containment
+pub fn outline(path: &Path) -> Result<String> {
+    let source = fs::read_to_string(path)
+        .context(format!("Failed to read file: {}", path.display()))?;
+
+    let (definitions, imports) = match parse(path, &source) {
+        Some(parsed) => parsed,
+        None => {
+            let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            let from_ctags = ctags(dir, std::slice::from_ref(&name)).and_then(|mut found| found.remove(&name));
+            match from_ctags {
+                Some(symbols) if !symbols.is_empty() => (symbols, Vec::new()),
+                _ => anyhow::bail!("No outline for {}: unsupported language (install Universal Ctags for more)", path.display()),
+            }
+        }
+    };
+
+    let mut text = format!("{} ({} lines)\n", path.display(), source.lines().count());
+    if !imports.is_empty() {
+        let (first, last) = (imports[0].line, imports[imports.len() - 1].end_line);
+        if first == last {
+            text.push_str(&format!("Imports (line {}):\n", first));
+        } else {
+            text.push_str(&format!("Imports (lines {}-{}):\n", first, last));
+        }
+        for import in &imports {
+            text.push_str(&format!("  {}: {}\n", import.line, shorten(&import.name, 120)));
+        }
+    }
+    text.push_str("Definitions:\n");
+    if definitions.is_empty() {
+        text.push_str("  (none found)\n");
+    }
+    // End lines of the enclosing definitions, innermost last
+    let mut open: Vec<usize> = Vec::new();
+    for symbol in &definitions {
+        while open.last().is_some_and(|&end| end < symbol.line) {
+            open.pop();
+        }
+        let range = if symbol.end_line > symbol.line {
+            format!("{}-{}", symbol.line, symbol.end_line)
+        } else {
+            symbol.line.to_string()
+        };
+        text.push_str(&format!("{}{} {} {}\n", "  ".repeat(open.len() + 1), range, symbol.kind, symbol.name));
+        open.push(symbol.end_line);
+    }
+    Ok(text)
+}
+
+fn shorten(text: &str, max: usize) -> String {
+    if text.chars().count() > max {
+        format!("{}…", crate::text::head(text, max - 1))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Definitions and imports of `source`, in file order; `None` without a
+/// grammar for the language of `path`
+fn parse(path: &Path, source: &str) -> Option<(Vec<Symbol>, Vec<Symbol>)> {
+    let grammar = grammar(path)?;
+    let mut parser = Parser::new();
+    parser.set_language(&(grammar.language)()).ok()?;
+    let tree = parser.parse(source, None)?;
+
+    let mut definitions = Vec::new();
+    let mut imports = Vec::new();
+    // End bytes of the definitions around the current node, innermost last
+    let mut enclosing: Vec<usize> = Vec::new();
+    let mut cursor = tree.walk();
+    'walk: loop {
+        let node = cursor.node();
+        while enclosing.last().is_some_and(|&end| end <= node.start_byte()) {
+            enclosing.pop();
+        }
+
+        let mut descend = true;
+        if node.is_named() && is_import(grammar, node, source) {
+            imports.push(Symbol {
+                name: collapse(text(node, source)),
+                kind: "import".to_string(),
+                line: node.start_position().row + 1,
+                end_line: node.end_position().row + 1,
+                exported: false,
+                nested: !enclosing.is_empty(),
+            });
+            descend = false;
+        } else if node.is_named()
+            && let Some((kind, name)) = definition(grammar, node, source)
+        {
+            definitions.push(Symbol {
+                exported: exported(grammar.visibility, node, &name, source),
+                name,
+                kind: kind.to_string(),
+                line: node.start_position().row + 1,
+                end_line: node.end_position().row + 1,
+                nested: !enclosing.is_empty(),
+            });
+            enclosing.push(node.end_byte());
+        }
+
+        if descend && cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                break 'walk;
+            }
+        }
+    }
+    Some((definitions, imports))
+}
+
+/// The kind shown for `node` and its name, when it defines something named
+fn definition(grammar: &Grammar, node: Node, source: &str) -> Option<(&'static str, String)> {
+    let &(_, kind) = grammar.definitions.iter().find(|(node_kind, _)| *node_kind == node.kind())?;
+    let field = |name| node.child_by_field_name(name).map(|child| text(child, source));
+    let name = match node.kind() {
+        // `impl Display for Cache<K, V>` is named "Display for Cache<K, V>"
+        "impl_item" => match field("trait") {
+            Some(implemented) => format!("{} for {}", implemented, field("type")?),
+            None => field("type")?.to_string(),
+        },
+        "singleton_method" => format!("{}.{}", field("object")?, field("name")?),
+        "variable_declarator" => {
+            let holds_definition = node.child_by_field_name("value").is_some_and(|value| {
+                matches!(value.kind(), "arrow_function" | "function_expression" | "generator_function" | "class")
+            });
+            let declaration = node.parent()?;
+            if !holds_definition && !is_exported(declaration) {
+                return None;
+            }
+            // `const`, `let` or `var`
+            let keyword = declaration.child(0)?.kind();
+            let kind = ["const", "let", "var"].into_iter().find(|k| *k == keyword)?;
+            return Some((kind, collapse(field("name")?)));
+        }
+        _ => field("name")?.to_string(),
+    };
+    Some((kind, collapse(&name)))
+}
+
+/// Whether `node` is a whole import statement; for JavaScript only
+/// declarations of a `require` call count, and for Ruby only calls of
+/// `require` and `require_relative`
+fn is_import(grammar: &Grammar, node: Node, source: &str) -> bool {
+    if !grammar.imports.contains(&node.kind()) {
+        return false;
+    }
+    let field = |node: Node, name| node.child_by_field_name(name).map(|child| text(child, source));
+    match node.kind() {
+        "call" => matches!(field(node, "method"), Some("require" | "require_relative")) && field(node, "receiver").is_none(),
+        "lexical_declaration" | "variable_declaration" => {
+            let mut cursor = node.walk();
+            node.named_children(&mut cursor)
+                .filter_map(|declarator| declarator.child_by_field_name("value"))
+                .any(|value| value.kind() == "call_expression" && field(value, "function") == Some("require"))
+        }
+        _ => true,
+    }
+}
+
+fn exported(visibility: Visibility, node: Node, name: &str, source: &str) -> bool {
+    match visibility {
+        Visibility::Modifier => {
+            let mut cursor = node.walk();
+            let modifiers: Vec<Node> = node.children(&mut cursor)
+                .filter(|child| matches!(child.kind(), "visibility_modifier" | "modifiers" | "modifier"))
+                .collect();
+            modifiers.into_iter().any(|modifier| {
+                text(modifier, source).split_whitespace().any(|word| word.starts_with("pub") || word == "public")
+            })
+        }
+        Visibility::Export => is_exported(node) || node.parent().is_some_and(is_exported),
+        Visibility::NoUnderscore => !name.starts_with('_'),
+        Visibility::Capitalized => name.starts_with(|c: char| c.is_uppercase()),
+        Visibility::Always => true,
+    }
+}
+
+/// Whether `node` sits directly in an `export` statement
+fn is_exported(node: Node) -> bool {
+    node.parent().is_some_and(|parent| parent.kind() == "export_statement")
+}
+
+fn text<'s>(node: Node, source: &'s str) -> &'s str {
+    &source[node.byte_range()]
+}
+
+/// Runs of whitespace, such as the line breaks of a long signature, as one space
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Whether Universal Ctags is on the PATH; checked once
+pub fn ctags_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        Command::new("ctags").arg("--version").output()
+            .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("Universal Ctags"))
+    })
+}
+
+#[derive(Deserialize)]
+struct CtagsEntry {
+    #[serde(rename = "_type")]
+    entry_type: String,
+    name: String,
+    path: String,
+    #[serde(default)]
+    line: usize,
+    #[serde(default)]
+    end: Option<usize>,
+    #[serde(default)]
+    kind: String,
+    #[serde(default)]
+    scope: Option<String>,
+    #[serde(default)]
+    access: Option<String>,
+}
+
+/// Kinds worth showing; variables, fields, parameters and imports are left out
+const CTAGS_KINDS: &[&str] = &[
+    "function", "method", "class", "struct", "enum", "interface", "trait", "type", "typedef",
+    "module", "namespace", "macro", "implementation", "union", "constant", "object", "record",
+];
+
+/// Runs ctags over `files` (relative to `root`), grouping the symbols by file;
+/// `None` when ctags is not installed or fails
+pub fn ctags(root: &Path, files: &[String]) -> Option<HashMap<String, Vec<Symbol>>> {
+    if !ctags_available() {
+        return None;
+    }
+
+    let mut child = Command::new("ctags")
+        .current_dir(root)
+        .args(["--output-format=json", "--fields=+nKaZe", "--extras=-F", "-f", "-", "-L", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    // Written from a thread so a full stdout pipe cannot deadlock the file list
+    let mut stdin = child.stdin.take()?;
+    let list = files.join("\n");
+    let writer = std::thread::spawn(move || stdin.write_all(list.as_bytes()));
+    let output = child.wait_with_output().ok()?;
+    let _ = writer.join();
+    if !output.status.success() {
+        return None;
+    }
+
+    let mut symbols: HashMap<String, Vec<Symbol>> = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Ok(entry) = serde_json::from_str::<CtagsEntry>(line) else {
+            continue;
+        };
+        if entry.entry_type != "tag" || !CTAGS_KINDS.contains(&entry.kind.as_str()) {
+            continue;
+        }
+        symbols.entry(entry.path).or_default().push(Symbol {
+            name: entry.name,
+            kind: entry.kind,
+            line: entry.line,
+            end_line: entry.end.unwrap_or(entry.line),
+            exported: entry.access.as_deref().is_none_or(|access| access == "public"),
+            nested: entry.scope.is_some(),
+        });
+    }
+    for list in symbols.values_mut() {
+        list.sort_by_key(|symbol| symbol.line);
+    }
+    Some(symbols)
+}
@@ -0,0 +1,16 @@
+//! Character-safe truncation shared by the modules that trim text for prompts
+//! and listings.
+
+/// The last `max_chars` characters of `text`
+pub fn tail(text: &str, max_chars: usize) -> &str {
+    if max_chars == 0 {
+        return "";
+    }
+    let start = text.char_indices().rev().nth(max_chars - 1).map_or(0, |(i, _)| i);
+    &text[start..]
+}
+
+/// The first `max_chars` characters of `text`
+pub fn head(text: &str, max_chars: usize) -> &str {
+    text.char_indices().nth(max_chars).map_or(text, |(end, _)| &text[..end])
+}
@@ -0,0 +1,66 @@
+//! Reasoning the model writes down with the `think` tool.
+//!
+//! Thoughts stay in the conversation for the model, but are a side channel
+//! for the user: shown collapsed to one dimmed line unless revealed with
+//! `/show-thoughts`, and left out of /share pages and training exports
+//! unless "thoughts.export" is set in config.json.
+
+use colored::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::config::ThoughtsConfig;
+use crate::ollama::Message;
+
+/// Start of the result of the `think` tool, which marks a message as a thought
+pub const HEADER: &str = "💭 Internal reasoning:\n";
+
+static SHOW: AtomicBool = AtomicBool::new(false);
+static EXPORT: AtomicBool = AtomicBool::new(false);
+
+/// Applies the "thoughts" config for the whole process
+pub fn init(config: &ThoughtsConfig) {
+    SHOW.store(config.show, Ordering::Relaxed);
+    EXPORT.store(config.export, Ordering::Relaxed);
+}
+
+pub fn shown() -> bool {
+    SHOW.load(Ordering::Relaxed)
+}
+
+pub fn set_shown(show: bool) {
+    SHOW.store(show, Ordering::Relaxed);
+}
+
+pub fn is_thought(message: &Message) -> bool {
+    message.role == "tool" && message.content.starts_with(HEADER)
+}
+
+/// The reasoning of a `think` result, without its header
+pub fn text(content: &str) -> &str {
+    content.strip_prefix(HEADER).unwrap_or(content)
+}
+
+/// A thought as it is shown: dimmed in full while thoughts are shown,
+/// otherwise collapsed to its `summary`
+pub fn display(content: &str) -> String {
+    if shown() {
+        format!("{} {}", "💭".dimmed(), text(content).trim().dimmed().italic())
+    } else {
+        format!("{} {}", "💭".dimmed(), summary(content).dimmed())
+    }
+}
+
+/// One line saying how long a thought is
+pub fn summary(content: &str) -> String {
+    let lines = text(content).trim().lines().count();
+    format!("thought, {} line{} (/show-thoughts to reveal)", lines, if lines == 1 { "" } else { "s" })
+}
+
+/// The messages to export: all of them with "thoughts.export", otherwise all but thoughts
+pub fn exported(messages: &[Message]) -> Vec<Message> {
+    let export = EXPORT.load(Ordering::Relaxed);
+    messages.iter()
+        .filter(|m| export || !is_thought(m))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,33 @@
+use crate::ollama::Message;
+
+/// Rough token count for a conversation; about four characters per token
+/// holds well enough for English text and code across common tokenizers
+pub fn estimate(messages: &[Message]) -> usize {
+    messages.iter().map(estimate_message).sum()
+}
+
+pub fn estimate_message(message: &Message) -> usize {
+    estimate_text(&message.content) + 4 // per-message framing
+}
+
+pub fn estimate_text(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Compact form used in prompts and previews, e.g. 950, 3.2k, 128k
+pub fn format_count(tokens: usize) -> String {
+    match tokens {
+        0..1_000 => tokens.to_string(),
+        1_000..10_000 => format!("{:.1}k", tokens as f64 / 1000.0),
+        _ => format!("{}k", tokens / 1000),
+    }
+}
+
+/// Context windows are usually powers of two, so 8192 reads as 8k
+pub fn format_window(tokens: usize) -> String {
+    if tokens >= 1024 && tokens.is_multiple_of(1024) {
+        format!("{}k", tokens / 1024)
+    } else {
+        format_count(tokens)
+    }
+}
@@ -0,0 +1,206 @@
+//! Reshaping JSON results of specific tools before they are shown and
+//! added to the conversation.
+//!
+//! Formatters are configured per tool under "toolFormatters" in config.json.
+//! `select` is a jq-style path such as `.items[]` or `.data.results[0]`;
+//! each value it finds is rendered through `template`, where `{path}` is
+//! replaced with the value at that path (`{user.login}`, `{labels[].name}`),
+//! or as compact JSON when there is no template.
+//!
+//! Separately, JSON results are shown indented and highlighted by `pretty`,
+//! with long arrays cut short; the conversation keeps the text as it came.
+
+use anyhow::{Context, Result};
+use colored::*;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::config::ToolFormatterConfig;
+
+#[derive(Debug, Clone)]
+enum Step {
+    Key(String),
+    Index(i64),
+    /// `[]`: every element of an array or value of an object
+    Each,
+}
+
+/// A formatter with its paths parsed
+pub struct Formatter {
+    select: Vec<Step>,
+    /// Literal text and paths, alternating
+    template: Option<Vec<(String, Vec<Step>)>>,
+    tail: String,
+    max_items: Option<usize>,
+}
+
+impl Formatter {
+    pub fn new(config: &ToolFormatterConfig) -> Result<Self> {
+        let select = parse_path(config.select.as_deref().unwrap_or("."))
+            .context("Invalid \"select\"")?;
+
+        let (template, tail) = match &config.template {
+            Some(template) => {
+                let placeholder = Regex::new(r"\{([^{}]+)\}").unwrap();
+                let mut parts = Vec::new();
+                let mut last = 0;
+                for found in placeholder.captures_iter(template) {
+                    let whole = found.get(0).unwrap();
+                    let path = parse_path(&found[1])
+                        .context(format!("Invalid placeholder {}", whole.as_str()))?;
+                    parts.push((template[last..whole.start()].to_string(), path));
+                    last = whole.end();
+                }
+                (Some(parts), template[last..].to_string())
+            }
+            None => (None, String::new()),
+        };
+
+        Ok(Self { select, template, tail, max_items: config.max_items })
+    }
+
+    /// The result reshaped, or `None` if it is not JSON
+    pub fn apply(&self, text: &str) -> Option<String> {
+        let value: Value = serde_json::from_str(text).ok()?;
+        let items = evaluate(&value, &self.select);
+
+        let shown = self.max_items.unwrap_or(usize::MAX).min(items.len());
+        let mut lines: Vec<String> = items[..shown].iter().map(|item| self.render(item)).collect();
+        if shown < items.len() {
+            lines.push(format!("… and {} more", items.len() - shown));
+        }
+        Some(lines.join("\n"))
+    }
+
+    fn render(&self, item: &Value) -> String {
+        let Some(parts) = &self.template else {
+            return item.to_string();
+        };
+        let mut text = String::new();
+        for (literal, path) in parts {
+            text.push_str(literal);
+            let values: Vec<String> = evaluate(item, path).into_iter().map(display).collect();
+            text.push_str(&values.join(", "));
+        }
+        text.push_str(&self.tail);
+        text
+    }
+}
+
+/// Arrays longer than this are cut short by `pretty` unless "jsonArrayItems" says otherwise
+pub const DEFAULT_ARRAY_ITEMS: usize = 10;
+
+/// An object or array result indented for the terminal, highlighted when
+/// `color` is set, with arrays past `max_items` elements collapsed to a count
+pub fn pretty(text: &str, max_items: usize, color: bool) -> Option<String> {
+    let value: Value = serde_json::from_str(text.trim()).ok()?;
+    if !value.is_object() && !value.is_array() {
+        return None;
+    }
+    let mut out = String::new();
+    write_pretty(&mut out, &value, 0, max_items, color);
+    Some(out)
+}
+
+fn write_pretty(out: &mut String, value: &Value, depth: usize, max_items: usize, color: bool) {
+    let paint = |text: String, colorize: fn(&str) -> ColoredString| {
+        if color { colorize(&text).to_string() } else { text }
+    };
+    let indent = "  ".repeat(depth + 1);
+    let close = "  ".repeat(depth);
+
+    match value {
+        Value::Object(map) if map.is_empty() => out.push_str("{}"),
+        Value::Object(map) => {
+            out.push_str("{\n");
+            for (i, (key, value)) in map.iter().enumerate() {
+                out.push_str(&indent);
+                out.push_str(&paint(Value::String(key.clone()).to_string(), |s| s.bright_cyan()));
+                out.push_str(": ");
+                write_pretty(out, value, depth + 1, max_items, color);
+                out.push_str(if i + 1 < map.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&close);
+            out.push('}');
+        }
+        Value::Array(items) if items.is_empty() => out.push_str("[]"),
+        Value::Array(items) => {
+            out.push_str("[\n");
+            let shown = items.len().min(max_items.max(1));
+            for (i, item) in items[..shown].iter().enumerate() {
+                out.push_str(&indent);
+                write_pretty(out, item, depth + 1, max_items, color);
+                out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            if shown < items.len() {
+                out.push_str(&indent);
+                out.push_str(&paint(format!("… {} more", items.len() - shown), |s| s.bright_black()));
+                out.push('\n');
+            }
+            out.push_str(&close);
+            out.push(']');
+        }
+        Value::String(_) => out.push_str(&paint(value.to_string(), |s| s.green())),
+        Value::Number(_) => out.push_str(&paint(value.to_string(), |s| s.yellow())),
+        Value::Bool(_) | Value::Null => out.push_str(&paint(value.to_string(), |s| s.magenta())),
+    }
+}
+
+/// Strings without quotes, arrays as a list and null as nothing
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(display).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+/// Values at `path`; missing keys and indices yield nothing
+fn evaluate<'a>(value: &'a Value, path: &[Step]) -> Vec<&'a Value> {
+    let Some((step, rest)) = path.split_first() else {
+        return vec![value];
+    };
+    let found: Vec<&Value> = match (step, value) {
+        (Step::Key(key), Value::Object(map)) => map.get(key).into_iter().collect(),
+        (Step::Index(index), Value::Array(items)) => {
+            let index = if *index < 0 { items.len() as i64 + index } else { *index };
+            usize::try_from(index).ok().and_then(|i| items.get(i)).into_iter().collect()
+        }
+        (Step::Each, Value::Array(items)) => items.iter().collect(),
+        (Step::Each, Value::Object(map)) => map.values().collect(),
+        _ => Vec::new(),
+    };
+    found.into_iter().flat_map(|value| evaluate(value, rest)).collect()
+}
+
+/// Parses `.a.b[0]`, `items[].name`, `.["odd key"]` or `.`
+fn parse_path(path: &str) -> Result<Vec<Step>> {
+    let mut steps = Vec::new();
+    let mut rest = path.trim();
+    rest = rest.strip_prefix('.').unwrap_or(rest);
+
+    while !rest.is_empty() {
+        if let Some(inner) = rest.strip_prefix('[') {
+            let end = inner.find(']').context(format!("Missing ] in {}", path))?;
+            let index = inner[..end].trim();
+            steps.push(if index.is_empty() {
+                Step::Each
+            } else if let Some(key) = index.strip_prefix('"').and_then(|k| k.strip_suffix('"')) {
+                Step::Key(key.to_string())
+            } else {
+                Step::Index(index.parse().context(format!("Invalid index [{}] in {}", index, path))?)
+            });
+            rest = &inner[end + 1..];
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            if end == 0 {
+                anyhow::bail!("Empty key in {}", path);
+            }
+            steps.push(Step::Key(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        rest = rest.strip_prefix('.').unwrap_or(rest);
+    }
+    Ok(steps)
+}
@@ -0,0 +1,71 @@
+//! Condensing large tool results before they enter the conversation.
+//!
+//! The full output is written to ~/.ai-chat-cli/tool-outputs/ and split into
+//! chunks that a (usually smaller) summarizer model condenses in parallel;
+//! the conversation receives the combined summary plus the path of the file,
+//! which is encrypted like sessions when encryption is enabled.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::ToolSummaryConfig;
+use crate::encryption;
+use crate::executor::AIExecutor;
+use crate::mapreduce::{self, DEFAULT_CHUNK_SIZE};
+
+pub const DEFAULT_THRESHOLD: usize = 4000;
+
+pub struct Condensed {
+    /// Message content for the conversation
+    pub content: String,
+    pub model: String,
+    pub path: PathBuf,
+}
+
+/// Summarizes `text` when enabled and longer than the threshold; `None` means it is kept as is
+pub async fn condense(
+    executor: &AIExecutor,
+    config: &ToolSummaryConfig,
+    tool: &str,
+    text: &str,
+) -> Result<Option<Condensed>> {
+    let threshold = config.threshold_chars.unwrap_or(DEFAULT_THRESHOLD);
+    if !config.enabled || text.chars().count() <= threshold {
+        return Ok(None);
+    }
+
+    let path = save(tool, text)?;
+    let model = config.model.clone().unwrap_or_else(|| executor.get_model().to_string());
+
+    let mut chunks = Vec::new();
+    mapreduce::split_text(tool, text, DEFAULT_CHUNK_SIZE, &mut chunks);
+    let instruction = format!(
+        "Summarize the output of the '{}' tool for an assistant that continues a task with it. \
+         Keep file names, numbers, error messages and other details that may matter verbatim.",
+        tool
+    );
+    let output = executor.map_reduce_on(&model, chunks, &instruction).await?;
+
+    let content = format!(
+        "[Output of {}: {} characters, summarized; full output in {}]\n{}",
+        tool, text.chars().count(), path.display(), output.answer.trim()
+    );
+    Ok(Some(Condensed { content, model, path }))
+}
+
+fn save(tool: &str, text: &str) -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .context("Could not find home directory")?;
+    let dir = home.join(".ai-chat-cli").join("tool-outputs");
+    fs::create_dir_all(&dir)
+        .context("Failed to create tool output directory")?;
+
+    let name: String = tool.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    let path = dir.join(format!("{}-{}.txt", chrono::Local::now().format("%Y%m%d-%H%M%S%.3f"), name));
+    encryption::write(&path, text)
+        .context(format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,84 @@
+//! Narrowing the tool list to the tools that could matter for a message.
+//!
+//! Small models choose tools better, and answer sooner, from a short list.
+//! With "toolSelection" set, each message is matched against the tools
+//! before it is sent, by the words in their names and descriptions or by
+//! asking a (small) model, and only the matches are described to the model.
+
+use anyhow::Result;
+use std::collections::HashSet;
+
+use crate::config::ToolSelectionConfig;
+use crate::executor::AIExecutor;
+use crate::mcp_client::Tool;
+use crate::ollama::Message;
+
+pub const DEFAULT_MAX_TOOLS: usize = 8;
+
+/// Words too common to say anything about a tool
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "from", "into", "what", "which", "can", "you",
+    "are", "was", "not", "but", "all", "any", "how", "why", "its", "your", "have", "has", "does",
+    "please", "about", "there", "then", "than", "them", "they", "will", "would", "could", "should",
+    "use", "using", "return", "returns", "file", "files",
+];
+
+/// Up to the configured number of tools sharing the most words with `text`, best first
+pub fn by_keywords(config: &ToolSelectionConfig, text: &str, tools: &[&Tool]) -> Vec<String> {
+    let query = words(text);
+    let mut scored: Vec<(usize, &str)> = tools.iter()
+        .filter_map(|tool| {
+            let described = words(&format!("{} {}", tool.name.replace('_', " "), tool.description));
+            let score = query.intersection(&described).count();
+            (score > 0).then_some((score, tool.name.as_str()))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+    scored.into_iter()
+        .take(config.max_tools.unwrap_or(DEFAULT_MAX_TOOLS))
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// The tools a model picks from the list as useful for `text`
+pub async fn by_model(executor: &AIExecutor, config: &ToolSelectionConfig, text: &str, tools: &[&Tool]) -> Result<Vec<String>> {
+    let list: Vec<String> = tools.iter()
+        .map(|tool| format!("- {}: {}", tool.name, tool.description.lines().next().unwrap_or_default()))
+        .collect();
+    let prompt = format!(
+        "Which of these tools could help with the request below? Reply with at most {} tool names \
+         separated by commas, most useful first, or with \"none\". Reply with nothing else.\n\n\
+         Tools:\n{}\n\nRequest: {}",
+        config.max_tools.unwrap_or(DEFAULT_MAX_TOOLS), list.join("\n"), text
+    );
+    let messages = vec![Message {
+        role: "user".to_string(),
+        content: prompt,
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    }];
+    let reply = executor.batch_complete(config.model.as_deref(), messages, None).await?;
+
+    // Names the model made up are dropped
+    let known: HashSet<&str> = tools.iter().map(|tool| tool.name.as_str()).collect();
+    Ok(reply.message.content
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|name| name.trim_matches(|c: char| !c.is_alphanumeric() && c != '_' && c != '-'))
+        .filter(|name| known.contains(name))
+        .take(config.max_tools.unwrap_or(DEFAULT_MAX_TOOLS))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Lowercase words of three or more letters, without stop words and
+/// with a trailing plural "s" removed so "issues" meets "issue"
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(|word| word.to_lowercase())
+        .filter(|word| word.chars().count() >= 3 && !STOP_WORDS.contains(&word.as_str()))
+        .map(|word| match word.strip_suffix('s') {
+            Some(stem) if stem.chars().count() >= 3 && !stem.ends_with('s') => stem.to_string(),
+            _ => word,
+        })
+        .collect()
+}
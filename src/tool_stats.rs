@@ -0,0 +1,121 @@
+//! How often each tool is called, how often it fails and how long it takes.
+//!
+//! Counts are kept across sessions in ~/.ai-chat-cli/tool-stats.json and
+//! shown by `/tools stats`, grouped by server, so servers whose tools are
+//! never used or mostly fail stand out. Calls of the current session are
+//! counted apart for its summary on exit.
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::accessibility;
+
+/// Calls per tool since the process started
+static SESSION: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolCounts {
+    server: String,
+    calls: u64,
+    failures: u64,
+    total_millis: u64,
+    /// Local date of the last call, e.g. "2026-10-16"
+    last_used: String,
+}
+
+/// Adds one call of `tool` to the totals
+pub fn record(tool: &str, server: &str, elapsed: Duration, failed: bool) -> Result<()> {
+    *SESSION.lock().unwrap().entry(tool.to_string()).or_default() += 1;
+
+    let path = path()?;
+    let mut stats = load(&path);
+    let counts = stats.entry(tool.to_string()).or_default();
+    counts.server = server.to_string();
+    counts.calls += 1;
+    counts.failures += u64::from(failed);
+    counts.total_millis += elapsed.as_millis() as u64;
+    counts.last_used = Local::now().format("%Y-%m-%d").to_string();
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(&stats)?)
+        .context("Failed to save tool statistics")
+}
+
+/// Calls per tool in this session
+pub fn session() -> BTreeMap<String, u64> {
+    SESSION.lock().unwrap().clone()
+}
+
+/// Prints the totals per server, busiest first; `servers` are the connected
+/// ones, so that those never called are listed too
+pub fn print(servers: &[String]) -> Result<()> {
+    let stats = load(&path()?);
+
+    let mut by_server: BTreeMap<&str, Vec<(&String, &ToolCounts)>> = BTreeMap::new();
+    for server in servers {
+        by_server.entry(server).or_default();
+    }
+    for (tool, counts) in &stats {
+        by_server.entry(&counts.server).or_default().push((tool, counts));
+    }
+    let mut by_server: Vec<_> = by_server.into_iter().collect();
+    by_server.sort_by_key(|(server, tools)| (std::cmp::Reverse(tools.iter().map(|(_, c)| c.calls).sum::<u64>()), *server));
+
+    println!("\n{}", "Tool usage across sessions:".bright_yellow().bold());
+    println!("{}", accessibility::rule('-').bright_black());
+    for (server, mut tools) in by_server {
+        let calls: u64 = tools.iter().map(|(_, c)| c.calls).sum();
+        let failures: u64 = tools.iter().map(|(_, c)| c.failures).sum();
+        let connected = if servers.iter().any(|s| s == server) { "" } else { " (not connected)" };
+        println!("\n{}{} {}", server.bright_magenta().bold(), connected.bright_black(),
+            format!("{} call(s), {}", calls, failure_rate(calls, failures).trim_start()).bright_black());
+        if tools.is_empty() {
+            println!("  {}", "No tool calls yet".bright_black());
+        }
+        tools.sort_by_key(|(tool, c)| (std::cmp::Reverse(c.calls), *tool));
+        for (tool, counts) in tools {
+            let rate = failure_rate(counts.calls, counts.failures);
+            let rate = if counts.failures * 2 > counts.calls { rate.bright_red() } else { rate.normal() };
+            println!("  {:<28} {:>5} call(s)  {}  avg {:>7}  last {}",
+                tool.bright_cyan(), counts.calls, rate,
+                format_millis(counts.total_millis / counts.calls.max(1)), counts.last_used);
+        }
+    }
+    println!();
+    Ok(())
+}
+
+fn failure_rate(calls: u64, failures: u64) -> String {
+    format!("{:>3.0}% failed", failures as f64 * 100.0 / calls.max(1) as f64)
+}
+
+fn format_millis(millis: u64) -> String {
+    if millis < 1000 {
+        format!("{}ms", millis)
+    } else {
+        format!("{:.1}s", millis as f64 / 1000.0)
+    }
+}
+
+/// Missing or unreadable statistics start over rather than failing the tool call
+fn load(path: &PathBuf) -> BTreeMap<String, ToolCounts> {
+    fs::read_to_string(path).ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn path() -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .context("Could not find home directory")?;
+    Ok(home.join(".ai-chat-cli").join("tool-stats.json"))
+}
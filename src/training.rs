@@ -0,0 +1,139 @@
+use anyhow::{Context, Result};
+use colored::*;
+use serde_json::json;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::args::ExportTrainingArgs;
+use crate::ollama::Message;
+use crate::session::Session;
+use crate::thoughts;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TrainingFormat {
+    /// {"messages": [{"role", "content"}]} as used by OpenAI fine-tuning
+    Openai,
+    /// {"conversations": [{"from", "value"}]} as used by ShareGPT-style trainers
+    Sharegpt,
+}
+
+impl TrainingFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "openai" => Some(Self::Openai),
+            "sharegpt" => Some(Self::Sharegpt),
+            _ => None,
+        }
+    }
+}
+
+/// Number of completed exchanges, i.e. assistant replies
+pub fn turns(messages: &[Message]) -> usize {
+    messages.iter().filter(|m| m.role == "assistant").count()
+}
+
+/// Converts a conversation into one training record, dropping any trailing
+/// messages that never received an assistant reply, and thoughts unless exported
+pub fn record(messages: &[Message], format: TrainingFormat) -> Option<serde_json::Value> {
+    let messages = &thoughts::exported(messages);
+    let last_reply = messages.iter().rposition(|m| m.role == "assistant")?;
+    let messages = messages[..=last_reply].iter().filter(|m| !m.content.trim().is_empty());
+
+    Some(match format {
+        TrainingFormat::Openai => json!({
+            "messages": messages
+                .map(|m| json!({"role": m.role, "content": m.content}))
+                .collect::<Vec<_>>()
+        }),
+        TrainingFormat::Sharegpt => json!({
+            "conversations": messages
+                .map(|m| {
+                    let from = match m.role.as_str() {
+                        "user" => "human",
+                        "assistant" => "gpt",
+                        other => other,
+                    };
+                    json!({"from": from, "value": m.content})
+                })
+                .collect::<Vec<_>>()
+        }),
+    })
+}
+
+/// Appends the conversation as a single record to a JSONL file
+pub fn append(path: &Path, messages: &[Message], format: TrainingFormat) -> Result<()> {
+    let record = record(messages, format)
+        .context("Conversation has no assistant replies to export")?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context(format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(&record)?)?;
+
+    Ok(())
+}
+
+/// Bulk export of saved sessions, for the export-training subcommand
+pub fn export(args: ExportTrainingArgs) -> Result<()> {
+    let mut paths = Vec::new();
+    for input in &args.inputs {
+        collect_sessions(input, &mut paths)?;
+    }
+
+    let mut lines = Vec::new();
+    let mut skipped = 0;
+
+    for path in &paths {
+        let session = match Session::load(path) {
+            Ok(session) => session,
+            Err(e) => {
+                eprintln!("{} Skipping {}: {:#}", "Warning:".bright_yellow(), path.display(), e);
+                skipped += 1;
+                continue;
+            }
+        };
+
+        let tagged = args.tag.as_ref().is_none_or(|tag| session.has_tag(tag));
+        if !tagged || turns(&session.messages) < args.min_turns {
+            skipped += 1;
+            continue;
+        }
+
+        match record(&session.messages, args.format) {
+            Some(record) => lines.push(serde_json::to_string(&record)?),
+            None => skipped += 1,
+        }
+    }
+
+    let mut output = lines.join("\n");
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    fs::write(&args.out, output)
+        .context(format!("Failed to write {}", args.out.display()))?;
+
+    println!("{} Exported {} conversation(s) to {} ({} skipped)",
+        "✓".bright_green(), lines.len(), args.out.display(), skipped);
+
+    Ok(())
+}
+
+fn collect_sessions(path: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+    if !path.is_dir() {
+        paths.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)
+        .context(format!("Failed to read directory: {}", path.display()))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    entries.sort();
+    paths.extend(entries);
+
+    Ok(())
+}
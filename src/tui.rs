@@ -0,0 +1,613 @@
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Flex, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Tabs, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::executor::AIExecutor;
+use crate::filters;
+use crate::lifecycle;
+use crate::mcp_manager::{McpManager, ServerStatus};
+use crate::thoughts;
+use crate::tool_format;
+use crate::notify;
+use crate::ollama::Message;
+use crate::policy::{Approval, ApprovalRequest};
+use crate::session::Session;
+
+/// How often MCP server health is re-checked
+const HEALTH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Lines kept in the tool output pane
+const TOOL_OUTPUT_LINES: usize = 500;
+
+struct ChatSession {
+    id: usize,
+    history: Vec<Message>,
+    pending: bool,
+    /// Lines scrolled up from the bottom; 0 follows new output
+    scroll: usize,
+}
+
+enum AppEvent {
+    Reply { session: usize, result: Result<String> },
+    ToolOutput(String),
+    /// A tool call waiting for the user's approval
+    Approval(PendingApproval),
+}
+
+struct PendingApproval {
+    request: ApprovalRequest,
+    arguments: serde_json::Value,
+}
+
+struct App {
+    executor: Arc<AIExecutor>,
+    mcp: Option<Arc<Mutex<McpManager>>>,
+    sessions: Vec<ChatSession>,
+    current: usize,
+    next_id: usize,
+    input: String,
+    tool_output: Vec<String>,
+    servers: Vec<ServerStatus>,
+    status: String,
+    approval: Option<PendingApproval>,
+    /// System prompts every session starts with: the "responseLanguage" and the repo map
+    context: Vec<String>,
+    /// "jsonArrayItems": array elements shown in JSON tool results
+    json_array_items: usize,
+    events: mpsc::UnboundedSender<AppEvent>,
+}
+
+pub async fn run(executor: AIExecutor, mcp_manager: Option<McpManager>, context: Vec<String>, json_array_items: usize) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let mut app = App {
+        executor: Arc::new(executor),
+        mcp: mcp_manager.map(|mcp| Arc::new(Mutex::new(mcp))),
+        sessions: Vec::new(),
+        current: 0,
+        next_id: 1,
+        input: String::new(),
+        tool_output: Vec::new(),
+        servers: Vec::new(),
+        status: String::new(),
+        approval: None,
+        context,
+        json_array_items,
+        events: tx,
+    };
+    app.new_session().await;
+
+    let mut terminal = ratatui::init();
+    let result = tokio::select! {
+        result = app.event_loop(&mut terminal, &mut rx) => result,
+        _ = lifecycle::terminated() => Ok(()),
+    };
+    ratatui::restore();
+
+    if let Some(mcp) = &app.mcp {
+        mcp.lock().await.shutdown().await;
+    }
+
+    result
+}
+
+impl App {
+    async fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        rx: &mut mpsc::UnboundedReceiver<AppEvent>,
+    ) -> Result<()> {
+        let mut last_health: Option<Instant> = None;
+
+        loop {
+            if last_health.is_none_or(|t| t.elapsed() >= HEALTH_INTERVAL) {
+                self.refresh_health();
+                last_health = Some(Instant::now());
+            }
+
+            while let Ok(event) = rx.try_recv() {
+                self.apply(event);
+            }
+
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(Duration::from_millis(50))?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key).await
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    fn refresh_health(&mut self) {
+        // Skip the check while a tool call holds the manager
+        if let Some(mcp) = &self.mcp
+            && let Ok(mut mcp) = mcp.try_lock()
+        {
+            self.servers = mcp.server_status();
+        }
+    }
+
+    fn apply(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::Reply { session, result } => {
+                let Some(chat) = self.sessions.iter_mut().find(|s| s.id == session) else {
+                    return;
+                };
+                chat.pending = false;
+
+                match result {
+                    Ok(response) => chat.history.push(Message {
+                        role: "assistant".to_string(),
+                        content: filters::apply(&response),
+                        images: Vec::new(),
+                        tool_calls: Vec::new(),
+                    }),
+                    Err(e) => self.status = format!("Error: {:#}", e),
+                }
+            }
+            AppEvent::ToolOutput(text) => {
+                self.tool_output.extend(text.lines().map(String::from));
+                let excess = self.tool_output.len().saturating_sub(TOOL_OUTPUT_LINES);
+                self.tool_output.drain(..excess);
+            }
+            AppEvent::Approval(pending) => self.approval = Some(pending),
+        }
+    }
+
+    /// Returns false when the app should exit
+    async fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+
+        // The approval panel takes every key until it is answered
+        if self.approval.is_some() {
+            let approval = match key.code {
+                KeyCode::Char(c @ ('y' | 'n' | 'a' | 'v')) => Approval::parse(&c.to_string()),
+                KeyCode::Esc => Approval::Deny,
+                _ => return true,
+            };
+            self.answer_approval(approval);
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Esc => return false,
+            KeyCode::Char('c' | 'q') if ctrl => return false,
+            KeyCode::Char('n') if ctrl => self.new_session().await,
+            KeyCode::Char('w') if ctrl => self.close_session(),
+            KeyCode::Left if ctrl => self.switch_by(-1),
+            KeyCode::Right if ctrl => self.switch_by(1),
+            KeyCode::Char(c @ '1'..='9') if alt => {
+                let index = c as usize - '1' as usize;
+                if index < self.sessions.len() {
+                    self.current = index;
+                }
+            }
+            KeyCode::PageUp => self.scroll_by(10),
+            KeyCode::PageDown => self.scroll_by(-10),
+            KeyCode::Up => self.scroll_by(1),
+            KeyCode::Down => self.scroll_by(-1),
+            KeyCode::End => self.sessions[self.current].scroll = 0,
+            KeyCode::Enter => return self.submit(),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) => self.input.push(c),
+            _ => {}
+        }
+
+        true
+    }
+
+    async fn new_session(&mut self) {
+        let mut history: Vec<Message> = self.context.iter()
+            .map(|prompt| Message {
+                role: "system".to_string(),
+                content: prompt.clone(),
+                images: Vec::new(),
+                tool_calls: Vec::new(),
+            })
+            .collect();
+        if let Some(mcp) = &self.mcp
+            && let Some(prompt) = {
+                let mut mcp = mcp.lock().await;
+                mcp.poll_pending().await;
+                mcp.tools_prompt()
+            }
+        {
+            history.push(Message {
+                role: "system".to_string(),
+                content: prompt,
+                images: Vec::new(),
+                tool_calls: Vec::new(),
+            });
+        }
+
+        self.sessions.push(ChatSession {
+            id: self.next_id,
+            history,
+            pending: false,
+            scroll: 0,
+        });
+        self.next_id += 1;
+        self.current = self.sessions.len() - 1;
+    }
+
+    fn close_session(&mut self) {
+        if self.sessions.len() == 1 {
+            self.status = "Cannot close the last session".to_string();
+            return;
+        }
+
+        self.sessions.remove(self.current);
+        self.current = self.current.min(self.sessions.len() - 1);
+    }
+
+    fn switch_by(&mut self, offset: isize) {
+        let count = self.sessions.len() as isize;
+        self.current = (self.current as isize + offset).rem_euclid(count) as usize;
+    }
+
+    fn scroll_by(&mut self, lines: isize) {
+        let chat = &mut self.sessions[self.current];
+        chat.scroll = chat.scroll.saturating_add_signed(lines);
+    }
+
+    /// Returns false when the app should exit
+    fn submit(&mut self) -> bool {
+        let input = std::mem::take(&mut self.input);
+        let input = input.trim();
+        self.status.clear();
+
+        match input {
+            "" => {}
+            "/quit" | "/exit" => return false,
+            "/clear" => self.sessions[self.current].history.retain(|m| m.role == "system"),
+            cmd if cmd.starts_with("/load ") => {
+                let filename = cmd.strip_prefix("/load ").unwrap().trim();
+                match Session::load(std::path::Path::new(filename)) {
+                    Ok(session) => {
+                        let chat = &mut self.sessions[self.current];
+                        chat.history = session.messages;
+                        chat.scroll = 0;
+                        self.status = format!("Loaded {}", filename);
+                    }
+                    Err(e) => self.status = format!("Error: {:#}", e),
+                }
+            }
+            cmd if cmd.starts_with("/save ") => {
+                let filename = cmd.strip_prefix("/save ").unwrap().trim();
+                let seed = self.executor.generation().pinned_seed();
+                let session = Session {
+                    model: seed.map(|_| self.executor.get_model().to_string()),
+                    seed,
+                    messages: self.sessions[self.current].history.clone(),
+                    ..Default::default()
+                };
+                self.status = match session.save(std::path::Path::new(filename)) {
+                    Ok(()) => format!("Saved {}", filename),
+                    Err(e) => format!("Error: {:#}", e),
+                };
+            }
+            cmd if cmd.starts_with("/mcp-call ") => {
+                let rest = cmd.strip_prefix("/mcp-call ").unwrap().trim();
+                self.call_tool(rest);
+            }
+            "/show-thoughts" | "/show-thoughts off" => {
+                thoughts::set_shown(input == "/show-thoughts");
+                self.status = if thoughts::shown() { "Showing thoughts".to_string() } else { "Hiding thoughts".to_string() };
+            }
+            cmd if cmd.starts_with('/') => {
+                self.status = format!("Unknown command: {}", cmd);
+            }
+            prompt => self.send(prompt.to_string()),
+        }
+
+        true
+    }
+
+    fn send(&mut self, prompt: String) {
+        let chat = &mut self.sessions[self.current];
+        if chat.pending {
+            self.status = "Still waiting for the previous reply".to_string();
+            return;
+        }
+
+        chat.history.push(Message {
+            role: "user".to_string(),
+            content: prompt,
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+        });
+        chat.pending = true;
+        chat.scroll = 0;
+
+        let executor = self.executor.clone();
+        let history = chat.history.clone();
+        let session = chat.id;
+        let events = self.events.clone();
+
+        tokio::spawn(async move {
+            let started = Instant::now();
+            let result = executor.chat(history).await;
+            match &result {
+                Ok(reply) => notify::finished(started, "Reply ready", reply),
+                Err(e) => notify::finished(started, "Reply failed", &format!("{:#}", e)),
+            }
+            let _ = events.send(AppEvent::Reply { session, result });
+        });
+    }
+
+    fn call_tool(&mut self, rest: &str) {
+        let Some(mcp) = self.mcp.clone() else {
+            self.status = "MCP not initialized".to_string();
+            return;
+        };
+
+        let Some((name, args)) = rest.split_once(' ') else {
+            self.status = "Usage: /mcp-call <tool_name> <json_args>".to_string();
+            return;
+        };
+
+        let arguments: serde_json::Value = match serde_json::from_str(args) {
+            Ok(arguments) => arguments,
+            Err(e) => {
+                self.status = format!("Invalid JSON: {}", e);
+                return;
+            }
+        };
+
+        let name = name.to_string();
+        let events = self.events.clone();
+        let max_items = self.json_array_items;
+        let _ = events.send(AppEvent::ToolOutput(format!("⚙ {} {}", name, args)));
+
+        tokio::spawn(async move {
+            let mut mcp = mcp.lock().await;
+            if let Some(request) = mcp.approval_request(&name, &arguments) {
+                let _ = events.send(AppEvent::Approval(PendingApproval { request, arguments }));
+                return;
+            }
+            let output = run_tool(&mut mcp, &name, arguments, max_items).await;
+            let _ = events.send(AppEvent::ToolOutput(output));
+        });
+    }
+
+    fn answer_approval(&mut self, approval: Approval) {
+        let (Some(pending), Some(mcp)) = (self.approval.take(), self.mcp.clone()) else {
+            return;
+        };
+        let name = pending.request.tool;
+        let events = self.events.clone();
+        let max_items = self.json_array_items;
+
+        tokio::spawn(async move {
+            let mut mcp = mcp.lock().await;
+            mcp.decide(&name, approval);
+            let output = match approval {
+                Approval::Once | Approval::Always => run_tool(&mut mcp, &name, pending.arguments, max_items).await,
+                Approval::Deny | Approval::Never => format!("✗ {}: declined", name),
+            };
+            let _ = events.send(AppEvent::ToolOutput(output));
+        });
+    }
+
+    fn draw_approval(&self, frame: &mut Frame, pending: &PendingApproval) {
+        let request = &pending.request;
+        let mut lines = Vec::new();
+        for (i, (heading, body)) in request.sections().into_iter().enumerate() {
+            if i > 0 {
+                lines.push(Line::default());
+            }
+            lines.push(Line::from(heading).cyan().bold());
+            lines.extend(body.into_iter().map(|l| Line::raw(format!("  {}", l))));
+        }
+        lines.push(Line::default());
+        lines.push(Line::from(Approval::PROMPT).yellow());
+
+        let area = frame.area();
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let [popup] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(area);
+        let [popup]: [Rect; 1] = Layout::horizontal([Constraint::Percentage(80)]).flex(Flex::Center).areas(popup);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(format!(" Approve tool call: {} ({}) ", request.tool, request.server));
+        frame.render_widget(Clear, popup);
+        frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(block), popup);
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [tabs_area, body, input_area, status_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(5),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ]).areas(frame.area());
+
+        let [chat_area, sidebar] = Layout::horizontal([
+            Constraint::Percentage(70),
+            Constraint::Percentage(30),
+        ]).areas(body);
+
+        let [servers_area, tools_area] = Layout::vertical([
+            Constraint::Percentage(40),
+            Constraint::Percentage(60),
+        ]).areas(sidebar);
+
+        let titles = self.sessions.iter().map(|s| {
+            let marker = if s.pending { " …" } else { "" };
+            format!("Chat {}{}", s.id, marker)
+        });
+        frame.render_widget(
+            Tabs::new(titles)
+                .select(self.current)
+                .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            tabs_area,
+        );
+
+        self.draw_chat(frame, chat_area);
+        self.draw_servers(frame, servers_area);
+
+        let block = Block::default().borders(Borders::ALL).title(" Tool output ");
+        let height = block.inner(tools_area).height as usize;
+        let start = self.tool_output.len().saturating_sub(height);
+        let lines: Vec<Line> = self.tool_output[start..].iter()
+            .map(|l| Line::raw(l.as_str()))
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block), tools_area);
+
+        let input = Paragraph::new(self.input.as_str())
+            .block(Block::default().borders(Borders::ALL).title(" Message "));
+        frame.render_widget(input, input_area);
+        frame.set_cursor_position(Position::new(
+            input_area.x + 1 + self.input.chars().count() as u16,
+            input_area.y + 1,
+        ));
+
+        let status = if self.status.is_empty() {
+            Line::from("Enter send · PgUp/PgDn scroll · Ctrl+N new · Ctrl+←/→ switch · Ctrl+W close · Esc quit").dark_gray()
+        } else {
+            Line::from(self.status.as_str()).yellow()
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+
+        if let Some(pending) = &self.approval {
+            self.draw_approval(frame, pending);
+        }
+    }
+
+    fn draw_chat(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} ", self.executor.get_model()));
+        let inner = block.inner(area);
+        let width = inner.width.max(1) as usize;
+
+        let chat = &mut self.sessions[self.current];
+        let mut lines = Vec::new();
+
+        for message in chat.history.iter().filter(|m| m.role != "system") {
+            let (label, color) = if message.role == "user" {
+                ("You: ", Color::Green)
+            } else {
+                ("AI: ", Color::Blue)
+            };
+
+            for (i, text) in wrap(&message.content, width.saturating_sub(label.len()).max(1)).into_iter().enumerate() {
+                let prefix = if i == 0 { label.to_string() } else { " ".repeat(label.len()) };
+                lines.push(Line::from(vec![
+                    Span::styled(prefix, Style::default().fg(color).add_modifier(Modifier::BOLD)),
+                    Span::raw(text),
+                ]));
+            }
+            lines.push(Line::default());
+        }
+
+        if chat.pending {
+            lines.push(Line::from("AI: thinking…").dark_gray());
+        }
+
+        // Clamp the scroll offset so the view never scrolls past the first line
+        let height = inner.height as usize;
+        let max_scroll = lines.len().saturating_sub(height);
+        chat.scroll = chat.scroll.min(max_scroll);
+
+        let end = lines.len() - chat.scroll;
+        let start = end.saturating_sub(height);
+        let visible: Vec<Line> = lines.drain(start..end).collect();
+
+        frame.render_widget(Paragraph::new(visible).block(block), area);
+    }
+
+    fn draw_servers(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let lines: Vec<Line> = if self.mcp.is_none() {
+            vec![Line::from("No MCP tools configured").dark_gray()]
+        } else {
+            self.servers.iter()
+                .map(|server| {
+                    let indicator = if server.alive {
+                        Span::styled("● ", Style::default().fg(Color::Green))
+                    } else {
+                        Span::styled("● ", Style::default().fg(Color::Red))
+                    };
+                    Line::from(vec![
+                        indicator,
+                        Span::styled(server.name.clone(), Style::default().fg(Color::Cyan)),
+                        Span::raw(format!(" [{}] {} tool(s)", server.transport, server.tools)),
+                    ])
+                })
+                .collect()
+        };
+
+        let block = Block::default().borders(Borders::ALL).title(" MCP servers ");
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+/// Calls a tool and formats the result for the tool output pane
+async fn run_tool(mcp: &mut McpManager, name: &str, arguments: serde_json::Value, max_items: usize) -> String {
+    match mcp.call_tool(name, arguments).await {
+        Ok(result) => {
+            let marker = if result.is_error == Some(true) { "✗" } else { "✓" };
+            let text: Vec<String> = result.content.into_iter()
+                .filter(|c| c.content_type == "text")
+                .map(|c| if !c.text.starts_with(thoughts::HEADER) {
+                    tool_format::pretty(&c.text, max_items, false).unwrap_or(c.text)
+                } else if thoughts::shown() {
+                    format!("💭 {}", thoughts::text(&c.text))
+                } else {
+                    format!("💭 {}", thoughts::summary(&c.text))
+                })
+                .collect();
+            format!("{} {}\n{}", marker, name, text.join("\n"))
+        }
+        Err(e) => format!("✗ {}: {:#}", name, e),
+    }
+}
+
+/// Greedy word wrap by character count
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for raw in text.lines() {
+        let mut line = String::new();
+        for word in raw.split(' ') {
+            let word_len = word.chars().count();
+            let line_len = line.chars().count();
+
+            if line_len > 0 && line_len + 1 + word_len > width {
+                lines.push(std::mem::take(&mut line));
+            }
+
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+
+            // Hard-split words longer than the pane
+            while line.chars().count() > width {
+                let head: String = line.chars().take(width).collect();
+                line = line.chars().skip(width).collect();
+                lines.push(head);
+            }
+        }
+        lines.push(line);
+    }
+
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
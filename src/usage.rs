@@ -0,0 +1,223 @@
+//! Token usage, cost and the monthly budget.
+//!
+//! Costs come from the `pricing` table in config.json; models without an
+//! entry (typically local ones) are counted but free. Month-to-date and
+//! all-time totals are kept in ~/.ai-chat-cli/usage.json. The executor
+//! owns the tracker, so every request it sends is gated and counted.
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::accessibility;
+use crate::config::{AppConfig, ModelPrice};
+use crate::error::Failure;
+use crate::ollama::ChatResponse;
+use crate::tokens;
+
+/// Share of the budget after which each reply carries a warning
+const BUDGET_WARNING_RATIO: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// USD
+    pub cost: f64,
+}
+
+impl Usage {
+    fn add(&mut self, other: &Usage) {
+        self.requests += other.requests;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost += other.cost;
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Ledger {
+    /// Calendar month the `month` totals belong to, e.g. "2026-10"
+    #[serde(rename = "currentMonth")]
+    current_month: String,
+    month: Usage,
+    total: Usage,
+}
+
+pub struct UsageTracker {
+    pricing: HashMap<String, ModelPrice>,
+    budget: Option<f64>,
+    session: BTreeMap<String, Usage>,
+    ledger: Ledger,
+    path: Option<PathBuf>,
+    /// Budget warning or failed save from the last request, until shown
+    warning: Option<String>,
+}
+
+impl UsageTracker {
+    pub fn load(config: &AppConfig) -> Self {
+        let path = dirs::home_dir().map(|home| home.join(".ai-chat-cli").join("usage.json"));
+        Self::at(path, config)
+    }
+
+    /// A tracker keeping its totals in `path`, or only in memory without one
+    pub fn at(path: Option<PathBuf>, config: &AppConfig) -> Self {
+        // A missing or unreadable ledger starts the counts over rather than blocking chat
+        let ledger = path.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        let mut tracker = Self {
+            pricing: config.pricing.clone(),
+            budget: config.monthly_budget,
+            session: BTreeMap::new(),
+            ledger,
+            path,
+            warning: None,
+        };
+        tracker.roll_month();
+        tracker
+    }
+
+    /// Everything used in this session, all models together
+    pub fn session_total(&self) -> Usage {
+        let mut total = Usage::default();
+        for usage in self.session.values() {
+            total.add(usage);
+        }
+        total
+    }
+
+    /// Fails with `Failure::BudgetExhausted` once this month's spend reaches the budget
+    pub fn check_budget(&mut self) -> Result<()> {
+        self.roll_month();
+        match self.budget {
+            Some(budget) if self.ledger.month.cost >= budget => Err(anyhow::anyhow!(
+                "Monthly budget of ${:.2} reached (${:.2} spent in {}); raise \"monthlyBudget\" in ~/.ai-chat-cli/config.json to continue",
+                budget, self.ledger.month.cost, self.ledger.current_month)
+                .context(Failure::BudgetExhausted)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts a reply, estimating the token counts a backend left out;
+    /// cached replies cost nothing
+    pub fn record_reply(&mut self, model: &str, prompt_estimate: usize, reply: &ChatResponse) {
+        if reply.cached {
+            return;
+        }
+        let prompt_tokens = reply.prompt_eval_count.unwrap_or(prompt_estimate as u64);
+        let completion_tokens = reply.eval_count.unwrap_or(tokens::estimate_message(&reply.message) as u64);
+        self.record(model, prompt_tokens, completion_tokens);
+    }
+
+    /// Adds one request to the session and persisted totals; a warning when
+    /// the monthly budget is nearly used up waits in `take_warning`
+    pub fn record(&mut self, model: &str, prompt_tokens: u64, completion_tokens: u64) {
+        let usage = Usage {
+            requests: 1,
+            prompt_tokens,
+            completion_tokens,
+            cost: self.price(model).map_or(0.0, |price| {
+                (prompt_tokens as f64 * price.input + completion_tokens as f64 * price.output) / 1_000_000.0
+            }),
+        };
+
+        self.roll_month();
+        self.session.entry(model.to_string()).or_default().add(&usage);
+        self.ledger.month.add(&usage);
+        self.ledger.total.add(&usage);
+
+        self.warning = match self.save() {
+            Err(e) => Some(format!("{} {:#}", "Warning:".bright_yellow(), e)),
+            Ok(()) => self.budget
+                .filter(|&budget| usage.cost > 0.0 && self.ledger.month.cost >= budget * BUDGET_WARNING_RATIO)
+                .map(|budget| format!("{} ${:.2} of the ${:.2} monthly budget used",
+                    "Warning:".bright_yellow(), self.ledger.month.cost, budget)),
+        };
+    }
+
+    /// The warning from the last request, once
+    pub fn take_warning(&mut self) -> Option<String> {
+        self.warning.take()
+    }
+
+    pub fn print_stats(&self) {
+        println!("\n{}", "Usage:".bright_yellow().bold());
+        println!("{}", accessibility::rule('-').bright_black());
+
+        if self.session.is_empty() {
+            println!("  No requests in this session yet");
+        }
+        let mut session = Usage::default();
+        for (model, usage) in &self.session {
+            println!("  {:<24} {}", model.bright_cyan(), describe(usage));
+            session.add(usage);
+        }
+
+        println!("{}", accessibility::rule('-').bright_black());
+        println!("  {:<24} {}", "This session", describe(&session));
+        println!("  {:<24} {}", format!("This month ({})", self.ledger.current_month), describe(&self.ledger.month));
+        println!("  {:<24} {}", "All time", describe(&self.ledger.total));
+
+        match self.budget {
+            Some(budget) => {
+                let left = (budget - self.ledger.month.cost).max(0.0);
+                let line = format!("  Budget: ${:.2} / month, ${:.2} left", budget, left);
+                if left == 0.0 {
+                    println!("{}", line.bright_red());
+                } else if self.ledger.month.cost >= budget * BUDGET_WARNING_RATIO {
+                    println!("{}", line.yellow());
+                } else {
+                    println!("{}", line);
+                }
+            }
+            None => println!("  {}", "No monthly budget set (\"monthlyBudget\" in config.json)".bright_black()),
+        }
+        println!();
+    }
+
+    /// Exact model names win over prefixes, so "gpt-4o" can be priced apart from "gpt-4"
+    fn price(&self, model: &str) -> Option<&ModelPrice> {
+        self.pricing.get(model).or_else(|| {
+            self.pricing.iter()
+                .filter(|(name, _)| model.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, price)| price)
+        })
+    }
+
+    fn roll_month(&mut self) {
+        let month = Local::now().format("%Y-%m").to_string();
+        if self.ledger.current_month != month {
+            self.ledger.current_month = month;
+            self.ledger.month = Usage::default();
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&self.ledger)?)
+            .context("Failed to save usage totals")
+    }
+}
+
+fn describe(usage: &Usage) -> String {
+    format!("{:>5} req  {:>7} in  {:>7} out  ${:.4}",
+        usage.requests,
+        tokens::format_count(usage.prompt_tokens as usize),
+        tokens::format_count(usage.completion_tokens as usize),
+        usage.cost)
+}
@@ -0,0 +1,82 @@
+//! Verification after tool calls that change files.
+//!
+//! With a "verify" command configured (or --verify), every successful call of
+//! a file-modifying tool is followed by that command, e.g. `cargo check`. Its
+//! outcome is appended to the tool result, so a model that broke the build
+//! sees the errors with the result of its edit and can fix them next.
+
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::config::VerifyConfig;
+
+const DEFAULT_TOOLS: &[&str] = &["edit_file", "write_file"];
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+/// The end of a failing command's output, where compilers put their summary
+const MAX_OUTPUT_LINES: usize = 80;
+const MAX_OUTPUT_CHARS: usize = 8000;
+
+/// `None` while verification is off
+static SETTINGS: Mutex<Option<Settings>> = Mutex::new(None);
+
+#[derive(Clone)]
+struct Settings {
+    command: String,
+    tools: Vec<String>,
+    timeout: Duration,
+}
+
+/// Applies the "verify" config for the whole process
+pub fn init(config: &VerifyConfig) {
+    *SETTINGS.lock().unwrap() = config.command.clone()
+        .filter(|command| !command.trim().is_empty())
+        .map(|command| Settings {
+            command,
+            tools: if config.tools.is_empty() {
+                DEFAULT_TOOLS.iter().map(|t| t.to_string()).collect()
+            } else {
+                config.tools.clone()
+            },
+            timeout: config.timeout_seconds.map(Duration::from_secs).unwrap_or(DEFAULT_TIMEOUT),
+        });
+}
+
+/// Runs the verification command if calls of `tool` are verified, and
+/// returns the text to append to the tool result
+pub async fn after_tool_call(tool: &str) -> Option<String> {
+    let settings = SETTINGS.lock().unwrap().clone()?;
+    if !settings.tools.iter().any(|t| t == tool) {
+        return None;
+    }
+
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(&settings.command).kill_on_drop(true);
+    crate::env_vars::apply(&mut command);
+    let output = command.output();
+    let text = match tokio::time::timeout(settings.timeout, output).await {
+        Ok(Ok(output)) if output.status.success() => {
+            format!("Verification passed: `{}`", settings.command)
+        }
+        Ok(Ok(output)) => {
+            let mut combined = String::from_utf8_lossy(&output.stdout).to_string();
+            combined.push_str(&String::from_utf8_lossy(&output.stderr));
+            format!("Verification failed: `{}` exited with code {} after this change. Fix these problems:\n{}",
+                settings.command, output.status.code().unwrap_or(-1), tail(&combined))
+        }
+        Ok(Err(e)) => format!("Verification could not run `{}`: {}", settings.command, e),
+        Err(_) => format!("Verification stopped: `{}` timed out after {} seconds",
+            settings.command, settings.timeout.as_secs()),
+    };
+    Some(text)
+}
+
+fn tail(output: &str) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    let text = lines[lines.len().saturating_sub(MAX_OUTPUT_LINES)..].join("\n");
+    if text.chars().count() > MAX_OUTPUT_CHARS {
+        format!("…{}", crate::text::tail(&text, MAX_OUTPUT_CHARS))
+    } else {
+        text
+    }
+}
@@ -0,0 +1,118 @@
+//! Voice prompts for /voice.
+//!
+//! A recorder command captures the microphone into a WAV file until it is
+//! interrupted, then the whisper.cpp CLI transcribes the file locally.
+//! Both commands are configured under "voice" in config.json.
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+use crate::config::VoiceConfig;
+
+/// SoX; records 16 kHz mono, the format whisper.cpp expects
+const DEFAULT_RECORDER: &str = "rec -q -c 1 -r 16000 -b 16 {output}";
+const DEFAULT_WHISPER: &str = "whisper-cli";
+
+/// Time the recorder gets to finish the WAV header after being interrupted
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Recording {
+    child: Child,
+    path: PathBuf,
+}
+
+/// Starts the recorder; it runs until `Recording::stop`
+pub fn start(config: &VoiceConfig) -> Result<Recording> {
+    // Checked first so nobody speaks into a recording that cannot be transcribed
+    model(config)?;
+
+    let path = std::env::temp_dir().join(format!("ai-chat-voice-{}.wav", uuid::Uuid::new_v4()));
+    let recorder = config.recorder.as_deref().unwrap_or(DEFAULT_RECORDER);
+
+    let mut parts = recorder.split_whitespace();
+    let program = parts.next().context("The voice recorder command is empty")?;
+    let args: Vec<String> = parts
+        .map(|part| part.replace("{output}", &path.to_string_lossy()))
+        .collect();
+
+    let child = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start recorder '{}' (set \"voice.recorder\" in config.json)", program))?;
+
+    Ok(Recording { child, path })
+}
+
+impl Recording {
+    /// Interrupts the recorder so it can close the file, returning its path
+    pub async fn stop(mut self) -> Result<PathBuf> {
+        #[cfg(unix)]
+        if let Some(pid) = self.child.id() {
+            unsafe {
+                libc::kill(pid as libc::pid_t, libc::SIGINT);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = self.child.start_kill();
+
+        if tokio::time::timeout(STOP_TIMEOUT, self.child.wait()).await.is_err() {
+            let _ = self.child.kill().await;
+        }
+
+        if !self.path.exists() {
+            anyhow::bail!("The recorder did not write any audio");
+        }
+        Ok(self.path)
+    }
+}
+
+/// Runs whisper.cpp on a recording and removes the file
+pub async fn transcribe(config: &VoiceConfig, audio: PathBuf) -> Result<String> {
+    let whisper = config.whisper.as_deref().unwrap_or(DEFAULT_WHISPER);
+    let mut command = Command::new(whisper);
+    command.arg("-m").arg(model(config)?)
+        .arg("-f").arg(&audio)
+        // Plain text only: no timestamps, no progress or system info
+        .args(["-nt", "-np"]);
+    if let Some(language) = &config.language {
+        command.args(["-l", language]);
+    }
+
+    let output = command.output().await;
+    let _ = std::fs::remove_file(&audio);
+    let output = output
+        .with_context(|| format!("Failed to run '{}' (set \"voice.whisper\" to the whisper.cpp CLI)", whisper))?;
+
+    if !output.status.success() {
+        anyhow::bail!("{} failed: {}", whisper, String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    Ok(text)
+}
+
+fn model(config: &VoiceConfig) -> Result<PathBuf> {
+    let model = config.model.as_deref()
+        .context("No whisper model configured; set \"voice\": {\"model\": \"<path to ggml model>\"} in ~/.ai-chat-cli/config.json")?;
+
+    let path = match model.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().context("Could not find home directory")?.join(rest),
+        None => PathBuf::from(model),
+    };
+    if !path.exists() {
+        anyhow::bail!("Whisper model not found: {}", path.display());
+    }
+    Ok(path)
+}
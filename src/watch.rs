@@ -0,0 +1,130 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use colored::*;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::args::WatchArgs;
+use crate::executor::AIExecutor;
+use crate::ollama::Message;
+
+/// Upper bound on the context sent with each run
+const MAX_CONTEXT_CHARS: usize = 24_000;
+
+pub async fn run(executor: &AIExecutor, args: WatchArgs) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        match result {
+            // Reading files for the prompt generates access events; ignore them
+            Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+            Ok(event) => {
+                let _ = tx.send(event.paths);
+            }
+            Err(e) => eprintln!("{} {}", "Watch error:".bright_yellow(), e),
+        }
+    })?;
+
+    for path in &args.paths {
+        watcher.watch(path, RecursiveMode::Recursive)
+            .context(format!("Failed to watch {}", path.display()))?;
+    }
+
+    println!("{} Watching {} (Ctrl+C to stop)", "✓".bright_green(),
+        args.paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", "));
+
+    // Answer once for the current state before waiting for changes
+    let mut changed = BTreeSet::new();
+    loop {
+        query(executor, &args, &changed).await;
+        changed.clear();
+
+        while changed.is_empty() {
+            let Some(paths) = rx.recv().await else {
+                return Ok(());
+            };
+            changed.extend(paths.into_iter().filter(|p| is_relevant(p)));
+        }
+
+        // Let a burst of saves settle, folding in everything that changed
+        tokio::time::sleep(Duration::from_millis(args.debounce)).await;
+        while let Ok(paths) = rx.try_recv() {
+            changed.extend(paths.into_iter().filter(|p| is_relevant(p)));
+        }
+    }
+}
+
+async fn query(executor: &AIExecutor, args: &WatchArgs, changed: &BTreeSet<PathBuf>) {
+    println!("\n{} {}", "━━━".bright_black(), Local::now().format("%H:%M:%S").to_string().bright_black());
+    for path in changed {
+        println!("  {} {}", "changed".bright_yellow(), path.display());
+    }
+
+    let context = collect_context(&args.paths, changed);
+    if context.trim().is_empty() {
+        println!("{}", "No changes to review".bright_black());
+        return;
+    }
+
+    let messages = vec![Message {
+        role: "user".to_string(),
+        content: format!("{}\n\n{}", args.prompt, context),
+        images: Vec::new(),
+        tool_calls: Vec::new(),
+    }];
+
+    match executor.batch_complete(args.model.as_deref(), messages, None).await {
+        Ok(response) => println!("{} {}", "AI:".bright_blue().bold(), response.message.content.trim()),
+        Err(e) => eprintln!("{} {:#}", "Error:".bright_red().bold(), e),
+    }
+}
+
+/// Prefers the git diff of the watched paths; outside a repository, or when
+/// the diff is empty, falls back to the contents of the changed files
+fn collect_context(paths: &[PathBuf], changed: &BTreeSet<PathBuf>) -> String {
+    let diff = Command::new("git")
+        .arg("diff")
+        .arg("HEAD")
+        .arg("--")
+        .args(paths)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .unwrap_or_default();
+
+    let context = if diff.trim().is_empty() {
+        changed.iter()
+            .filter_map(|path| {
+                let text = fs::read_to_string(path).ok()?;
+                Some(format!("File: {}\n```\n{}\n```\n", path.display(), text))
+            })
+            .collect()
+    } else {
+        format!("```diff\n{}```\n", diff)
+    };
+
+    if context.len() > MAX_CONTEXT_CHARS {
+        let end = (0..=MAX_CONTEXT_CHARS).rev().find(|&i| context.is_char_boundary(i)).unwrap_or(0);
+        format!("{}\n... (truncated)", &context[..end])
+    } else {
+        context
+    }
+}
+
+/// Skips build output, VCS internals and editor swap files
+fn is_relevant(path: &Path) -> bool {
+    let ignored_dir = path.components().any(|c| {
+        matches!(c.as_os_str().to_str(), Some(".git" | "target" | "node_modules"))
+    });
+
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let temp_file = name.ends_with('~') || name.ends_with(".swp") || name.starts_with(".#");
+
+    !ignored_dir && !temp_file && path.is_file()
+}